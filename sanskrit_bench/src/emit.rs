@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use sanskrit_common::errors::*;
+use crate::measure::*;
+use crate::opcodes::INT_KINDS;
+
+//The integer opcodes the externals compile to (each is measured for every integer kind)
+const INT_OPS:[&str;16] = ["SpecialLit", "And", "Or", "Xor", "Not", "Add", "Sub", "Mul", "Div", "Eq", "Lt", "Gt", "Lte", "Gte", "ToData", "FromData"];
+//The opcodes and system calls whose cost depends on the number of processed bytes (function name, benchmark)
+// the runtime verifies the signatures of a section in a batch, so the batched verification is charged
+const BYTE_OPS:[(&str, &str);10] = [
+    ("data_lit", "Data"),
+    ("and_data", "And(Data)"),
+    ("or_data", "Or(Data)"),
+    ("xor_data", "Xor(Data)"),
+    ("not_data", "Not(Data)"),
+    ("eq_data", "Eq(Data)"),
+    ("plain_hash", "TypedSysInvoke(plain_hash)"),
+    ("sha256", "TypedSysInvoke(sha256)"),
+    ("keccak256", "TypedSysInvoke(keccak256)"),
+    ("ecdsa_verify", "SysInvoke(ecdsa_verify_batched)"),
+];
+
+//SpecialLit -> SPECIAL_LIT
+fn const_name(op:&str) -> String {
+    let mut res = String::new();
+    for (i, c) in op.chars().enumerate() {
+        if c.is_uppercase() && i != 0 {
+            res.push('_');
+        }
+        res.push(c.to_ascii_uppercase());
+    }
+    res
+}
+
+//The fitted models by benchmark name
+pub struct Results {
+    pub models:BTreeMap<String, Model>,
+    pub settings:Settings,
+}
+
+impl Model {
+    pub fn plus(&self, other:&Model) -> Self {
+        Model { constant: self.constant + other.constant, per_unit: self.per_unit + other.per_unit }
+    }
+}
+
+impl Results {
+    pub fn new(measurements:&[Measurement], settings:Settings) -> Self {
+        Results {
+            models: measurements.iter().map(|m|(m.name.clone(), m.fit())).collect(),
+            settings
+        }
+    }
+
+    fn model(&self, name:&str) -> Result<Model> {
+        match self.models.get(name) {
+            Some(model) => Ok(*model),
+            None => owned_error(||format!("Benchmark {} was not run", name))
+        }
+    }
+
+    fn constant(&self, name:&str) -> Result<u64> {
+        Ok(to_gas(self.model(name)?.constant, &self.settings))
+    }
+
+    fn linear(&self, name:&str, param:&str) -> Result<String> {
+        let (constant, per_unit) = self.model(name)?.to_gas(&self.settings);
+        Ok(format!("{} + {} * ({} as u64)", constant, per_unit, param))
+    }
+
+    fn header(&self, out:&mut String) {
+        writeln!(out, "//Note we rounded up - No fractions").unwrap();
+        writeln!(out, "// Based on sanskrit_bench - main").unwrap();
+        writeln!(out, "//  Generated with {} gas per ns ({} runs of {} repetitions per sample)", self.settings.gas_per_ns, self.settings.runs, self.settings.repetitions).unwrap();
+    }
+
+    //Renders a drop in replacement for sanskrit_compile/src/gas_table.rs
+    pub fn gas_table(&self) -> Result<String> {
+        let void = self.model("Void")?;
+        //The measured Try executes a Void and the RepeatedInvoke is wrapped into a Try and ends in a Rollback
+        let r#try = self.model("Try")?.minus(&void);
+        let rollback = self.model("Rollback")?.minus(&r#try);
+        let repetition = self.model("RepeatedInvoke")?.minus(&r#try.plus(&rollback));
+        let gas = match self.models.get("ConsumeGas") {
+            Some(model) => format!("{}", to_gas(model.constant, &self.settings)),
+            None => "2 /*Not measured (run with the dynamic_gas feature)*/".to_string()
+        };
+
+        let mut out = String::new();
+        self.header(&mut out);
+        writeln!(out, "pub mod gas {{").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "    pub fn call(args:usize) -> u64 {{").unwrap();
+        writeln!(out, "        {}", self.linear("Invoke", "args")?).unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "    pub fn repeated_call(args:usize, reps:u64) -> u64 {{").unwrap();
+        writeln!(out, "        call(args) + reps*{}", to_gas(repetition.per_unit, &self.settings)).unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "    pub fn void() -> u64 {{").unwrap();
+        writeln!(out, "        {}", self.constant("Void")?).unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "    pub fn gas() -> u64 {{ {} }}", gas).unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "    pub fn sig(fields:usize) -> u64 {{").unwrap();
+        writeln!(out, "        {}", self.linear("CreateSig", "fields")?).unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "    pub fn _let() -> u64 {{ {} }}", self.constant("Let")?).unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "    pub fn unpack(fields:usize) -> u64 {{").unwrap();
+        writeln!(out, "        {}", self.linear("Unpack", "fields")?).unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "    pub fn field() -> u64 {{").unwrap();
+        writeln!(out, "        {}", self.constant("Get")?).unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "    pub fn pack(fields:usize) -> u64 {{").unwrap();
+        writeln!(out, "        {}", self.linear("Pack", "fields")?).unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+        //The compiler charges the fields of a switch with a separate unpack
+        writeln!(out, "    pub fn switch() -> u64 {{").unwrap();
+        writeln!(out, "        {}", self.constant("Switch")?).unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "    pub fn ret(rets:usize) -> u64 {{").unwrap();
+        writeln!(out, "        {}", self.linear("Return", "rets")?).unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "    pub fn rollback() -> u64 {{").unwrap();
+        writeln!(out, "        {}", to_gas(rollback.constant, &self.settings)).unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "    pub fn r#try() -> u64 {{ {} }}", to_gas(r#try.constant, &self.settings)).unwrap();
+        writeln!(out, "}}").unwrap();
+        Ok(out)
+    }
+
+    fn processing_cost(&self, out:&mut String, name:&str, model:Model) {
+        //keep at least two significant digits in the multiplier
+        let per_unit = model.per_unit * self.settings.gas_per_ns;
+        let divider = if per_unit >= 10.0 { 1 } else if per_unit >= 1.0 { 10 } else { 100 };
+        let (constant, multiplier, divider) = model.to_gas_fraction(&self.settings, divider);
+        writeln!(out, "pub const {}: DataProcessingCost = DataProcessingCost {{", name).unwrap();
+        writeln!(out, "    cost_constant: {},", constant).unwrap();
+        writeln!(out, "    cost_multiplier: {},", multiplier).unwrap();
+        writeln!(out, "    cost_divider: {}", divider).unwrap();
+        writeln!(out, "}};").unwrap();
+        writeln!(out).unwrap();
+    }
+
+    //Renders the DataProcessingCost constants of sanskrit_runtime/src/lib.rs
+    pub fn processing_costs(&self) -> Result<String> {
+        let load = self.model("Store::load")?;
+        let write = self.model("Store::write")?;
+        let parse = self.model("Encoding::parse")?;
+        let serialize = self.model("Encoding::serialize")?;
+
+        let mut out = String::new();
+        self.header(&mut out);
+        self.processing_cost(&mut out, "STORE_LOAD_COST", load);
+        self.processing_cost(&mut out, "STORE_WRITE_COST", write);
+        self.processing_cost(&mut out, "STORE_LOAD_AND_ENCODE_COST", load.plus(&parse));
+        self.processing_cost(&mut out, "STORE_WRITE_AND_ENCODE_COST", write.plus(&serialize));
+        self.processing_cost(&mut out, "ENCODING_COST", parse);
+        self.processing_cost(&mut out, "COPYING_COST", self.model("Copying")?);
+        Ok(out)
+    }
+
+    //Renders a drop in replacement for sanskrit_default_externals/src/costs.rs (the gas the externals charge)
+    pub fn external_costs(&self) -> Result<String> {
+        let mut out = String::new();
+        self.header(&mut out);
+        for kind in INT_KINDS {
+            writeln!(out).unwrap();
+            for op in INT_OPS {
+                let gas = self.constant(&format!("{}({:?})", op, kind))?;
+                writeln!(out, "pub const {}_{}:u64 = {};", const_name(op), format!("{:?}", kind).to_uppercase(), gas).unwrap();
+            }
+        }
+        writeln!(out).unwrap();
+        writeln!(out, "pub const JOIN_HASH:u64 = {};", self.constant("SysInvoke(join_hash)")?).unwrap();
+        for (fun, bench) in BYTE_OPS {
+            let (constant, per_byte) = self.model(bench)?.to_gas(&self.settings);
+            writeln!(out).unwrap();
+            //written so that clippy accepts the generated code (no multiplications by 0 or 1)
+            match per_byte {
+                0 => writeln!(out, "pub const fn {}(_bytes:u64) -> u64 {{ {} }}", fun, constant).unwrap(),
+                1 => writeln!(out, "pub const fn {}(bytes:u64) -> u64 {{ {} + bytes }}", fun, constant).unwrap(),
+                _ => writeln!(out, "pub const fn {}(bytes:u64) -> u64 {{ {} + {}*bytes }}", fun, constant, per_byte).unwrap(),
+            }
+        }
+        Ok(out)
+    }
+
+    //Renders the cost of every opcode and system call (used for the literal gas of the externals)
+    // x is the number of fields, arguments, returns, repetitions or data bytes the opcode processes
+    pub fn opcode_costs(&self) -> String {
+        let mut out = String::new();
+        self.header(&mut out);
+        writeln!(out, "//(opcode, constant gas, gas per x)").unwrap();
+        writeln!(out, "pub const OPCODE_COSTS:&[(&str, u64, u64)] = &[").unwrap();
+        for (name, model) in &self.models {
+            if name.starts_with("Store::") || name.starts_with("Encoding::") || name == "Copying" {
+                continue
+            }
+            let (constant, per_unit) = model.to_gas(&self.settings);
+            writeln!(out, "    (\"{}\", {}, {}),", name, constant, per_unit).unwrap();
+        }
+        writeln!(out, "];").unwrap();
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opcodes::benches;
+
+    //Every benchmark costs 10ns (5 gas) unless overridden
+    fn results(overrides:&[(&str, Model)]) -> Results {
+        let mut models:BTreeMap<String, Model> = benches().into_iter().map(|b|(b.name, Model::constant(10.0))).collect();
+        for name in ["Store::write", "Store::load", "Encoding::serialize", "Encoding::parse", "Copying"] {
+            models.insert(name.to_string(), Model::constant(10.0));
+        }
+        for (name, model) in overrides {
+            models.insert(name.to_string(), *model);
+        }
+        Results{ models, settings: Settings { runs: 1, repetitions: 1, gas_per_ns: 0.5 } }
+    }
+
+    #[test]
+    fn constant_names_are_upper_snake_case() {
+        assert_eq!(const_name("SpecialLit"), "SPECIAL_LIT");
+        assert_eq!(const_name("Add"), "ADD");
+        assert_eq!(const_name("FromData"), "FROM_DATA");
+    }
+
+    #[test]
+    fn external_costs_cover_every_integer_opcode() {
+        let costs = results(&[("Add(U8)", Model::constant(40.0))]).external_costs().unwrap();
+        let consts = costs.lines().filter(|l|l.starts_with("pub const ") && !l.starts_with("pub const fn")).count();
+        assert_eq!(consts, INT_KINDS.len()*INT_OPS.len() + 1);
+        assert!(costs.contains("pub const ADD_U8:u64 = 20;"));
+        assert!(costs.contains("pub const SPECIAL_LIT_U256:u64 = 5;"));
+        assert!(costs.contains("pub const FROM_DATA_I128:u64 = 5;"));
+        assert!(costs.contains("pub const JOIN_HASH:u64 = 5;"));
+    }
+
+    #[test]
+    fn external_byte_costs_are_valid_rust() {
+        let costs = results(&[
+            ("Data", Model{ constant: 10.0, per_unit: 2.0 }),
+            ("TypedSysInvoke(sha256)", Model{ constant: 100.0, per_unit: 5.5 }),
+        ]).external_costs().unwrap();
+        assert!(costs.contains("pub const fn data_lit(bytes:u64) -> u64 { 5 + bytes }"));
+        assert!(costs.contains("pub const fn sha256(bytes:u64) -> u64 { 50 + 3*bytes }"));
+        assert!(costs.contains("pub const fn eq_data(_bytes:u64) -> u64 { 5 }"));
+        assert!(costs.contains("pub const fn ecdsa_verify(_bytes:u64) -> u64 { 5 }"));
+    }
+
+    #[test]
+    fn tables_need_all_their_benchmarks() {
+        let mut res = results(&[]);
+        res.models.remove("SysInvoke(ecdsa_verify_batched)");
+        assert!(res.external_costs().is_err());
+        assert!(res.gas_table().is_ok());
+        res.models.remove("Store::load");
+        assert!(res.processing_costs().is_err());
+    }
+
+    #[test]
+    fn gas_table_uses_the_fitted_models() {
+        let table = results(&[
+            ("Invoke", Model{ constant: 44.0, per_unit: 4.0 }),
+            ("Pack", Model{ constant: 10.0, per_unit: 3.0 }),
+        ]).gas_table().unwrap();
+        assert!(table.contains("pub fn call(args:usize) -> u64 {\n        22 + 2 * (args as u64)\n    }"));
+        assert!(table.contains("pub fn pack(fields:usize) -> u64 {\n        5 + 2 * (fields as u64)\n    }"));
+        //without the dynamic_gas feature ConsumeGas is not measured
+        if !cfg!(feature = "dynamic_gas") {
+            assert!(table.contains("Not measured"));
+        }
+    }
+
+    #[test]
+    fn processing_costs_choose_the_divider_by_the_per_unit_cost() {
+        let costs = results(&[
+            ("Store::load", Model{ constant: 100.0, per_unit: 0.01 }),
+            ("Store::write", Model{ constant: 100.0, per_unit: 30.0 }),
+        ]).processing_costs().unwrap();
+        assert!(costs.contains("pub const STORE_LOAD_COST: DataProcessingCost = DataProcessingCost {\n    cost_constant: 50,\n    cost_multiplier: 1,\n    cost_divider: 100\n};"));
+        assert!(costs.contains("pub const STORE_WRITE_COST: DataProcessingCost = DataProcessingCost {\n    cost_constant: 50,\n    cost_multiplier: 15,\n    cost_divider: 1\n};"));
+    }
+}
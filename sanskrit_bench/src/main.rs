@@ -0,0 +1,109 @@
+extern crate sanskrit_common;
+extern crate sanskrit_interpreter;
+extern crate sanskrit_default_externals;
+extern crate sanskrit_memory_store;
+extern crate sanskrit_sled_store;
+extern crate ed25519_consensus;
+
+mod measure;
+mod opcodes;
+mod store;
+mod emit;
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::{env, fs};
+use sanskrit_common::errors::*;
+use sanskrit_memory_store::BTreeMapStore;
+use sanskrit_sled_store::SledStore;
+use crate::emit::Results;
+use crate::measure::Settings;
+use crate::opcodes::{benches, Runner};
+
+//The factor used for the current gas table (measured on a home laptop)
+const DEFAULT_GAS_PER_NS:f64 = 0.6;
+//The number of entries in the store before the store is measured
+const DEFAULT_STATE_SIZE:usize = 200_000;
+
+fn parse<T:std::str::FromStr>(args:&[String], pos:usize) -> Result<T> {
+    match args.get(pos+1).map(|a|a.parse()) {
+        Some(Ok(val)) => Ok(val),
+        _ => owned_error(||format!("{} needs a numeric value", args[pos]))
+    }
+}
+
+//Usage: sanskrit_bench [-runs <n>] [-reps <n>] [-gas-per-ns <f>] [-only <prefix>] [-sled <dir>] [-state <n>] [-out <dir>]
+// Measures all opcodes, system calls and the store and emits the regenerated gas tables
+// The sled directory should be empty, the store is filled with -state entries before it is measured
+// Should be run in release mode on the reference hardware
+pub fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let mut settings = Settings { runs: 11, repetitions: 256, gas_per_ns: DEFAULT_GAS_PER_NS };
+    let mut only:Option<String> = None;
+    let mut sled:Option<String> = None;
+    let mut out:Option<String> = None;
+    let mut state = DEFAULT_STATE_SIZE;
+
+    let mut pos = 1;
+    while pos < args.len() {
+        match args[pos].as_str() {
+            "-runs" => settings.runs = parse(&args, pos)?,
+            "-reps" => settings.repetitions = parse(&args, pos)?,
+            "-gas-per-ns" => settings.gas_per_ns = parse(&args, pos)?,
+            "-only" => only = args.get(pos+1).cloned(),
+            "-sled" => sled = args.get(pos+1).cloned(),
+            "-state" => state = parse(&args, pos)?,
+            "-out" => out = args.get(pos+1).cloned(),
+            _ => return error(||"usage: sanskrit_bench [-runs <n>] [-reps <n>] [-gas-per-ns <f>] [-only <prefix>] [-sled <dir>] [-state <n>] [-out <dir>]")
+        }
+        pos += 2;
+    }
+    if settings.runs == 0 || settings.repetitions == 0 {
+        return error(||"runs and reps must be positive")
+    }
+
+    let mut measurements = Vec::new();
+    let mut runner = Runner::new();
+    for bench in benches() {
+        if let Some(ref prefix) = only {
+            if !bench.name.starts_with(prefix.as_str()) {
+                continue
+            }
+        }
+        let measurement = runner.measure(&bench, &settings)?;
+        let model = measurement.fit();
+        println!("{}: {:.1}ns + {:.3}ns * x", measurement.name, model.constant, model.per_unit);
+        measurements.push(measurement);
+    }
+
+    //The store is only measured on a full run
+    if only.is_none() {
+        let store_measurements = match sled {
+            Some(dir) => store::measure(&SledStore::new(Path::new(&dir), BTreeSet::new()), &|store, class| store.flush(class), state, &settings)?,
+            None => store::measure(&BTreeMapStore::new(), &|_, _| {}, state, &settings)?,
+        };
+        for measurement in store_measurements {
+            let model = measurement.fit();
+            println!("{}: {:.1}ns + {:.3}ns * x", measurement.name, model.constant, model.per_unit);
+            measurements.push(measurement);
+        }
+    }
+
+    let results = Results::new(&measurements, settings);
+    let mut files = vec![("opcode_costs.rs", results.opcode_costs())];
+    if only.is_none() {
+        files.push(("gas_table.rs", results.gas_table()?));
+        files.push(("processing_costs.rs", results.processing_costs()?));
+        files.push(("costs.rs", results.external_costs()?));
+    }
+
+    for (name, content) in files {
+        match out {
+            Some(ref dir) => if fs::write(Path::new(dir).join(name), content).is_err() {
+                return owned_error(||format!("could not write {}", name))
+            },
+            None => println!("\n//{}\n{}", name, content),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+//How often a program is run before the measured runs start
+pub const WARMUP_RUNS:usize = 3;
+
+//The settings shared by all benchmarks
+#[derive(Copy, Clone, Debug)]
+pub struct Settings {
+    //how many measured runs are done per sample (the median is taken)
+    pub runs:usize,
+    //how often the measured operation is repeated inside a single run
+    pub repetitions:usize,
+    //how many gas units one nanosecond is worth on the measuring machine
+    pub gas_per_ns:f64,
+}
+
+//The result of one benchmark: the cost (in ns) of a single operation for each input size
+#[derive(Clone, Debug)]
+pub struct Measurement {
+    pub name:String,
+    pub samples:Vec<(u64,f64)>,
+}
+
+//A linear cost model: constant + per_unit*x (both in ns)
+#[derive(Copy, Clone, Debug)]
+pub struct Model {
+    pub constant:f64,
+    pub per_unit:f64,
+}
+
+impl Model {
+    //A model for an operation which does not depend on the input size
+    pub fn constant(constant:f64) -> Self {
+        Model { constant, per_unit: 0.0 }
+    }
+
+    //Removes the cost of another model (used to strip the cost of helper opcodes from a measurement)
+    pub fn minus(&self, other:&Model) -> Self {
+        Model {
+            constant: (self.constant - other.constant).max(0.0),
+            per_unit: (self.per_unit - other.per_unit).max(0.0)
+        }
+    }
+
+    //Converts the model to gas (rounded up - no fractions)
+    pub fn to_gas(self, settings:&Settings) -> (u64,u64) {
+        (to_gas(self.constant, settings), to_gas(self.per_unit, settings))
+    }
+
+    //Converts the model to a gas fraction (constant, multiplier, divider) as used by the data processing costs
+    pub fn to_gas_fraction(self, settings:&Settings, divider:u64) -> (u64,u64,u64) {
+        let multiplier = (self.per_unit * settings.gas_per_ns * divider as f64).ceil().max(0.0) as u64;
+        (to_gas(self.constant, settings), multiplier, divider)
+    }
+}
+
+pub fn to_gas(ns:f64, settings:&Settings) -> u64 {
+    (ns * settings.gas_per_ns).ceil().max(0.0) as u64
+}
+
+impl Measurement {
+    //Fits a linear model with least squares (a single sample gives a constant model)
+    pub fn fit(&self) -> Model {
+        let n = self.samples.len() as f64;
+        if self.samples.len() < 2 {
+            return Model::constant(self.samples.first().map(|(_,y)|*y).unwrap_or(0.0).max(0.0))
+        }
+        let mean_x = self.samples.iter().map(|(x,_)|*x as f64).sum::<f64>() / n;
+        let mean_y = self.samples.iter().map(|(_,y)|*y).sum::<f64>() / n;
+        let mut cov = 0.0;
+        let mut var = 0.0;
+        for (x,y) in &self.samples {
+            let dx = *x as f64 - mean_x;
+            cov += dx * (*y - mean_y);
+            var += dx * dx;
+        }
+        let per_unit = if var == 0.0 { 0.0 } else { (cov / var).max(0.0) };
+        let constant = (mean_y - per_unit * mean_x).max(0.0);
+        Model { constant, per_unit }
+    }
+}
+
+//The median of some timings in ns
+pub fn median(mut timings:Vec<Duration>) -> f64 {
+    if timings.is_empty() {
+        return 0.0
+    }
+    timings.sort();
+    timings[timings.len()/2].as_nanos() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS:Settings = Settings { runs: 1, repetitions: 1, gas_per_ns: 0.5 };
+
+    fn measurement(samples:&[(u64,f64)]) -> Measurement {
+        Measurement{ name: "Test".into(), samples: samples.to_vec() }
+    }
+
+    #[test]
+    fn fit_finds_the_line_through_the_samples() {
+        let model = measurement(&[(0, 10.0), (10, 30.0), (20, 50.0)]).fit();
+        assert!((model.constant - 10.0).abs() < 1e-9);
+        assert!((model.per_unit - 2.0).abs() < 1e-9);
+        //noise around the line is averaged out
+        let model = measurement(&[(0, 11.0), (10, 29.0), (20, 51.0), (30, 69.0)]).fit();
+        assert!((model.per_unit - 1.96).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fit_never_produces_negative_costs() {
+        let model = measurement(&[(0, 50.0), (10, 30.0), (20, 10.0)]).fit();
+        assert_eq!((model.constant, model.per_unit), (30.0, 0.0));
+        let model = measurement(&[(10, 1.0), (20, 100.0)]).fit();
+        assert_eq!(model.constant, 0.0);
+        //a single sample or a single size gives a constant model
+        let model = measurement(&[(7, 12.0)]).fit();
+        assert_eq!((model.constant, model.per_unit), (12.0, 0.0));
+        let model = measurement(&[(7, 12.0), (7, 14.0)]).fit();
+        assert_eq!((model.constant, model.per_unit), (13.0, 0.0));
+        let model = measurement(&[]).fit();
+        assert_eq!((model.constant, model.per_unit), (0.0, 0.0));
+    }
+
+    #[test]
+    fn gas_is_rounded_up() {
+        assert_eq!(to_gas(10.0, &SETTINGS), 5);
+        assert_eq!(to_gas(10.1, &SETTINGS), 6);
+        assert_eq!(to_gas(-3.0, &SETTINGS), 0);
+        assert_eq!(Model{ constant: 3.0, per_unit: 0.5 }.to_gas(&SETTINGS), (2, 1));
+    }
+
+    #[test]
+    fn gas_fractions_keep_the_precision_of_the_divider() {
+        //0.03 gas per unit: 3/100
+        let model = Model{ constant: 20.0, per_unit: 0.06 };
+        assert_eq!(model.to_gas_fraction(&SETTINGS, 100), (10, 3, 100));
+        //rounded up to the precision of the divider
+        assert_eq!(model.to_gas_fraction(&SETTINGS, 10), (10, 1, 10));
+        let model = Model{ constant: 0.0, per_unit: 5.0 };
+        assert_eq!(model.to_gas_fraction(&SETTINGS, 1), (0, 3, 1));
+    }
+}
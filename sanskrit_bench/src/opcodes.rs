@@ -0,0 +1,377 @@
+use std::time::{Duration, Instant};
+use ed25519_consensus::{SigningKey, VerificationKey};
+use sanskrit_common::arena::{Heap, HeapArena};
+use sanskrit_common::errors::*;
+use sanskrit_common::model::{Ptr, SlicePtr, Tag, ValueRef};
+use sanskrit_interpreter::interpreter::{ExecutionContext, Frame};
+use sanskrit_interpreter::model::{Entry, Exp, Kind, LitDesc, OpCode};
+#[cfg(feature = "dynamic_gas")]
+use sanskrit_interpreter::model::TxTFunction;
+use sanskrit_interpreter::externals::{RuntimeExternals, SignatureBatch};
+use sanskrit_default_externals::ServerExternals;
+use crate::measure::*;
+
+//Limits of the interpreter memory (the stack is limited by the size of a ValueRef)
+const STACK_SIZE:usize = u16::MAX as usize;
+const FRAME_SIZE:usize = 1024;
+const VALUE_SIZE:usize = 64*1024*1024;
+const CODE_SIZE:usize = 16*1024*1024;
+
+//The input sizes used for opcodes which depend on a number of fields, arguments or returns
+pub const FIELD_SIZES:[u64;7] = [0, 1, 2, 4, 8, 16, 32];
+//The input sizes used for opcodes which depend on the length of some data
+pub const DATA_SIZES:[u64;6] = [0, 32, 128, 512, 2048, 4096];
+//The number of repetitions used for measuring repeated calls
+pub const REPETITION_SIZES:[u64;7] = [1, 2, 4, 8, 16, 32, 64];
+
+//All the integer kinds (Data is benchmarked separately as it depends on the data length)
+pub const INT_KINDS:[Kind;11] = [Kind::I8, Kind::U8, Kind::I16, Kind::U16, Kind::I32, Kind::U32, Kind::I64, Kind::U64, Kind::I128, Kind::U128, Kind::U256];
+
+#[cfg(feature = "dynamic_gas")]
+type Function<'c> = TxTFunction<'c>;
+#[cfg(not(feature = "dynamic_gas"))]
+type Function<'c> = Ptr<'c, Exp<'c>>;
+
+//The code of a benchmark: the measured opcodes are executed after the setup produced their inputs
+// the callee is the function with index 0 (used by the call benchmarks)
+pub struct Body<'c> {
+    callee:Vec<OpCode<'c>>,
+    setup:Vec<OpCode<'c>>,
+    measured:Vec<OpCode<'c>>,
+}
+
+type Builder = Box<dyn for<'c,'h> Fn(&'c HeapArena<'h>, u64, usize) -> Result<Body<'c>>>;
+
+//A benchmark measuring one opcode for each of its input sizes
+pub struct Bench {
+    pub name:String,
+    pub sizes:Vec<u64>,
+    build:Builder,
+    //signature checks are collected and verified as a batch at the end of each run
+    batched:bool,
+}
+
+//Resolves the setup values for one repetition of the measured opcode
+struct Refs {
+    depth:usize
+}
+
+impl Refs {
+    //the j-th value produced by the setup
+    fn at(&self, j:usize) -> ValueRef {
+        ValueRef((self.depth - 1 - j) as u16)
+    }
+
+    fn repeated(&self, j:usize, times:u64) -> Vec<ValueRef> {
+        (0..times).map(|_|self.at(j)).collect()
+    }
+}
+
+//Repeats an opcode that pushes a fixed number of values (as often as the stack allows)
+fn repeat<'c>(n:usize, setup:usize, pushes:usize, op:impl Fn(&Refs) -> Result<OpCode<'c>>) -> Result<Vec<OpCode<'c>>> {
+    let n = n.min((STACK_SIZE - setup - 1) / pushes.max(1));
+    (0..n).map(|k| op(&Refs{ depth: setup + k*pushes })).collect()
+}
+
+fn function<'c, 'h>(code:&'c HeapArena<'h>, ops:&[OpCode<'c>]) -> Result<Function<'c>> {
+    let body = code.alloc(Exp(code.copy_alloc_slice(ops)?));
+    #[cfg(feature = "dynamic_gas")]
+    return Ok(TxTFunction{ gas: 0, body });
+    #[cfg(not(feature = "dynamic_gas"))]
+    return Ok(body);
+}
+
+fn empty<'c, 'h>(code:&'c HeapArena<'h>) -> Ptr<'c, Exp<'c>> {
+    code.alloc(Exp(SlicePtr::empty()))
+}
+
+fn data<'c, 'h>(code:&'c HeapArena<'h>, len:u64) -> Result<OpCode<'c>> {
+    let bytes:Vec<u8> = (0..len).map(|i| i as u8).collect();
+    Ok(OpCode::Data(code.copy_alloc_slice(&bytes)?))
+}
+
+pub fn kind_width(kind:Kind) -> u64 {
+    match kind {
+        Kind::I8 | Kind::U8 => 1,
+        Kind::I16 | Kind::U16 => 2,
+        Kind::I32 | Kind::U32 => 4,
+        Kind::I64 | Kind::U64 => 8,
+        Kind::I128 | Kind::U128 => 16,
+        Kind::U256 => 32,
+        Kind::Data => 0,
+    }
+}
+
+fn lit_desc(kind:Kind) -> LitDesc {
+    match kind {
+        Kind::I8 => LitDesc::I8,
+        Kind::U8 => LitDesc::U8,
+        Kind::I16 => LitDesc::I16,
+        Kind::U16 => LitDesc::U16,
+        Kind::I32 => LitDesc::I32,
+        Kind::U32 => LitDesc::U32,
+        Kind::I64 => LitDesc::I64,
+        Kind::U64 => LitDesc::U64,
+        Kind::I128 => LitDesc::I128,
+        Kind::U128 => LitDesc::U128,
+        Kind::U256 => LitDesc::U256,
+        Kind::Data => LitDesc::Data,
+    }
+}
+
+//The literal 1 of a kind (chosen so that no arithmetic opcode over- or underflows)
+fn one<'c, 'h>(code:&'c HeapArena<'h>, kind:Kind) -> Result<OpCode<'c>> {
+    let mut bytes = vec![0; kind_width(kind) as usize];
+    if let Some(last) = bytes.last_mut() {
+        *last = 1;
+    }
+    Ok(OpCode::SpecialLit(code.copy_alloc_slice(&bytes)?, lit_desc(kind)))
+}
+
+fn bench(name:String, sizes:&[u64], build:Builder) -> Bench {
+    Bench{ name, sizes: sizes.to_vec(), build, batched: false }
+}
+
+//An opcode with a fixed cost that pushes a single value and consumes the values of the setup
+fn simple(name:String, setup:impl for<'c,'h> Fn(&'c HeapArena<'h>) -> Result<Vec<OpCode<'c>>> + 'static, op:impl Fn(&Refs) -> OpCode<'static> + 'static) -> Bench {
+    bench(name, &[0], Box::new(move |code, _, n| {
+        let setup = setup(code)?;
+        let measured = repeat(n, setup.len(), 1, |r| Ok(op(r)))?;
+        Ok(Body{ callee: vec![], setup, measured })
+    }))
+}
+
+fn binary(name:&str, kind:Kind, op:fn(Kind, ValueRef, ValueRef) -> OpCode<'static>) -> Bench {
+    simple(format!("{}({:?})", name, kind), move |code| Ok(vec![one(code, kind)?, one(code, kind)?]), move |r| op(kind, r.at(0), r.at(1)))
+}
+
+fn binary_data(name:&str, op:fn(Kind, ValueRef, ValueRef) -> OpCode<'static>) -> Bench {
+    bench(format!("{}(Data)", name), &DATA_SIZES, Box::new(move |code, x, n| {
+        let setup = vec![data(code, x)?, data(code, x)?];
+        let measured = repeat(n, setup.len(), 1, |r| Ok(op(Kind::Data, r.at(0), r.at(1))))?;
+        Ok(Body{ callee: vec![], setup, measured })
+    }))
+}
+
+//An adt with x fields (the setup produces a void value followed by the adt)
+fn adt<'c, 'h>(code:&'c HeapArena<'h>, fields:u64) -> Result<Vec<OpCode<'c>>> {
+    let refs:Vec<ValueRef> = (0..fields).map(|_|ValueRef(0)).collect();
+    Ok(vec![OpCode::Void, OpCode::Pack(Tag(0), code.copy_alloc_slice(&refs)?)])
+}
+
+//Produces the inputs of a signature verification over a message of len bytes
+fn signed<'c, 'h>(code:&'c HeapArena<'h>, len:u64) -> Result<Vec<OpCode<'c>>> {
+    let key = SigningKey::from([7; 32]);
+    let msg:Vec<u8> = (0..len).map(|i| i as u8).collect();
+    let sig = key.sign(&msg);
+    let pk = VerificationKey::from(&key);
+    Ok(vec![
+        OpCode::Data(code.copy_alloc_slice(&msg)?),
+        OpCode::Data(code.copy_alloc_slice(&pk.to_bytes())?),
+        OpCode::Data(code.copy_alloc_slice(&sig.to_bytes())?),
+    ])
+}
+
+//All the opcode and system call benchmarks
+pub fn benches() -> Vec<Bench> {
+    let mut res = vec![
+        simple("Void".into(), |_| Ok(vec![]), |_| OpCode::Void),
+        simple("Id".into(), |_| Ok(vec![OpCode::Void]), |r| OpCode::Id(r.at(0))),
+        bench("Data".into(), &DATA_SIZES, Box::new(|code, x, n| {
+            let lit = data(code, x)?;
+            Ok(Body{ callee: vec![], setup: vec![], measured: repeat(n, 0, 1, |_| Ok(lit))? })
+        })),
+        bench("Let".into(), &[0], Box::new(|code, _, n| {
+            let bind = empty(code);
+            Ok(Body{ callee: vec![], setup: vec![], measured: repeat(n, 0, 0, |_| Ok(OpCode::Let(bind)))? })
+        })),
+        bench("Unpack".into(), &FIELD_SIZES, Box::new(|code, x, n| {
+            let setup = adt(code, x)?;
+            let measured = repeat(n, setup.len(), x as usize, |r| Ok(OpCode::Unpack(r.at(1))))?;
+            Ok(Body{ callee: vec![], setup, measured })
+        })),
+        simple("Get".into(), |code| adt(code, 1), |r| OpCode::Get(r.at(1), 0)),
+        bench("Switch".into(), &FIELD_SIZES, Box::new(|code, x, n| {
+            let setup = adt(code, x)?;
+            let cases = code.copy_alloc_slice(&[empty(code)])?;
+            let measured = repeat(n, setup.len(), 0, |r| Ok(OpCode::Switch(r.at(1), cases)))?;
+            Ok(Body{ callee: vec![], setup, measured })
+        })),
+        bench("Pack".into(), &FIELD_SIZES, Box::new(|code, x, n| {
+            let measured = repeat(n, 1, 1, |r| Ok(OpCode::Pack(Tag(0), code.copy_alloc_slice(&r.repeated(0, x))?)))?;
+            Ok(Body{ callee: vec![], setup: vec![OpCode::Void], measured })
+        })),
+        bench("CreateSig".into(), &FIELD_SIZES, Box::new(|code, x, n| {
+            let measured = repeat(n, 1, 1, |r| Ok(OpCode::CreateSig(0, code.copy_alloc_slice(&r.repeated(0, x))?)))?;
+            Ok(Body{ callee: vec![], setup: vec![OpCode::Void], measured })
+        })),
+        bench("InvokeSig".into(), &FIELD_SIZES, Box::new(|code, x, n| {
+            let setup = vec![OpCode::Void, OpCode::CreateSig(0, SlicePtr::empty())];
+            let measured = repeat(n, setup.len(), 0, |r| Ok(OpCode::InvokeSig(r.at(1), code.copy_alloc_slice(&r.repeated(0, x))?)))?;
+            Ok(Body{ callee: vec![], setup, measured })
+        })),
+        bench("Invoke".into(), &FIELD_SIZES, Box::new(|code, x, n| {
+            let measured = repeat(n, 1, 0, |r| Ok(OpCode::Invoke(0, code.copy_alloc_slice(&r.repeated(0, x))?)))?;
+            Ok(Body{ callee: vec![], setup: vec![OpCode::Void], measured })
+        })),
+        //The condition never reaches the abort tag, so each invoke runs x times and then rolls back
+        // the cost of the surrounding Try and the Rollback is measured separately
+        bench("RepeatedInvoke".into(), &REPETITION_SIZES, Box::new(|code, x, n| {
+            let callee = vec![OpCode::Return(code.copy_alloc_slice(&[ValueRef(0)])?)];
+            let setup = vec![OpCode::Pack(Tag(0), SlicePtr::empty())];
+            let (succ, fail) = (empty(code), empty(code));
+            let measured = repeat(n, setup.len(), 0, |r| {
+                let rep = OpCode::RepeatedInvoke(0, code.copy_alloc_slice(&[r.at(0)])?, ValueRef(0), Tag(1), x as u8);
+                Ok(OpCode::Try(code.alloc(rep), succ, fail))
+            })?;
+            Ok(Body{ callee, setup, measured })
+        })),
+        bench("Try".into(), &[0], Box::new(|code, _, n| {
+            let (op, succ, fail) = (code.alloc(OpCode::Void), empty(code), empty(code));
+            Ok(Body{ callee: vec![], setup: vec![], measured: repeat(n, 0, 0, |_| Ok(OpCode::Try(op, succ, fail)))? })
+        })),
+        //Measured inside a Try (a top level Rollback would abort the run)
+        bench("Rollback".into(), &[0], Box::new(|code, _, n| {
+            let (op, succ, fail) = (code.alloc(OpCode::Rollback), empty(code), empty(code));
+            Ok(Body{ callee: vec![], setup: vec![], measured: repeat(n, 0, 0, |_| Ok(OpCode::Try(op, succ, fail)))? })
+        })),
+        bench("Return".into(), &FIELD_SIZES, Box::new(|code, x, n| {
+            let measured = repeat(n, 1, x as usize, |r| Ok(OpCode::Return(code.copy_alloc_slice(&r.repeated(0, x))?)))?;
+            Ok(Body{ callee: vec![], setup: vec![OpCode::Void], measured })
+        })),
+    ];
+
+    for kind in INT_KINDS {
+        res.push(bench(format!("SpecialLit({:?})", kind), &[0], Box::new(move |code, _, n| {
+            let lit = one(code, kind)?;
+            Ok(Body{ callee: vec![], setup: vec![], measured: repeat(n, 0, 1, |_| Ok(lit))? })
+        })));
+        res.push(binary("And", kind, OpCode::And));
+        res.push(binary("Or", kind, OpCode::Or));
+        res.push(binary("Xor", kind, OpCode::Xor));
+        res.push(simple(format!("Not({:?})", kind), move |code| Ok(vec![one(code, kind)?]), move |r| OpCode::Not(kind, r.at(0))));
+        res.push(binary("Add", kind, OpCode::Add));
+        res.push(binary("Sub", kind, OpCode::Sub));
+        res.push(binary("Mul", kind, OpCode::Mul));
+        res.push(binary("Div", kind, OpCode::Div));
+        res.push(binary("Eq", kind, OpCode::Eq));
+        res.push(binary("Lt", kind, OpCode::Lt));
+        res.push(binary("Gt", kind, OpCode::Gt));
+        res.push(binary("Lte", kind, OpCode::Lte));
+        res.push(binary("Gte", kind, OpCode::Gte));
+        res.push(simple(format!("ToData({:?})", kind), move |code| Ok(vec![one(code, kind)?]), move |r| OpCode::ToData(kind, r.at(0))));
+        res.push(simple(format!("FromData({:?})", kind), move |code| Ok(vec![data(code, kind_width(kind))?]), move |r| OpCode::FromData(kind, r.at(0))));
+    }
+
+    res.push(binary_data("And", OpCode::And));
+    res.push(binary_data("Or", OpCode::Or));
+    res.push(binary_data("Xor", OpCode::Xor));
+    res.push(binary_data("Eq", OpCode::Eq));
+    res.push(bench("Not(Data)".into(), &DATA_SIZES, Box::new(|code, x, n| {
+        let setup = vec![data(code, x)?];
+        let measured = repeat(n, setup.len(), 1, |r| Ok(OpCode::Not(Kind::Data, r.at(0))))?;
+        Ok(Body{ callee: vec![], setup, measured })
+    })));
+
+    //The typed system calls (see ServerExternals::typed_system_call)
+    for (id, name) in [(0, "plain_hash"), (1, "sha256"), (2, "keccak256")] {
+        res.push(bench(format!("TypedSysInvoke({})", name), &DATA_SIZES, Box::new(move |code, x, n| {
+            let setup = vec![data(code, x)?];
+            let measured = repeat(n, setup.len(), 1, |r| Ok(OpCode::TypedSysInvoke(id, Kind::Data, code.copy_alloc_slice(&[r.at(0)])?)))?;
+            Ok(Body{ callee: vec![], setup, measured })
+        })));
+    }
+
+    //The untyped system calls (see ServerExternals::system_call)
+    res.push(bench("SysInvoke(join_hash)".into(), &[0], Box::new(|code, _, n| {
+        let setup = vec![data(code, 20)?, data(code, 20)?];
+        let measured = repeat(n, setup.len(), 1, |r| Ok(OpCode::SysInvoke(0, code.copy_alloc_slice(&[r.at(0), r.at(1)])?)))?;
+        Ok(Body{ callee: vec![], setup, measured })
+    })));
+    //The batched verification is resolved at the end of each run so its share is included
+    for (name, batched) in [("ecdsa_verify", false), ("ecdsa_verify_batched", true)] {
+        let mut verify = bench(format!("SysInvoke({})", name), &DATA_SIZES, Box::new(|code, x, n| {
+            let setup = signed(code, x)?;
+            let measured = repeat(n, setup.len(), 1, |r| Ok(OpCode::SysInvoke(1, code.copy_alloc_slice(&[r.at(0), r.at(1), r.at(2)])?)))?;
+            Ok(Body{ callee: vec![], setup, measured })
+        }));
+        verify.batched = batched;
+        res.push(verify);
+    }
+
+    #[cfg(feature = "dynamic_gas")]
+    res.push(simple("ConsumeGas".into(), |_| Ok(vec![]), |_| OpCode::ConsumeGas(1)));
+
+    res
+}
+
+//Executes the benchmark programs on a reused heap
+pub struct Runner {
+    heap:Option<Heap>,
+}
+
+impl Runner {
+    pub fn new() -> Self {
+        let structural = 2*Heap::max_elems_space::<Entry>(STACK_SIZE) + Heap::max_elems_space::<Frame>(FRAME_SIZE);
+        Runner{ heap: Some(Heap::new(structural + VALUE_SIZE + 2*Heap::ARENA_ALIGN, 1.0)) }
+    }
+
+    fn execute(heap:&Heap, functions:&[Function], batched:bool) -> Result<Duration> {
+        let structural = heap.new_arena(2*Heap::max_elems_space::<Entry>(STACK_SIZE) + Heap::max_elems_space::<Frame>(FRAME_SIZE));
+        let alloc = heap.new_virtual_arena(VALUE_SIZE);
+        let mut stack = structural.alloc_stack::<Entry>(STACK_SIZE);
+        let mut frames = structural.alloc_stack::<Frame>(FRAME_SIZE);
+        let mut return_stack = structural.alloc_stack::<Entry>(STACK_SIZE);
+        let mut signatures = SignatureBatch::new();
+        let start = Instant::now();
+        ExecutionContext::interpret::<ServerExternals>(functions, &mut stack, &mut frames, &mut return_stack, &alloc, if batched { Some(&mut signatures) } else { None }, #[cfg(feature = "dynamic_gas")] u64::MAX)?;
+        if !ServerExternals::verify_signature_batch(&signatures) {
+            return error(||"Signature batch did not verify")
+        }
+        Ok(start.elapsed())
+    }
+
+    //The median execution time of a program
+    fn time(&mut self, functions:&[Function], batched:bool, settings:&Settings) -> Result<f64> {
+        let mut timings = Vec::with_capacity(settings.runs);
+        for i in 0..(WARMUP_RUNS + settings.runs) {
+            let heap = self.heap.take().expect("heap is always returned").reuse();
+            let res = Runner::execute(&heap, functions, batched);
+            self.heap = Some(heap);
+            let elapsed = res?;
+            if i >= WARMUP_RUNS {
+                timings.push(elapsed);
+            }
+        }
+        Ok(median(timings))
+    }
+
+    //The cost of a single measured opcode (the setup is measured separately and subtracted)
+    fn sample(&mut self, bench:&Bench, x:u64, settings:&Settings) -> Result<f64> {
+        let code_heap = Heap::new(CODE_SIZE + Heap::ARENA_ALIGN, 1.0);
+        let code = code_heap.new_arena(CODE_SIZE);
+        let body = (bench.build)(&code, x, settings.repetitions)?;
+        if body.measured.is_empty() {
+            return error(||"Benchmark does not contain any measured opcodes")
+        }
+        //A Void is appended so that no measured opcode is in tail position
+        let mut full = body.setup.clone();
+        full.extend_from_slice(&body.measured);
+        full.push(OpCode::Void);
+        let mut base = body.setup.clone();
+        base.push(OpCode::Void);
+
+        let callee = function(&code, &body.callee)?;
+        let full_time = self.time(&[callee, function(&code, &full)?], bench.batched, settings)?;
+        let base_time = self.time(&[callee, function(&code, &base)?], bench.batched, settings)?;
+        Ok(((full_time - base_time) / body.measured.len() as f64).max(0.0))
+    }
+
+    pub fn measure(&mut self, bench:&Bench, settings:&Settings) -> Result<Measurement> {
+        let mut samples = Vec::with_capacity(bench.sizes.len());
+        for x in &bench.sizes {
+            samples.push((*x, self.sample(bench, *x, settings)?));
+        }
+        Ok(Measurement{ name: bench.name.clone(), samples })
+    }
+}
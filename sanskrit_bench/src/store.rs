@@ -0,0 +1,145 @@
+use std::time::{Duration, Instant};
+use sanskrit_common::arena::{Heap, VirtualHeapArena};
+use sanskrit_common::encoding::{Parser, Serializer};
+use sanskrit_common::errors::*;
+use sanskrit_common::model::{Hash, SlicePtr};
+use sanskrit_common::store::{store_hash, StorageClass, Store};
+use sanskrit_interpreter::model::{Entry, ValueSchema};
+use crate::measure::*;
+
+//The sizes of the stored values (values are limited by the u16 size of ValueSchema::Data)
+pub const VALUE_SIZES:[u64;6] = [0, 64, 256, 1024, 4096, 16384];
+//The structural depth is irrelevant for plain data values
+const MAX_DEPTH:usize = 16;
+//The size of the values that fill the state (the size of a typical entry)
+const STATE_VALUE_SIZE:usize = 128;
+//How many state entries are written before each commit and flush
+const STATE_BATCH:usize = 10_000;
+
+//The timings of one run over all the keys
+struct Run {
+    write:Duration,
+    load:Duration,
+    encode:Duration,
+    parse:Duration,
+    copy:Duration,
+}
+
+//The keys look random (like the hashes the runtime uses as keys), so consecutive keys are spread over the whole store
+fn key(space:&str, i:usize) -> Hash {
+    store_hash(&[space.as_bytes(), &(i as u64).to_be_bytes()])
+}
+
+//Writes the entries the benchmark does not touch, so the store has the depth of a real state
+fn fill_state<S:Store>(store:&S, flush:&dyn Fn(&S, StorageClass), entries:usize) -> Result<()> {
+    let value = vec![0xAB; STATE_VALUE_SIZE];
+    for i in 0..entries {
+        store.set(StorageClass::EntryValue, key("state", i), value.clone())?;
+        if (i+1) % STATE_BATCH == 0 || i+1 == entries {
+            store.commit(StorageClass::EntryValue);
+            flush(store, StorageClass::EntryValue);
+        }
+    }
+    Ok(())
+}
+
+//Writes the values the load phase reads later (they are flushed and never touched before they are loaded)
+fn fill_cold<S:Store>(store:&S, flush:&dyn Fn(&S, StorageClass), space:&str, data:&[u8], entries:usize) -> Result<()> {
+    for i in 0..entries {
+        store.set(StorageClass::EntryValue, key(space, i), data.to_vec())?;
+    }
+    store.commit(StorageClass::EntryValue);
+    flush(store, StorageClass::EntryValue);
+    Ok(())
+}
+
+//Writes fresh keys, loads cold keys and encodes, parses and copies one value per key
+// first is the index of the first key of this run (each run uses its own keys)
+fn run<S:Store>(store:&S, flush:&dyn Fn(&S, StorageClass), values:&[Entry], schema:ValueSchema, size:u64, first:usize, alloc:&VirtualHeapArena) -> Result<Run> {
+    let written = format!("written{}", size);
+    let cold = format!("cold{}", size);
+
+    let start = Instant::now();
+    let mut encoded = Vec::with_capacity(values.len());
+    for value in values {
+        let mut s = Serializer::new(MAX_DEPTH);
+        schema.serialize_value(*value, &mut s)?;
+        encoded.push(s.extract());
+    }
+    let encode = start.elapsed();
+
+    //the node commits after each section and flushes after each block, both are part of the write cost
+    let start = Instant::now();
+    for (i, data) in encoded.into_iter().enumerate() {
+        store.set(StorageClass::EntryValue, key(&written, first + i), data)?;
+    }
+    store.commit(StorageClass::EntryValue);
+    flush(store, StorageClass::EntryValue);
+    let write = start.elapsed();
+
+    let start = Instant::now();
+    let mut loaded = Vec::with_capacity(values.len());
+    for i in 0..values.len() {
+        loaded.push(store.get(StorageClass::EntryValue, &key(&cold, first + i), |d| d.to_vec())?);
+    }
+    let load = start.elapsed();
+
+    let start = Instant::now();
+    let mut parsed = Vec::with_capacity(values.len());
+    for data in &loaded {
+        let mut parser = Parser::new(data, MAX_DEPTH);
+        parsed.push(schema.parse_value(&mut parser, alloc)?);
+    }
+    let parse = start.elapsed();
+
+    let start = Instant::now();
+    for value in &parsed {
+        alloc.copy_alloc_slice(&unsafe {value.data})?;
+    }
+    let copy = start.elapsed();
+    Ok(Run{ write, load, encode, parse, copy })
+}
+
+//Measures the store and the entry encoding for each value size
+// the store is first filled with state entries, the written and loaded keys are spread over it
+// every loaded key is read exactly once and was flushed before the measurement started
+// flush persists a storage class (a no-op for in memory stores)
+pub fn measure<S:Store>(store:&S, flush:&dyn Fn(&S, StorageClass), state:usize, settings:&Settings) -> Result<Vec<Measurement>> {
+    let names = ["Store::write", "Store::load", "Encoding::serialize", "Encoding::parse", "Copying"];
+    let mut res:Vec<Measurement> = names.iter().map(|n| Measurement{ name: n.to_string(), samples: vec![] }).collect();
+    let n = settings.repetitions;
+    let total_runs = WARMUP_RUNS + settings.runs;
+    let mut heap = Heap::new(2*n*(*VALUE_SIZES.last().unwrap() as usize + 64) + Heap::ARENA_ALIGN, 1.0);
+
+    fill_state(store, flush, state)?;
+    for size in VALUE_SIZES {
+        let bytes:Vec<u8> = (0..size).map(|i| i as u8).collect();
+        let mut s = Serializer::new(MAX_DEPTH);
+        ValueSchema::Data(size as u16).serialize_value(Entry{ data: SlicePtr::wrap(&bytes) }, &mut s)?;
+        fill_cold(store, flush, &format!("cold{}", size), &s.extract(), n*total_runs)?;
+    }
+
+    for size in VALUE_SIZES {
+        let bytes:Vec<u8> = (0..size).map(|i| i as u8).collect();
+        let values:Vec<Entry> = (0..n).map(|_| Entry{ data: SlicePtr::wrap(&bytes) }).collect();
+        let schema = ValueSchema::Data(size as u16);
+
+        let mut runs = Vec::with_capacity(settings.runs);
+        for i in 0..total_runs {
+            heap = heap.reuse();
+            let alloc = heap.new_virtual_arena(2*n*(size as usize + 64));
+            let timings = run(store, flush, &values, schema, size, i*n, &alloc)?;
+            if i >= WARMUP_RUNS {
+                runs.push(timings);
+            }
+        }
+
+        let per_op = |select:fn(&Run) -> Duration| median(runs.iter().map(select).collect()) / n as f64;
+        res[0].samples.push((size, per_op(|r| r.write)));
+        res[1].samples.push((size, per_op(|r| r.load)));
+        res[2].samples.push((size, per_op(|r| r.encode)));
+        res[3].samples.push((size, per_op(|r| r.parse)));
+        res[4].samples.push((size, per_op(|r| r.copy)));
+    }
+    Ok(res)
+}
@@ -0,0 +1,511 @@
+//! Builds transaction bundles
+//!
+//! The builder collects the invokes of a bundle and assigns the indexes of the descriptors, stored entries, literals and witnesses.
+//! Each of them is only included once, no matter how often it is referenced.
+//! When the bundle is built, the gas and memory limits are computed by the verification of the runtime and the witnesses are signed.
+//! Witnesses of other parties can be collected with a partial bundle, which is exchanged until everyone signed.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use alloc::format;
+use alloc::collections::BTreeMap;
+use sanskrit_common::errors::*;
+use sanskrit_common::model::{Hash, SlicePtr, LargeVec};
+use sanskrit_common::store::Store;
+use sanskrit_common::encoding::{Serializer, VirtualSize};
+use sanskrit_common::arena::VirtualHeapArena;
+use sanskrit_common::hashing::HashingDomain;
+use sanskrit_interpreter::model::TransactionDescriptor;
+use sanskrit_runtime::{read_transaction_desc, Context, CONFIG};
+use sanskrit_runtime::direct_stored::{SystemDataManager, StatefulEntryStoreVerifier};
+use sanskrit_runtime::verify::{required_resources, TransactionVerificationContext};
+use sanskrit_runtime::model::*;
+use crate::signer::Signer;
+use crate::partial::{PartialBundle, RequiredSigner, BundleData};
+
+//A scratch pad slot that transfers a value between the transactions of a bundle
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Slot(pub u8);
+
+//A witness produced by a registered signer or co-signer
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct WitnessRef(pub u16);
+
+//Where the value of a parameter comes from
+#[derive(Clone, Debug)]
+pub enum Input {
+    //a serialized value
+    Literal(Vec<u8>),
+    Witness(WitnessRef),
+    //a value created by the system (like the block info)
+    Provided,
+    //an entry from the store
+    Load(ParamMode, Hash),
+    //a value put into the scratch pad by an earlier transaction of the same section
+    Fetch(ParamMode, Slot),
+}
+
+//What happens with a returned value
+#[derive(Copy, Clone, Debug)]
+pub enum Output {
+    Store,
+    Put(Slot),
+    Drop,
+    Log,
+    Fee,
+}
+
+enum Txt {
+    Invoke {
+        desc:u16,
+        params:Vec<ParamRef>,
+        returns:Vec<RetType>,
+    },
+    Deploy {
+        gas_limit:u64,
+        typ:DeployType,
+        data:Vec<u8>,
+    }
+}
+
+struct Section {
+    typ:SectionType,
+    txts:Vec<Txt>,
+}
+
+//signer is None if the witness is provided by another party
+struct Witness<'a> {
+    public_key:Vec<u8>,
+    size:u16,
+    signer:Option<Box<dyn Signer + 'a>>,
+}
+
+pub struct BundleBuilder<'a> {
+    earliest_block:u64,
+    meta:Vec<u8>,
+    sections:Vec<Section>,
+    descriptors:Vec<Hash>,
+    descriptor_index:BTreeMap<Hash,u16>,
+    stored:Vec<Hash>,
+    stored_index:BTreeMap<Hash,u16>,
+    literals:Vec<Vec<u8>>,
+    literal_index:BTreeMap<Vec<u8>,u16>,
+    signers:Vec<Witness<'a>>,
+    slots:u8,
+}
+
+fn next_index(len:usize) -> Result<u16> {
+    if len >= u16::MAX as usize {
+        return error(||"Bundle has to many elements of a kind")
+    }
+    Ok(len as u16)
+}
+
+//The bundle encodes the witness sizes as u16
+fn checked_witness_size(size:usize) -> Result<u16> {
+    if size > u16::MAX as usize {
+        return error(||"Witness is to large")
+    }
+    Ok(size as u16)
+}
+
+impl<'a> BundleBuilder<'a> {
+    //Starts a bundle with an essential section
+    // The bundle is valid from earliest_block until earliest_block + TXT_BLOCK_WINDOW
+    pub fn new(earliest_block:u64) -> Self {
+        BundleBuilder {
+            earliest_block,
+            meta: Vec::new(),
+            sections: alloc::vec![Section{ typ: SectionType::Essential, txts: Vec::new() }],
+            descriptors: Vec::new(),
+            descriptor_index: BTreeMap::new(),
+            stored: Vec::new(),
+            stored_index: BTreeMap::new(),
+            literals: Vec::new(),
+            literal_index: BTreeMap::new(),
+            signers: Vec::new(),
+            slots: 0,
+        }
+    }
+
+    //Meta data is ignored by the runtime but is part of the bundle hash
+    pub fn meta(&mut self, meta:Vec<u8>) -> &mut Self {
+        self.meta = meta;
+        self
+    }
+
+    //Starts a new section, the following invokes are added to it
+    // Only the first section can be essential
+    pub fn section(&mut self, typ:SectionType) -> Result<&mut Self> {
+        if typ == SectionType::Essential {
+            return error(||"Essential Section must be at the beginning of a bundle and only one is allowed")
+        }
+        if self.sections.len() >= u8::MAX as usize {
+            return error(||"to many sections in a bundle only 256 are allowed")
+        }
+        //An unused essential section is dropped so the bundle can start with a custom one
+        if self.sections.len() == 1 && self.sections[0].txts.is_empty() {
+            self.sections.clear();
+        }
+        self.sections.push(Section{ typ, txts: Vec::new() });
+        Ok(self)
+    }
+
+    //Reserves a new scratch pad slot
+    pub fn slot(&mut self) -> Result<Slot> {
+        if self.slots == u8::MAX {
+            return error(||"Bundle has to many scratch pad slots")
+        }
+        self.slots += 1;
+        Ok(Slot(self.slots - 1))
+    }
+
+    //Registers a signer, its witness is created when the bundle is built
+    pub fn signer(&mut self, signer:Box<dyn Signer + 'a>) -> Result<WitnessRef> {
+        let size = checked_witness_size(signer.witness_size())?;
+        let index = next_index(self.signers.len())?;
+        self.signers.push(Witness{ public_key: signer.public_key(), size, signer: Some(signer) });
+        Ok(WitnessRef(index))
+    }
+
+    //Registers a party that adds its witness to the partial bundle later
+    pub fn co_signer(&mut self, public_key:Vec<u8>, witness_size:usize) -> Result<WitnessRef> {
+        let size = checked_witness_size(witness_size)?;
+        let index = next_index(self.signers.len())?;
+        self.signers.push(Witness{ public_key, size, signer: None });
+        Ok(WitnessRef(index))
+    }
+
+    //The descriptors in the order they are included in the bundle
+    pub fn descriptors(&self) -> &[Hash] {
+        &self.descriptors
+    }
+
+    //Adds a call to the deployed transaction with the descriptor desc to the current section
+    pub fn invoke(&mut self, desc:Hash, params:&[Input], returns:&[Output]) -> Result<&mut Self> {
+        //checked upfront so that a failing invoke leaves the builder unchanged
+        for param in params {
+            match *param {
+                Input::Witness(WitnessRef(index)) if index as usize >= self.signers.len() => return error(||"Witness refers to an unknown signer"),
+                Input::Fetch(_, Slot(slot)) if slot >= self.slots => return error(||"Scratch pad slot was not reserved"),
+                _ => {}
+            }
+        }
+        for ret in returns {
+            if let Output::Put(Slot(slot)) = *ret {
+                if slot >= self.slots { return error(||"Scratch pad slot was not reserved") }
+            }
+        }
+        if self.sections.last().unwrap().txts.len() >= u8::MAX as usize {
+            return error(||"to many transactions in a section only 256 are allowed")
+        }
+
+        let desc = match self.descriptor_index.get(&desc) {
+            Some(index) => *index,
+            None => {
+                let index = next_index(self.descriptors.len())?;
+                self.descriptors.push(desc);
+                self.descriptor_index.insert(desc, index);
+                index
+            }
+        };
+
+        let mut param_refs = Vec::with_capacity(params.len());
+        for param in params {
+            param_refs.push(match *param {
+                Input::Literal(ref data) => ParamRef::Literal(match self.literal_index.get(data) {
+                    Some(index) => *index,
+                    None => {
+                        let index = next_index(self.literals.len())?;
+                        self.literals.push(data.clone());
+                        self.literal_index.insert(data.clone(), index);
+                        index
+                    }
+                }),
+                Input::Witness(WitnessRef(index)) => ParamRef::Witness(index),
+                Input::Provided => ParamRef::Provided,
+                Input::Load(mode, ref hash) => ParamRef::Load(mode, match self.stored_index.get(hash) {
+                    Some(index) => *index,
+                    None => {
+                        let index = next_index(self.stored.len())?;
+                        self.stored.push(*hash);
+                        self.stored_index.insert(*hash, index);
+                        index
+                    }
+                }),
+                Input::Fetch(mode, Slot(slot)) => ParamRef::Fetch(mode, slot),
+            })
+        }
+
+        let mut ret_types = Vec::with_capacity(returns.len());
+        for ret in returns {
+            ret_types.push(match *ret {
+                Output::Store => RetType::Store,
+                Output::Put(Slot(slot)) => RetType::Put(slot),
+                Output::Drop => RetType::Drop,
+                Output::Log => RetType::Log,
+                Output::Fee => RetType::Fee,
+            })
+        }
+
+        self.push(Txt::Invoke { desc, params: param_refs, returns: ret_types })
+    }
+
+    //Adds the deploy of a module, transaction or package to the current section
+    pub fn deploy(&mut self, typ:DeployType, gas_limit:u64, data:Vec<u8>) -> Result<&mut Self> {
+        self.push(Txt::Deploy { gas_limit, typ, data })
+    }
+
+    fn push(&mut self, txt:Txt) -> Result<&mut Self> {
+        //There is always at least one section
+        let section = self.sections.last_mut().unwrap();
+        if section.txts.len() >= u8::MAX as usize {
+            return error(||"to many transactions in a section only 256 are allowed")
+        }
+        section.txts.push(txt);
+        Ok(self)
+    }
+
+    //Checks that the invokes match their descriptors (the runtime does not check this before executing them)
+    fn check_invokes(&self, descs:&[TransactionDescriptor]) -> Result<()> {
+        for section in &self.sections {
+            for txt in &section.txts {
+                if let Txt::Invoke { desc, ref params, ref returns } = *txt {
+                    let txt_desc = &descs[desc as usize];
+                    if txt_desc.params.len() != params.len() {
+                        return owned_error(||format!("Expected {} params, provided {}", txt_desc.params.len(), params.len()))
+                    }
+                    if txt_desc.returns.len() != returns.len() {
+                        return owned_error(||format!("Expected {} returns, received {}", txt_desc.returns.len(), returns.len()))
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    //Builds and signs the bundle
+    // the descriptors are read from the store to compute the limits and gas costs of the bundle
+    // SDM defines the costs of provided values and has to match the system the bundle is executed on
+    pub fn build<'c, S:Store, SDM:SystemDataManager<BundleWithHash<'c>>>(&self, store:&S, alloc:&'c VirtualHeapArena) -> Result<BundleWithHash<'c>> {
+        if self.signers.iter().any(|w|w.signer.is_none()) {
+            return error(||"Bundle needs witnesses from other parties (build a partial bundle instead)")
+        }
+        let (mut core, core_data, byte_size) = self.build_core::<_,SDM>(store, alloc)?;
+        core.byte_size = Some(core_data.len());
+        let bundle_hash = HashingDomain::Bundle.hash(&core_data);
+
+        let mut witness = Vec::with_capacity(self.signers.len());
+        for w in &self.signers {
+            //checked above
+            let signer = w.signer.as_ref().unwrap();
+            witness.push(alloc.copy_alloc_slice(&sign_witness(&**signer, w.size as usize, &bundle_hash)?)?);
+        }
+
+        Ok(BundleWithHash {
+            txt_bundle: BaseTransactionBundle {
+                byte_size: Some(byte_size),
+                core,
+                witness: alloc.copy_alloc_slice(&witness)?,
+            },
+            bundle_hash,
+        })
+    }
+
+    //Builds the bundle and adds the witnesses of the local signers, the co-signers add theirs to the returned partial bundle
+    pub fn build_partial<'c, S:Store, SDM:SystemDataManager<BundleWithHash<'c>>>(&self, store:&S, alloc:&'c VirtualHeapArena) -> Result<PartialBundle> {
+        let (_, core_data, _) = self.build_core::<_,SDM>(store, alloc)?;
+        let bundle_hash = HashingDomain::Bundle.hash(&core_data);
+        let mut signers = Vec::with_capacity(self.signers.len());
+        for w in &self.signers {
+            let witness = match w.signer {
+                Some(ref signer) => Some(LargeVec(sign_witness(&**signer, w.size as usize, &bundle_hash)?)),
+                None => None
+            };
+            signers.push(RequiredSigner{ public_key: LargeVec(w.public_key.clone()), witness_size: w.size, witness });
+        }
+        Ok(PartialBundle{ core: BundleData(core_data), signers: LargeVec(signers) })
+    }
+
+    //Produces the core of the bundle (with its gas costs) and returns it together with its serialized form and the size of the whole bundle
+    fn build_core<'c, S:Store, SDM:SystemDataManager<BundleWithHash<'c>>>(&self, store:&S, alloc:&'c VirtualHeapArena) -> Result<(TransactionBundleCore<'c>, Vec<u8>, usize)> {
+        let mut descs = Vec::with_capacity(self.descriptors.len());
+        for hash in &self.descriptors {
+            descs.push(read_transaction_desc(hash, store, alloc)?);
+        }
+
+        self.check_invokes(&descs)?;
+
+        let mut transaction_heap_limit = SlicePtr::<TransactionDescriptor>::SIZE as u32;
+        let mut stack_elem_limit:u16 = 0;
+        let mut stack_frame_limit:u16 = 0;
+        let mut runtime_heap_limit:u16 = 0;
+        for txt_desc in &descs {
+            transaction_heap_limit += txt_desc.virt_size.unwrap() as u32;
+            stack_elem_limit = stack_elem_limit.max(txt_desc.max_stack);
+            stack_frame_limit = stack_frame_limit.max(txt_desc.max_frames);
+            runtime_heap_limit = runtime_heap_limit.max(txt_desc.max_mem);
+        }
+
+        let mut sections = Vec::with_capacity(self.sections.len());
+        for section in &self.sections {
+            let mut txts = Vec::with_capacity(section.txts.len());
+            for txt in &section.txts {
+                txts.push(match *txt {
+                    Txt::Invoke { desc, ref params, ref returns } => Transaction::Invoke(Invoke {
+                        txt_desc: desc,
+                        params: alloc.copy_alloc_slice(params)?,
+                        returns: alloc.copy_alloc_slice(returns)?,
+                    }),
+                    Txt::Deploy { gas_limit, typ, ref data } => Transaction::Deploy(DeployTransaction {
+                        gas_limit,
+                        typ,
+                        data: alloc.copy_alloc_slice(data)?,
+                    }),
+                })
+            }
+            sections.push(BundleSection { typ: section.typ, txts: alloc.copy_alloc_slice(&txts)? });
+        }
+
+        let mut literals = Vec::with_capacity(self.literals.len());
+        for lit in &self.literals {
+            literals.push(alloc.copy_alloc_slice(lit)?);
+        }
+
+        let mut core = TransactionBundleCore {
+            byte_size: None,
+            meta: alloc.copy_alloc_slice(&self.meta)?,
+            earliest_block: self.earliest_block,
+            //The gas and the parameter heap are filled in when they are computed
+            param_heap_limit: 0,
+            scratch_pad_limit: self.slots,
+            essential_gas_cost: 0,
+            total_gas_cost: 0,
+            transaction_heap_limit,
+            stack_elem_limit,
+            stack_frame_limit,
+            runtime_heap_limit,
+            sections: alloc.copy_alloc_slice(&sections)?,
+            descriptors: alloc.copy_alloc_slice(&self.descriptors)?,
+            stored: alloc.copy_alloc_slice(&self.stored)?,
+            literal: alloc.copy_alloc_slice(&literals)?,
+        };
+
+        //The gas values and the parameter heap have a fixed size, so the size does not change when they are filled in
+        let core_size = Serializer::serialize_fully(&core, CONFIG.max_structural_dept)?.len();
+        let witness_size:usize = 2 + self.signers.iter().map(|w|2 + w.size as usize).sum::<usize>();
+        let byte_size = core_size + witness_size;
+
+        //The runtime computes the costs, it only needs to know the number of witnesses (their size is in the byte size)
+        let bundle = BundleWithHash {
+            txt_bundle: BaseTransactionBundle {
+                byte_size: Some(byte_size),
+                core: core.clone(),
+                witness: alloc.repeated_slice(SlicePtr::empty(), self.signers.len())?,
+            },
+            bundle_hash: [0; 20],
+        };
+        let required = {
+            let desc_alloc = alloc.temp_arena()?;
+            let verifier:StatefulEntryStoreVerifier<BundleWithHash<'c>, SDM> = TransactionVerificationContext::<S, _>::new();
+            required_resources(&verifier, &Context{ store, txt_bundle: &bundle }, &desc_alloc)?
+        };
+        if required.param_heap > u16::MAX as u32 {
+            return error(||"Bundle needs to much parameter heap space")
+        }
+        core.param_heap_limit = required.param_heap as u16;
+        core.essential_gas_cost = required.essential_gas;
+        core.total_gas_cost = required.total_gas;
+
+        let core_data = Serializer::serialize_fully(&core, CONFIG.max_structural_dept)?;
+        Ok((core, core_data, byte_size))
+    }
+}
+
+pub(crate) fn sign_witness(signer:&dyn Signer, size:usize, bundle_hash:&Hash) -> Result<Vec<u8>> {
+    let data = signer.sign(bundle_hash)?;
+    if data.len() != size {
+        return error(||"Witness has a different size than announced by its signer")
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate sanskrit_memory_store;
+
+    use super::*;
+    use alloc::vec;
+    use sanskrit_common::arena::Heap;
+    use sanskrit_common::model::Ptr;
+    use sanskrit_interpreter::model::{Entry, RuntimeType};
+    use sanskrit_runtime::BlockInfo;
+    use self::sanskrit_memory_store::BTreeMapStore;
+
+    //The bundles in these tests have no invokes, so nothing is ever provided
+    struct NoProvided;
+    impl<'c> SystemDataManager<BundleWithHash<'c>> for NoProvided {
+        fn providable_size(_typ:Ptr<RuntimeType>) -> Result<u32> { error(||"Nothing is providable") }
+        fn providable_gas(_typ:Ptr<RuntimeType>) -> Result<u64> { error(||"Nothing is providable") }
+        fn is_chain_value(_typ:Ptr<RuntimeType>) -> bool { false }
+        fn provided_value_key(_typ:Ptr<RuntimeType>, _section_no:u8, _txt_no:u8, _p_num:u8) -> Option<Vec<u8>> { None }
+        fn create_provided_value<'a,'h>(_bundle:&BundleWithHash<'c>, _typ:Ptr<RuntimeType>, _alloc:&'a VirtualHeapArena<'h>, _block:BlockInfo, _section_no:u8, _txt_no:u8, _p_num:u8) -> Result<Entry<'a>> { error(||"Nothing is providable") }
+        fn is_fee(_typ:Ptr<RuntimeType>) -> bool { false }
+        fn fee_value(_value:&Entry) -> Result<u64> { error(||"No fees") }
+        fn fee_payer(_value:&Entry) -> Result<Hash> { error(||"No fees") }
+    }
+
+    //Produces a witness of the announced size filled with the key
+    struct FixedSigner {
+        key:u8,
+        size:usize,
+    }
+
+    impl Signer for FixedSigner {
+        fn public_key(&self) -> Vec<u8> { vec![self.key] }
+        fn witness_size(&self) -> usize { self.size }
+        fn sign(&self, _bundle_hash:&Hash) -> Result<Vec<u8>> { Ok(vec![self.key; self.size]) }
+    }
+
+    fn deploying_builder<'a>() -> BundleBuilder<'a> {
+        let mut builder = BundleBuilder::new(0);
+        builder.deploy(DeployType::Module, 1000, vec![0; 8]).unwrap();
+        builder
+    }
+
+    #[test]
+    fn oversized_witnesses_are_rejected() {
+        let mut builder = deploying_builder();
+        let too_large = u16::MAX as usize + 1;
+        assert!(builder.signer(Box::new(FixedSigner{ key: 1, size: too_large })).is_err());
+        assert!(builder.co_signer(vec![2], too_large).is_err());
+        assert!(builder.signer(Box::new(FixedSigner{ key: 1, size: u16::MAX as usize })).is_ok());
+        assert!(builder.co_signer(vec![2], u16::MAX as usize).is_ok());
+    }
+
+    #[test]
+    fn partial_bundle_keeps_the_announced_witness_sizes() {
+        let store = BTreeMapStore::new();
+        let heap = Heap::new(256 * 1024, 1.0);
+        let alloc = heap.new_virtual_arena(128 * 1024);
+
+        let mut builder = deploying_builder();
+        builder.signer(Box::new(FixedSigner{ key: 1, size: 3 })).unwrap();
+        builder.co_signer(vec![2], u16::MAX as usize).unwrap();
+        assert!(builder.build::<_,NoProvided>(&store, &alloc).is_err());
+
+        let partial = builder.build_partial::<_,NoProvided>(&store, &alloc).unwrap();
+        let mut partial = PartialBundle::from_bytes(&partial.to_bytes().unwrap()).unwrap();
+        assert_eq!(partial.signers.0[0].witness_size, 3);
+        assert_eq!(partial.signers.0[0].witness.as_ref().map(|w|w.0.clone()), Some(vec![1; 3]));
+        assert_eq!(partial.signers.0[1].witness_size, u16::MAX);
+        assert_eq!(partial.missing(), vec![&[2u8][..]]);
+
+        assert!(partial.add_witness(&[2], &[2; 64]).is_err());
+        partial.sign(&FixedSigner{ key: 2, size: u16::MAX as usize }).unwrap();
+        assert!(partial.is_complete());
+
+        let bundle = partial.finalize(&alloc).unwrap();
+        assert_eq!(bundle.txt_bundle.byte_size, Some(partial.core.0.len() + 2 + (2 + 3) + (2 + u16::MAX as usize)));
+    }
+}
@@ -0,0 +1,18 @@
+#![no_std]
+
+extern crate alloc;
+extern crate sanskrit_common;
+extern crate sanskrit_interpreter;
+extern crate sanskrit_runtime;
+#[macro_use]
+extern crate sanskrit_derive;
+#[cfg(feature = "ed25519")]
+extern crate ed25519_dalek;
+
+pub mod signer;
+pub mod builder;
+pub mod partial;
+
+pub use builder::{BundleBuilder, Input, Output, Slot, WitnessRef};
+pub use signer::Signer;
+pub use partial::PartialBundle;
@@ -0,0 +1,139 @@
+//! A bundle that still misses witnesses of some of its signers
+//!
+//! Multi-party bundles are built by one party, which then hands the partial bundle to the others (as bytes, over any channel).
+//! Every party checks the core, adds its witness and passes it on, once all witnesses are present it can be finalized and submitted.
+//! The core is fixed when the partial bundle is built, so the gas and memory limits already account for all the witnesses.
+
+use alloc::vec::Vec;
+use sanskrit_common::encoding::*;
+use sanskrit_common::errors::*;
+use sanskrit_common::hashing::HashingDomain;
+use sanskrit_common::model::{Hash, LargeVec};
+use sanskrit_runtime::model::{BaseTransactionBundle, BundleWithHash, TransactionBundleCore};
+use sanskrit_runtime::CONFIG;
+use sanskrit_common::arena::VirtualHeapArena;
+use crate::builder::sign_witness;
+use crate::signer::Signer;
+
+//The partial bundle is a flat structure (Bundle -> Signers -> Signer -> Option -> Witness)
+// every level uses up one unit of depth and the parser fails when it reaches zero
+const MAX_PARTIAL_DEPTH:usize = 6;
+
+#[derive(Clone, Debug, Parsable, Serializable)]
+pub struct PartialBundle {
+    //the serialized core, its hash is what the signers sign
+    pub core:BundleData,
+    //one entry per witness of the bundle (in the same order)
+    pub signers:LargeVec<RequiredSigner>,
+}
+
+#[derive(Clone, Debug, Parsable, Serializable)]
+pub struct RequiredSigner {
+    pub public_key:LargeVec<u8>,
+    pub witness_size:u16,
+    pub witness:Option<LargeVec<u8>>,
+}
+
+//Bytes with an u32 length prefix (bundles can be larger than an u16 length allows)
+#[derive(Clone, Debug)]
+pub struct BundleData(pub Vec<u8>);
+
+impl<'a> Parsable<'a> for BundleData {
+    fn parse<A: ParserAllocator>(p: &mut Parser, alloc:&'a A) -> Result<Self> {
+        let len = u32::parse(p,alloc)?;
+        Ok(BundleData(p.consume_bytes(len as usize)?.to_vec()))
+    }
+}
+
+impl Serializable for BundleData {
+    fn serialize(&self, s:&mut Serializer) -> Result<()> {
+        if self.0.len() > u32::MAX as usize {
+            return error(||"Bundle is to large")
+        }
+        (self.0.len() as u32).serialize(s)?;
+        s.produce_bytes(&self.0);
+        Ok(())
+    }
+}
+
+impl PartialBundle {
+    pub fn from_bytes(data:&[u8]) -> Result<Self> {
+        Parser::parse_fully(data, MAX_PARTIAL_DEPTH, &NoCustomAlloc())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Serializer::serialize_fully(self, MAX_PARTIAL_DEPTH)
+    }
+
+    //The hash the witnesses are computed over
+    pub fn bundle_hash(&self) -> Hash {
+        HashingDomain::Bundle.hash(&self.core.0)
+    }
+
+    //Parses the core, allows a co-signer to inspect what it signs
+    pub fn core<'c>(&self, alloc:&'c VirtualHeapArena) -> Result<TransactionBundleCore<'c>> {
+        Parser::parse_fully(&self.core.0, CONFIG.max_structural_dept, alloc)
+    }
+
+    //Adds a witness for all the still missing witnesses of the signer with the public key
+    // the witness is not verified, this happens when the bundle is executed
+    pub fn add_witness(&mut self, public_key:&[u8], witness:&[u8]) -> Result<()> {
+        let mut added = false;
+        for signer in self.signers.0.iter_mut() {
+            if signer.witness.is_some() || signer.public_key.0 != public_key { continue }
+            if witness.len() != signer.witness_size as usize {
+                return error(||"Witness has a different size than required by the bundle")
+            }
+            signer.witness = Some(LargeVec(witness.to_vec()));
+            added = true;
+        }
+        if !added {
+            return error(||"Bundle does not miss a witness from this signer")
+        }
+        Ok(())
+    }
+
+    //Signs the bundle and adds the witness
+    pub fn sign(&mut self, signer:&dyn Signer) -> Result<()> {
+        let public_key = signer.public_key();
+        let size = match self.signers.iter().find(|s|s.witness.is_none() && s.public_key.0 == public_key) {
+            Some(required) => required.witness_size as usize,
+            None => return error(||"Bundle does not miss a witness from this signer")
+        };
+        let witness = sign_witness(signer, size, &self.bundle_hash())?;
+        self.add_witness(&public_key, &witness)
+    }
+
+    //The public keys of the signers whose witnesses are missing
+    pub fn missing(&self) -> Vec<&[u8]> {
+        self.signers.iter().filter(|s|s.witness.is_none()).map(|s|&s.public_key.0[..]).collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.signers.iter().all(|s|s.witness.is_some())
+    }
+
+    //Produces the bundle that can be executed, fails if a witness is missing
+    pub fn finalize<'c>(&self, alloc:&'c VirtualHeapArena) -> Result<BundleWithHash<'c>> {
+        let core = self.core(alloc)?;
+        let mut witness = Vec::with_capacity(self.signers.len());
+        let mut witness_size = 2;
+        for signer in self.signers.iter() {
+            match signer.witness {
+                Some(ref data) => {
+                    witness_size += 2 + data.len();
+                    witness.push(alloc.copy_alloc_slice(&data.0)?)
+                },
+                None => return error(||"Bundle misses witnesses")
+            }
+        }
+        Ok(BundleWithHash {
+            txt_bundle: BaseTransactionBundle {
+                byte_size: Some(self.core.0.len() + witness_size),
+                core,
+                witness: alloc.copy_alloc_slice(&witness)?,
+            },
+            bundle_hash: self.bundle_hash(),
+        })
+    }
+}
@@ -0,0 +1,34 @@
+//! Signers produce the witnesses of a bundle
+//!
+//! A witness is computed over the bundle hash, which covers everything except the witnesses themselves.
+//! As the size of the witnesses is part of the gas calculation, a signer has to know the size of its witness before signing.
+
+use alloc::vec::Vec;
+use sanskrit_common::errors::*;
+use sanskrit_common::model::Hash;
+
+pub trait Signer {
+    //identifies the signer towards the other parties of a bundle
+    fn public_key(&self) -> Vec<u8>;
+    //the number of bytes the witness will have
+    fn witness_size(&self) -> usize;
+    //produces the witness for the bundle with the given hash
+    fn sign(&self, bundle_hash:&Hash) -> Result<Vec<u8>>;
+}
+
+#[cfg(feature = "ed25519")]
+impl Signer for ed25519_dalek::SigningKey {
+    fn public_key(&self) -> Vec<u8> {
+        self.verifying_key().to_bytes().to_vec()
+    }
+
+    fn witness_size(&self) -> usize {
+        ed25519_dalek::SIGNATURE_LENGTH
+    }
+
+    fn sign(&self, bundle_hash:&Hash) -> Result<Vec<u8>> {
+        use ed25519_dalek::Signer as _;
+        let signature:ed25519_dalek::Signature = self.try_sign(bundle_hash).or_else(|_|error(||"Signing the bundle failed"))?;
+        Ok(signature.to_bytes().to_vec())
+    }
+}
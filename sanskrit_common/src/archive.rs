@@ -0,0 +1,143 @@
+//Archive of the whole committed state of a store, allows to move a state between machines and store backends
+// layout: magic, version, state root, for each class in the order of STORAGE_CLASSES:
+//   class, number of entries (u64), the entries ordered by key: key, value length (u32), value
+// followed by a checksum over everything before it
+// the checksum and the state root detect corrupted archives, they do not authenticate them:
+// anyone can produce a matching checksum and the state root can be forged (see ClassDigest)
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use alloc::format;
+use crate::errors::*;
+use crate::encoding::*;
+use crate::model::{Hash, HASH_SIZE};
+use crate::store::*;
+
+//version 2 added the balance class, version 3 computes the state root from the class digests
+pub const ARCHIVE_VERSION:u8 = 3;
+const ARCHIVE_MAGIC:&[u8] = b"SKSA";
+
+//Produces the archive of the committed content of the store and returns it with its state root
+// the root is computed from the archived entries, so the store is only read once
+pub fn export_state<S:ListableStore>(store:&S) -> Result<(Vec<u8>, Hash)> {
+    let mut s = Serializer::new(1);
+    s.produce_bytes(ARCHIVE_MAGIC);
+    ARCHIVE_VERSION.serialize(&mut s)?;
+    //filled in once all entries are written
+    let root_start = ARCHIVE_MAGIC.len() + 1;
+    [0u8; HASH_SIZE].serialize(&mut s)?;
+    let mut builder = StateRootBuilder::new();
+    for class in STORAGE_CLASSES {
+        let entries = store.list(class)?;
+        let mut digest = ClassDigest::default();
+        (class as u8).serialize(&mut s)?;
+        (entries.len() as u64).serialize(&mut s)?;
+        for (key, value) in entries {
+            if value.len() > u32::MAX as usize {
+                return error(||"Stored value is to large to be archived")
+            }
+            digest.insert(&key, &value);
+            key.serialize(&mut s)?;
+            (value.len() as u32).serialize(&mut s)?;
+            s.produce_bytes(&value);
+        }
+        builder.class(class, &digest);
+    }
+    let root = builder.finalize();
+    let mut data = s.extract();
+    data[root_start..root_start + HASH_SIZE].copy_from_slice(&root);
+    let checksum = store_hash(&[&data]);
+    data.extend_from_slice(&checksum);
+    Ok((data, root))
+}
+
+//Checks an archived value against its key
+// modules, transactions and descriptors are stored under the hash of their content
+fn verify_entry(class:StorageClass, key:&Hash, value:&[u8], entry_hashes:&mut BTreeSet<Hash>) -> Result<()> {
+    match class {
+        StorageClass::Module | StorageClass::Transaction | StorageClass::Descriptor => if store_hash(&[value]) != *key {
+            return owned_error(||format!("Archived {:?} does not match its hash", class))
+        },
+        //every entry has a hash (of its type and value) and a value
+        StorageClass::EntryHash => {
+            if value.len() != HASH_SIZE {
+                return error(||"Archived entry hash has the wrong size")
+            }
+            entry_hashes.insert(*key);
+        },
+        StorageClass::EntryValue => if !entry_hashes.remove(key) {
+            return error(||"Archived entry value has no entry hash")
+        },
+        //fee balances are plain amounts
+        StorageClass::Balance => if value.len() != 8 {
+            return error(||"Archived balance is not an amount")
+        },
+    }
+    Ok(())
+}
+
+fn import_content<S:Store>(content:&[u8], store:&S) -> Result<Hash> {
+    let mut p = Parser::new(content, 1);
+    if p.consume_bytes(ARCHIVE_MAGIC.len())? != ARCHIVE_MAGIC {
+        return error(||"Data is not a state archive")
+    }
+    let version = p.consume_byte()?;
+    if version != ARCHIVE_VERSION {
+        return owned_error(||format!("Archive version {} is not supported", version))
+    }
+    let expected_root = Hash::parse(&mut p, &NoCustomAlloc())?;
+    let mut builder = StateRootBuilder::new();
+    let mut entry_hashes = BTreeSet::new();
+    for class in STORAGE_CLASSES {
+        if p.consume_byte()? != class as u8 {
+            return error(||"Archive sections are not in the expected order")
+        }
+        let entries = u64::parse(&mut p, &NoCustomAlloc())?;
+        let mut digest = ClassDigest::default();
+        let mut last:Option<Hash> = None;
+        for _ in 0..entries {
+            let key = Hash::parse(&mut p, &NoCustomAlloc())?;
+            let len = u32::parse(&mut p, &NoCustomAlloc())?;
+            let value = p.consume_bytes(len as usize)?;
+            if matches!(last, Some(last) if last >= key) {
+                return error(||"Archive entries are not ordered by key")
+            }
+            verify_entry(class, &key, value, &mut entry_hashes)?;
+            digest.insert(&key, value);
+            store.set(class, key, value.to_vec())?;
+            last = Some(key);
+        }
+        builder.class(class, &digest);
+    }
+    if !entry_hashes.is_empty() {
+        return error(||"Archived entry hash has no entry value")
+    }
+    if p.index != content.len() {
+        return error(||"Archive has trailing data")
+    }
+    let root = builder.finalize();
+    if root != expected_root {
+        return error(||"Archive content does not match its state root")
+    }
+    Ok(root)
+}
+
+//Imports the archive into the store (which should be empty) and returns the state root of the imported state
+// nothing is kept if the archive is corrupted, does not verify or contains a key that is already in the store
+pub fn import_state<S:Store>(data:&[u8], store:&S) -> Result<Hash> {
+    if data.len() < HASH_SIZE {
+        return error(||"Archive is to short")
+    }
+    let (content, checksum) = data.split_at(data.len() - HASH_SIZE);
+    if store_hash(&[content])[..] != checksum[..] {
+        return error(||"Archive checksum does not match")
+    }
+    let res = import_content(content, store);
+    for class in STORAGE_CLASSES {
+        if res.is_ok() {
+            store.commit(class);
+        } else {
+            store.rollback(class);
+        }
+    }
+    res
+}
@@ -29,8 +29,11 @@ pub struct Heap {
 }
 
 impl Heap {
+    //The alignment of the start of an arena, each arena needs up to ARENA_ALIGN-1 bytes more than its size
+    pub const ARENA_ALIGN:usize = mem::align_of::<u128>();
+
     pub fn new(real: usize,  convert:f64) -> Self {
-        //We assume real accounts for alignement (max_elems_space can be used to compute sizes)
+        //We assume real accounts for alignement (max_elems_space can be used to compute sizes) including the start of each arena
         Heap {
             buffer: RefCell::new(Vec::with_capacity(real)),
             pos: Cell::new(0),
@@ -45,9 +48,11 @@ impl Heap {
     }
 
     pub fn new_arena(&self, size: usize) -> HeapArena {
-        //No alignment as we do not know the type size: However, this mean size must account for alignement offsets
-        let start = self.pos.get();
-        let end = self.pos.get() + size;
+        //Arenas start at the largest alignment (u128 is 16 byte aligned), so a virtual arena does not lose space on the padding of its first allocation
+        // size must still account for the alignment offsets between allocations of different types
+        let addr = self.buffer.borrow().as_ptr() as usize + self.pos.get();
+        let start = self.pos.get() + align_address(addr, Self::ARENA_ALIGN);
+        let end = start + size;
         if self.buffer.borrow().capacity() < end {
             panic!("Not enough space for allocating arena");
         }
@@ -149,6 +154,8 @@ impl<'h> HeapArena<'h> {
     #[allow(clippy::mut_from_ref)]
     unsafe fn alloc_raw_slice<T: Sized + Copy>(&self, len: usize) -> &mut [T] {
         if self.locked.get() {panic!()}
+        //empty slices take no space (they would otherwise still be padded)
+        if len == 0 { return from_raw_parts_mut(ptr::NonNull::dangling().as_ptr(), 0) }
         let size = len * mem::size_of::<T>();
         let pos = self.pos.get();
         let ptr = self.buffer.borrow_mut().as_mut_ptr().add(pos);
@@ -3,6 +3,8 @@ use core::result::Result as OResult;
 use alloc::string::String;
 #[cfg(feature = "string_errors")]
 use alloc::borrow::ToOwned;
+#[cfg(feature = "string_errors")]
+use alloc::format;
 
 //The error in case we need something to interpret (in tests)
 #[cfg(not(feature = "string_errors"))]
@@ -46,6 +48,17 @@ pub fn owned_error<'a, T,F:FnOnce()-> String>(msg:F) -> Result<T>{
     Err(msg())
 }
 
+//Adds details about where an error happened (they are only computed if errors are captured)
+#[cfg(not(feature = "string_errors"))]
+pub fn error_context<F:FnOnce()-> String>(err:ErrorType, _details:F) -> ErrorType {
+    err
+}
+
+#[cfg(feature = "string_errors")]
+pub fn error_context<F:FnOnce()-> String>(err:ErrorType, details:F) -> ErrorType {
+    format!("{} ({})", err, details())
+}
+
 #[cfg(not(feature = "string_errors"))]
 pub fn error_to_string(_err:&()) -> &str {
     "error was not captured"
@@ -15,7 +15,10 @@ extern crate sanskrit_derive;
 
 pub mod encoding;
 pub mod store;
+pub mod archive;
 pub mod errors;
 pub mod model;
 pub mod hashing;
 pub mod arena;
+pub mod limiter;
+pub mod names;
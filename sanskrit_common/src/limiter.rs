@@ -0,0 +1,36 @@
+use core::cell::Cell;
+use crate::errors::*;
+
+//Counts the work units spent on a task with untrusted input (like validating and compiling a deploy)
+// the task is aborted as soon as it uses more units than its limit
+pub struct Limiter {
+    limit:u64,
+    used:Cell<u64>,
+}
+
+impl Limiter {
+    pub fn new(limit:u64) -> Self {
+        Limiter {
+            limit,
+            used: Cell::new(0)
+        }
+    }
+
+    //For inputs that are trusted or already checked (like preloaded modules)
+    pub fn unlimited() -> Self {
+        Limiter::new(u64::MAX)
+    }
+
+    pub fn consume(&self, units:u64) -> Result<()> {
+        let used = self.used.get().saturating_add(units);
+        self.used.set(used);
+        if used > self.limit {
+            return error(||"Work limit exceeded")
+        }
+        Ok(())
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used.get()
+    }
+}
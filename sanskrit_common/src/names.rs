@@ -0,0 +1,57 @@
+//! Names of modules and their components for human readable output
+//!
+//! The types of the core model and the runtime types of the interpreter are both rendered with them
+//! Unknown modules are shown by the start of their hash and unknown components by their index
+
+use core::fmt;
+use alloc::string::String;
+use crate::model::Hash;
+
+//Provides the names of modules and their components
+// Everything is optional, unknown names fall back to hashes and indexes
+pub trait Names {
+    fn module_name(&self, _module:&Hash) -> Option<String> { None }
+    fn data_name(&self, _module:&Hash, _offset:u8) -> Option<String> { None }
+    fn sig_name(&self, _module:&Hash, _offset:u8) -> Option<String> { None }
+    fn function_name(&self, _module:&Hash, _offset:u8) -> Option<String> { None }
+    fn implement_name(&self, _module:&Hash, _offset:u8) -> Option<String> { None }
+}
+
+//Used when no names are available
+pub struct NoNames;
+impl Names for NoNames {}
+
+#[derive(Copy, Clone)]
+pub enum Component {
+    Data,
+    Sig,
+    Function,
+    Implement,
+}
+
+//Writes the first 4 bytes of a hash, which is enough to tell modules apart in a message
+pub fn write_short_hash<W:fmt::Write>(f:&mut W, hash:&Hash) -> fmt::Result {
+    for b in &hash[..4] { write!(f, "{:02x}", b)? }
+    Ok(())
+}
+
+//Writes a component as `Module.Name`
+pub fn write_component<W:fmt::Write, N:Names>(f:&mut W, names:&N, module:&Hash, kind:Component, offset:u8) -> fmt::Result {
+    match names.module_name(module) {
+        Some(name) => write!(f, "{}", name)?,
+        None => write_short_hash(f, module)?
+    }
+    let name = match kind {
+        Component::Data => names.data_name(module, offset),
+        Component::Sig => names.sig_name(module, offset),
+        Component::Function => names.function_name(module, offset),
+        Component::Implement => names.implement_name(module, offset),
+    };
+    match (name, kind) {
+        (Some(name), _) => write!(f, ".{}", name),
+        (None, Component::Data) => write!(f, ".data{}", offset),
+        (None, Component::Sig) => write!(f, ".sig{}", offset),
+        (None, Component::Function) => write!(f, ".fun{}", offset),
+        (None, Component::Implement) => write!(f, ".impl{}", offset),
+    }
+}
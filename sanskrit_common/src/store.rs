@@ -36,6 +36,125 @@ pub trait Store {
 
 }
 
+//Allows to use a borrowed store where an owned one is expected (for example in a CachedStore)
+impl<'a, S:Store> Store for &'a S {
+    fn delete(&self, class:StorageClass, key: &Hash) -> Result<()> {
+        (*self).delete(class, key)
+    }
+
+    fn get<P,F:FnOnce(&[u8]) -> P>(&self, class:StorageClass, key: &Hash, f:F) -> Result<P> {
+        (*self).get(class, key, f)
+    }
+
+    fn set(&self, class:StorageClass, key:Hash, data:Vec<u8>) -> Result<()> {
+        (*self).set(class, key, data)
+    }
+
+    fn commit(&self, class:StorageClass) {
+        (*self).commit(class)
+    }
+
+    fn rollback(&self, class:StorageClass) {
+        (*self).rollback(class)
+    }
+}
+
+//A store that keeps its changes to itself and reads everything else from an underlying store
+// the underlying store (including its pending changes) is never modified, dropping the overlay discards the changes
+pub struct OverlayStore<S:Store> {
+    base:S,
+    committed:RefCell<OverlayChanges>,
+    pending:RefCell<OverlayChanges>,
+}
+
+//None marks a deleted entry
+type OverlayChanges = BTreeMap<(StorageClass, Hash), Option<Vec<u8>>>;
+
+impl<S:Store> OverlayStore<S> {
+    pub fn new(base:S) -> Self {
+        OverlayStore {
+            base,
+            committed: RefCell::new(BTreeMap::new()),
+            pending: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    fn contains(&self, class:StorageClass, key:&Hash) -> bool {
+        let entry = (class, *key);
+        match self.pending.borrow().get(&entry).or(self.committed.borrow().get(&entry)) {
+            Some(change) => change.is_some(),
+            None => self.base.get(class, key, |_|()).is_ok()
+        }
+    }
+}
+
+impl<S:Store> Store for OverlayStore<S> {
+    fn delete(&self, class:StorageClass, key: &Hash) -> Result<()> {
+        if !self.contains(class, key) {
+            return error(||"Value was not in store")
+        }
+        self.pending.borrow_mut().insert((class, *key), None);
+        Ok(())
+    }
+
+    fn get<P,F:FnOnce(&[u8]) -> P>(&self, class:StorageClass, key: &Hash, f:F) -> Result<P> {
+        let entry = (class, *key);
+        match self.pending.borrow().get(&entry).or(self.committed.borrow().get(&entry)) {
+            Some(Some(data)) => Ok(f(data)),
+            Some(None) => error(||"Value was not in store"),
+            None => self.base.get(class, key, f)
+        }
+    }
+
+    fn set(&self, class:StorageClass, key:Hash, data:Vec<u8>) -> Result<()> {
+        if self.contains(class, &key) {
+            return error(||"Value was already in store")
+        }
+        self.pending.borrow_mut().insert((class, key), Some(data));
+        Ok(())
+    }
+
+    fn commit(&self, class:StorageClass) {
+        let mut pending = self.pending.borrow_mut();
+        let mut committed = self.committed.borrow_mut();
+        let keys:Vec<(StorageClass, Hash)> = pending.keys().filter(|(c,_)|*c == class).cloned().collect();
+        for entry in keys {
+            let change = pending.remove(&entry).unwrap();
+            committed.insert(entry, change);
+        }
+    }
+
+    fn rollback(&self, class:StorageClass) {
+        self.pending.borrow_mut().retain(|(c,_),_|*c != class);
+    }
+}
+
+//A store that can enumerate its committed content (pending changes are not included)
+// needed to compare the states held by different stores
+pub trait ListableStore: Store {
+    //returns the entries of the class ordered by their key
+    fn list(&self, class:StorageClass) -> Result<Vec<(Hash, Vec<u8>)>>;
+    //returns the digest of the entries of the class
+    // the default lists the class, stores should keep it up to date when they commit instead
+    fn digest(&self, class:StorageClass) -> Result<ClassDigest> {
+        let mut digest = ClassDigest::default();
+        for (key, value) in self.list(class)? {
+            digest.insert(&key, &value);
+        }
+        Ok(digest)
+    }
+}
+
+impl<S:ListableStore> ListableStore for &S {
+    fn list(&self, class:StorageClass) -> Result<Vec<(Hash, Vec<u8>)>> {
+        (*self).list(class)
+    }
+
+    fn digest(&self, class:StorageClass) -> Result<ClassDigest> {
+        (*self).digest(class)
+    }
+}
+
 //enum pointing to different sections in the store
 #[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone, Debug)]
 #[repr(u8)]
@@ -44,7 +163,91 @@ pub enum StorageClass{
     Transaction,
     Descriptor,
     EntryHash,  // hash(type||value)
-    EntryValue //Value will only be needed by state providers
+    EntryValue, //Value will only be needed by state providers
+    Balance //Fee balances (u64) of block producers and refunded payers, kept apart from the entries
+}
+
+pub const STORAGE_CLASSES:[StorageClass;6] = [
+    StorageClass::Module,
+    StorageClass::Transaction,
+    StorageClass::Descriptor,
+    StorageClass::EntryHash,
+    StorageClass::EntryValue,
+    StorageClass::Balance
+];
+
+//The number of entries in a class and the sum (modulo 2^160) of their hashes
+// a sum does not depend on the order of the entries, so it can be updated with just the entries that are written or deleted
+// Note: this detects accidental differences but is not a commitment, a generalized birthday attack can find
+//       other entries with the same sum, so it must not be used to authenticate content received from others
+#[derive(Copy, Clone, Default, Debug, Eq, PartialEq)]
+pub struct ClassDigest {
+    pub entries:u64,
+    pub sum:Hash,
+}
+
+impl ClassDigest {
+    fn entry_hash(key:&Hash, value:&[u8]) -> Hash {
+        store_hash(&[key, &store_hash(&[value])])
+    }
+
+    pub fn insert(&mut self, key:&Hash, value:&[u8]) {
+        let mut carry = 0;
+        for (digit, add) in self.sum.iter_mut().zip(Self::entry_hash(key, value).iter()).rev() {
+            let res = *digit as u16 + *add as u16 + carry;
+            *digit = res as u8;
+            carry = res >> 8;
+        }
+        self.entries += 1;
+    }
+
+    //the entry has to be in the class (with this value)
+    pub fn remove(&mut self, key:&Hash, value:&[u8]) {
+        let mut borrow = 0;
+        for (digit, sub) in self.sum.iter_mut().zip(Self::entry_hash(key, value).iter()).rev() {
+            let res = *digit as i16 - *sub as i16 - borrow;
+            *digit = res as u8;
+            borrow = (res < 0) as i16;
+        }
+        self.entries -= 1;
+    }
+}
+
+//Computes the state root from the digests of the classes
+// the classes have to be added in the order of STORAGE_CLASSES
+pub struct StateRootBuilder(Hasher);
+
+impl Default for StateRootBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateRootBuilder {
+    pub fn new() -> Self {
+        StateRootBuilder(Hasher::new())
+    }
+
+    pub fn class(&mut self, class:StorageClass, digest:&ClassDigest) {
+        self.0.update(&[class as u8]);
+        self.0.update(&digest.entries.to_be_bytes());
+        self.0.update(&digest.sum);
+    }
+
+    pub fn finalize(self) -> Hash {
+        self.0.finalize()
+    }
+}
+
+//Digest over the committed content of all classes
+// two stores have the same root if they hold the same entries, no matter how they store them
+// the root inherits the limits of ClassDigest: it shows that executions diverged but can be forged
+pub fn state_root<S:ListableStore>(store:&S) -> Result<Hash> {
+    let mut builder = StateRootBuilder::new();
+    for class in STORAGE_CLASSES {
+        builder.class(class, &store.digest(class)?);
+    }
+    Ok(builder.finalize())
 }
 
 //Helper to calc the key for a storage slot
@@ -60,7 +263,8 @@ pub fn store_hash(data:&[&[u8]]) -> Hash {
 }
 
 pub struct CachedStore<P, S:Store> {
-    cache:RefCell<BTreeMap<Hash, Rc<P>>>,
+    //the parsed values with the size of their encoding
+    cache:RefCell<BTreeMap<Hash, (Rc<P>, usize)>>,
     class:StorageClass,
     store:S,
 
@@ -88,17 +292,27 @@ impl<P, S:Store> CachedStore<P,S> {
             store
         }
     }
+
+    pub fn get_store(&self) -> &S {
+        &self.store
+    }
 }
 
 impl<'a, P:Parsable<'a>, S:Store> CachedStore<P,S>  {
 
     pub fn get_cached<A: ParserAllocator>(&self, key: &Hash, max_dept:usize, alloc:&'a A) -> Result<Rc<P>>{
+        Ok(self.get_cached_sized(key, max_dept, alloc)?.0)
+    }
+
+    //Like get_cached but returns the size of the stored encoding as well (independent of whether it was cached)
+    pub fn get_cached_sized<A: ParserAllocator>(&self, key: &Hash, max_dept:usize, alloc:&'a A) -> Result<(Rc<P>, usize)>{
         let mut cache = self.cache.borrow_mut();
 
         if !cache.contains_key(key) {
-            let val:Rc<P> = Rc::new(self.store.parsed_get(self.class,key,max_dept,alloc)?);
-            cache.insert(key.clone(), val.clone());
-            Ok(val)
+            let (val, size):(P, usize) = self.store.get(self.class,key,|d| Parser::parse_fully::<P,A>(d, max_dept, alloc).map(|p| (p, d.len())))??;
+            let val:Rc<P> = Rc::new(val);
+            cache.insert(key.clone(), (val.clone(), size));
+            Ok((val, size))
         } else {
             //just use the existing
             Ok(cache[key].clone())
@@ -110,11 +324,11 @@ impl<'a, P:Parsable<'a>, S:Store> CachedStore<P,S>  {
         if !cache.contains_key(key) {
             let parsed: P = Parser::parse_fully(data, max_dept, alloc)?;
             let val:Rc<P>  = Rc::new(parsed);
-            cache.insert(key.clone(), val.clone());
+            cache.insert(key.clone(), (val.clone(), data.len()));
             Ok(val)
         } else {
             //just use the existing
-            Ok(cache[key].clone())
+            Ok(cache[key].0.clone())
         }
     }
 }
\ No newline at end of file
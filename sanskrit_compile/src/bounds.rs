@@ -0,0 +1,154 @@
+//! Computes the worst case resources of the functions and implements of a module
+//!
+//! As Sanskrit has neither recursion nor unbounded loops each body has a statically known upper bound on gas, memory, stack and frames.
+//! The same compaction that is used for transactions computes these bounds for module components, so that they can be published and used for budgeting before composing calls.
+
+use alloc::vec::Vec;
+use sanskrit_common::model::*;
+use sanskrit_common::store::*;
+use sanskrit_common::errors::*;
+use sanskrit_common::arena::HeapArena;
+use sanskrit_common::limiter::Limiter;
+use sanskrit_core::model::*;
+use sanskrit_core::model::linking::Ref;
+use sanskrit_core::loader::Loader;
+use sanskrit_core::resolver::Context;
+use crate::compacting::Compactor;
+use crate::collector::COMPONENT_COST;
+use crate::externals::{CompilationExternals, ExpResources};
+
+pub use crate::compacting::BranchBounds;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum CallableKind {
+    Function,
+    Implement,
+}
+
+//The worst case resources of a single function or implement (including everything it calls)
+#[derive(Clone, Debug)]
+pub struct CallableBounds {
+    pub kind:CallableKind,
+    pub offset:u8,
+    pub resources:ExpResources,
+    //the resources of each branch of the switches and tries in the body
+    pub branches:Vec<BranchBounds>,
+}
+
+//Computes the bounds for each function and implement of the module that can be used by other modules
+// local and external components are skipped (the later have no body to analyze)
+// each component is analyzed in a temporary arena, so alloc must fit the largest of them
+pub fn module_bounds<S:Store, CE:CompilationExternals>(store:&CachedStore<Module,S>, module_hash:Hash, alloc:&HeapArena, limiter:&Limiter) -> Result<Vec<CallableBounds>> {
+    let resolver = Loader::new_complete(store, limiter);
+    let module = resolver.get_module(module_hash)?;
+    let link = resolver.dedup_module_link(ModuleLink::Remote(module_hash));
+    let mut res = Vec::new();
+
+    for (offset, fun) in module.functions.iter().enumerate() {
+        if let Accessibility::Local = fun.scope { continue }
+        if let CallableImpl::Internal { ref code, .. } = fun.body {
+            let temp = alloc.temp_arena();
+            limiter.consume(COMPONENT_COST)?;
+            let context = Context::from_module_component(fun, &link, true, &resolver)?;
+            let (resources, branches) = Compactor::bounds::<_,CE>(fun.shared.params.len(), code, &context, &resolver, &temp, limiter)?;
+            res.push(CallableBounds{ kind: CallableKind::Function, offset: offset as u8, resources, branches });
+        }
+    }
+
+    for (offset, imp) in module.implements.iter().enumerate() {
+        if let Accessibility::Local = imp.scope { continue }
+        if let CallableImpl::Internal { ref code, .. } = imp.body {
+            let temp = alloc.temp_arena();
+            limiter.consume(COMPONENT_COST)?;
+            let context = Context::from_module_component(imp, &link, true, &resolver)?;
+            //the captured params come before the params of the signature
+            let num_params = imp.params.len() + imp.sig.fetch(&context)?.get_sig()?.params.len();
+            let (resources, branches) = Compactor::bounds::<_,CE>(num_params, code, &context, &resolver, &temp, limiter)?;
+            res.push(CallableBounds{ kind: CallableKind::Implement, offset: offset as u8, resources, branches });
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate sanskrit_memory_store;
+
+    use super::*;
+    use alloc::vec;
+    use sanskrit_common::arena::Heap;
+    use sanskrit_common::encoding::Serializer;
+    use sanskrit_core::model::Exp;
+    use sanskrit_core::model::OpCode;
+    use sanskrit_interpreter::model::ValueSchema;
+    use crate::externals::CompilationResult;
+    use self::sanskrit_memory_store::BTreeMapStore;
+
+    struct NoExternals;
+    impl CompilationExternals for NoExternals {
+        fn compile_call<'b,'h>(_module:&ModuleLink, _fun_idx:u8, _params:SlicePtr<'b,ValueRef>, _caller:&Hash, _alloc:&'b HeapArena<'h>) -> Result<CompilationResult<'b>> {
+            error(||"No system calls")
+        }
+        fn compile_lit<'b,'h>(_module:&ModuleLink, _data_idx:u8, _data:SlicePtr<'b,u8>, _caller:&Hash, _alloc:&'b HeapArena<'h>) -> Result<CompilationResult<'b>> {
+            error(||"No system literals")
+        }
+        fn get_literal_checker<'b,'h>(_module:&ModuleLink, _data_idx:u8, _len:u16, _alloc:&'b HeapArena<'h>) -> Result<ValueSchema<'b>> {
+            error(||"No system literals")
+        }
+    }
+
+    fn no_imports() -> PublicImport {
+        PublicImport { modules: vec![], types: vec![] }
+    }
+
+    //A function without parameters that returns nothing
+    fn function(scope:Accessibility) -> FunctionComponent {
+        FunctionComponent {
+            byte_size: None,
+            scope,
+            shared: FunSigShared { transactional: false, generics: vec![], import: no_imports(), params: vec![], returns: vec![] },
+            body: CallableImpl::Internal {
+                byte_size: None,
+                imports: BodyImport { public: no_imports(), callables: vec![], permissions: vec![] },
+                code: Exp(LargeVec(vec![OpCode::Return(vec![])]))
+            }
+        }
+    }
+
+    fn store_module(store:&BTreeMapStore) -> Hash {
+        let module = Module {
+            byte_size: None,
+            system_module: false,
+            meta: LargeVec(vec![]),
+            data: vec![],
+            sigs: vec![],
+            data_sig_order: BitSerializedVec(vec![]),
+            functions: vec![function(Accessibility::Local), function(Accessibility::Global)],
+            implements: vec![],
+            fun_impl_order: BitSerializedVec(vec![true, true]),
+        };
+        let data = Serializer::serialize_fully(&module, 64).unwrap();
+        let hash = store_hash(&[&data]);
+        store.set(StorageClass::Module, hash, data).unwrap();
+        store.commit(StorageClass::Module);
+        hash
+    }
+
+    #[test]
+    fn bounds_cover_the_public_functions_and_release_their_arena() {
+        let store = BTreeMapStore::new();
+        let hash = store_module(&store);
+        let cache = CachedStore::<Module,_>::new(&store, StorageClass::Module);
+        let heap = Heap::new(4096, 1.0);
+        let alloc = heap.new_arena(4096);
+        let limiter = Limiter::unlimited();
+        let first = module_bounds::<_,NoExternals>(&cache, hash, &alloc, &limiter).unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].kind, CallableKind::Function);
+        assert_eq!(first[0].offset, 1);
+        assert!(first[0].resources.gas > 0);
+        //the temporary arenas are released, so the same arena can be used again
+        let second = module_bounds::<_,NoExternals>(&cache, hash, &alloc, &limiter).unwrap();
+        assert_eq!(second[0].resources.gas, first[0].resources.gas);
+    }
+}
@@ -8,8 +8,11 @@ use sanskrit_common::model::*;
 use sanskrit_common::store::*;
 use sanskrit_core::utils::Crc;
 use sanskrit_core::loader::{Loader, FetchCache};
+use sanskrit_common::limiter::Limiter;
 
 const FUNCTION_LIMIT:usize = u16::max_value() as usize;
+//Work units charged for loading a function or implement and resolving its context
+pub(crate) const COMPONENT_COST:u64 = 100;
 
 pub enum CollectResult {
     Function(FetchCache<FunctionComponent>),
@@ -32,14 +35,14 @@ pub struct Collector {
 }
 
 impl Collector {
-    pub fn collect<S:Store>(fun:&FunctionComponent, store:&Loader<S>) -> Result<Vec<CollectResult>>{
+    //collects the functions and implements reachable from the component with the context ctx (dependencies first)
+    pub fn collect<S:Store>(ctx:&Context<S>, store:&Loader<S>, limiter:&Limiter) -> Result<Vec<CollectResult>>{
         let mut col = Collector {
             stack: Vec::new(),
             recorded_funs: BTreeSet::new(),
             functions: Vec::new()
         };
-        let ctx = Context::from_top_component(fun,store)?;
-        col.collect_dependencies(&ctx)?;
+        col.collect_dependencies(ctx)?;
         while !col.stack.is_empty() {
             match col.stack.pop().unwrap() {
                 Action::PushDependencies(module, offset, is_implement) => {
@@ -47,6 +50,7 @@ impl Collector {
                     // Note: as the dependencies form a DAG it is guaranteed that we process each function only once
                     let key = (module, offset, is_implement);
                     if col.recorded_funs.contains(&key) { continue; }
+                    limiter.consume(COMPONENT_COST)?;
                     if is_implement {
                         col.collect_record_module_impl_dependencies(key.0,key.1,store)?;
                     } else {
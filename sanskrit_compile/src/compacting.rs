@@ -27,9 +27,13 @@ use sanskrit_common::arena::*;
 use crate::gas_table::gas;
 use sanskrit_core::utils::Crc;
 use sanskrit_common::encoding::VirtualSize;
-use crate::collector::{Collector, CollectResult};
+use crate::collector::{Collector, CollectResult, COMPONENT_COST};
 use sanskrit_core::loader::Loader;
 use crate::externals::{CompilationResult, ExpResources, CompilationExternals};
+use sanskrit_common::limiter::Limiter;
+
+//Work units charged for compacting an opcode
+const OPCODE_COST:u64 = 10;
 
 struct State {
     //the gas used in this trace
@@ -76,7 +80,26 @@ pub struct Compactor<'b,'h> {
     //allocator
     alloc:&'b HeapArena<'h>,
     // block
-    block: Vec<ROpCode<'b>>
+    block: Vec<ROpCode<'b>>,
+    //accounts for the work done during compilation
+    limiter:&'b Limiter,
+    //(branch, opcode) pairs leading to the currently processed opcode
+    path:Vec<(u8,u16)>,
+    //the branch the next processed expression belongs to
+    branch:u8,
+    //the resources of each branch (only collected when computing bounds)
+    branches:Option<Vec<BranchBounds>>,
+}
+
+//The resources used by a single branch of a switch or try
+#[derive(Clone, Debug)]
+pub struct BranchBounds {
+    //(branch, opcode) pairs leading from the body to the switch or try
+    pub path:Vec<(u8,u16)>,
+    //the case of a switch or 0 for success and 1 for failure of a try
+    pub branch:u8,
+    pub gas:u64,
+    pub mem:u64,
 }
 
 #[cfg(not(feature = "dynamic_gas"))]
@@ -291,14 +314,52 @@ type CollectRes<'b> = (SlicePtr<'b,TxTFunction<'b>>, ExpResources);
 
 impl<'b,'h> Compactor<'b,'h> {
 
-    pub fn compact<S:Store,CE:CompilationExternals>(fun:&FunctionComponent, body:&Exp, store:&Loader<S>, alloc:&'b HeapArena<'h>) -> Result<CollectRes<'b>> {
-        let functions = Collector::collect(fun,store)?;
+    pub fn compact<S:Store,CE:CompilationExternals>(fun:&FunctionComponent, body:&Exp, store:&Loader<S>, alloc:&'b HeapArena<'h>, limiter:&'b Limiter) -> Result<CollectRes<'b>> {
+        //get the top context
+        limiter.consume(COMPONENT_COST)?;
+        let top_context = Context::from_top_component(fun, store)?;
+        let mut compactor = Compactor::with_dependencies::<_,CE>(&top_context, store, alloc, limiter)?;
+        //compact the top function
+        let (processed, resources) = compactor.process_func::<_,CE>(fun.shared.params.len(), body, &top_context)?;
+        //fill the slot with the compacted function
+        #[cfg(not(feature = "dynamic_gas"))]
+        compactor.functions.push(processed);
+        #[cfg(feature = "dynamic_gas")]
+        if resources.local_gas > u32::MAX as u64 {return error(||"Consumed Gas out of range")}
+        #[cfg(feature = "dynamic_gas")]
+        compactor.functions.push(TxTFunction{
+            gas: resources.local_gas as u32,
+            body: processed
+        });
+        //get all functions
+        let res = compactor.functions.finish();
+        //return the result
+        Ok((res,resources))
+    }
+
+    //computes the worst case resources of a function or implement body with the context context
+    // besides the totals the resources used by each branch of the body's switches and tries are returned
+    pub fn bounds<S:Store,CE:CompilationExternals>(num_params:usize, body:&Exp, context:&Context<S>, store:&Loader<S>, alloc:&'b HeapArena<'h>, limiter:&'b Limiter) -> Result<(ExpResources, Vec<BranchBounds>)> {
+        let mut compactor = Compactor::with_dependencies::<_,CE>(context, store, alloc, limiter)?;
+        //only the branches of the analyzed body are of interest
+        compactor.branches = Some(Vec::new());
+        let (_, resources) = compactor.process_func::<_,CE>(num_params, body, context)?;
+        Ok((resources, compactor.branches.unwrap()))
+    }
+
+    //creates a compactor with all the functions and implements reachable from the component with the context ctx already compacted
+    fn with_dependencies<S:Store,CE:CompilationExternals>(ctx:&Context<S>, store:&Loader<S>, alloc:&'b HeapArena<'h>, limiter:&'b Limiter) -> Result<Self> {
+        let functions = Collector::collect(ctx,store,limiter)?;
         let mut compactor = Compactor {
             state:State::new(),
             fun_mapping: BTreeMap::new(),
             functions: alloc.slice_builder(functions.len()+1)?,
             alloc,
             block: Vec::new(),
+            limiter,
+            path: Vec::new(),
+            branch: 0,
+            branches: None,
         };
 
         for col_res in functions {
@@ -369,24 +430,7 @@ impl<'b,'h> Compactor<'b,'h> {
             //  for the case that someone gets the idea to allow recursion
             assert_eq!(old,None);
         }
-        //get the top context
-        let top_context = Context::from_top_component(fun, store)?;
-        //compact the top function
-        let (processed, resources) = compactor.process_func::<_,CE>(fun.shared.params.len(), body, &top_context)?;
-        //fill the slot with the compacted function
-        #[cfg(not(feature = "dynamic_gas"))]
-        compactor.functions.push(processed);
-        #[cfg(feature = "dynamic_gas")]
-        if resources.local_gas > u32::MAX as u64 {return error(||"Consumed Gas out of range")}
-        #[cfg(feature = "dynamic_gas")]
-        compactor.functions.push(TxTFunction{
-            gas: resources.local_gas as u32,
-            body: processed
-        });
-        //get all functions
-        let res = compactor.functions.finish();
-        //return the result
-        Ok((res,resources))
+        Ok(compactor)
     }
 
 
@@ -409,6 +453,19 @@ impl<'b,'h> Compactor<'b,'h> {
         Ok((body, state.extract_call_resources()))
     }
 
+    //remembers the resources of the branch that was just processed
+    // the state counts from the start of the branch so the branch is charged without its surroundings
+    fn record_branch(&mut self, branch:u8) {
+        if let Some(ref mut branches) = self.branches {
+            branches.push(BranchBounds {
+                path: self.path.clone(),
+                branch,
+                gas: self.state.gas,
+                mem: self.state.mem,
+            })
+        }
+    }
+
     fn manifest_stack(&mut self, actual_elems:i16, expected_elems:u8) -> Result<()> {
         //flag that check if manifest is needed
         let mut require_manifest = actual_elems != expected_elems as i16;
@@ -442,17 +499,22 @@ impl<'b,'h> Compactor<'b,'h> {
         if tail_info.is_none() {self.state.add_frame();}
         //in case of a return we need to find out which opcodes we can eliminate
         let old_opcodes = mem::replace(&mut self.block, Vec::with_capacity(exp.0.len()));
+        let branch = mem::replace(&mut self.branch, 0);
         let mut actual_rets = -1;
         let iter = &mut exp.0.iter();
         let len = iter.len();
         //Process all but last
-        for code in iter.take(len - 1) {
+        for (idx, code) in iter.take(len - 1).enumerate() {
             //process the opcode
+            self.path.push((branch, idx as u16));
             let (manifest, rets) = self.process_opcode::<_,CE>(code, context, None)?;
+            self.path.pop();
             if manifest { actual_rets = rets as i16; }
         }
         //process the last one special (needs adapted tail_info if None it becomes this expressions start)
+        self.path.push((branch, (len - 1) as u16));
         let (manifest, expect_rets) = self.process_opcode::<_,CE>(iter.next().unwrap(), context, tail_info.or(Some(ret_point.0)))?;
+        self.path.pop();
         //Note: If !manifest then tail_info was ignored anyways (as all actual calls return true for manifest)
         //      Conclusion: If we used tail info for optimisation then actual_rets == expect_rets & The returned elems are on top of the stack already
         //                  Thus self.manifest_stack will not produce a return opcode
@@ -479,6 +541,7 @@ impl<'b,'h> Compactor<'b,'h> {
 
     //compact or even eliminate an opcode
     pub fn process_opcode<S:Store,CE:CompilationExternals>(&mut self, opcode:&OpCode, context:&Context<S>, tail_info:Option<u32>) -> Result<(bool,u8)> {
+        self.limiter.consume(OPCODE_COST)?;
         //delegate each opcode to a dedicated function
         match *opcode {
             OpCode::Lit(ref data, perm) => self.lit::<_,CE>(data, perm, context),
@@ -757,7 +820,9 @@ impl<'b,'h> Compactor<'b,'h> {
                     self.state.push_real()?
                 }
                 //process the branch body
+                self.branch = tag as u8;
                 let (n_exp, b_rets) = self.process_exp::<_,CE>(exp, ret_point, context, tail_info)?;
+                self.record_branch(tag as u8);
                 rets = b_rets;
                 //push the exp
                 new_exps.push(n_exp);
@@ -828,6 +893,7 @@ impl<'b,'h> Compactor<'b,'h> {
         }
         //proccess the expression
         let (new_succ, s_rets) = self.process_exp::<_,CE>(succ, ret_point, context, tail_info)?;
+        self.record_branch(0);
         //ready for the next branch if their is one or not is irrelevant
         self.state.next_branch();
         //eliminate the stack effects of the previous branch
@@ -839,7 +905,9 @@ impl<'b,'h> Compactor<'b,'h> {
             self.state.push_alias(pos);
         }
         //proccess the expression
+        self.branch = 1;
         let (new_fail, _)  = self.process_exp::<_,CE>(fail, ret_point, context, tail_info)?;
+        self.record_branch(1);
         //finish branching
         self.state.end_branching(branch_point);
         //if the inner is a continuation we need to drop a try frame
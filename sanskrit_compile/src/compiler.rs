@@ -21,13 +21,15 @@ use sanskrit_common::encoding::NoCustomAlloc;
 use sanskrit_core::utils::Crc;
 use sanskrit_common::arena::HeapArena;
 use crate::externals::CompilationExternals;
+use sanskrit_common::limiter::Limiter;
+use alloc::format;
 
 //Entry point that compiles all types and public functions of a module
-pub fn compile_transaction<'b, 'h, S:Store, CE:CompilationExternals>(transaction_hash:&Hash, store:&S, alloc:&'b HeapArena<'h>) -> Result<TransactionDescriptor<'b>>{
+pub fn compile_transaction<'b, 'h, S:Store, CE:CompilationExternals>(transaction_hash:&Hash, store:&CachedStore<Module,S>, alloc:&'b HeapArena<'h>, limiter:&'b Limiter) -> Result<TransactionDescriptor<'b>>{
 
     //load the module
     let fun:FunctionComponent = store.parsed_get(StorageClass::Transaction, transaction_hash, usize::MAX, &NoCustomAlloc())?;
-    let resolver = Loader::new_complete(store);
+    let resolver = Loader::new_complete(store, limiter);
 
     //generate descriptors for all internal functions
     if fun.scope != Accessibility::Global {
@@ -39,7 +41,7 @@ pub fn compile_transaction<'b, 'h, S:Store, CE:CompilationExternals>(transaction
                 //Prepare the context
                 let context = Context::from_top_component(&fun, &resolver)?;
                 //call the generator
-                generate_transaction_descriptor::<_,CE>(&fun, code, &context, &alloc)
+                generate_transaction_descriptor::<_,CE>(&fun, code, &context, &alloc, limiter)
             },
         }
     }
@@ -47,7 +49,7 @@ pub fn compile_transaction<'b, 'h, S:Store, CE:CompilationExternals>(transaction
 }
 
 //generates a function descriptor
-fn generate_transaction_descriptor<'b,'h, S:Store,CE:CompilationExternals>(fun:&FunctionComponent, code:&SExp, ctx:&Context<S>, alloc:&'b HeapArena<'h>) -> Result<TransactionDescriptor<'b>> {
+fn generate_transaction_descriptor<'b,'h, S:Store,CE:CompilationExternals>(fun:&FunctionComponent, code:&SExp, ctx:&Context<S>, alloc:&'b HeapArena<'h>, limiter:&'b Limiter) -> Result<TransactionDescriptor<'b>> {
     
     //collect the params type builder
     let mut params = alloc.slice_builder(fun.shared.params.len())?;
@@ -85,7 +87,7 @@ fn generate_transaction_descriptor<'b,'h, S:Store,CE:CompilationExternals>(fun:&
     }
     let returns = returns.finish();
     //do the compaction process
-    let (functions,ressources) = Compactor::compact::<_,CE>(fun, code,  &ctx.store, alloc)?;
+    let (functions,ressources) = Compactor::compact::<_,CE>(fun, code,  &ctx.store, alloc, limiter)?;
 
     if functions.len() > u16::MAX as usize {
         return error(||"Number of functions out of range")
@@ -112,6 +114,11 @@ fn generate_transaction_descriptor<'b,'h, S:Store,CE:CompilationExternals>(fun:&
     Ok(desc)
 }
 
+//The validation only lets top types and primitives through as parameters and returns of transactions
+// this is reached if that changes without the compiler being adapted
+fn unsupported<T>(typ:&ResolvedType) -> Result<T> {
+    owned_error(||format!("Type {} can not be passed to or returned from a transaction", typ))
+}
 
 pub fn resolved_to_runtime_type<'b,'h>(typ:&ResolvedType, alloc:&'b HeapArena<'h>) -> Result<RuntimeType<'b>> {
     //build an adt type
@@ -133,12 +140,12 @@ pub fn resolved_to_runtime_type<'b,'h>(typ:&ResolvedType, alloc:&'b HeapArena<'h
     
     Ok(match *typ {
         //transactions have no generics
-        ResolvedType::Generic { .. } => unreachable!() ,
+        ResolvedType::Generic { .. } => return unsupported(typ),
         //transactions can not take or return sigs
         //it is unreachable as transaction params and returns are limited to top types or primitives
         // Sig itself is neither & top wrappers require persist which sig has not
         // If in the future a top type witch does allow a inner Sig is introduced this needs implementation (which is impossible without changing the runtime completely)
-        ResolvedType::Sig {..} => unreachable!(),
+        ResolvedType::Sig {..} => return unsupported(typ),
         ResolvedType::Projection { depth, ref un_projected } => {
             let inner = resolved_to_runtime_type(&**un_projected, alloc)?;
             RuntimeType::Projection {
@@ -198,11 +205,11 @@ pub fn resolved_to_value_descriptor<'b,'h, S:Store, CE:CompilationExternals>(typ
 
     Ok(match *typ {
         //transactions have no generics
-        ResolvedType::Generic {  .. } => unreachable!(),
+        ResolvedType::Generic {  .. } => return unsupported(typ),
         //Virtuals never have instances of them
-        ResolvedType::Virtual(_) => unreachable!(),
+        ResolvedType::Virtual(_) => return unsupported(typ),
         //sigs are never primitives
-        ResolvedType::Sig {..} => unreachable!(),
+        ResolvedType::Sig {..} => return unsupported(typ),
         //images have the same repr as the inner
         ResolvedType::Projection { ref un_projected, .. } => resolved_to_value_descriptor::<_,CE>(&**un_projected, ctx, alloc)?,
         ResolvedType::Lit { ref module, offset, ref applies, .. }
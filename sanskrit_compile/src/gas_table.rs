@@ -1,58 +1,51 @@
-
-//Note we rounded up - No freactions
+//Note we rounded up - No fractions
 // Based on sanskrit_bench - main
-//  Current Run on home laptop with approx 3/5 as factor (in release)
+//  Generated with 0.6 gas per ns (11 runs of 256 repetitions per sample)
 pub mod gas {
 
-    //16
     pub fn call(args:usize) -> u64 {
-        20 + 2 * (args as u64)
+        27 + 2 * (args as u64)
     }
 
-    //todo: may be cheaper as arguments do not need reajusting after first run
-    //todo: may be more expensive as we need the abort check
-    //todo: measure
     pub fn repeated_call(args:usize, reps:u64) -> u64 {
-        reps*call(args)
+        call(args) + reps*24
     }
 
     pub fn void() -> u64 {
-        3
+        6
     }
 
-    pub fn gas() -> u64 { 2 /*Note this one is purely guessed*/ }
+    pub fn gas() -> u64 { 4 }
 
     pub fn sig(fields:usize) -> u64 {
-       pack(fields)
+        14 + 3 * (fields as u64)
     }
 
-    //Remeasuring gave
-    pub fn _let() -> u64 { 20 }
+    pub fn _let() -> u64 { 22 }
 
     pub fn unpack(fields:usize) -> u64 {
-        3 + 2*(fields as u64)
+        6 + 2 * (fields as u64)
     }
 
     pub fn field() -> u64 {
-        5
+        9
     }
 
     pub fn pack(fields:usize) -> u64 {
-        13 + 2*(fields as u64)
+        14 + 3 * (fields as u64)
     }
 
     pub fn switch() -> u64 {
-        20
+        25
     }
 
-    //This looks strange why so expensive (+4?)
     pub fn ret(rets:usize) -> u64 {
-        5 + 2*(rets as u64)
+        6 + 3 * (rets as u64)
     }
 
     pub fn rollback() -> u64 {
         5
     }
 
-    pub fn r#try() -> u64 { 20 }
+    pub fn r#try() -> u64 { 21 }
 }
@@ -11,19 +11,22 @@ mod compacting;
 pub mod compiler;
 mod gas_table;
 pub mod externals;
+pub mod bounds;
 
 use sanskrit_common::model::*;
 use sanskrit_common::store::*;
 use sanskrit_common::errors::*;
 use sanskrit_common::encoding::*;
 use sanskrit_common::arena::Heap;
+use sanskrit_common::limiter::Limiter;
 use alloc::vec::Vec;
 use externals::CompilationExternals;
+use sanskrit_core::model::Module;
 
 //compiles a single top function
-pub fn compile_function<S:Store, CE:CompilationExternals>(store:&S, function_hash:Hash, auto_commit:bool) -> Result<(Hash, usize)>{
+pub fn compile_function<S:Store, CE:CompilationExternals>(store:&CachedStore<Module,S>, function_hash:Hash, auto_commit:bool, limiter:&Limiter) -> Result<(Hash, usize)>{
     //create it
-    let (key, data) = create_descriptor::<_,CE>(store, function_hash)?;
+    let (key, data) = create_descriptor::<_,CE>(store, function_hash, limiter)?;
     //result size
     let size = data.len();
     //we ignore if it is already in
@@ -43,11 +46,11 @@ pub fn compile_function<S:Store, CE:CompilationExternals>(store:&S, function_has
     Ok((key, size))
 }
 
-pub fn create_descriptor<S:Store, CE:CompilationExternals>(store:&S, function_hash:Hash) -> Result<(Hash, Vec<u8>)>{
+pub fn create_descriptor<S:Store, CE:CompilationExternals>(store:&CachedStore<Module,S>, function_hash:Hash, limiter:&Limiter) -> Result<(Hash, Vec<u8>)>{
     let heap = Heap::new(10000,4.0);
     let alloc = heap.new_arena(10000);
     //compiles the content
-    let txt_desc = compiler::compile_transaction::<S, CE>(&function_hash, store, &alloc)?;
+    let txt_desc = compiler::compile_transaction::<S, CE>(&function_hash, store, &alloc, limiter)?;
     //serializes the content
     let data = Serializer::serialize_fully(&txt_desc, usize::MAX)?;
     //calcs the Key for the store
@@ -0,0 +1,396 @@
+//! Exports the interface of a module as json
+//!
+//! The export describes everything a client needs to interact with the module: the data types with their constructors and fields,
+//!  the signatures, and the functions and implements that other modules and transactions can call.
+//! Types are resolved and given structurally as well as in their readable form, names come from the metadata of the modules (if it uses the standard layout)
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::fmt::Write;
+use serde_json::{json, Value};
+use sanskrit_common::errors::*;
+use sanskrit_common::model::{Hash, ModuleLink};
+use sanskrit_common::store::{CachedStore, Store};
+use sanskrit_common::limiter::Limiter;
+use crate::model::*;
+use crate::model::bitsets::{CapSet, BitSet};
+use crate::model::display::Named;
+use crate::model::resolved::ResolvedType;
+use crate::metadata::{ModuleMetadata, MetadataNames, Name};
+use crate::loader::Loader;
+use crate::resolver::Context;
+use crate::utils::Crc;
+
+const CAPS:[Capability;6] = [Capability::Drop, Capability::Copy, Capability::Persist, Capability::Primitive, Capability::Value, Capability::Unbound];
+
+fn hex(data:&[u8]) -> String {
+    let mut res = String::with_capacity(data.len()*2);
+    for b in data {
+        //writing to a string can not fail
+        let _ = write!(res, "{:02x}", b);
+    }
+    res
+}
+
+fn caps_json(caps:CapSet) -> Value {
+    Value::Array(CAPS.iter().filter(|c|caps.contains(**c)).map(|c|Value::String(c.to_string())).collect())
+}
+
+fn generics_json(generics:&[Generic], names:Option<&[Name]>) -> Value {
+    Value::Array(generics.iter().enumerate().map(|(i, generic)|{
+        let name = names.and_then(|n|n.get(i)).map(|n|n.0.clone());
+        match *generic {
+            Generic::Phantom => json!({"name": name, "phantom": true}),
+            Generic::Physical(caps) => json!({"name": name, "phantom": false, "caps": caps_json(caps)}),
+        }
+    }).collect())
+}
+
+fn scope_json(scope:&Accessibility) -> Value {
+    match *scope {
+        Accessibility::Local => json!("local"),
+        Accessibility::Global => json!("global"),
+        Accessibility::Guarded(ref gens) => json!({"guarded": gens.iter().map(|g|g.0).collect::<Vec<_>>()}),
+    }
+}
+
+fn link_hash(link:&ModuleLink) -> &Hash {
+    match *link {
+        ModuleLink::Remote(ref hash)
+        | ModuleLink::This(ref hash) => hash
+    }
+}
+
+//The structure of a type
+fn type_structure(typ:&ResolvedType) -> Value {
+    match *typ {
+        ResolvedType::Generic { caps, offset, is_phantom } => json!({
+            "kind": "generic", "offset": offset, "phantom": is_phantom, "caps": caps_json(caps)
+        }),
+        ResolvedType::Projection { depth, ref un_projected } => json!({
+            "kind": "projection", "depth": depth, "type": type_structure(un_projected)
+        }),
+        ResolvedType::Sig { caps, ref module, offset, ref applies } => json!({
+            "kind": "sig", "module": hex(link_hash(module)), "offset": offset,
+            "applies": applies.iter().map(|t|type_structure(t)).collect::<Vec<_>>(), "caps": caps_json(caps)
+        }),
+        ResolvedType::Data { caps, ref module, offset, ref applies, .. } => json!({
+            "kind": "data", "module": hex(link_hash(module)), "offset": offset,
+            "applies": applies.iter().map(|t|type_structure(t)).collect::<Vec<_>>(), "caps": caps_json(caps)
+        }),
+        ResolvedType::Lit { caps, ref module, offset, ref applies, size, .. } => json!({
+            "kind": "lit", "module": hex(link_hash(module)), "offset": offset, "size": size,
+            "applies": applies.iter().map(|t|type_structure(t)).collect::<Vec<_>>(), "caps": caps_json(caps)
+        }),
+        ResolvedType::Virtual(ref hash) => json!({"kind": "virtual", "hash": hex(hash)}),
+    }
+}
+
+fn type_json(typ:&Crc<ResolvedType>, names:&BTreeMap<Hash, ModuleMetadata>) -> Value {
+    json!({
+        "display": Named(&**typ, &MetadataNames(names)).to_string(),
+        "structure": type_structure(typ)
+    })
+}
+
+fn params_json<S:Store>(params:&[Param], param_names:Option<&[Name]>, ctx:&Context<S>, names:&BTreeMap<Hash, ModuleMetadata>) -> Result<Value> {
+    let mut res = Vec::with_capacity(params.len());
+    for (i, param) in params.iter().enumerate() {
+        res.push(json!({
+            "name": param_names.and_then(|n|n.get(i)).map(|n|n.0.clone()),
+            "consumes": param.consumes,
+            "type": type_json(&ctx.get_type(param.typ)?, names)
+        }))
+    }
+    Ok(Value::Array(res))
+}
+
+fn returns_json<S:Store>(returns:&[TypeRef], ctx:&Context<S>, names:&BTreeMap<Hash, ModuleMetadata>) -> Result<Value> {
+    let mut res = Vec::with_capacity(returns.len());
+    for ret in returns {
+        res.push(type_json(&ctx.get_type(*ret)?, names))
+    }
+    Ok(Value::Array(res))
+}
+
+//Loads the metadata of the module and of all the modules it imports
+// known metadata is kept for modules whose metadata section does not use the standard layout
+fn collect_metadata<S:Store>(loader:&Loader<S>, module_hash:Hash, module:&Module, mut metas:BTreeMap<Hash, ModuleMetadata>) -> Result<BTreeMap<Hash, ModuleMetadata>> {
+    let mut imports:Vec<&PublicImport> = Vec::new();
+    imports.extend(module.data.iter().map(|d|&d.import));
+    imports.extend(module.sigs.iter().map(|s|&s.shared.import));
+    imports.extend(module.functions.iter().map(|f|&f.shared.import));
+    imports.extend(module.implements.iter().map(|i|&i.import));
+
+    let mut visited = BTreeSet::new();
+    visited.insert(module_hash);
+    if let Some(meta) = ModuleMetadata::from_module(module) {
+        metas.insert(module_hash, meta);
+    }
+    for import in imports {
+        for link in &import.modules {
+            let hash = link_hash(link);
+            if !visited.insert(*hash) { continue }
+            if let Some(meta) = ModuleMetadata::from_module(&*loader.get_module(*hash)?) {
+                metas.insert(*hash, meta);
+            }
+        }
+    }
+    Ok(metas)
+}
+
+//Produces the interface of a deployed module
+// Data types are always included as every module can use them as types, the scopes tell which operations are available to others
+// Signatures, functions and implements that are only usable inside of the module are left out
+// known can provide names for modules that were compiled without the standard metadata layout
+pub fn export_abi<S:Store>(store:&CachedStore<Module,S>, module_hash:Hash, known:BTreeMap<Hash, ModuleMetadata>) -> Result<Value> {
+    //the abi is produced for already deployed modules and not metered
+    let limiter = Limiter::unlimited();
+    let loader = Loader::new_complete(store, &limiter);
+    let module = loader.get_module(module_hash)?;
+    let link = loader.dedup_module_link(ModuleLink::Remote(module_hash));
+    let names = collect_metadata(&loader, module_hash, &module, known)?;
+    let meta = names.get(&module_hash);
+
+    let mut data = Vec::with_capacity(module.data.len());
+    for (offset, comp) in module.data.iter().enumerate() {
+        let ctx = Context::from_module_component(comp, &link, false, &loader)?;
+        let data_meta = meta.and_then(|m|m.data.get(offset));
+        let body = match comp.body {
+            DataImpl::External(size) => json!({"external": size}),
+            DataImpl::Internal { ref constructors } => {
+                let mut ctrs = Vec::with_capacity(constructors.len());
+                for (tag, case) in constructors.iter().enumerate() {
+                    let ctr_meta = data_meta.and_then(|d|d.ctrs.get(tag));
+                    let mut fields = Vec::with_capacity(case.fields.len());
+                    for (i, field) in case.fields.iter().enumerate() {
+                        fields.push(json!({
+                            "name": ctr_meta.and_then(|c|c.fields.get(i)).map(|n|n.0.clone()),
+                            "indexed": field.indexed,
+                            "type": type_json(&ctx.get_type(field.typ)?, &names)
+                        }))
+                    }
+                    ctrs.push(json!({
+                        "tag": tag,
+                        "name": ctr_meta.map(|c|c.name.0.clone()),
+                        "fields": fields
+                    }))
+                }
+                json!({"constructors": ctrs})
+            }
+        };
+        data.push(json!({
+            "offset": offset,
+            "name": data_meta.map(|d|d.name.0.clone()),
+            "create_scope": scope_json(&comp.create_scope),
+            "consume_scope": scope_json(&comp.consume_scope),
+            "inspect_scope": scope_json(&comp.inspect_scope),
+            "caps": caps_json(comp.provided_caps),
+            "generics": generics_json(&comp.generics, data_meta.map(|d|&d.generics[..])),
+            "body": body
+        }))
+    }
+
+    let mut sigs = Vec::with_capacity(module.sigs.len());
+    for (offset, comp) in module.sigs.iter().enumerate() {
+        if comp.call_scope == Accessibility::Local && comp.implement_scope == Accessibility::Local { continue }
+        let ctx = Context::from_module_component(comp, &link, false, &loader)?;
+        let sig_meta = meta.and_then(|m|m.sigs.get(offset));
+        sigs.push(json!({
+            "offset": offset,
+            "name": sig_meta.map(|s|s.name.0.clone()),
+            "call_scope": scope_json(&comp.call_scope),
+            "implement_scope": scope_json(&comp.implement_scope),
+            "caps": caps_json(comp.provided_caps),
+            "transactional": comp.shared.transactional,
+            "generics": generics_json(&comp.shared.generics, sig_meta.map(|s|&s.generics[..])),
+            "params": params_json(&comp.shared.params, sig_meta.map(|s|&s.params[..]), &ctx, &names)?,
+            "returns": returns_json(&comp.shared.returns, &ctx, &names)?
+        }))
+    }
+
+    let mut functions = Vec::with_capacity(module.functions.len());
+    for (offset, comp) in module.functions.iter().enumerate() {
+        if comp.scope == Accessibility::Local { continue }
+        let ctx = Context::from_module_component(comp, &link, false, &loader)?;
+        let fun_meta = meta.and_then(|m|m.functions.get(offset));
+        functions.push(json!({
+            "offset": offset,
+            "name": fun_meta.map(|f|f.name.0.clone()),
+            "scope": scope_json(&comp.scope),
+            "external": matches!(comp.body, CallableImpl::External),
+            "transactional": comp.shared.transactional,
+            "generics": generics_json(&comp.shared.generics, fun_meta.map(|f|&f.generics[..])),
+            "params": params_json(&comp.shared.params, fun_meta.map(|f|&f.params[..]), &ctx, &names)?,
+            "returns": returns_json(&comp.shared.returns, &ctx, &names)?
+        }))
+    }
+
+    let mut implements = Vec::with_capacity(module.implements.len());
+    for (offset, comp) in module.implements.iter().enumerate() {
+        if comp.scope == Accessibility::Local { continue }
+        let ctx = Context::from_module_component(comp, &link, false, &loader)?;
+        let impl_meta = meta.and_then(|m|m.implements.get(offset));
+        let sig = ctx.get_perm(comp.sig)?;
+        implements.push(json!({
+            "offset": offset,
+            "name": impl_meta.map(|i|i.name.0.clone()),
+            "scope": scope_json(&comp.scope),
+            "sig": type_json(sig.get_type()?, &names),
+            "generics": generics_json(&comp.generics, impl_meta.map(|i|&i.generics[..])),
+            //the captured values, the remaining params are the ones of the signature
+            "params": params_json(&comp.params, impl_meta.map(|i|&i.params[..]), &ctx, &names)?
+        }))
+    }
+
+    Ok(json!({
+        "hash": hex(&module_hash),
+        "name": meta.map(|m|m.name.0.clone()),
+        "system_module": module.system_module,
+        "data": data,
+        "sigs": sigs,
+        "functions": functions,
+        "implements": implements
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use sanskrit_common::encoding::Serializer;
+    use sanskrit_common::model::{LargeVec, ValueRef, Tag};
+    use sanskrit_common::store::{store_hash, StorageClass};
+    use sanskrit_memory_store::BTreeMapStore;
+    use crate::metadata::{DataMetadata, CtrMetadata, CallableMetadata};
+
+    fn name(name:&str) -> Name {
+        Name(name.to_string())
+    }
+
+    //Choice(TypeRef 0 of the components) is the first data type of the module itself
+    fn choice_import() -> PublicImport {
+        PublicImport{ modules: vec![], types: vec![TypeImport::Data{ link: DataLink{ module: ModRef(0), offset: 0 }, applies: vec![] }] }
+    }
+
+    fn data(provided_caps:CapSet, import:PublicImport, constructors:Vec<Case>) -> DataComponent {
+        DataComponent {
+            byte_size: None,
+            create_scope: Accessibility::Global,
+            consume_scope: Accessibility::Local,
+            inspect_scope: Accessibility::Global,
+            provided_caps,
+            generics: vec![],
+            import,
+            body: DataImpl::Internal{ constructors },
+        }
+    }
+
+    fn function(scope:Accessibility) -> FunctionComponent {
+        FunctionComponent {
+            byte_size: None,
+            scope,
+            shared: FunSigShared {
+                transactional: false,
+                generics: vec![],
+                import: choice_import(),
+                params: vec![Param{ consumes: false, typ: TypeRef(0) }],
+                returns: vec![TypeRef(0)],
+            },
+            body: CallableImpl::Internal {
+                byte_size: None,
+                imports: BodyImport{ public: PublicImport{ modules: vec![], types: vec![] }, callables: vec![], permissions: vec![] },
+                code: Exp(LargeVec(vec![OpCode::Pack(PermRef(0), Tag(0), vec![]), OpCode::Return(vec![ValueRef(0)])])),
+            }
+        }
+    }
+
+    //Choice = Cheap | Expensive, Pair(first:Choice, second:Choice), pick(choice:Choice):Choice and a local helper
+    fn module() -> Vec<u8> {
+        let meta = ModuleMetadata {
+            version: ModuleMetadata::VERSION,
+            name: name("Choices"),
+            data: vec![
+                DataMetadata{ name: name("Choice"), generics: vec![], ctrs: vec![
+                    CtrMetadata{ name: name("Cheap"), fields: vec![] },
+                    CtrMetadata{ name: name("Expensive"), fields: vec![] },
+                ]},
+                DataMetadata{ name: name("Pair"), generics: vec![], ctrs: vec![
+                    CtrMetadata{ name: name("Pair"), fields: vec![name("first"), name("second")] },
+                ]},
+            ],
+            sigs: vec![],
+            functions: vec![
+                CallableMetadata{ name: name("pick"), generics: vec![], params: vec![name("choice")] },
+                CallableMetadata{ name: name("helper"), generics: vec![], params: vec![name("choice")] },
+            ],
+            implements: vec![],
+        };
+        let field = Field{ indexed: vec![], typ: TypeRef(0) };
+        let module = Module {
+            byte_size: None,
+            system_module: false,
+            meta: LargeVec(meta.to_bytes().unwrap()),
+            data: vec![
+                data(CapSet::primitive(), PublicImport{ modules: vec![], types: vec![] }, vec![Case{ fields: vec![] }, Case{ fields: vec![] }]),
+                data(CapSet::from_entry(Capability::Drop), choice_import(), vec![Case{ fields: vec![field.clone(), field] }]),
+            ],
+            sigs: vec![],
+            data_sig_order: BitSerializedVec(vec![true, true]),
+            functions: vec![function(Accessibility::Global), function(Accessibility::Local)],
+            fun_impl_order: BitSerializedVec(vec![true, true]),
+            implements: vec![],
+        };
+        Serializer::serialize_fully(&module, 100).unwrap()
+    }
+
+    #[test]
+    fn abi_names_the_public_interface() {
+        let module = module();
+        let hash = store_hash(&[&module]);
+        let store = CachedStore::new(BTreeMapStore::new(), StorageClass::Module);
+        store.set(StorageClass::Module, hash, module).unwrap();
+        store.commit(StorageClass::Module);
+
+        let abi = export_abi(&store, hash, BTreeMap::new()).unwrap();
+        let choice = json!({
+            "display": "Choices.Choice{Drop,Copy,Persist,Primitive,Value,Unbound}",
+            "structure": {"kind": "data", "module": hex(&hash), "offset": 0, "applies": [], "caps": ["Drop", "Copy", "Persist", "Primitive", "Value", "Unbound"]}
+        });
+        assert_eq!(abi["hash"], json!(hex(&hash)));
+        assert_eq!(abi["name"], json!("Choices"));
+        assert_eq!(abi["system_module"], json!(false));
+        assert_eq!(abi["data"][0]["name"], json!("Choice"));
+        assert_eq!(abi["data"][0]["body"]["constructors"], json!([
+            {"tag": 0, "name": "Cheap", "fields": []},
+            {"tag": 1, "name": "Expensive", "fields": []},
+        ]));
+        assert_eq!(abi["data"][1], json!({
+            "offset": 1,
+            "name": "Pair",
+            "create_scope": "global",
+            "consume_scope": "local",
+            "inspect_scope": "global",
+            "caps": ["Drop"],
+            "generics": [],
+            "body": {"constructors": [{"tag": 0, "name": "Pair", "fields": [
+                {"name": "first", "indexed": [], "type": choice},
+                {"name": "second", "indexed": [], "type": choice},
+            ]}]}
+        }));
+        //the local helper is not part of the interface
+        assert_eq!(abi["functions"], json!([{
+            "offset": 0,
+            "name": "pick",
+            "scope": "global",
+            "external": false,
+            "transactional": false,
+            "generics": [],
+            "params": [{"name": "choice", "consumes": false, "type": choice}],
+            "returns": [choice]
+        }]));
+        assert_eq!(abi["sigs"], json!([]));
+        assert_eq!(abi["implements"], json!([]));
+    }
+}
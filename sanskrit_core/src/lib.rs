@@ -10,3 +10,6 @@ pub mod utils;
 pub mod loader;
 pub mod resolver;
 pub mod model;
+pub mod metadata;
+#[cfg(feature = "abi")]
+pub mod abi;
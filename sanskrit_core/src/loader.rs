@@ -13,6 +13,12 @@ use sanskrit_common::encoding::NoCustomAlloc;
 use crate::model::*;
 use crate::model::linking::{Link, Component};
 use core::marker::PhantomData;
+use sanskrit_common::limiter::Limiter;
+
+//Work units charged for each loaded dependency (in addition to its size)
+const LOAD_COST:u64 = 100;
+//Work units charged per byte of a loaded dependency
+const BYTE_COST:u64 = 1;
 
 pub struct Loader<'a, S:Store + 'a> {
     //Caches the modules
@@ -21,6 +27,7 @@ pub struct Loader<'a, S:Store + 'a> {
     dedup_hash:RefCell<CrcDeDup<ModuleLink>>,
     modules:RefCell<BTreeMap<Hash,Crc<Module>>>,
     store:&'a CachedStore<Module, S>, // a reference to the store in case a module is not cached
+    pub(crate) limiter:&'a Limiter,   // accounts for the loaded modules and the resolved imports
     // Object loaders
     // deduplication
     dedup_type:RefCell<CrcDeDup<ResolvedType>>,
@@ -47,7 +54,7 @@ pub struct FetchCache<T:Component> {
 impl<'a, S:Store + 'a> Loader<'a,S> {
     //A new partially loaded Storage Cache
     //it starts out with the module currently processed
-    pub fn new_incremental(store:&'a CachedStore<Module, S>,  link:Hash, module:Rc<Module>) -> Self{
+    pub fn new_incremental(store:&'a CachedStore<Module, S>,  link:Hash, module:Rc<Module>, limiter:&'a Limiter) -> Self{
         //A new empty cache
         let mut modules = BTreeMap::new();
         //Insert the Module
@@ -57,6 +64,7 @@ impl<'a, S:Store + 'a> Loader<'a,S> {
         Loader {
             modules:RefCell::new(modules),
             store,
+            limiter,
             dedup_type: RefCell::new(CrcDeDup::new()),
             dedup_call: RefCell::new(CrcDeDup::new()),
             dedup_perm: RefCell::new(CrcDeDup::new()),
@@ -72,11 +80,12 @@ impl<'a, S:Store + 'a> Loader<'a,S> {
     }
 
     //A new fully loaded storage cache
-    pub fn new_complete(store:&'a CachedStore<Module, S>) -> Self{
+    pub fn new_complete(store:&'a CachedStore<Module, S>, limiter:&'a Limiter) -> Self{
         //Works As: current need to use this and all other that can be used from this can not use this
         Loader {
             modules:RefCell::new(BTreeMap::new()),
             store,
+            limiter,
             dedup_type: RefCell::new(CrcDeDup::new()),
             dedup_call: RefCell::new(CrcDeDup::new()),
             dedup_perm: RefCell::new(CrcDeDup::new()),
@@ -97,7 +106,9 @@ impl<'a, S:Store + 'a> Loader<'a,S> {
         //if already their ignore it else create it
         if !modules.contains_key(&hash) {
             //get the module from the store by its hash
-            let module = self.store.get_cached::<NoCustomAlloc>(&hash, usize::max_value(), &NoCustomAlloc())?;
+            let (module, size) = self.store.get_cached_sized::<NoCustomAlloc>(&hash, usize::max_value(), &NoCustomAlloc())?;
+            //charge the size of the encoding, so that the charge does not depend on what is cached
+            self.limiter.consume(LOAD_COST + size as u64 * BYTE_COST)?;
             //Ref count it and insert it
             let res = Crc{elem:module};
             modules.insert(hash,res.clone());
@@ -0,0 +1,186 @@
+//! The standard layout of the metadata section of a module (`Module.meta`)
+//!
+//! Sanskrit itself never looks at the metadata, it is there for tools that want to show names instead of indexes.
+//! Compilers that use this layout make their modules readable by every tool (explorers, wallets, SDK generators, ...).
+//! All the lists are in the same order as the corresponding components in the module.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::collections::BTreeMap;
+use sanskrit_common::encoding::*;
+use sanskrit_common::errors::*;
+use sanskrit_common::model::Hash;
+use crate::model::Module;
+use crate::model::display::Names;
+
+//Allows for the nesting of the layout (Module -> Data -> Constructors -> Fields)
+const MAX_METADATA_DEPTH:usize = 8;
+
+#[derive(Debug, Parsable, Serializable)]
+pub struct ModuleMetadata {
+    pub version:u8,
+    pub name:Name,
+    pub data:Vec<DataMetadata>,
+    pub sigs:Vec<CallableMetadata>,
+    pub functions:Vec<CallableMetadata>,
+    pub implements:Vec<CallableMetadata>,
+}
+
+#[derive(Debug, Parsable, Serializable)]
+pub struct DataMetadata {
+    pub name:Name,
+    pub generics:Vec<Name>,
+    pub ctrs:Vec<CtrMetadata>,
+}
+
+#[derive(Debug, Parsable, Serializable)]
+pub struct CtrMetadata {
+    pub name:Name,
+    pub fields:Vec<Name>,
+}
+
+#[derive(Debug, Parsable, Serializable)]
+pub struct CallableMetadata {
+    pub name:Name,
+    pub generics:Vec<Name>,
+    pub params:Vec<Name>,
+}
+
+//An utf8 string with an u16 length prefix
+#[derive(Clone, Debug)]
+pub struct Name(pub String);
+
+impl<'a> Parsable<'a> for Name {
+    fn parse<A: ParserAllocator>(p: &mut Parser, alloc:&'a A) -> Result<Self> {
+        let len = u16::parse(p,alloc)?;
+        let data = p.consume_bytes(len as usize)?;
+        match String::from_utf8(data.to_vec()) {
+            Ok(name) => Ok(Name(name)),
+            Err(_) => error(||"Names must be utf8 encoded")
+        }
+    }
+}
+
+impl Serializable for Name {
+    fn serialize(&self, s:&mut Serializer) -> Result<()> {
+        if self.0.len() > u16::MAX as usize {
+            return error(||"Name is to long")
+        }
+        (self.0.len() as u16).serialize(s)?;
+        s.produce_bytes(self.0.as_bytes());
+        Ok(())
+    }
+}
+
+impl ModuleMetadata {
+    pub const VERSION:u8 = 1;
+
+    //Reads the metadata of a module (None if its metadata does not follow this layout)
+    pub fn from_module(module:&Module) -> Option<Self> {
+        let meta:ModuleMetadata = Parser::parse_fully(&module.meta.0, MAX_METADATA_DEPTH, &NoCustomAlloc()).ok()?;
+        if meta.version == Self::VERSION {
+            Some(meta)
+        } else {
+            None
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Serializer::serialize_fully(self, MAX_METADATA_DEPTH)
+    }
+}
+
+fn name_at<T>(elems:&[T], offset:u8, name:impl Fn(&T) -> &Name) -> Option<String> {
+    elems.get(offset as usize).map(|elem|name(elem).0.clone())
+}
+
+//The metadata of several modules, allows to show names for types from other modules
+pub struct MetadataNames<'a>(pub &'a BTreeMap<Hash, ModuleMetadata>);
+
+impl<'a> Names for MetadataNames<'a> {
+    fn module_name(&self, module:&Hash) -> Option<String> {
+        self.0.get(module).map(|meta|meta.name.0.clone())
+    }
+
+    fn data_name(&self, module:&Hash, offset:u8) -> Option<String> {
+        name_at(&self.0.get(module)?.data, offset, |d|&d.name)
+    }
+
+    fn sig_name(&self, module:&Hash, offset:u8) -> Option<String> {
+        name_at(&self.0.get(module)?.sigs, offset, |s|&s.name)
+    }
+
+    fn function_name(&self, module:&Hash, offset:u8) -> Option<String> {
+        name_at(&self.0.get(module)?.functions, offset, |f|&f.name)
+    }
+
+    fn implement_name(&self, module:&Hash, offset:u8) -> Option<String> {
+        name_at(&self.0.get(module)?.implements, offset, |i|&i.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use sanskrit_common::model::LargeVec;
+    use crate::model::BitSerializedVec;
+
+    fn name(name:&str) -> Name {
+        Name(name.to_string())
+    }
+
+    fn metadata() -> ModuleMetadata {
+        ModuleMetadata {
+            version: ModuleMetadata::VERSION,
+            name: name("Token"),
+            data: vec![DataMetadata{ name: name("Coin"), generics: vec![name("T")], ctrs: vec![
+                CtrMetadata{ name: name("Coin"), fields: vec![name("amount"), name("owner")] }
+            ]}],
+            sigs: vec![CallableMetadata{ name: name("Check"), generics: vec![], params: vec![name("coin")] }],
+            functions: vec![CallableMetadata{ name: name("mint"), generics: vec![name("T")], params: vec![name("amount"), name("ünïcödé")] }],
+            implements: vec![],
+        }
+    }
+
+    fn module(meta:Vec<u8>) -> Module {
+        Module {
+            byte_size: None,
+            system_module: false,
+            meta: LargeVec(meta),
+            data: vec![],
+            sigs: vec![],
+            data_sig_order: BitSerializedVec(vec![]),
+            functions: vec![],
+            fun_impl_order: BitSerializedVec(vec![]),
+            implements: vec![],
+        }
+    }
+
+    #[test]
+    fn metadata_survives_a_round_trip() {
+        let bytes = metadata().to_bytes().unwrap();
+        let parsed = ModuleMetadata::from_module(&module(bytes.clone())).unwrap();
+        assert_eq!(parsed.to_bytes().unwrap(), bytes);
+        assert_eq!(parsed.name.0, "Token");
+        assert_eq!(parsed.data[0].ctrs[0].fields.iter().map(|f|f.0.as_str()).collect::<Vec<_>>(), vec!["amount", "owner"]);
+        assert_eq!(parsed.sigs[0].params[0].0, "coin");
+        assert_eq!(parsed.functions[0].params[1].0, "ünïcödé");
+        assert!(parsed.implements.is_empty());
+    }
+
+    #[test]
+    fn other_layouts_are_ignored() {
+        let mut meta = metadata();
+        meta.version = ModuleMetadata::VERSION + 1;
+        assert!(ModuleMetadata::from_module(&module(meta.to_bytes().unwrap())).is_none());
+        assert!(ModuleMetadata::from_module(&module(vec![1, 2, 3])).is_none());
+        assert!(ModuleMetadata::from_module(&module(vec![])).is_none());
+        //names must be utf8
+        let mut bytes = metadata().to_bytes().unwrap();
+        let pos = bytes.windows(5).position(|w|w == b"Token").unwrap();
+        bytes[pos] = 0xff;
+        assert!(ModuleMetadata::from_module(&module(bytes)).is_none());
+    }
+}
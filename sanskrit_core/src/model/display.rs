@@ -0,0 +1,230 @@
+//! Human readable rendering of resolved types, callables and permissions
+//!
+//! Modules and their components are shown by name if a `Names` source knows them, otherwise the module is shown by the start of its hash and the component by its index
+//! A type is rendered as `Module.Name[Applies]{Capabilities}`, for example `Token.Coin[Data.Data20]{Drop,Copy}`, where the capabilities are only shown on the outermost type
+
+use core::fmt;
+use sanskrit_common::model::ModuleLink;
+use sanskrit_common::names::{self, Component};
+use crate::model::{Capability, Permission};
+use crate::model::bitsets::{CapSet, PermSet, BitSet};
+use crate::model::resolved::{ResolvedType, ResolvedCallable, ResolvedPermission};
+use crate::utils::Crc;
+
+pub use sanskrit_common::names::{Names, NoNames};
+
+//Renders an element with the names from a source
+pub struct Named<'a, T:?Sized, N:Names>(pub &'a T, pub &'a N);
+
+fn write_component<N:Names>(f:&mut fmt::Formatter<'_>, names:&N, module:&ModuleLink, kind:Component, offset:u8) -> fmt::Result {
+    match *module {
+        ModuleLink::Remote(ref hash)
+        | ModuleLink::This(ref hash) => names::write_component(f, names, hash, kind, offset)
+    }
+}
+
+fn write_applies<N:Names>(f:&mut fmt::Formatter<'_>, names:&N, applies:&[Crc<ResolvedType>]) -> fmt::Result {
+    if applies.is_empty() {
+        return Ok(())
+    }
+    write!(f, "[")?;
+    for (i, typ) in applies.iter().enumerate() {
+        if i != 0 { write!(f, ",")? }
+        write_type(f, names, typ)?;
+    }
+    write!(f, "]")
+}
+
+//Writes a type without its capabilities
+fn write_type<N:Names>(f:&mut fmt::Formatter<'_>, names:&N, typ:&ResolvedType) -> fmt::Result {
+    match *typ {
+        ResolvedType::Generic { offset, is_phantom, .. } => {
+            if is_phantom { write!(f, "phantom ")? }
+            write!(f, "${}", offset)
+        },
+        ResolvedType::Projection { depth, ref un_projected } => {
+            for _ in 0..depth { write!(f, "^")? }
+            write_type(f, names, un_projected)
+        },
+        ResolvedType::Sig { ref module, offset, ref applies, .. } => {
+            write_component(f, names, module, Component::Sig, offset)?;
+            write_applies(f, names, applies)
+        },
+        ResolvedType::Data { ref module, offset, ref applies, .. }
+        | ResolvedType::Lit { ref module, offset, ref applies, .. } => {
+            write_component(f, names, module, Component::Data, offset)?;
+            write_applies(f, names, applies)
+        },
+        ResolvedType::Virtual(ref hash) => {
+            write!(f, "virtual(")?;
+            names::write_short_hash(f, hash)?;
+            write!(f, ")")
+        }
+    }
+}
+
+impl<'a, N:Names> fmt::Display for Named<'a, ResolvedType, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_type(f, self.1, self.0)?;
+        write!(f, "{}", self.0.get_caps())
+    }
+}
+
+impl<'a, N:Names> fmt::Display for Named<'a, ResolvedCallable, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self.0 {
+            ResolvedCallable::Function { ref module, offset, ref applies } => {
+                write_component(f, self.1, module, Component::Function, offset)?;
+                write_applies(f, self.1, applies)
+            },
+            ResolvedCallable::Implement { ref module, offset, ref applies } => {
+                write_component(f, self.1, module, Component::Implement, offset)?;
+                write_applies(f, self.1, applies)
+            },
+        }
+    }
+}
+
+impl<'a, N:Names> fmt::Display for Named<'a, ResolvedPermission, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self.0 {
+            ResolvedPermission::TypeSig { perm, ref typ, .. }
+            | ResolvedPermission::TypeData { perm, ref typ, .. }
+            | ResolvedPermission::TypeLit { perm, ref typ, .. } => {
+                write!(f, "{} on ", perm)?;
+                write_type(f, self.1, typ)
+            },
+            ResolvedPermission::FunSig { perm, ref fun, .. } => write!(f, "{} on {}", perm, Named(&**fun, self.1)),
+        }
+    }
+}
+
+impl fmt::Display for ResolvedType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Named(self, &NoNames).fmt(f)
+    }
+}
+
+impl fmt::Display for ResolvedCallable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Named(self, &NoNames).fmt(f)
+    }
+}
+
+impl fmt::Display for ResolvedPermission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Named(self, &NoNames).fmt(f)
+    }
+}
+
+impl<T:fmt::Display> fmt::Display for Crc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            Capability::Drop => "Drop",
+            Capability::Copy => "Copy",
+            Capability::Persist => "Persist",
+            Capability::Primitive => "Primitive",
+            Capability::Value => "Value",
+            Capability::Unbound => "Unbound",
+        })
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            Permission::Create => "Create",
+            Permission::Consume => "Consume",
+            Permission::Inspect => "Inspect",
+            Permission::Call => "Call",
+            Permission::Implement => "Implement",
+        })
+    }
+}
+
+fn write_set<T:fmt::Display>(f:&mut fmt::Formatter<'_>, elems:&[(T, bool)]) -> fmt::Result {
+    write!(f, "{{")?;
+    let mut first = true;
+    for (elem, _) in elems.iter().filter(|(_, contained)|*contained) {
+        if !first { write!(f, ",")? }
+        first = false;
+        write!(f, "{}", elem)?;
+    }
+    write!(f, "}}")
+}
+
+impl fmt::Display for CapSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let caps = [Capability::Drop, Capability::Copy, Capability::Persist, Capability::Primitive, Capability::Value, Capability::Unbound];
+        write_set(f, &caps.map(|c|(c, self.contains(c))))
+    }
+}
+
+impl fmt::Display for PermSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let perms = [Permission::Create, Permission::Consume, Permission::Inspect, Permission::Call, Permission::Implement];
+        write_set(f, &perms.map(|p|(p, self.contains(p))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::rc::Rc;
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use sanskrit_common::model::Hash;
+
+    const TOKEN:Hash = [1;20];
+    const OTHER:Hash = [0xab;20];
+
+    struct TestNames;
+    impl Names for TestNames {
+        fn module_name(&self, module:&Hash) -> Option<String> {
+            if *module == TOKEN { Some("Token".to_string()) } else { None }
+        }
+
+        fn data_name(&self, module:&Hash, offset:u8) -> Option<String> {
+            if *module == TOKEN && offset == 0 { Some("Coin".to_string()) } else { None }
+        }
+    }
+
+    fn crc<T>(elem:T) -> Crc<T> {
+        Crc { elem: Rc::new(elem) }
+    }
+
+    fn data(module:Hash, offset:u8, applies:Vec<Crc<ResolvedType>>, caps:CapSet) -> Crc<ResolvedType> {
+        crc(ResolvedType::Data { generic_caps: caps, caps, module: crc(ModuleLink::Remote(module)), offset, applies })
+    }
+
+    #[test]
+    fn types_are_shown_by_name_with_the_outer_capabilities() {
+        let caps = CapSet::empty().with_elems([Capability::Drop, Capability::Copy].into_iter());
+        let inner = data(OTHER, 3, vec![], CapSet::all());
+        let coin = data(TOKEN, 0, vec![inner], caps);
+        assert_eq!(Named(&*coin, &TestNames).to_string(), "Token.Coin[abababab.data3]{Drop,Copy}");
+        assert_eq!(coin.to_string(), "01010101.data0[abababab.data3]{Drop,Copy}");
+    }
+
+    #[test]
+    fn projections_and_generics_use_their_capabilities() {
+        let generic = crc(ResolvedType::Generic { caps: CapSet::from_entry(Capability::Drop), offset: 1, is_phantom: true });
+        assert_eq!(generic.to_string(), "phantom $1{Drop}");
+        let projection = crc(ResolvedType::Projection { depth: 2, un_projected: data(TOKEN, 0, vec![], CapSet::empty()) });
+        assert_eq!(Named(&*projection, &TestNames).to_string(), "^^Token.Coin{Drop,Copy,Persist,Primitive,Value,Unbound}");
+    }
+
+    #[test]
+    fn callables_and_permissions_are_named() {
+        let fun = crc(ResolvedCallable::Function { module: crc(ModuleLink::Remote(TOKEN)), offset: 4, applies: vec![data(TOKEN, 0, vec![], CapSet::empty())] });
+        assert_eq!(Named(&*fun, &TestNames).to_string(), "Token.fun4[Token.Coin]");
+        assert_eq!(PermSet::from_entry(Permission::Call).to_string(), "{Call}");
+    }
+}
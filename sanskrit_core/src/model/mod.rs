@@ -2,6 +2,7 @@ pub mod resolved;
 pub mod linking;
 pub mod bitsets;
 pub mod efficency;
+pub mod display;
 
 use alloc::vec::Vec;
 use sanskrit_common::model::*;
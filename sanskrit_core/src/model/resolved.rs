@@ -121,6 +121,26 @@ impl Crc<ResolvedPermission> {
 
 }
 
+//Functions that do not need the type to be shared (they are reachable through a Crc as well)
+impl ResolvedType {
+    //Extracts the capabilities
+    // This has nothing injected and have the constraints only in case of generics
+    //   Thanks to this we can still have a Option[<Drop,Embed> T] if we want to deny using it with non-drop types.
+    //  This must be used when checking that a type applied to a generic full fills its constraint
+    //  This must be used when checking if the correct caps are available to execute a operation
+    // Note: this only influences generics and applied types with generic inputs
+    pub fn get_caps(&self) -> CapSet {
+        match *self {
+            ResolvedType::Generic { caps, .. }
+            | ResolvedType::Sig { caps, .. }
+            | ResolvedType::Lit { caps, .. }
+            | ResolvedType::Data { caps, .. } => caps,
+            ResolvedType::Projection { .. } => CapSet::all(),
+            ResolvedType::Virtual(_) => CapSet::empty()
+        }
+    }
+}
+
 //Some usefull functions on the Type
 impl Crc<ResolvedType> {
     //Extracts the capabilities
@@ -140,23 +160,6 @@ impl Crc<ResolvedType> {
         }
     }
 
-    //Extracts the capabilities
-    // This has nothing injected and have the constraints only in case of generics
-    //   Thanks to this we can still have a Option[<Drop,Embed> T] if we want to deny using it with non-drop types.
-    //  This must be used when checking that a type applied to a generic full fills its constraint
-    //  This must be used when checking if the correct caps are available to execute a operation
-    // Note: this only influences generics and applied types with generic inputs
-    pub fn get_caps(&self) -> CapSet {
-        match **self {
-            ResolvedType::Generic { caps, .. }
-            | ResolvedType::Sig { caps, .. }
-            | ResolvedType::Lit { caps, .. }
-            | ResolvedType::Data { caps, .. } => caps,
-            ResolvedType::Projection { .. } => CapSet::all(),
-            ResolvedType::Virtual(_) => CapSet::empty()
-        }
-    }
-
     pub fn get_target(&self) -> &Crc<ResolvedType> {
         match **self {
             ResolvedType::Projection { ref un_projected, .. } => {
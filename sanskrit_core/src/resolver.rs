@@ -10,6 +10,9 @@ use crate::model::linking::{Component, CallableComponent};
 use alloc::rc::Rc;
 use crate::model::bitsets::{CapSet, BitSet};
 
+//Work units charged for each resolved import
+const IMPORT_COST:u64 = 10;
+
 //All things that can be cached in an import
 pub struct CachedImports {
     mref_cache:Vec<Crc<ModuleLink>>,                 //Cached Modules
@@ -225,6 +228,7 @@ impl<'b, S:Store + 'b> Context<'b, S> {
 
     fn resolve_modules(&mut self, imps:&[ModuleLink]) -> Result<()>  {
         for imp in imps {
+            self.store.limiter.consume(IMPORT_COST)?;
             let dedup = self.store.dedup_module_link(imp.clone());
             self.resolve_module_plain(dedup)?;
         }
@@ -233,6 +237,7 @@ impl<'b, S:Store + 'b> Context<'b, S> {
 
     fn resolve_types(&mut self, imps:&[TypeImport]) -> Result<()> {
         for imp in imps {
+            self.store.limiter.consume(IMPORT_COST)?;
             let res = match imp {
                 TypeImport::Data{ link:DataLink {module, offset}, applies} => self.resolve_data_type(*module, *offset, &applies),
                 //Its a general
@@ -249,6 +254,7 @@ impl<'b, S:Store + 'b> Context<'b, S> {
 
     fn resolve_callables(&mut self, imps:&[CallableImport]) -> Result<()> {
         for imp in imps {
+            self.store.limiter.consume(IMPORT_COST)?;
             let res = match imp {
                 CallableImport::Function { link: FuncLink{module, offset}, ref applies,.. } => self.resolve_function_callable(*module,*offset, applies),
                 CallableImport::Implement { link: ImplLink{module, offset}, ref applies,.. } => self.resolve_implement_callable(*module,*offset, applies),
@@ -260,6 +266,7 @@ impl<'b, S:Store + 'b> Context<'b, S> {
 
     fn resolve_perms(&mut self, imps:&[PermissionImport]) -> Result<()> {
         for imp in imps {
+            self.store.limiter.consume(IMPORT_COST)?;
             let res = match imp {
                 PermissionImport::Type(perm,tref) => {
                     let typ = self.get_type(*tref)?;
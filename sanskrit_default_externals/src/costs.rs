@@ -0,0 +1,212 @@
+//Note we rounded up - No fractions
+// Based on sanskrit_bench - main
+//  Generated with 0.6 gas per ns (11 runs of 256 repetitions per sample)
+
+pub const SPECIAL_LIT_I8:u64 = 13;
+pub const AND_I8:u64 = 13;
+pub const OR_I8:u64 = 12;
+pub const XOR_I8:u64 = 13;
+pub const NOT_I8:u64 = 9;
+pub const ADD_I8:u64 = 13;
+pub const SUB_I8:u64 = 12;
+pub const MUL_I8:u64 = 14;
+pub const DIV_I8:u64 = 11;
+pub const EQ_I8:u64 = 10;
+pub const LT_I8:u64 = 11;
+pub const GT_I8:u64 = 11;
+pub const LTE_I8:u64 = 11;
+pub const GTE_I8:u64 = 11;
+pub const TO_DATA_I8:u64 = 17;
+pub const FROM_DATA_I8:u64 = 12;
+
+pub const SPECIAL_LIT_U8:u64 = 13;
+pub const AND_U8:u64 = 14;
+pub const OR_U8:u64 = 14;
+pub const XOR_U8:u64 = 14;
+pub const NOT_U8:u64 = 12;
+pub const ADD_U8:u64 = 12;
+pub const SUB_U8:u64 = 10;
+pub const MUL_U8:u64 = 11;
+pub const DIV_U8:u64 = 13;
+pub const EQ_U8:u64 = 10;
+pub const LT_U8:u64 = 10;
+pub const GT_U8:u64 = 12;
+pub const LTE_U8:u64 = 11;
+pub const GTE_U8:u64 = 11;
+pub const TO_DATA_U8:u64 = 16;
+pub const FROM_DATA_U8:u64 = 10;
+
+pub const SPECIAL_LIT_I16:u64 = 14;
+pub const AND_I16:u64 = 13;
+pub const OR_I16:u64 = 13;
+pub const XOR_I16:u64 = 14;
+pub const NOT_I16:u64 = 11;
+pub const ADD_I16:u64 = 13;
+pub const SUB_I16:u64 = 14;
+pub const MUL_I16:u64 = 13;
+pub const DIV_I16:u64 = 15;
+pub const EQ_I16:u64 = 10;
+pub const LT_I16:u64 = 13;
+pub const GT_I16:u64 = 12;
+pub const LTE_I16:u64 = 12;
+pub const GTE_I16:u64 = 14;
+pub const TO_DATA_I16:u64 = 15;
+pub const FROM_DATA_I16:u64 = 10;
+
+pub const SPECIAL_LIT_U16:u64 = 14;
+pub const AND_U16:u64 = 14;
+pub const OR_U16:u64 = 10;
+pub const XOR_U16:u64 = 13;
+pub const NOT_U16:u64 = 12;
+pub const ADD_U16:u64 = 13;
+pub const SUB_U16:u64 = 12;
+pub const MUL_U16:u64 = 12;
+pub const DIV_U16:u64 = 14;
+pub const EQ_U16:u64 = 9;
+pub const LT_U16:u64 = 13;
+pub const GT_U16:u64 = 12;
+pub const LTE_U16:u64 = 12;
+pub const GTE_U16:u64 = 13;
+pub const TO_DATA_U16:u64 = 16;
+pub const FROM_DATA_U16:u64 = 10;
+
+pub const SPECIAL_LIT_I32:u64 = 14;
+pub const AND_I32:u64 = 13;
+pub const OR_I32:u64 = 14;
+pub const XOR_I32:u64 = 15;
+pub const NOT_I32:u64 = 12;
+pub const ADD_I32:u64 = 14;
+pub const SUB_I32:u64 = 14;
+pub const MUL_I32:u64 = 14;
+pub const DIV_I32:u64 = 15;
+pub const EQ_I32:u64 = 10;
+pub const LT_I32:u64 = 13;
+pub const GT_I32:u64 = 13;
+pub const LTE_I32:u64 = 12;
+pub const GTE_I32:u64 = 13;
+pub const TO_DATA_I32:u64 = 14;
+pub const FROM_DATA_I32:u64 = 11;
+
+pub const SPECIAL_LIT_U32:u64 = 14;
+pub const AND_U32:u64 = 16;
+pub const OR_U32:u64 = 15;
+pub const XOR_U32:u64 = 13;
+pub const NOT_U32:u64 = 12;
+pub const ADD_U32:u64 = 14;
+pub const SUB_U32:u64 = 13;
+pub const MUL_U32:u64 = 16;
+pub const DIV_U32:u64 = 14;
+pub const EQ_U32:u64 = 9;
+pub const LT_U32:u64 = 12;
+pub const GT_U32:u64 = 12;
+pub const LTE_U32:u64 = 13;
+pub const GTE_U32:u64 = 10;
+pub const TO_DATA_U32:u64 = 17;
+pub const FROM_DATA_U32:u64 = 11;
+
+pub const SPECIAL_LIT_I64:u64 = 14;
+pub const AND_I64:u64 = 14;
+pub const OR_I64:u64 = 12;
+pub const XOR_I64:u64 = 12;
+pub const NOT_I64:u64 = 11;
+pub const ADD_I64:u64 = 11;
+pub const SUB_I64:u64 = 12;
+pub const MUL_I64:u64 = 13;
+pub const DIV_I64:u64 = 13;
+pub const EQ_I64:u64 = 9;
+pub const LT_I64:u64 = 11;
+pub const GT_I64:u64 = 11;
+pub const LTE_I64:u64 = 10;
+pub const GTE_I64:u64 = 12;
+pub const TO_DATA_I64:u64 = 16;
+pub const FROM_DATA_I64:u64 = 12;
+
+pub const SPECIAL_LIT_U64:u64 = 13;
+pub const AND_U64:u64 = 12;
+pub const OR_U64:u64 = 15;
+pub const XOR_U64:u64 = 12;
+pub const NOT_U64:u64 = 11;
+pub const ADD_U64:u64 = 10;
+pub const SUB_U64:u64 = 12;
+pub const MUL_U64:u64 = 12;
+pub const DIV_U64:u64 = 13;
+pub const EQ_U64:u64 = 10;
+pub const LT_U64:u64 = 10;
+pub const GT_U64:u64 = 13;
+pub const LTE_U64:u64 = 12;
+pub const GTE_U64:u64 = 11;
+pub const TO_DATA_U64:u64 = 15;
+pub const FROM_DATA_U64:u64 = 12;
+
+pub const SPECIAL_LIT_I128:u64 = 15;
+pub const AND_I128:u64 = 12;
+pub const OR_I128:u64 = 12;
+pub const XOR_I128:u64 = 12;
+pub const NOT_I128:u64 = 12;
+pub const ADD_I128:u64 = 13;
+pub const SUB_I128:u64 = 12;
+pub const MUL_I128:u64 = 19;
+pub const DIV_I128:u64 = 21;
+pub const EQ_I128:u64 = 8;
+pub const LT_I128:u64 = 11;
+pub const GT_I128:u64 = 11;
+pub const LTE_I128:u64 = 11;
+pub const GTE_I128:u64 = 11;
+pub const TO_DATA_I128:u64 = 16;
+pub const FROM_DATA_I128:u64 = 11;
+
+pub const SPECIAL_LIT_U128:u64 = 14;
+pub const AND_U128:u64 = 14;
+pub const OR_U128:u64 = 14;
+pub const XOR_U128:u64 = 14;
+pub const NOT_U128:u64 = 12;
+pub const ADD_U128:u64 = 14;
+pub const SUB_U128:u64 = 15;
+pub const MUL_U128:u64 = 15;
+pub const DIV_U128:u64 = 15;
+pub const EQ_U128:u64 = 10;
+pub const LT_U128:u64 = 11;
+pub const GT_U128:u64 = 11;
+pub const LTE_U128:u64 = 12;
+pub const GTE_U128:u64 = 13;
+pub const TO_DATA_U128:u64 = 17;
+pub const FROM_DATA_U128:u64 = 12;
+
+pub const SPECIAL_LIT_U256:u64 = 17;
+pub const AND_U256:u64 = 45;
+pub const OR_U256:u64 = 42;
+pub const XOR_U256:u64 = 44;
+pub const NOT_U256:u64 = 40;
+pub const ADD_U256:u64 = 21;
+pub const SUB_U256:u64 = 23;
+pub const MUL_U256:u64 = 32;
+pub const DIV_U256:u64 = 33;
+pub const EQ_U256:u64 = 13;
+pub const LT_U256:u64 = 12;
+pub const GT_U256:u64 = 14;
+pub const LTE_U256:u64 = 13;
+pub const GTE_U256:u64 = 11;
+pub const TO_DATA_U256:u64 = 15;
+pub const FROM_DATA_U256:u64 = 9;
+
+pub const JOIN_HASH:u64 = 145;
+
+pub const fn data_lit(bytes:u64) -> u64 { 10 + bytes }
+
+pub const fn and_data(bytes:u64) -> u64 { 34 + bytes }
+
+pub const fn or_data(bytes:u64) -> u64 { 34 + bytes }
+
+pub const fn xor_data(bytes:u64) -> u64 { 37 + bytes }
+
+pub const fn not_data(bytes:u64) -> u64 { 31 + bytes }
+
+pub const fn eq_data(bytes:u64) -> u64 { 37 + bytes }
+
+pub const fn plain_hash(bytes:u64) -> u64 { 184 + bytes }
+
+pub const fn sha256(bytes:u64) -> u64 { 64 + bytes }
+
+pub const fn keccak256(bytes:u64) -> u64 { 172 + 10*bytes }
+
+pub const fn ecdsa_verify(bytes:u64) -> u64 { 16488 + 13*bytes }
@@ -1,11 +1,15 @@
 use alloc::format;
+use ed25519_consensus::batch;
+use rand_core::{CryptoRng, RngCore, Error};
 use sanskrit_common::hashing::{Hasher, HashingDomain};
 use ed25519_consensus::*;
 use sanskrit_common::model::{ValueRef, SlicePtr};
 use sanskrit_common::errors::*;
 use sanskrit_common::arena::VirtualHeapArena;
-use sanskrit_interpreter::externals::ExecutionInterface;
+use sanskrit_interpreter::externals::{ExecutionInterface, SignatureBatch};
 use sanskrit_interpreter::model::{Entry, Adt, Kind};
+use sha2::{Sha256, Digest};
+use sha3::Keccak256;
 
 
 pub fn raw_plain_hash<'a,'h>(data:&[u8], alloc_heap:&'a VirtualHeapArena<'h>) -> Result<SlicePtr<'a,u8>> {
@@ -28,6 +32,40 @@ pub fn plain_hash<'interpreter, 'transaction:'interpreter, 'heap:'transaction, I
     Ok(())
 }
 
+pub fn raw_sha256_hash<'a,'h>(data:&[u8], alloc_heap:&'a VirtualHeapArena<'h>) -> Result<SlicePtr<'a,u8>> {
+    //Unlike the internal hashes this is the full 32 byte digest to stay compatible with other ecosystems
+    let hash = Sha256::digest(data);
+    //allocate on the heap
+    alloc_heap.copy_alloc_slice(&hash)
+}
+
+//hashes the data input with SHA-256 (used to verify proofs & messages produced outside of sanskrit)
+pub fn sha256_hash<'interpreter, 'transaction:'interpreter, 'heap:'transaction, I:ExecutionInterface<'interpreter, 'transaction, 'heap>>(inter:&mut I, kind:Kind, ValueRef(val):ValueRef, tail:bool) -> Result<()> {
+    let op1 = inter.get(val as usize)?;
+    let alloc_heap = inter.get_heap();
+    let hash_data =  I::process_entry_slice(kind,op1, |data| raw_sha256_hash(data, alloc_heap))?;
+    //get ownership and return
+    inter.get_stack(tail).push(Entry{data:hash_data})?;
+    Ok(())
+}
+
+pub fn raw_keccak256_hash<'a,'h>(data:&[u8], alloc_heap:&'a VirtualHeapArena<'h>) -> Result<SlicePtr<'a,u8>> {
+    //Unlike the internal hashes this is the full 32 byte digest to stay compatible with other ecosystems
+    let hash = Keccak256::digest(data);
+    //allocate on the heap
+    alloc_heap.copy_alloc_slice(&hash)
+}
+
+//hashes the data input with Keccak-256 (used to verify proofs & messages produced outside of sanskrit)
+pub fn keccak256_hash<'interpreter, 'transaction:'interpreter, 'heap:'transaction, I:ExecutionInterface<'interpreter, 'transaction, 'heap>>(inter:&mut I, kind:Kind, ValueRef(val):ValueRef, tail:bool) -> Result<()> {
+    let op1 = inter.get(val as usize)?;
+    let alloc_heap = inter.get_heap();
+    let hash_data =  I::process_entry_slice(kind,op1, |data| raw_keccak256_hash(data, alloc_heap))?;
+    //get ownership and return
+    inter.get_stack(tail).push(Entry{data:hash_data})?;
+    Ok(())
+}
+
 pub fn raw_join_hash<'a,'h>(data1:&[u8], data2:&[u8], domain:HashingDomain, alloc_heap:&'a VirtualHeapArena<'h>)  -> Result<SlicePtr<'a,u8>>{
     let mut context = domain.get_domain_hasher();
     //fill the hash with first value
@@ -63,8 +101,13 @@ pub fn ecdsa_verify<'interpreter, 'transaction:'interpreter, 'heap:'transaction,
     }
 
     let res = match (VerificationKey::try_from(pk_data), Signature::try_from(sig_data)) {
-        (Ok(vk), Ok(sig)) => {
-            match vk.verify(&sig,&msg_data) {
+        (Ok(vk), Ok(sig)) => match inter.signature_batch() {
+            //the check is collected and answered with true, if it does not hold the section fails when the batch is verified
+            Some(batch) => {
+                batch.queue(&msg_data, vk.to_bytes(), sig.to_bytes());
+                1
+            },
+            None => match vk.verify(&sig,&msg_data) {
                 Ok(_) => 1,
                 Err(_) => 0
             }
@@ -75,3 +118,95 @@ pub fn ecdsa_verify<'interpreter, 'transaction:'interpreter, 'heap:'transaction,
     inter.get_stack(tail).push(Entry{ adt: Adt(res, SlicePtr::empty())})?;
     Ok(())
 }
+
+//Generates the coefficients of a batch verification from the hash of the verified checks
+// they have to be the same on every node but must not be predictable before the signatures are chosen
+struct TranscriptRng {
+    seed:[u8;32],
+    counter:u64,
+}
+
+impl RngCore for TranscriptRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest:&mut [u8]) {
+        for chunk in dest.chunks_mut(32) {
+            let mut block = Sha256::new();
+            block.update(self.seed);
+            block.update(self.counter.to_le_bytes());
+            self.counter += 1;
+            chunk.copy_from_slice(&block.finalize()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest:&mut [u8]) -> core::result::Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for TranscriptRng {}
+
+//verifies all the collected signature checks of a bundle at once
+// ed25519-consensus uses the ZIP215 rules for both, so the batch holds exactly if every individual check holds
+pub fn verify_signature_batch(signatures:&SignatureBatch) -> bool {
+    if signatures.is_empty() {
+        return true
+    }
+    let mut transcript = Sha256::new();
+    let mut verifier = batch::Verifier::new();
+    for (msg, pk, sig) in &signatures.checks {
+        transcript.update(pk);
+        transcript.update(sig);
+        transcript.update((msg.len() as u64).to_le_bytes());
+        transcript.update(msg);
+        verifier.queue((VerificationKeyBytes::from(*pk), Signature::from(*sig), msg));
+    }
+    verifier.verify(TranscriptRng{ seed: transcript.finalize().into(), counter: 0 }).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed(seed:u8, msg:&[u8]) -> ([u8;32], [u8;64]) {
+        let key = SigningKey::from([seed; 32]);
+        (VerificationKey::from(&key).to_bytes(), key.sign(msg).to_bytes())
+    }
+
+    #[test]
+    fn empty_batch_verifies() {
+        assert!(verify_signature_batch(&SignatureBatch::new()));
+    }
+
+    #[test]
+    fn batch_of_valid_signatures_verifies() {
+        let mut batch = SignatureBatch::new();
+        for seed in 0..8u8 {
+            let msg = [seed; 20];
+            let (pk, sig) = signed(seed, &msg);
+            batch.queue(&msg, pk, sig);
+        }
+        assert!(verify_signature_batch(&batch));
+    }
+
+    #[test]
+    fn batch_with_one_invalid_signature_fails() {
+        let mut batch = SignatureBatch::new();
+        for seed in 0..4u8 {
+            let (pk, sig) = signed(seed, b"message");
+            batch.queue(b"message", pk, sig);
+        }
+        let (pk, sig) = signed(9, b"message");
+        batch.queue(b"other message", pk, sig);
+        assert!(!verify_signature_batch(&batch));
+    }
+}
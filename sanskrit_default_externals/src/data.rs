@@ -4,7 +4,8 @@ use sanskrit_common::errors::*;
 use sanskrit_common::encoding::*;
 use sanskrit_compile::externals::{just_local_gas_and_mem, CompilationResult};
 use sanskrit_interpreter::model::{ValueSchema, OpCode, Kind};
-use crate::External;
+use crate::{External, data_size};
+use crate::costs;
 
 pub const EXT_DATA:&'static dyn External = &Data;
 
@@ -24,7 +25,7 @@ impl External for Data {
     global external(32) primitive data Data32
     */
     fn compile_lit<'b, 'h>(&self, _data_idx: u8, data: SlicePtr<'b, u8>, _caller: &[u8; 20], _alloc: &'b HeapArena<'h>) -> Result<CompilationResult<'b>> {
-        Ok(just_local_gas_and_mem((13 + data.len()/50) as u64, data.len() as u64, OpCode::Data(data)))
+        Ok(just_local_gas_and_mem(costs::data_lit(data.len() as u64), data.len() as u64, OpCode::Data(data)))
     }
 
     fn get_literal_checker<'b, 'h>(&self, _data_idx: u8, len: u16, _alloc: &'b HeapArena<'h>) -> Result<ValueSchema<'b>> {
@@ -46,9 +47,9 @@ impl External for Data {
             global external function eq32(data1:Data32, data2:Data32):(res:Bool)
             */
             //currently we have max 32 Byte:
-            x if x < 10 => Ok(just_local_gas_and_mem(14, 0, OpCode::Eq(Kind::Data, params[0], params[1]))),
+            x if x < 10 => Ok(just_local_gas_and_mem(costs::eq_data(data_size(x)?), 0, OpCode::Eq(Kind::Data, params[0], params[1]))),
             // extFun joinHash(data1:.Data20, data2:.Data20):(res:.Data20);
-            10 => Ok(just_local_gas_and_mem(120, Hash::SIZE as u64, OpCode::SysInvoke(0, params))),
+            10 => Ok(just_local_gas_and_mem(costs::JOIN_HASH, Hash::SIZE as u64, OpCode::SysInvoke(0, params))),
             /*
             global external function hash1(data1:Data1):Hash
             global external function hash2(data1:Data2):Hash
@@ -62,7 +63,7 @@ impl External for Data {
             global external function hash32(data1:Data32):Hash
             */
             //currently we have max 32 Byte:
-            _ =>  Ok(just_local_gas_and_mem(120, 20, OpCode::TypedSysInvoke(0, Kind::Data, params))),
+            _ =>  Ok(just_local_gas_and_mem(costs::plain_hash(data_size(fun_idx - 11)?), 20, OpCode::TypedSysInvoke(0, Kind::Data, params))),
         }
 
     }
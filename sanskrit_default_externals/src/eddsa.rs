@@ -4,7 +4,8 @@ use sanskrit_common::errors::*;
 use sanskrit_common::encoding::*;
 use sanskrit_compile::externals::{just_local_gas_and_mem, CompilationResult};
 use sanskrit_interpreter::model::{ValueSchema, OpCode, Kind};
-use crate::External;
+use crate::{External, data_size};
+use crate::costs;
 
 pub const EXT_ECDSA:&'static dyn External = &Ecdsa;
 
@@ -16,8 +17,8 @@ impl External for Ecdsa{
     */
     fn compile_lit<'b, 'h>(&self, data_idx: u8, data: SlicePtr<'b, u8>, _caller: &[u8; 20], _alloc: &'b HeapArena<'h>) -> Result<CompilationResult<'b>> {
         match data_idx {
-            0 => Ok(just_local_gas_and_mem(14, 32, OpCode::Data(data))),
-            _ => Ok(just_local_gas_and_mem(15, 64, OpCode::Data(data))),
+            0 => Ok(just_local_gas_and_mem(costs::data_lit(32), 32, OpCode::Data(data))),
+            _ => Ok(just_local_gas_and_mem(costs::data_lit(64), 64, OpCode::Data(data))),
         }
     }
 
@@ -31,7 +32,7 @@ impl External for Ecdsa{
     fn compile_call<'b, 'h>(&self, fun_idx: u8, params: SlicePtr<'b, ValueRef>, _caller: &[u8; 20], _alloc: &'b HeapArena<'h>) -> Result<CompilationResult<'b>> {
         match fun_idx {
             //global external function derivePublicId(pk:Pk):Id
-            0 => Ok(just_local_gas_and_mem(65, Hash::SIZE as u64, OpCode::TypedSysInvoke(0, Kind::Data, params))),
+            0 => Ok(just_local_gas_and_mem(costs::plain_hash(32), Hash::SIZE as u64, OpCode::TypedSysInvoke(0, Kind::Data, params))),
             /*
             global external function verify1(msg:Data1, pk:Pk, sig:Sig):(res:Bool)
             global external function verify2(msg:Data2, pk:Pk, sig:Sig):(res:Bool)
@@ -44,8 +45,8 @@ impl External for Ecdsa{
             global external function verify28(msg:Data28, pk:Pk, sig:Sig):(res:Bool)
             global external function verify32(msg:Data32, pk:Pk, sig:Sig):(res:Bool)
             */
-            //Todo: measure this it is guessed based on ethereum gas costs for similar operations
-            _ => Ok(just_local_gas_and_mem(2250, 0, OpCode::SysInvoke(1, params))),
+            //the runtime verifies the signatures of a section as a batch, so this is the measured share of a batched verification
+            x => Ok(just_local_gas_and_mem(costs::ecdsa_verify(data_size(x - 1)?), 0, OpCode::SysInvoke(1, params))),
 
         }
     }
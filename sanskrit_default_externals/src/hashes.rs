@@ -0,0 +1,162 @@
+use sanskrit_common::model::{SlicePtr, ValueRef};
+use sanskrit_common::arena::HeapArena;
+use sanskrit_common::errors::*;
+use sanskrit_compile::externals::{just_local_gas_and_mem, CompilationResult};
+use sanskrit_interpreter::model::{ValueSchema, OpCode, Kind};
+use crate::{External, DATA_SIZES};
+use crate::costs;
+
+pub const EXT_HASHES:&'static dyn External = &Hashes;
+
+//The digest size of Sha256 & Keccak256
+pub const DIGEST_SIZE:u64 = 32;
+
+//The integer types in the order they are declared in the Kind enum
+// U256 was added last, so its functions come after the keccak256 functions of the other integers
+const INT_KINDS:[Kind;11] = [Kind::I8, Kind::U8, Kind::I16, Kind::U16, Kind::I32, Kind::U32, Kind::I64, Kind::U64, Kind::I128, Kind::U128, Kind::U256];
+
+fn int_size(kind:Kind) -> Result<u64> {
+    Ok(match kind {
+        Kind::I8 | Kind::U8 => 1,
+        Kind::I16 | Kind::U16 => 2,
+        Kind::I32 | Kind::U32 => 4,
+        Kind::I64 | Kind::U64 => 8,
+        Kind::I128 | Kind::U128 => 16,
+        Kind::U256 => 32,
+        Kind::Data => return error(||"Data is not an integer kind")
+    })
+}
+
+pub struct Hashes;
+impl External for Hashes{
+
+    fn compile_lit<'b, 'h>(&self, _data_idx: u8, _data: SlicePtr<'b, u8>, _caller: &[u8; 20], _alloc: &'b HeapArena<'h>) -> Result<CompilationResult<'b>> {
+        error(||"External lit is not defined")
+    }
+
+    fn get_literal_checker<'b, 'h>(&self, _data_idx: u8, _len: u16, _alloc: &'b HeapArena<'h>) -> Result<ValueSchema<'b>> {
+        error(||"External lit is not defined")
+    }
+
+    fn compile_call<'b, 'h>(&self, fun_idx: u8, params: SlicePtr<'b, ValueRef>, _caller: &[u8; 20], _alloc: &'b HeapArena<'h>) -> Result<CompilationResult<'b>> {
+        Ok(match fun_idx {
+            /*
+            global external function sha256Data1(data:Data.Data1):(res:Data.Data32)
+            global external function sha256Data2(data:Data.Data2):(res:Data.Data32)
+            global external function sha256Data4(data:Data.Data4):(res:Data.Data32)
+            global external function sha256Data8(data:Data.Data8):(res:Data.Data32)
+            global external function sha256Data12(data:Data.Data12):(res:Data.Data32)
+            global external function sha256Data16(data:Data.Data16):(res:Data.Data32)
+            global external function sha256Data20(data:Data.Data20):(res:Data.Data32)
+            global external function sha256Data24(data:Data.Data24):(res:Data.Data32)
+            global external function sha256Data28(data:Data.Data28):(res:Data.Data32)
+            global external function sha256Data32(data:Data.Data32):(res:Data.Data32)
+            */
+            x if x < 10 => just_local_gas_and_mem(costs::sha256(DATA_SIZES[x as usize]), DIGEST_SIZE, OpCode::TypedSysInvoke(1, Kind::Data, params)),
+            /*
+            global external function keccak256Data1(data:Data.Data1):(res:Data.Data32)
+            global external function keccak256Data2(data:Data.Data2):(res:Data.Data32)
+            global external function keccak256Data4(data:Data.Data4):(res:Data.Data32)
+            global external function keccak256Data8(data:Data.Data8):(res:Data.Data32)
+            global external function keccak256Data12(data:Data.Data12):(res:Data.Data32)
+            global external function keccak256Data16(data:Data.Data16):(res:Data.Data32)
+            global external function keccak256Data20(data:Data.Data20):(res:Data.Data32)
+            global external function keccak256Data24(data:Data.Data24):(res:Data.Data32)
+            global external function keccak256Data28(data:Data.Data28):(res:Data.Data32)
+            global external function keccak256Data32(data:Data.Data32):(res:Data.Data32)
+            */
+            x if x < 20 => just_local_gas_and_mem(costs::keccak256(DATA_SIZES[(x - 10) as usize]), DIGEST_SIZE, OpCode::TypedSysInvoke(2, Kind::Data, params)),
+            /*
+            global external function sha256I8(num:I8.I8):(res:Data.Data32)
+            global external function sha256U8(num:U8.U8):(res:Data.Data32)
+            global external function sha256I16(num:I16.I16):(res:Data.Data32)
+            global external function sha256U16(num:U16.U16):(res:Data.Data32)
+            global external function sha256I32(num:I32.I32):(res:Data.Data32)
+            global external function sha256U32(num:U32.U32):(res:Data.Data32)
+            global external function sha256I64(num:I64.I64):(res:Data.Data32)
+            global external function sha256U64(num:U64.U64):(res:Data.Data32)
+            global external function sha256I128(num:I128.I128):(res:Data.Data32)
+            global external function sha256U128(num:U128.U128):(res:Data.Data32)
+            */
+            x if x < 30 => {
+                let kind = INT_KINDS[(x - 20) as usize];
+                just_local_gas_and_mem(costs::sha256(int_size(kind)?), DIGEST_SIZE, OpCode::TypedSysInvoke(1, kind, params))
+            },
+            /*
+            global external function keccak256I8(num:I8.I8):(res:Data.Data32)
+            global external function keccak256U8(num:U8.U8):(res:Data.Data32)
+            global external function keccak256I16(num:I16.I16):(res:Data.Data32)
+            global external function keccak256U16(num:U16.U16):(res:Data.Data32)
+            global external function keccak256I32(num:I32.I32):(res:Data.Data32)
+            global external function keccak256U32(num:U32.U32):(res:Data.Data32)
+            global external function keccak256I64(num:I64.I64):(res:Data.Data32)
+            global external function keccak256U64(num:U64.U64):(res:Data.Data32)
+            global external function keccak256I128(num:I128.I128):(res:Data.Data32)
+            global external function keccak256U128(num:U128.U128):(res:Data.Data32)
+            */
+            x if x < 40 => {
+                let kind = INT_KINDS[(x - 30) as usize];
+                just_local_gas_and_mem(costs::keccak256(int_size(kind)?), DIGEST_SIZE, OpCode::TypedSysInvoke(2, kind, params))
+            },
+            //global external function sha256U256(num:U256.U256):(res:Data.Data32)
+            40 => just_local_gas_and_mem(costs::sha256(int_size(INT_KINDS[10])?), DIGEST_SIZE, OpCode::TypedSysInvoke(1, INT_KINDS[10], params)),
+            //global external function keccak256U256(num:U256.U256):(res:Data.Data32)
+            41 => just_local_gas_and_mem(costs::keccak256(int_size(INT_KINDS[10])?), DIGEST_SIZE, OpCode::TypedSysInvoke(2, INT_KINDS[10], params)),
+            _ => return error(||"External call is not defined")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sanskrit_common::arena::Heap;
+    use sanskrit_compile::externals::ExpResources;
+    use crate::crypto::{raw_sha256_hash, raw_keccak256_hash};
+
+    fn hex(data:&str) -> alloc::vec::Vec<u8> {
+        (0..data.len()).step_by(2).map(|i| u8::from_str_radix(&data[i..i+2], 16).unwrap()).collect()
+    }
+
+    fn compile(fun_idx:u8) -> Result<(ExpResources, u8, Kind)> {
+        let heap = Heap::new(1024, 1.0);
+        let alloc = heap.new_arena(1024);
+        let params = alloc.copy_alloc_slice(&[ValueRef(0)])?;
+        match Hashes.compile_call(fun_idx, params, &[0;20], &alloc)? {
+            CompilationResult::OpCodeResult(res, OpCode::TypedSysInvoke(id, kind, _)) => Ok((res, id, kind)),
+            _ => error(||"Unexpected compilation result")
+        }
+    }
+
+    #[test]
+    fn digests_match_reference_vectors() {
+        let heap = Heap::new(1024, 1.0);
+        let alloc = heap.new_virtual_arena(1024);
+        let sha = raw_sha256_hash(b"abc", &alloc).unwrap();
+        assert_eq!(&*sha, &hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")[..]);
+        let keccak = raw_keccak256_hash(b"", &alloc).unwrap();
+        assert_eq!(&*keccak, &hex("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")[..]);
+    }
+
+    #[test]
+    fn calls_are_charged_by_input_size() {
+        let (res, id, kind) = compile(9).unwrap();
+        assert_eq!((res.gas, res.mem, id, kind), (costs::sha256(32), DIGEST_SIZE, 1, Kind::Data));
+        let (res, id, kind) = compile(10).unwrap();
+        assert_eq!((res.gas, id, kind), (costs::keccak256(1), 2, Kind::Data));
+        let (res, id, kind) = compile(29).unwrap();
+        assert_eq!((res.gas, id, kind), (costs::sha256(16), 1, Kind::U128));
+        let (res, id, kind) = compile(36).unwrap();
+        assert_eq!((res.gas, id, kind), (costs::keccak256(8), 2, Kind::I64));
+        let (res, id, kind) = compile(40).unwrap();
+        assert_eq!((res.gas, id, kind), (costs::sha256(32), 1, Kind::U256));
+        let (res, id, kind) = compile(41).unwrap();
+        assert_eq!((res.gas, id, kind), (costs::keccak256(32), 2, Kind::U256));
+    }
+
+    #[test]
+    fn unknown_calls_are_rejected() {
+        assert!(compile(42).is_err());
+        assert!(int_size(Kind::Data).is_err());
+    }
+}
@@ -4,6 +4,7 @@ use sanskrit_common::errors::*;
 use sanskrit_compile::externals::{just_local_gas_and_mem, CompilationResult};
 use sanskrit_interpreter::model::{ValueSchema, OpCode, Kind, LitDesc};
 use crate::External;
+use crate::costs;
 
 pub const EXT_I128:&'static dyn External = &I128;
 
@@ -11,7 +12,7 @@ pub struct I128;
 impl External for I128{
     //global external(16) data I128;
     fn compile_lit<'b, 'h>(&self, _data_idx: u8, data: SlicePtr<'b, u8>, _caller: &[u8; 20], _alloc: &'b HeapArena<'h>) -> Result<CompilationResult<'b>> {
-        Ok(just_local_gas_and_mem(7, 0, OpCode::SpecialLit(data, LitDesc::I128)))
+        Ok(just_local_gas_and_mem(costs::SPECIAL_LIT_I128, 0, OpCode::SpecialLit(data, LitDesc::I128)))
     }
 
     fn get_literal_checker<'b, 'h>(&self, _data_idx: u8, _len: u16, _alloc: &'b HeapArena<'h>) -> Result<ValueSchema<'b>> {
@@ -22,37 +23,37 @@ impl External for I128{
         Ok(match fun_idx {
             //this is the identity funcntion (used for conversions where bit pattern does not change)
             //global external function eq(num1:.I128, num2:.I128):(res:Bool.Bool);
-            0 => just_local_gas_and_mem(15, 0, OpCode::Eq(Kind::I128, params[0], params[1])),
+            0 => just_local_gas_and_mem(costs::EQ_I128, 0, OpCode::Eq(Kind::I128, params[0], params[1])),
             //global external function lt(num1:.I128, num2:.I128):(res:Bool.Bool);
-            1 => just_local_gas_and_mem(15, 0, OpCode::Lt(Kind::I128, params[0], params[1])),
+            1 => just_local_gas_and_mem(costs::LT_I128, 0, OpCode::Lt(Kind::I128, params[0], params[1])),
             //global external function lte(num1:.I128, num2:.I128):(res:Bool.Bool);
-            2 => just_local_gas_and_mem(15, 0, OpCode::Lte(Kind::I128, params[0], params[1])),
+            2 => just_local_gas_and_mem(costs::LTE_I128, 0, OpCode::Lte(Kind::I128, params[0], params[1])),
             //global external function gt(num1:.I128, num2:.I128):(res:Bool.Bool);
-            3 => just_local_gas_and_mem(15, 0, OpCode::Gt(Kind::I128, params[0], params[1])),
+            3 => just_local_gas_and_mem(costs::GT_I128, 0, OpCode::Gt(Kind::I128, params[0], params[1])),
             //global external function gte(num1:.I128, num2:.I128):(res:Bool.Bool);
-            4 => just_local_gas_and_mem(15, 0, OpCode::Gte(Kind::I128, params[0], params[1])),
+            4 => just_local_gas_and_mem(costs::GTE_I128, 0, OpCode::Gte(Kind::I128, params[0], params[1])),
             //global external function add(num1:.I128, num2:.I128):(res:.I128);
-            5 => just_local_gas_and_mem(15, 0, OpCode::Add(Kind::I128, params[0], params[1])),
+            5 => just_local_gas_and_mem(costs::ADD_I128, 0, OpCode::Add(Kind::I128, params[0], params[1])),
             //global external function sub(num1:.I128, num2:.I128):(res:.I128);
-            6 => just_local_gas_and_mem(15, 0, OpCode::Sub(Kind::I128, params[0], params[1])),
+            6 => just_local_gas_and_mem(costs::SUB_I128, 0, OpCode::Sub(Kind::I128, params[0], params[1])),
             //global external function div(num1:.I128, num2:.I128):(res:.I128);
-            7 => just_local_gas_and_mem(20, 0, OpCode::Div(Kind::I128, params[0], params[1])),
+            7 => just_local_gas_and_mem(costs::DIV_I128, 0, OpCode::Div(Kind::I128, params[0], params[1])),
             //global external function mul(num1:.I128, num2:.I128):(res:.I128);
-            8 => just_local_gas_and_mem(15, 0, OpCode::Mul(Kind::I128, params[0], params[1])),
+            8 => just_local_gas_and_mem(costs::MUL_I128, 0, OpCode::Mul(Kind::I128, params[0], params[1])),
             //global transactional external function and(num1:.I128, num2:.I128):(res:.I128);
-            9 => just_local_gas_and_mem(15, 0, OpCode::And(Kind::I128, params[0], params[1])),
+            9 => just_local_gas_and_mem(costs::AND_I128, 0, OpCode::And(Kind::I128, params[0], params[1])),
             //global transactional external function or(num1:.I128, num2:.I128):(res:.I128);
-            10 => just_local_gas_and_mem(15, 0, OpCode::Or(Kind::I128, params[0], params[1])),
+            10 => just_local_gas_and_mem(costs::OR_I128, 0, OpCode::Or(Kind::I128, params[0], params[1])),
             //global transactional external function xor(num1:.I128, num2:.I128):(res:.I128);
-            11 => just_local_gas_and_mem(15, 0, OpCode::Xor(Kind::I128, params[0], params[1])),
+            11 => just_local_gas_and_mem(costs::XOR_I128, 0, OpCode::Xor(Kind::I128, params[0], params[1])),
             //global transactional external function not(num1:.I128):(res:.I128);
-            12 => just_local_gas_and_mem(15, 0, OpCode::Not(Kind::I128, params[0])),
+            12 => just_local_gas_and_mem(costs::NOT_I128, 0, OpCode::Not(Kind::I128, params[0])),
             //global external function toData(num:.I128):(res:Data.Data16);
-            13 => just_local_gas_and_mem(20, 16, OpCode::ToData(Kind::I128, params[0])),
+            13 => just_local_gas_and_mem(costs::TO_DATA_I128, 16, OpCode::ToData(Kind::I128, params[0])),
             //global external function fromData(data:Data.Data16):(res:.I128);
-            14 => just_local_gas_and_mem(20, 0, OpCode::FromData(Kind::I128, params[0])),
+            14 => just_local_gas_and_mem(costs::FROM_DATA_I128, 0, OpCode::FromData(Kind::I128, params[0])),
             //global external function hash(num:.I128):(res:Data.Data20);
-            15 => just_local_gas_and_mem(120, 20, OpCode::TypedSysInvoke(0, Kind::I128, params)),
+            15 => just_local_gas_and_mem(costs::plain_hash(16), 20, OpCode::TypedSysInvoke(0, Kind::I128, params)),
             _ => return error(||"External call is not defined")
         })
     }
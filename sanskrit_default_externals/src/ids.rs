@@ -5,6 +5,7 @@ use sanskrit_common::encoding::*;
 use sanskrit_compile::externals::{just_local_gas_and_mem, CompilationResult};
 use sanskrit_interpreter::model::{ValueSchema, OpCode, Kind};
 use crate::External;
+use crate::costs;
 
 pub const EXT_IDS:&'static dyn External = &Ids;
 
@@ -14,7 +15,7 @@ impl External for Ids{
     local external(20) standard data PrivateId
     */
     fn compile_lit<'b, 'h>(&self, _data_idx: u8, data: SlicePtr<'b, u8>, _caller: &[u8; 20], _alloc: &'b HeapArena<'h>) -> Result<CompilationResult<'b>> {
-        Ok(just_local_gas_and_mem(14, 20, OpCode::Data(data)))
+        Ok(just_local_gas_and_mem(costs::data_lit(20), 20, OpCode::Data(data)))
     }
 
     fn get_literal_checker<'b, 'h>(&self, _data_idx: u8, _len: u16, _alloc: &'b HeapArena<'h>) -> Result<ValueSchema<'b>> {
@@ -24,7 +25,7 @@ impl External for Ids{
     fn compile_call<'b, 'h>(&self, fun_idx: u8, params: SlicePtr<'b, ValueRef>, caller: &[u8; 20], alloc: &'b HeapArena<'h>) -> Result<CompilationResult<'b>> {
         match fun_idx {
             //global external function moduleId():(priv:PrivateModuleId)
-            0 =>  Ok(just_local_gas_and_mem(15, Hash::SIZE as u64, OpCode::Data(alloc.copy_alloc_slice(caller)?))),
+            0 =>  Ok(just_local_gas_and_mem(costs::data_lit(Hash::SIZE as u64), Hash::SIZE as u64, OpCode::Data(alloc.copy_alloc_slice(caller)?))),
             /*
             global external function idFromData(dat:Data20):Id
             global external function idToData(id:Id):Data20
@@ -36,7 +37,7 @@ impl External for Ids{
             global external function eqId(id1:Id, id2:Id):Bool
             global external function eqModuleId(id1:ModuleId, id2:ModuleId):Bool
             */
-            x if x >= 5 && x < 7 => Ok(just_local_gas_and_mem(15, 0, OpCode::Eq(Kind::Data, params[0], params[1]))),
+            x if x >= 5 && x < 7 => Ok(just_local_gas_and_mem(costs::eq_data(Hash::SIZE as u64), 0, OpCode::Eq(Kind::Data, params[0], params[1]))),
 
             /*
             global external function privateIdderive(priv:PrivateId, hash:Hash):PrivateId
@@ -44,7 +45,7 @@ impl External for Ids{
             global external function privateModuleIdDerive(priv:PrivateModuleId, hash:Hash):PrivateId
             global external function moduleIdDerive(id:ModuleId, hash:Hash):Id
             */
-            _ =>  Ok(just_local_gas_and_mem(120, Hash::SIZE as u64, OpCode::SysInvoke(0, params))),
+            _ =>  Ok(just_local_gas_and_mem(costs::JOIN_HASH, Hash::SIZE as u64, OpCode::SysInvoke(0, params))),
 
         }
     }
@@ -4,6 +4,9 @@ extern crate sanskrit_compile;
 extern crate sanskrit_common;
 extern crate sanskrit_interpreter;
 extern crate ed25519_consensus;
+extern crate sha2;
+extern crate sha3;
+extern crate rand_core;
 
 #[macro_use]
 extern crate lazy_static;
@@ -18,11 +21,11 @@ use spin::Mutex;
 use sanskrit_common::errors::*;
 use sanskrit_common::model::{ValueRef, Hash, ModuleLink};
 use sanskrit_common::model::{SlicePtr};
-use crypto::{ecdsa_verify, join_hash, plain_hash};
+use crypto::{ecdsa_verify, join_hash, plain_hash, sha256_hash, keccak256_hash};
 use sanskrit_common::arena::HeapArena;
 use sanskrit_interpreter::model::{Kind, ValueSchema};
 use sanskrit_common::hashing::HashingDomain;
-use sanskrit_interpreter::externals::{RuntimeExternals, ExecutionInterface};
+use sanskrit_interpreter::externals::{RuntimeExternals, ExecutionInterface, SignatureBatch};
 use sanskrit_compile::externals::{CompilationResult, CompilationExternals};
 
 
@@ -36,11 +39,26 @@ pub mod u16;
 pub mod u32;
 pub mod u64;
 pub mod u128;
+pub mod u256;
 pub mod data;
 pub mod ids;
 pub mod eddsa;
 pub mod _unsafe;
 pub mod crypto;
+pub mod hashes;
+//Generated by sanskrit_bench
+pub mod costs;
+
+//The sizes of the Data types in the order they are declared in the Data module
+pub(crate) const DATA_SIZES:[u64;10] = [1,2,4,8,12,16,20,24,28,32];
+
+//The size of the Data type with the given index
+pub(crate) fn data_size(idx:u8) -> Result<u64> {
+    match DATA_SIZES.get(idx as usize) {
+        Some(size) => Ok(*size),
+        None => error(||"Data size index is out of range")
+    }
+}
 
 pub trait External:Sync{
     fn compile_lit<'b,'h>(&self, data_idx: u8, data:SlicePtr<'b,u8>, caller: &Hash, alloc:&'b HeapArena<'h>) -> Result<CompilationResult<'b>>;
@@ -61,7 +79,7 @@ lazy_static! {
 }
 
 lazy_static! {
-    pub static ref SYS_MODS: [fn(Hash)->();16] = [
+    pub static ref SYS_MODS: [fn(Hash)->();18] = [
             |h|{EXT_MAP.lock().insert(h, i8::EXT_I8);},        //0
             |h|{EXT_MAP.lock().insert(h, i16::EXT_I16);},      //1
             |h|{EXT_MAP.lock().insert(h, i32::EXT_I32);},      //2
@@ -78,6 +96,8 @@ lazy_static! {
             |h|{EXT_MAP.lock().insert(h, eddsa::EXT_ECDSA);},  //13
             |h|{EXT_MAP.lock().insert(h,_unsafe::EXT_UNSAFE);},//14
             |h|{EDDSA_HASH.lock().set(h);},                    //15
+            |h|{EXT_MAP.lock().insert(h, hashes::EXT_HASHES);},//16
+            |h|{EXT_MAP.lock().insert(h, u256::EXT_U256);},    //17
     ];
 }
 
@@ -111,6 +131,10 @@ impl RuntimeExternals for ServerExternals {
         match id {
             //Hash
             0 => plain_hash(interface, kind, values[0], tail),
+            //Sha256
+            1 => sha256_hash(interface, kind, values[0], tail),
+            //Keccak256
+            2 => keccak256_hash(interface, kind, values[0], tail),
             _ => unreachable!("Non Existent typed System Call")
         }
     }
@@ -124,4 +148,8 @@ impl RuntimeExternals for ServerExternals {
             _ => unreachable!("Non Existent System Call")
         }
     }
+
+    fn verify_signature_batch(batch:&SignatureBatch) -> bool {
+        crypto::verify_signature_batch(batch)
+    }
 }
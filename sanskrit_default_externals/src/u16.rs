@@ -4,6 +4,7 @@ use sanskrit_common::errors::*;
 use sanskrit_compile::externals::{just_local_gas_and_mem, CompilationResult};
 use sanskrit_interpreter::model::{ValueSchema, OpCode, Kind, LitDesc};
 use crate::External;
+use crate::costs;
 
 pub const EXT_U16:&'static dyn External = &U16;
 
@@ -11,7 +12,7 @@ pub struct U16;
 impl External for U16{
     //global external(2) data U16;
     fn compile_lit<'b, 'h>(&self, _data_idx: u8, data: SlicePtr<'b, u8>, _caller: &[u8; 20], _alloc: &'b HeapArena<'h>) -> Result<CompilationResult<'b>> {
-        Ok(just_local_gas_and_mem(7, 0, OpCode::SpecialLit(data, LitDesc::U16)))
+        Ok(just_local_gas_and_mem(costs::SPECIAL_LIT_U16, 0, OpCode::SpecialLit(data, LitDesc::U16)))
     }
 
     fn get_literal_checker<'b, 'h>(&self, _data_idx: u8, _len: u16, _alloc: &'b HeapArena<'h>) -> Result<ValueSchema<'b>> {
@@ -22,37 +23,37 @@ impl External for U16{
         Ok(match fun_idx {
             //this is the identity funcntion (used for conversions where bit pattern does not change)
             //global external function eq(num1:.U16, num2:.U16):(res:Bool.Bool);
-            0 => just_local_gas_and_mem(15, 0, OpCode::Eq(Kind::U16, params[0], params[1])),
+            0 => just_local_gas_and_mem(costs::EQ_U16, 0, OpCode::Eq(Kind::U16, params[0], params[1])),
             //global external function lt(num1:.U16, num2:.U16):(res:Bool.Bool);
-            1 => just_local_gas_and_mem(15, 0, OpCode::Lt(Kind::U16, params[0], params[1])),
+            1 => just_local_gas_and_mem(costs::LT_U16, 0, OpCode::Lt(Kind::U16, params[0], params[1])),
             //global external function lte(num1:.U16, num2:.U16):(res:Bool.Bool);
-            2 => just_local_gas_and_mem(15, 0, OpCode::Lte(Kind::U16, params[0], params[1])),
+            2 => just_local_gas_and_mem(costs::LTE_U16, 0, OpCode::Lte(Kind::U16, params[0], params[1])),
             //global external function gt(num1:.U16, num2:.U16):(res:Bool.Bool);
-            3 => just_local_gas_and_mem(15, 0, OpCode::Gt(Kind::U16, params[0], params[1])),
+            3 => just_local_gas_and_mem(costs::GT_U16, 0, OpCode::Gt(Kind::U16, params[0], params[1])),
             //global external function gte(num1:.U16, num2:.U16):(res:Bool.Bool);
-            4 => just_local_gas_and_mem(15, 0, OpCode::Gte(Kind::U16, params[0], params[1])),
+            4 => just_local_gas_and_mem(costs::GTE_U16, 0, OpCode::Gte(Kind::U16, params[0], params[1])),
             //global external function add(num1:.U16, num2:.U16):(res:.U16);
-            5 => just_local_gas_and_mem(15, 0, OpCode::Add(Kind::U16, params[0], params[1])),
+            5 => just_local_gas_and_mem(costs::ADD_U16, 0, OpCode::Add(Kind::U16, params[0], params[1])),
             //global external function sub(num1:.U16, num2:.U16):(res:.U16);
-            6 => just_local_gas_and_mem(15, 0, OpCode::Sub(Kind::U16, params[0], params[1])),
+            6 => just_local_gas_and_mem(costs::SUB_U16, 0, OpCode::Sub(Kind::U16, params[0], params[1])),
             //global external function div(num1:.U16, num2:.U16):(res:.U16);
-            7 => just_local_gas_and_mem(20, 0, OpCode::Div(Kind::U16, params[0], params[1])),
+            7 => just_local_gas_and_mem(costs::DIV_U16, 0, OpCode::Div(Kind::U16, params[0], params[1])),
             //global external function mul(num1:.U16, num2:.U16):(res:.U16);
-            8 => just_local_gas_and_mem(15, 0, OpCode::Mul(Kind::U16, params[0], params[1])),
+            8 => just_local_gas_and_mem(costs::MUL_U16, 0, OpCode::Mul(Kind::U16, params[0], params[1])),
             //global transactional external function and(num1:.U16, num2:.U16):(res:.U16);
-            9 => just_local_gas_and_mem(15, 0, OpCode::And(Kind::U16, params[0], params[1])),
+            9 => just_local_gas_and_mem(costs::AND_U16, 0, OpCode::And(Kind::U16, params[0], params[1])),
             //global transactional external function or(num1:.U16, num2:.U16):(res:.U16);
-            10 => just_local_gas_and_mem(15, 0, OpCode::Or(Kind::U16, params[0], params[1])),
+            10 => just_local_gas_and_mem(costs::OR_U16, 0, OpCode::Or(Kind::U16, params[0], params[1])),
             //global transactional external function xor(num1:.U16, num2:.U16):(res:.U16);
-            11 => just_local_gas_and_mem(15, 0, OpCode::Xor(Kind::U16, params[0], params[1])),
+            11 => just_local_gas_and_mem(costs::XOR_U16, 0, OpCode::Xor(Kind::U16, params[0], params[1])),
             //global transactional external function not(num1:.U16):(res:.U16);
-            12 => just_local_gas_and_mem(15, 0, OpCode::Not(Kind::U16, params[0])),
+            12 => just_local_gas_and_mem(costs::NOT_U16, 0, OpCode::Not(Kind::U16, params[0])),
             //global external function toData(num:.U16):(res:Data.Data2);
-            13 => just_local_gas_and_mem(20, 2, OpCode::ToData(Kind::U16, params[0])),
+            13 => just_local_gas_and_mem(costs::TO_DATA_U16, 2, OpCode::ToData(Kind::U16, params[0])),
             //global external function fromData(data:Data.Data2):(res:.U16);
-            14 => just_local_gas_and_mem(20, 0, OpCode::FromData(Kind::U16, params[0])),
+            14 => just_local_gas_and_mem(costs::FROM_DATA_U16, 0, OpCode::FromData(Kind::U16, params[0])),
             //global external function hash(num:.U16):(res:Data.Data20);
-            15 => just_local_gas_and_mem(120, 20, OpCode::TypedSysInvoke(0, Kind::U16, params)),
+            15 => just_local_gas_and_mem(costs::plain_hash(2), 20, OpCode::TypedSysInvoke(0, Kind::U16, params)),
             _ => return error(||"External call is not defined")
         })
     }
@@ -20,10 +20,73 @@ use alloc::vec::Vec;
 use sanskrit_core::resolver::Context;
 use sanskrit_common::model::*;
 use sanskrit_core::utils::Crc;
+use sanskrit_common::limiter::Limiter;
+use crate::validate::ComponentKind;
+use core::cell::Cell;
+use core::fmt;
+use sanskrit_core::model::display::{Names, NoNames, Named};
 
 //Todo: Make Configurable
 //used to ensure that their is a stack size that prevents stack overflows
 const MAX_NESTING_DEPTH:usize = 50;
+//Work units charged for type checking an opcode
+const OPCODE_COST:u64 = 10;
+
+//A step on the way from the body to a nested opcode
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct ExpStep {
+    pub branch:u8,      //Which Exp of the enclosing opcode (case number for switches, 0 = success & 1 = failure for tries, 0 otherwise)
+    pub opcode:u16,     //The index of the opcode in that Exp
+}
+
+//A stack element at the time of the failure
+#[derive(Clone, Debug)]
+pub struct StackSlot {
+    pub typ:Crc<ResolvedType>,
+    pub status:Status,
+    pub locked:bool,
+}
+
+//Describes where and why the type checking of a component failed
+// it is recorded independent of string_errors (only with it the error describes it as well)
+#[derive(Clone, Debug)]
+pub struct CheckFailure {
+    pub kind:ComponentKind,
+    pub index:usize,
+    pub path:Vec<ExpStep>,                          //empty if the failure happened outside of the body (for example in the return types)
+    pub mismatch:Option<(Crc<ResolvedType>, Crc<ResolvedType>)>,   //expected and actual type if the failure is a type mismatch
+    pub stack:Vec<StackSlot>,                       //bottom first
+}
+
+impl CheckFailure {
+    //Renders the failure with the types shown by name
+    pub fn write_named<W:fmt::Write, N:Names>(&self, f:&mut W, names:&N) -> fmt::Result {
+        write!(f, "{:?} {}", self.kind, self.index)?;
+        for (i, step) in self.path.iter().enumerate() {
+            if i == 0 {
+                write!(f, " at opcode {}", step.opcode)?;
+            } else {
+                write!(f, " / branch {} opcode {}", step.branch, step.opcode)?;
+            }
+        }
+        if let Some((ref expected, ref actual)) = self.mismatch {
+            write!(f, ", expected {} but got {}", Named(&**expected, names), Named(&**actual, names))?;
+        }
+        write!(f, ", stack [")?;
+        for (i, slot) in self.stack.iter().enumerate() {
+            if i != 0 { write!(f, ", ")? }
+            write!(f, "{}:{:?}", Named(&*slot.typ, names), slot.status)?;
+            if slot.locked { write!(f, "(locked)")? }
+        }
+        write!(f, "]")
+    }
+}
+
+impl fmt::Display for CheckFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_named(f, &NoNames)
+    }
+}
 
 pub struct TypeCheckerContext<'b, S:Store + 'b> {
     context: Context<'b, S>,                 //The Resolved Components from the input
@@ -31,18 +94,64 @@ pub struct TypeCheckerContext<'b, S:Store + 'b> {
     transactional:bool,
     depth:usize,
     limit:usize,
+    limiter:&'b Limiter,                     //Accounts for the work done during type checking
+    kind:ComponentKind,                      //The checked component (for diagnostics)
+    index:usize,
+    path:Vec<ExpStep>,                       //The opcode currently checked
+    mismatch:Cell<Option<(Crc<ResolvedType>, Crc<ResolvedType>)>>, //The last type comparison that failed
+    failure:Option<CheckFailure>,
 }
 
 impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
     //Creates a new Empty context
-    pub fn new(context: Context<'b, S>) -> Self {
+    pub fn new(context: Context<'b, S>, kind:ComponentKind, index:usize, limiter:&'b Limiter) -> Self {
         //Define some reused types and capabilities
         TypeCheckerContext {
             context,
             stack: LinearStack::new(),
             transactional: false,
             depth: 0,
-            limit: MAX_NESTING_DEPTH
+            limit: MAX_NESTING_DEPTH,
+            limiter,
+            kind,
+            index,
+            path: Vec::new(),
+            mismatch: Cell::new(None),
+            failure: None,
+        }
+    }
+
+    //Where and why the type checking failed (if it did)
+    pub fn failure(&self) -> Option<&CheckFailure> {
+        self.failure.as_ref()
+    }
+
+    //Captures the state at the first error and adds it to the error
+    // errors passing through enclosing opcodes are already described
+    // the failure is always captured, but without the string_errors feature the error carries no context
+    fn fail(&mut self, err:ErrorType) -> ErrorType {
+        if self.failure.is_some() {
+            return err
+        }
+        let failure = CheckFailure {
+            kind: self.kind,
+            index: self.index,
+            path: self.path.clone(),
+            mismatch: self.mismatch.take(),
+            stack: self.stack.slots().map(|(typ, status, locked)|StackSlot{ typ:typ.clone(), status, locked }).collect(),
+        };
+        let err = error_context(err, ||format!("{}", failure));
+        self.failure = Some(failure);
+        err
+    }
+
+    //Compares a type with the expected one and remembers both if they differ
+    fn same_type(&self, expected:&Crc<ResolvedType>, actual:&Crc<ResolvedType>) -> bool {
+        if expected == actual {
+            true
+        } else {
+            self.mismatch.set(Some((expected.clone(), actual.clone())));
+            false
         }
     }
 
@@ -72,6 +181,17 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
 
     //todo: I hate this duplication but the signtures are different and unification is hard
     pub fn type_check_implement(&mut self, imp:&ImplementComponent, code:&Exp) -> Result<()>{
+        let res = self.check_implement(imp, code);
+        res.map_err(|err|self.fail(err))
+    }
+
+    //Type checks a function in the current context
+    pub fn type_check_function(&mut self, func:&FunctionComponent, code:&Exp) -> Result<()>{
+        let res = self.check_function(func, code);
+        res.map_err(|err|self.fail(err))
+    }
+
+    fn check_implement(&mut self, imp:&ImplementComponent, code:&Exp) -> Result<()>{
         //Fetch the Permission
         let r_perm = imp.sig.fetch(&self.context)?;
         //Check that it is the correct one
@@ -103,7 +223,7 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
         //Start a new block for th body of the function
         let block = self.stack.start_block();
         //Type check the function body
-        let rets = self.type_check_exp(code)?;
+        let rets = self.type_check_exp(code, 0)?;
 
         //Type check the Result
         //Ensure the amount is correct
@@ -114,7 +234,7 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
         for (v,t) in sig.returns.iter().rev().enumerate() {
             //Check if the returned value has the expected type
             assert!(v <= u8::max_value() as usize);
-            if self.stack.value_of(ValueRef(v as u16))? != *t {
+            if !self.same_type(t, &self.stack.value_of(ValueRef(v as u16))?) {
                 return error(||"Returned value has different type from return type declaration of the signature")
             }
         }
@@ -157,8 +277,7 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
         self.stack.check_function_param_signature(imp.params.len() as u16 + sig.params.len() as u16)
     }
 
-    //Type checks a function in the current context
-    pub fn type_check_function(&mut self, func:&FunctionComponent, code:&Exp) -> Result<()>{
+    fn check_function(&mut self, func:&FunctionComponent, code:&Exp) -> Result<()>{
         //Capture transactional declaration
         self.transactional = func.shared.transactional;
         //Push the input parameters onto the stack
@@ -175,7 +294,7 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
         //Start a new block for th body of the function
         let block = self.stack.start_block();
         //Type check the function body
-        let rets = self.type_check_exp(code)?;
+        let rets = self.type_check_exp(code, 0)?;
         //Type check the Result
         //Ensure the amount is correct
         if rets as usize != func.shared.returns.len() {
@@ -186,7 +305,7 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
             //Check if the returned value has the expected type
             let ret_typ = t.fetch(&self.context)?;
             assert!(idx <= u8::max_value() as usize);
-            if self.stack.value_of(ValueRef(idx as u16))? != ret_typ {
+            if !self.same_type(&ret_typ, &self.stack.value_of(ValueRef(idx as u16))?) {
                 return error(||"Returned value has different type from return type declaration of function")
             }
         }
@@ -216,7 +335,8 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
     }
 
     //Type checks an expression in the current context
-    fn type_check_exp<'c>(&mut self, exp: &'c Exp) -> Result<u8> {
+    // branch identifies the expression in the enclosing opcode
+    fn type_check_exp<'c>(&mut self, exp: &'c Exp, branch:u8) -> Result<u8> {
         //This is done to prevent a stack overflow
         // Basically it expresses that functions with more than self.limit levels are invalid
         //increase the nesting size
@@ -230,8 +350,14 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
         //prepare the lock_holder
         let mut lock_holder:Vec<LockInfo> = Vec::new();
         //Type check the opcodes leading up to this Return
-        for op in exp.0.iter() {
-            rets = self.type_check_op_code(op, &mut lock_holder)?
+        for (i, op) in exp.0.iter().enumerate() {
+            //on error the path is left as it is so that it points to the failing opcode
+            self.path.push(ExpStep{ branch, opcode: i as u16 });
+            rets = match self.type_check_op_code(op, &mut lock_holder) {
+                Ok(rets) => rets,
+                Err(err) => return Err(self.fail(err))
+            };
+            self.path.pop();
         }
 
         for lock in lock_holder {
@@ -255,6 +381,7 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
 
     //The heavy lifter that type checks op sys
     fn type_check_op_code(&mut self, code: &OpCode, lock_holder:&mut Vec<LockInfo>) -> Result<u8> {
+        self.limiter.consume(OPCODE_COST)?;
         //Branch on the opcode type and check it
         match *code {
             OpCode::Lit(ref data, perm) => self.lit(data, perm),
@@ -312,7 +439,7 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
         //Tell the stack that a new scope has started
         let block = self.stack.start_block();
         //Type check the content of the Let
-        let results = self.type_check_exp(bind)?;
+        let results = self.type_check_exp(bind, 0)?;
         //discard unneeded items
         self.clean_frame(results,start_height)?;
         //Close the scope leaving only the results on the Stack
@@ -559,7 +686,7 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
             };
 
             //remaining operations are specified by branch sys and now type checked
-            let res = self.type_check_exp(case)?;
+            let res = self.type_check_exp(case, i as u8)?;
             //pass intermediary result to next iter
             loop_res = Some(res);
         }
@@ -610,7 +737,7 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
             }
 
             //Check that the type of the param matches
-            if r_ctr[t as usize][i] != r_v {
+            if !self.same_type(&r_ctr[t as usize][i], &r_v) {
                 return error(||"Parameter for data constructor has wrong type")
             }
         }
@@ -769,7 +896,7 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
         //Prepare the Inputs
         let inputs:Vec<(ValueRef,bool)> = vals.iter().zip(signature.params.iter()).map(|(v,p)| {
             //Ensure tat the argument has the expected type
-            if self.stack.value_of(*v)? != p.typ {
+            if !self.same_type(&p.typ, &self.stack.value_of(*v)?) {
                 error(||"Parameter for function call has wrong type")
             } else {
                 Ok((*v, p.consumes))
@@ -799,7 +926,7 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
         //Prepare the Inputs
         let inputs:Vec<(ValueRef,bool)> = vals.iter().zip(signature.params.iter()).map(|((essential,v),p)| {
             //Ensure that the argument has the expected type
-            if self.stack.value_of(*v)? != p.typ {
+            if !self.same_type(&p.typ, &self.stack.value_of(*v)?) {
                 return error(||"Parameter for function call has wrong type")
             }
             if *essential {
@@ -829,7 +956,7 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
                 self.stack.provide(ret.clone())?;
             }
             //on success operations are specified by branch sys and now type checked
-            let suc_res = self.type_check_exp(succ)?;
+            let suc_res = self.type_check_exp(succ, 0)?;
             //discard unneeded items
             self.clean_frame( suc_res,start_height)?;
         //go to the failure case branch
@@ -839,7 +966,7 @@ impl<'b, S:Store + 'b> TypeCheckerContext<'b,S> {
                 self.stack.provide(param.typ.clone())?;
             }
             //on failure operations are specified by branch sys and now type checked
-            let fail_res = self.type_check_exp(fail)?;
+            let fail_res = self.type_check_exp(fail, 1)?;
             //discard unneeded items
             self.clean_frame( fail_res,start_height)?;
         //end the branch
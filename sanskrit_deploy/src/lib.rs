@@ -16,78 +16,105 @@ mod validate;
 //mod native;
 mod code_type_checker;
 
+pub use validate::{ComponentKind, ValidationTracker};
+pub use code_type_checker::{CheckFailure, ExpStep, StackSlot};
+pub use linear_stack::Status;
+
 
 use sanskrit_common::store::*;
 use sanskrit_common::errors::*;
 use alloc::vec::Vec;
 use sanskrit_common::model::*;
+use sanskrit_common::limiter::Limiter;
 use sanskrit_core::model::Module;
 
 //Todo: Make Configurable
 const INPUT_SIZE_LIMIT:usize = 256000;
 //const INPUT_SIZE_LIMIT:usize = 2048000; //for test
+//Work units charged per input byte (accounts for parsing the input)
+const BYTE_COST:u64 = 1;
 
-pub fn deploy_stored_module<S:Store>(store:&CachedStore<Module,S>, module_hash:Hash, system_mode_on:bool) -> Result<()>{
+pub fn deploy_stored_module<S:Store>(store:&CachedStore<Module,S>, module_hash:Hash, system_mode_on:bool, limiter:&Limiter) -> Result<()>{
     store.get(StorageClass::Module, &module_hash, |data|{
-        inner_deploy_module(store,module_hash,data,system_mode_on)
+        inner_deploy_module(store,module_hash,data,system_mode_on,limiter,&mut ())
     })?
 }
 
-pub fn deploy_module<S:Store>(store:&CachedStore<Module,S>, data:Vec<u8>, system_mode_on:bool, auto_commit:bool) -> Result<Hash>{
+pub fn deploy_module<S:Store>(store:&CachedStore<Module,S>, data:Vec<u8>, system_mode_on:bool, auto_commit:bool, limiter:&Limiter) -> Result<Hash>{
+    let module_hash = stage_module(store, data, system_mode_on, limiter, &mut ())?;
+    if auto_commit {
+        store.commit(StorageClass::Module);
+    }
+    Ok(module_hash)
+}
+
+//Validates and stores a module without committing it, the tracker is informed about each validated component
+pub fn stage_module<S:Store, T:ValidationTracker>(store:&CachedStore<Module,S>, data:Vec<u8>, system_mode_on:bool, limiter:&Limiter, tracker:&mut T) -> Result<Hash>{
     //calcs the ModuleHash
     let module_hash = store_hash(&[&data]);
-    inner_deploy_module(store,module_hash,&data,system_mode_on)?;
+    inner_deploy_module(store,module_hash,&data,system_mode_on,limiter,tracker)?;
     //stores the input
     match store.set(StorageClass::Module, module_hash,data) {
         Ok(_) => {}
         //Todo: We ignore for now if it is already in the store
         Err(_) => {}
     }
-    if auto_commit {
-        store.commit(StorageClass::Module);
-    }
     Ok(module_hash)
 }
 
-fn inner_deploy_module<S:Store>(store:&CachedStore<Module,S>, module_hash:Hash, data:&[u8], system_mode_on:bool) -> Result<()>{
+fn inner_deploy_module<S:Store, T:ValidationTracker>(store:&CachedStore<Module,S>, module_hash:Hash, data:&[u8], system_mode_on:bool, limiter:&Limiter, tracker:&mut T) -> Result<()>{
     //Check input limitation constraint
     if data.len() > INPUT_SIZE_LIMIT {
         return error(||"Input is to big")
     }
+    if data.is_empty() {
+        return error(||"Module is empty")
+    }
+    limiter.consume(data.len() as u64 * BYTE_COST)?;
     //Read the system module flag and disable system mode if not set
     let system_module:bool = data[0] != 0;
     //if it is already deployed we can ignore it
     //validates the input
-    validate::validate(&data, store, module_hash, system_mode_on & system_module)?;
+    validate::validate(&data, store, module_hash, system_mode_on & system_module, limiter, tracker)?;
     Ok(())
 }
 
 //Processes a function used by compiler to check top level transactions
-pub fn deploy_function<S:Store>(store:&CachedStore<Module,S>, data:Vec<u8>, auto_commit:bool) -> Result<Hash>{
+pub fn deploy_function<S:Store>(store:&CachedStore<Module,S>, data:Vec<u8>, auto_commit:bool, limiter:&Limiter) -> Result<Hash>{
+    let function_hash = stage_function(store, data, limiter, &mut ())?;
+    if auto_commit {
+        store.commit(StorageClass::Transaction);
+    }
+    Ok(function_hash)
+}
+
+//Validates and stores a top level function without committing it
+pub fn stage_function<S:Store, T:ValidationTracker>(store:&CachedStore<Module,S>, data:Vec<u8>, limiter:&Limiter, tracker:&mut T) -> Result<Hash>{
     //calcs the FunctionHash
     let function_hash = store_hash(&[&data]);
     //if it is already deployed we can ignore it
     //validates the input
-    validate_function(store, &data)?;
+    inner_validate_function(store, &data, limiter, tracker)?;
     //stores the input
     match store.set(StorageClass::Transaction, function_hash, data) {
         Ok(_) => {}
         //Todo: We ignore for now if it is already in the store
         Err(_) => {}
     }
-    if auto_commit {
-        store.commit(StorageClass::Transaction);
-    }
-    //}
     Ok(function_hash)
 }
 
-pub fn validate_function<S:Store>(store:&CachedStore<Module,S>, data:&Vec<u8>) -> Result<()>{
+pub fn validate_function<S:Store>(store:&CachedStore<Module,S>, data:&Vec<u8>, limiter:&Limiter) -> Result<()>{
+    inner_validate_function(store, data, limiter, &mut ())
+}
+
+fn inner_validate_function<S:Store, T:ValidationTracker>(store:&CachedStore<Module,S>, data:&[u8], limiter:&Limiter, tracker:&mut T) -> Result<()>{
     //Check input limitation constraint
     if data.len() > INPUT_SIZE_LIMIT {
         return error(||"Input is to big")
     }
+    limiter.consume(data.len() as u64 * BYTE_COST)?;
     //if it is already deployed we can ignore it
     //validates the input
-    validate::validate_top_function(data, store)
+    validate::validate_top_function(data, store, limiter, tracker)
 }
@@ -155,6 +155,11 @@ impl<T:Clone+Eq> LinearStack<T>  {
         Ok(elem)
     }
 
+    //Lists the elements from the bottom of the stack to the top with their status and whether they are locked
+    pub fn slots(&self) -> impl Iterator<Item=(&T, Status, bool)> {
+        self.stack.iter().map(|e|(&e.value, e.status.get(), e.locked))
+    }
+
     fn get_elem_absolute(&mut self, index: usize) -> Result<&mut Elem<T>> {
         if index >= self.stack.len() {
             return error(||"Accesed value lies outside of the stack");
@@ -11,17 +11,57 @@ use sanskrit_common::encoding::*;
 use sanskrit_common::store::{CachedStore, Store};
 use sanskrit_core::utils::Crc;
 use sanskrit_core::loader::Loader;
-use crate::code_type_checker::TypeCheckerContext;
+use crate::code_type_checker::{TypeCheckerContext, CheckFailure};
 use sanskrit_common::model::ModuleLink;
 use sanskrit_common::model::Hash;
 use sanskrit_core::model::bitsets::{CapSet, BitSet, PermSet};
+use sanskrit_common::limiter::Limiter;
+
+//Work units charged for each validated component (its imports and dependencies are charged by the loader)
+const COMPONENT_COST:u64 = 100;
+
+//The kinds of components that are validated
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ComponentKind {
+    Data,
+    Signature,
+    Function,
+    Implement,
+    Transaction
+}
+
+//Follows the progress of a validation
+// a component that is started but not finished is the one that failed
+pub trait ValidationTracker {
+    fn component_start(&mut self, kind:ComponentKind, offset:usize);
+    fn component_finish(&mut self, kind:ComponentKind, offset:usize);
+    //Called with the details if the type checking of a component body failed
+    fn check_failed(&mut self, _failure:&CheckFailure) {}
+}
+
+//Used when no one is interested in the progress
+impl ValidationTracker for () {
+    fn component_start(&mut self, _kind:ComponentKind, _offset:usize) {}
+    fn component_finish(&mut self, _kind:ComponentKind, _offset:usize) {}
+}
+
+//Hands the details of a failed type check to the tracker
+// the error already names the location and the types involved (see TypeCheckerContext::fail)
+fn report_failure<S:Store, T:ValidationTracker>(res:Result<()>, checker:&TypeCheckerContext<S>, tracker:&mut T) -> Result<()> {
+    if let Some(failure) = checker.failure() {
+        tracker.check_failed(failure);
+    }
+    res
+}
 
-pub fn validate_top_function<S:Store>(data:&[u8], store:&CachedStore<Module,S>) -> Result<()>{
+pub fn validate_top_function<S:Store, T:ValidationTracker>(data:&[u8], store:&CachedStore<Module,S>, limiter:&Limiter, tracker:&mut T) -> Result<()>{
     //Parse the function
     let fun:FunctionComponent = Parser::parse_fully::<FunctionComponent,NoCustomAlloc>(data, usize::max_value(),&NoCustomAlloc())?;
     //Prepare the cache for this iteration
-    let resolver = Loader::new_complete(store);
+    let resolver = Loader::new_complete(store, limiter);
+    tracker.component_start(ComponentKind::Transaction, 0);
     //Prepare the context
+    limiter.consume(COMPONENT_COST)?;
     let context = Context::from_top_component(&fun, &resolver)?;
 
     //let context = match Context::from_top_component(&fun, &resolver)
@@ -29,13 +69,15 @@ pub fn validate_top_function<S:Store>(data:&[u8], store:&CachedStore<Module,S>)
     validate_transaction(&fun, &context)?;
     //Do the type checking of the sys in the function body
     if let CallableImpl::Internal {ref code, ..} = fun.body {
-        let mut checker = TypeCheckerContext::<S>::new(context);
-        checker.type_check_function(&fun, code)?;
+        let mut checker = TypeCheckerContext::<S>::new(context, ComponentKind::Transaction, 0, limiter);
+        let res = checker.type_check_function(&fun, code);
+        report_failure(res, &checker, tracker)?;
     }
+    tracker.component_finish(ComponentKind::Transaction, 0);
     Ok(())
 }
 
-pub fn validate<S:Store>(data:&[u8], store:&CachedStore<Module,S>, link:Hash, system_mode_on:bool) -> Result<()> {
+pub fn validate<S:Store, T:ValidationTracker>(data:&[u8], store:&CachedStore<Module,S>, link:Hash, system_mode_on:bool, limiter:&Limiter, tracker:&mut T) -> Result<()> {
     //Parse the module
     let parsed: Rc<Module> = store.get_direct::<NoCustomAlloc>(data, &link, usize::MAX, &NoCustomAlloc())?;
     //Check if it is a system Module
@@ -47,7 +89,7 @@ pub fn validate<S:Store>(data:&[u8], store:&CachedStore<Module,S>, link:Hash, sy
         }
     }
     //Prepare the cache for this iteration
-    let resolver = Loader::new_incremental(store, link, parsed);
+    let resolver = Loader::new_incremental(store, link, parsed, limiter);
     //Get a reference to the cached Module
     let module = resolver.get_module(link)?;
     // get the module lnk
@@ -69,10 +111,13 @@ pub fn validate<S:Store>(data:&[u8], store:&CachedStore<Module,S>, link:Hash, sy
             }
             //get it
             let d = &module.data[cur_adt_offset];
+            tracker.component_start(ComponentKind::Data, cur_adt_offset);
             //Prepare the context
+            limiter.consume(COMPONENT_COST)?;
             let context = Context::from_module_component(d, &module_link, false,&resolver)?;
             //Ensure the input is formally correct and has the expected properties
             validate_adt(d, &context, system_mode_on, #[cfg(feature = "forward_type_ref")] cur_adt_offset)?;
+            tracker.component_finish(ComponentKind::Data, cur_adt_offset);
             cur_adt_offset += 1;
             //Hint the cache that a new Adt is available in the current module
             #[cfg(not(feature = "forward_type_ref"))]
@@ -84,10 +129,13 @@ pub fn validate<S:Store>(data:&[u8], store:&CachedStore<Module,S>, link:Hash, sy
             }
             //get it
             let s = &module.sigs[cur_sig_offset];
+            tracker.component_start(ComponentKind::Signature, cur_sig_offset);
             //Prepare the context
+            limiter.consume(COMPONENT_COST)?;
             let context = Context::from_module_component(s, &module_link, false, &resolver)?;
             //Ensure the input is formally correct and has the expected properties
             validate_sig(s, &context)?;
+            tracker.component_finish(ComponentKind::Signature, cur_sig_offset);
             cur_sig_offset += 1;
             //Hint the cache that a new Signature is available in the current module
             #[cfg(not(feature = "forward_type_ref"))]
@@ -104,14 +152,18 @@ pub fn validate<S:Store>(data:&[u8], store:&CachedStore<Module,S>, link:Hash, sy
             }
             //get it
             let f = &module.functions[tdf];
+            tracker.component_start(ComponentKind::Function, tdf);
+            limiter.consume(COMPONENT_COST)?;
             let context = Context::from_module_component(f, &module_link, true, &resolver)?;
             //Ensure the input is formally correct and has the expected properties
             validate_function(f, &context, system_mode_on)?;
             //Do the type checking of the sys in the function body
             if let CallableImpl::Internal {ref code, ..} = f.body {
-                let mut checker = TypeCheckerContext::<S>::new(context);
-                checker.type_check_function(f, code)?;
+                let mut checker = TypeCheckerContext::<S>::new(context, ComponentKind::Function, tdf, limiter);
+                let res = checker.type_check_function(f, code);
+                report_failure(res, &checker, tracker)?;
             }
+            tracker.component_finish(ComponentKind::Function, tdf);
             //Hint the cache that a new Function is available in the current module
             resolver.this_deployed_functions.set(tdf + 1);
         } else {
@@ -122,15 +174,19 @@ pub fn validate<S:Store>(data:&[u8], store:&CachedStore<Module,S>, link:Hash, sy
             }
             //get it
             let i = &module.implements[tdi];
+            tracker.component_start(ComponentKind::Implement, tdi);
             //Prepare the context
+            limiter.consume(COMPONENT_COST)?;
             let context = Context::from_module_component(i, &module_link, true,&resolver)?;
             //Ensure the input is formally correct and has the expected properties
             validate_implement(i, &context, system_mode_on)?;
             //Do the type checking of the sys in the function body
             if let CallableImpl::Internal {ref code, ..} = i.body {
-                let mut checker = TypeCheckerContext::<S>::new(context);
-                checker.type_check_implement(i, code)?;
+                let mut checker = TypeCheckerContext::<S>::new(context, ComponentKind::Implement, tdi, limiter);
+                let res = checker.type_check_implement(i, code);
+                report_failure(res, &checker, tracker)?;
             }
+            tracker.component_finish(ComponentKind::Implement, tdi);
             //Hint the cache that a new Implement is available in the current module
             resolver.this_deployed_implements.set(tdi+1);
         }
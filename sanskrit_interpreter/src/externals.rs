@@ -1,16 +1,43 @@
-use sanskrit_common::errors::*;
-use crate::model::{Entry, Kind};
-use sanskrit_common::model::ValueRef;
-use sanskrit_common::arena::{HeapStack, VirtualHeapArena};
-
-pub trait ExecutionInterface<'interpreter, 'transaction, 'heap> {
-    fn get(&self, idx: usize) -> Result<Entry<'transaction>>;
-    fn get_stack(&mut self, tail: bool) -> &mut HeapStack<'interpreter, Entry<'transaction>>;
-    fn get_heap(&self) -> &'transaction VirtualHeapArena<'heap>;
-    fn process_entry_slice<R: Sized, F: FnOnce(&[u8]) -> R>(kind: Kind, op1: Entry<'transaction>, proc: F) -> R;
-}
-
-pub trait RuntimeExternals {
-    fn typed_system_call<'interpreter, 'transaction:'interpreter, 'heap:'transaction, I:ExecutionInterface<'interpreter, 'transaction, 'heap>>(interface:&mut I, id:u8, kind:Kind, values: &[ValueRef], tail:bool) -> Result<()>;
-    fn system_call<'interpreter, 'transaction:'interpreter, 'heap:'transaction, I:ExecutionInterface<'interpreter, 'transaction, 'heap>>(interface:&mut I, id:u8, values: &[ValueRef], tail:bool) -> Result<()>;
+use sanskrit_common::errors::*;
+use crate::model::{Entry, Kind};
+use sanskrit_common::model::ValueRef;
+use sanskrit_common::arena::{HeapStack, VirtualHeapArena};
+use alloc::vec::Vec;
+
+//Signature checks that were answered with true in advance
+// they are verified together at the end of the section, if one of them does not hold the section fails
+#[derive(Default)]
+pub struct SignatureBatch {
+    //message, public key and signature of each check
+    pub checks:Vec<(Vec<u8>, [u8;32], [u8;64])>,
+}
+
+impl SignatureBatch {
+    pub fn new() -> Self {
+        SignatureBatch { checks: Vec::new() }
+    }
+
+    pub fn queue(&mut self, msg:&[u8], pk:[u8;32], sig:[u8;64]) {
+        self.checks.push((msg.to_vec(), pk, sig))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checks.is_empty()
+    }
+}
+
+pub trait ExecutionInterface<'interpreter, 'transaction, 'heap> {
+    fn get(&self, idx: usize) -> Result<Entry<'transaction>>;
+    fn get_stack(&mut self, tail: bool) -> &mut HeapStack<'interpreter, Entry<'transaction>>;
+    fn get_heap(&self) -> &'transaction VirtualHeapArena<'heap>;
+    fn process_entry_slice<R: Sized, F: FnOnce(&[u8]) -> R>(kind: Kind, op1: Entry<'transaction>, proc: F) -> R;
+    //the batch collecting the signature checks of the section (None if signatures have to be checked individually)
+    fn signature_batch(&mut self) -> Option<&mut SignatureBatch>;
+}
+
+pub trait RuntimeExternals {
+    fn typed_system_call<'interpreter, 'transaction:'interpreter, 'heap:'transaction, I:ExecutionInterface<'interpreter, 'transaction, 'heap>>(interface:&mut I, id:u8, kind:Kind, values: &[ValueRef], tail:bool) -> Result<()>;
+    fn system_call<'interpreter, 'transaction:'interpreter, 'heap:'transaction, I:ExecutionInterface<'interpreter, 'transaction, 'heap>>(interface:&mut I, id:u8, values: &[ValueRef], tail:bool) -> Result<()>;
+    //verifies the signature checks collected during a bundle at once (externals that never collect any can keep the default)
+    fn verify_signature_batch(batch:&SignatureBatch) -> bool { batch.is_empty() }
 }
\ No newline at end of file
@@ -5,7 +5,8 @@ use crate::model::*;
 use sanskrit_common::arena::*;
 use byteorder::{ByteOrder};
 use sanskrit_common::encoding::EncodingByteOrder;
-use crate::externals::{ExecutionInterface, RuntimeExternals};
+use crate::externals::{ExecutionInterface, RuntimeExternals, SignatureBatch};
+use ethnum::U256;
 
 //enum to indicate if a block had a result or an error as return
 #[derive(Copy, Clone, Debug)]
@@ -49,6 +50,8 @@ pub struct ExecutionContext<'transaction, 'code, 'interpreter, 'execution, 'heap
     #[cfg(feature = "dynamic_gas")]
     used_gas:u64,
     #[cfg(feature = "dynamic_gas")]
+    gas_limit:u64,
+    #[cfg(feature = "dynamic_gas")]
     functions: &'code [TxTFunction<'code>],                                                     // all the sys
     #[cfg(not(feature = "dynamic_gas"))]
     functions: &'code [Ptr<'code,Exp<'code>>],                                                  // all the sys
@@ -56,6 +59,7 @@ pub struct ExecutionContext<'transaction, 'code, 'interpreter, 'execution, 'heap
     stack: &'execution mut HeapStack<'interpreter, Entry<'transaction> >,                       //The current stack
     alloc: &'transaction VirtualHeapArena<'heap>,
     return_stack: &'execution mut HeapStack<'interpreter, Entry<'transaction> >,
+    signatures: Option<&'execution mut SignatureBatch>,
     try_ptr:Option<usize>,
 }
 
@@ -63,7 +67,7 @@ pub struct ExecutionContext<'transaction, 'code, 'interpreter, 'execution, 'heap
 pub fn create_lit_object<'transaction, 'heap>(data:&[u8], typ:LitDesc, alloc:&'transaction VirtualHeapArena<'heap>) -> Result<Entry<'transaction>> {
     //find out which literal to create
     Ok(match typ {
-        LitDesc::Id | LitDesc::Data => Entry { data: alloc.copy_alloc_slice(data)? },
+        LitDesc::Id | LitDesc::Data | LitDesc::U256 => Entry { data: alloc.copy_alloc_slice(data)? },
         LitDesc::I8 => Entry { i8:data[0] as i8 },
         LitDesc::U8 => Entry { u8:data[0]},
         LitDesc::I16 => Entry {i16:EncodingByteOrder::read_i16(data)},
@@ -143,9 +147,13 @@ impl <'transaction,'code,'interpreter,'execution,'heap> ExecutionInterface<'inte
                 EncodingByteOrder::write_u128(&mut input, unsafe {op1.u128});
                 proc(&input)
             },
-            Kind::Data => proc(unsafe {&op1.data})
+            Kind::Data | Kind::U256 => proc(unsafe {&op1.data})
         }
     }
+
+    fn signature_batch(&mut self) -> Option<&mut SignatureBatch> {
+        self.signatures.as_deref_mut()
+    }
 }
 
 #[cfg(not(feature = "dynamic_gas"))]
@@ -164,16 +172,23 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
         stack:&'execution mut HeapStack<'interpreter,Entry<'transaction>>,
         frames:&'execution mut HeapStack<'interpreter,Frame<'code>>,
         return_stack:&'execution mut HeapStack<'interpreter,Entry<'transaction>>,
-        alloc:&'transaction VirtualHeapArena<'heap>
+        alloc:&'transaction VirtualHeapArena<'heap>,
+        //collects the signature checks instead of verifying them individually
+        signatures:Option<&'execution mut SignatureBatch>,
+        //the execution is aborted if it uses more gas
+        #[cfg(feature = "dynamic_gas")]
+        gas_limit:u64,
     ) -> InterpreterResult {
         //Define some reused types and capabilities
         let mut context = ExecutionContext {
             #[cfg(feature = "dynamic_gas")] used_gas:0,
+            #[cfg(feature = "dynamic_gas")] gas_limit,
             functions,
             frames,
             stack,
             alloc,
             return_stack,
+            signatures,
             try_ptr:None,
         };
         #[cfg(feature = "dynamic_gas")]
@@ -186,8 +201,12 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
 
     #[inline(always)]
     #[cfg(feature = "dynamic_gas")]
-    fn use_gas(&mut self, gas: u32) -> () {
+    fn use_gas(&mut self, gas: u32) -> Result<()> {
         self.used_gas += gas as u64;
+        if self.used_gas > self.gas_limit {
+            return error(||"Out of gas")
+        }
+        Ok(())
     }
 
     //TExecutes a function in the current context
@@ -197,7 +216,7 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
         let code = &self.functions[fun_idx as usize];
 
         #[cfg(feature = "dynamic_gas")]
-        self.use_gas(code.gas);
+        self.use_gas(code.gas)?;
 
         self.frames.push(Frame::Continuation {
             #[cfg(feature = "dynamic_gas")] exp: &code.body,
@@ -381,7 +400,7 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
                         //can we do more iterations?
                         if counter != 0 {
                             //add gas for the run
-                            self.use_gas(gas);
+                            self.use_gas(gas)?;
                             //Re-push ourself
                             self.frames.push(Frame::Repeat { exp, gas, stack_height, cond_value:ValueRef(idx), abort_tag, counter: counter -1})?;
                             //Push the next iteration
@@ -493,7 +512,7 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
     #[cfg(feature = "dynamic_gas")]
     fn gas(&mut self, gas:u32) -> Result<Continuation<'code>> {
         //consume the gas
-        self.use_gas(gas);
+        self.use_gas(gas)?;
         Ok(Continuation::Next)
     }
 
@@ -618,7 +637,7 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
         let fun_code: &Exp = &fun.body;
         //add gas for call
         #[cfg(feature = "dynamic_gas")]
-        self.use_gas(fun.gas);
+        self.use_gas(fun.gas)?;
 
 
         //fetch the height
@@ -655,7 +674,7 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
         let fun_code: &Exp = &fun.body;
         //add gas for call
         #[cfg(feature = "dynamic_gas")]
-        self.use_gas(fun.gas);
+        self.use_gas(fun.gas)?;
         //fetch the height
         let stack_height = self.stack.len();
         //push the arguments
@@ -744,7 +763,8 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => Entry{u64: unsafe {op1.u64 & op2.u64}},
             Kind::I128 => Entry{i128: unsafe {op1.i128 & op2.i128}},
             Kind::U128 => Entry{u128: unsafe {op1.u128 & op2.u128}},
-            Kind::Data => {
+            //U256 is stored as 32 byte big endian data so bitwise ops are the same
+            Kind::Data | Kind::U256 => {
                 let data1 = unsafe {op1.data};
                 let data2 = unsafe {op2.data};
                 let mut builder = self.alloc.slice_builder(data1.len())?;
@@ -772,7 +792,8 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => Entry{u64: unsafe {op1.u64 | op2.u64}},
             Kind::I128 => Entry{i128: unsafe {op1.i128 | op2.i128}},
             Kind::U128 => Entry{u128: unsafe {op1.u128 | op2.u128}},
-            Kind::Data => {
+            //U256 is stored as 32 byte big endian data so bitwise ops are the same
+            Kind::Data | Kind::U256 => {
                 let data1 = unsafe {op1.data};
                 let data2 = unsafe {op2.data};
                 let mut builder = self.alloc.slice_builder(data1.len())?;
@@ -800,7 +821,8 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => Entry{u64: unsafe {op1.u64 ^ op2.u64}},
             Kind::I128 => Entry{i128: unsafe {op1.i128 ^ op2.i128}},
             Kind::U128 => Entry{u128: unsafe {op1.u128 ^ op2.u128}},
-            Kind::Data => {
+            //U256 is stored as 32 byte big endian data so bitwise ops are the same
+            Kind::Data | Kind::U256 => {
                 let data1 = unsafe {op1.data};
                 let data2 = unsafe {op2.data};
                 let mut builder = self.alloc.slice_builder(data1.len())?;
@@ -827,7 +849,8 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => Entry{u64: unsafe {!op1.u64}},
             Kind::I128 => Entry{i128: unsafe {!op1.i128}},
             Kind::U128 => Entry{u128: unsafe {!op1.u128}},
-            Kind::Data => {
+            //U256 is stored as 32 byte big endian data so bitwise ops are the same
+            Kind::Data | Kind::U256 => {
                 let data1 = unsafe {op1.data};
                 let mut builder = self.alloc.slice_builder(data1.len())?;
                 for i in 0..data1.len() {
@@ -874,6 +897,7 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => unsafe {op1.u64.checked_add(op2.u64)}.map(|r| Entry{u64: r}),
             Kind::I128 => unsafe {op1.i128.checked_add(op2.i128)}.map(|r| Entry{i128: r}),
             Kind::U128 => unsafe {op1.u128.checked_add(op2.u128)}.map(|r| Entry{u128: r}),
+            Kind::U256 => self.u256_op(op1, op2, U256::checked_add)?,
             Kind::Data => unreachable!(),
         };
 
@@ -901,6 +925,7 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => unsafe {op1.u64.checked_sub(op2.u64)}.map(|r| Entry{u64: r}),
             Kind::I128 => unsafe {op1.i128.checked_sub(op2.i128)}.map(|r| Entry{i128: r}),
             Kind::U128 => unsafe {op1.u128.checked_sub(op2.u128)}.map(|r| Entry{u128: r}),
+            Kind::U256 => self.u256_op(op1, op2, U256::checked_sub)?,
             Kind::Data => unreachable!(),
         };
 
@@ -928,6 +953,7 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => unsafe {op1.u64.checked_mul(op2.u64)}.map(|r| Entry{u64: r}),
             Kind::I128 => unsafe {op1.i128.checked_mul(op2.i128)}.map(|r| Entry{i128: r}),
             Kind::U128 => unsafe {op1.u128.checked_mul(op2.u128)}.map(|r| Entry{u128: r}),
+            Kind::U256 => self.u256_op(op1, op2, U256::checked_mul)?,
             Kind::Data => unreachable!(),
         };
 
@@ -955,6 +981,7 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => unsafe {op1.u64.checked_div(op2.u64)}.map(|r| Entry{u64: r}),
             Kind::I128 => unsafe {op1.i128.checked_div(op2.i128)}.map(|r| Entry{i128: r}),
             Kind::U128 => unsafe {op1.u128.checked_div(op2.u128)}.map(|r| Entry{u128: r}),
+            Kind::U256 => self.u256_op(op1, op2, U256::checked_div)?,
             Kind::Data => unreachable!(),
         };
 
@@ -982,7 +1009,7 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => Entry{ adt: Adt(unsafe {op1.u64 == op2.u64} as u8, SlicePtr::empty())},
             Kind::I128 => Entry{ adt: Adt(unsafe {op1.i128 == op2.i128} as u8, SlicePtr::empty())},
             Kind::U128 => Entry{ adt: Adt(unsafe {op1.u128 == op2.u128} as u8, SlicePtr::empty())},
-            Kind::Data => Entry{ adt: Adt(unsafe {op1.data == op2.data} as u8, SlicePtr::empty())},
+            Kind::Data | Kind::U256 => Entry{ adt: Adt(unsafe {op1.data == op2.data} as u8, SlicePtr::empty())},
         })?;
         Ok(Continuation::Next)
     }
@@ -1012,6 +1039,8 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => Entry{adt: Adt(unsafe {op1.u64 < op2.u64} as u8, SlicePtr::empty())},
             Kind::I128 => Entry{adt: Adt(unsafe {op1.i128 < op2.i128} as u8, SlicePtr::empty())},
             Kind::U128 => Entry{adt: Adt(unsafe {op1.u128 < op2.u128} as u8, SlicePtr::empty())},
+            //both are 32 byte big endian so the byte order is the numeric order
+            Kind::U256 => Entry{adt: Adt(unsafe {op1.data.as_ref() < op2.data.as_ref()} as u8, SlicePtr::empty())},
             Kind::Data => unreachable!(),
         })?;
         Ok(Continuation::Next)
@@ -1032,6 +1061,8 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => Entry{adt: Adt(unsafe {op1.u64 > op2.u64} as u8, SlicePtr::empty())},
             Kind::I128 => Entry{adt: Adt(unsafe {op1.i128 > op2.i128} as u8, SlicePtr::empty())},
             Kind::U128 => Entry{adt: Adt(unsafe {op1.u128 > op2.u128} as u8, SlicePtr::empty())},
+            //both are 32 byte big endian so the byte order is the numeric order
+            Kind::U256 => Entry{adt: Adt(unsafe {op1.data.as_ref() > op2.data.as_ref()} as u8, SlicePtr::empty())},
             Kind::Data => unreachable!(),
         })?;
         Ok(Continuation::Next)
@@ -1052,6 +1083,8 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => Entry{adt: Adt(unsafe {op1.u64 <= op2.u64} as u8, SlicePtr::empty())},
             Kind::I128 => Entry{adt: Adt(unsafe {op1.i128 <= op2.i128} as u8, SlicePtr::empty())},
             Kind::U128 => Entry{adt: Adt(unsafe {op1.u128 <= op2.u128} as u8, SlicePtr::empty())},
+            //both are 32 byte big endian so the byte order is the numeric order
+            Kind::U256 => Entry{adt: Adt(unsafe {op1.data.as_ref() <= op2.data.as_ref()} as u8, SlicePtr::empty())},
             Kind::Data => unreachable!(),
         })?;
         Ok(Continuation::Next)
@@ -1072,6 +1105,8 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => Entry { adt: Adt(unsafe { op1.u64 >= op2.u64 } as u8, SlicePtr::empty())},
             Kind::I128 => Entry { adt: Adt(unsafe { op1.i128 >= op2.i128 } as u8, SlicePtr::empty())},
             Kind::U128 => Entry { adt: Adt(unsafe { op1.u128 >= op2.u128 } as u8, SlicePtr::empty())},
+            //both are 32 byte big endian so the byte order is the numeric order
+            Kind::U256 => Entry { adt: Adt(unsafe { op1.data.as_ref() >= op2.data.as_ref() } as u8, SlicePtr::empty())},
             Kind::Data => unreachable!(),
         })?;
         Ok(Continuation::Next)
@@ -1093,13 +1128,25 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
             Kind::U64 => Entry{u64:EncodingByteOrder::read_u64(&op1)},
             Kind::I128 => Entry{i128:EncodingByteOrder::read_i128(&op1)},
             Kind::U128 => Entry{u128:EncodingByteOrder::read_u128(&op1)},
-            Kind::Data => Entry{data:op1},
+            Kind::Data | Kind::U256 => Entry{data:op1},
         }
     }
 
+    //applies a checked 256 bit operation to two 32 byte big endian values
+    // U256 has no native Entry field, it is kept as data in the EncodingByteOrder (big endian)
+    // so ToData & FromData produce the same byte order for it as for the smaller integers
+    fn u256_op(&mut self, op1:Entry<'transaction>, op2:Entry<'transaction>, op:fn(U256, U256) -> Option<U256>) -> Result<Option<Entry<'transaction>>> {
+        let num1 = U256::from_be_bytes(read_u256(unsafe {&op1.data}));
+        let num2 = U256::from_be_bytes(read_u256(unsafe {&op2.data}));
+        Ok(match op(num1, num2) {
+            None => None,
+            Some(r) => Some(Entry{data:self.alloc.copy_alloc_slice(&r.to_be_bytes())?}),
+        })
+    }
+
     //converts numeric input to data
     //uses byteorder crate for conversion where not trivial
-    // conversion is little endian
+    // conversion is in the EncodingByteOrder (big endian)
     fn convert_to_data(&mut self, kind:Kind, ValueRef(val):ValueRef, tail:bool) -> Result<Continuation<'code>> {
         let op1 = self.get(val as usize)?;
         let res = Entry{ data:self.entry_to_data(kind,op1)?};
@@ -1109,7 +1156,7 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
 
     //converts dat input to numerics
     //uses byteorder crate for conversion where not trivial
-    // conversion is little endian
+    // conversion is in the EncodingByteOrder (big endian)
     fn convert_from_data(&mut self, kind:Kind, ValueRef(val):ValueRef, tail:bool) -> Result<Continuation<'code>> {
         let op1 = self.get(val as usize)?;
         let res = self.data_to_entry(kind,unsafe{op1.data});
@@ -1117,3 +1164,100 @@ impl<'transaction,'code,'interpreter,'execution,'heap> ExecutionContext<'transac
         Ok(Continuation::Next)
     }
 }
+
+//copies the 32 bytes of a U256 value (size is a static guarantee)
+fn read_u256(data:&[u8]) -> [u8;32] {
+    let mut bytes = [0; 32];
+    bytes.copy_from_slice(data);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sanskrit_common::arena::Heap;
+    use crate::model::{Exp, Kind, LitDesc, OpCode};
+    #[cfg(feature = "dynamic_gas")]
+    use crate::model::TxTFunction;
+
+    struct NoExternals;
+    impl RuntimeExternals for NoExternals {
+        fn typed_system_call<'interpreter, 'transaction:'interpreter, 'heap:'transaction, I:ExecutionInterface<'interpreter, 'transaction, 'heap>>(_interface:&mut I, _id:u8, _kind:Kind, _values: &[ValueRef], _tail:bool) -> Result<()> {
+            error(||"No system calls")
+        }
+        fn system_call<'interpreter, 'transaction:'interpreter, 'heap:'transaction, I:ExecutionInterface<'interpreter, 'transaction, 'heap>>(_interface:&mut I, _id:u8, _values: &[ValueRef], _tail:bool) -> Result<()> {
+            error(||"No system calls")
+        }
+    }
+
+    fn u256(value:u8) -> [u8;32] {
+        let mut bytes = [0; 32];
+        bytes[31] = value;
+        bytes
+    }
+
+    //Runs the code and returns the value it pushed last (as data)
+    fn run(code:&[OpCode]) -> Result<alloc::vec::Vec<u8>> {
+        run_limited(code, u64::MAX)
+    }
+
+    #[cfg_attr(not(feature = "dynamic_gas"), allow(unused_variables))]
+    fn run_limited(code:&[OpCode], gas_limit:u64) -> Result<alloc::vec::Vec<u8>> {
+        let heap = Heap::new(64*1024, 1.0);
+        let structural = heap.new_arena(2*Heap::max_elems_space::<Entry>(64) + Heap::max_elems_space::<Frame>(8));
+        let alloc = heap.new_virtual_arena(16*1024);
+        let mut stack = structural.alloc_stack::<Entry>(64);
+        let mut frames = structural.alloc_stack::<Frame>(8);
+        let mut return_stack = structural.alloc_stack::<Entry>(64);
+        let code_arena = heap.new_arena(16*1024);
+        let mut ops = code.to_vec();
+        ops.push(OpCode::Return(code_arena.copy_alloc_slice(&[ValueRef(0)])?));
+        let body = code_arena.alloc(Exp(code_arena.copy_alloc_slice(&ops)?));
+        #[cfg(feature = "dynamic_gas")]
+        let functions = [TxTFunction{ gas: 0, body }];
+        #[cfg(not(feature = "dynamic_gas"))]
+        let functions = [body];
+        ExecutionContext::interpret::<NoExternals>(&functions, &mut stack, &mut frames, &mut return_stack, &alloc, None, #[cfg(feature = "dynamic_gas")] gas_limit)?;
+        match stack.as_slice() {
+            [value] => Ok(unsafe { value.data }.to_vec()),
+            _ => error(||"Code must return a single value")
+        }
+    }
+
+    #[test]
+    fn u256_to_data_uses_the_order_of_the_smaller_integers() {
+        let heap = Heap::new(1024, 1.0);
+        let code = heap.new_arena(1024);
+        let one_u128 = OpCode::SpecialLit(code.copy_alloc_slice(&1u128.to_be_bytes()).unwrap(), LitDesc::U128);
+        let one_u256 = OpCode::SpecialLit(code.copy_alloc_slice(&u256(1)).unwrap(), LitDesc::U256);
+        let u128_data = run(&[one_u128, OpCode::ToData(Kind::U128, ValueRef(0))]).unwrap();
+        let u256_data = run(&[one_u256, OpCode::ToData(Kind::U256, ValueRef(0))]).unwrap();
+        assert_eq!(u128_data[..], u256_data[16..]);
+        assert_eq!(u256_data, u256(1));
+    }
+
+    #[test]
+    fn u256_arithmetic_is_checked() {
+        let heap = Heap::new(1024, 1.0);
+        let code = heap.new_arena(1024);
+        let lit = |value:[u8;32]| OpCode::SpecialLit(code.copy_alloc_slice(&value).unwrap(), LitDesc::U256);
+        let sum = run(&[lit(u256(200)), lit(u256(100)), OpCode::Add(Kind::U256, ValueRef(1), ValueRef(0))]).unwrap();
+        let mut expected = u256(44);
+        expected[30] = 1;
+        assert_eq!(sum, expected);
+        let difference = run(&[lit(u256(200)), lit(u256(100)), OpCode::Sub(Kind::U256, ValueRef(1), ValueRef(0))]).unwrap();
+        assert_eq!(difference, u256(100));
+        assert!(run(&[lit(u256(100)), lit(u256(200)), OpCode::Sub(Kind::U256, ValueRef(1), ValueRef(0))]).is_err());
+        assert!(run(&[lit([0xFF; 32]), lit(u256(1)), OpCode::Add(Kind::U256, ValueRef(1), ValueRef(0))]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "dynamic_gas")]
+    fn consumed_gas_is_bounded_by_the_limit() {
+        let heap = Heap::new(1024, 1.0);
+        let code = heap.new_arena(1024);
+        let lit = OpCode::SpecialLit(code.copy_alloc_slice(&u256(1)).unwrap(), LitDesc::U256);
+        assert!(run_limited(&[OpCode::ConsumeGas(50), lit], 50).is_ok());
+        assert!(run_limited(&[OpCode::ConsumeGas(50), lit], 49).is_err());
+    }
+}
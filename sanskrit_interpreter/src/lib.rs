@@ -9,6 +9,7 @@ extern crate sanskrit_derive;
 extern crate alloc;
 extern crate ed25519_dalek;
 extern crate sha2;
+extern crate ethnum;
 
 pub mod interpreter;
 pub mod model;
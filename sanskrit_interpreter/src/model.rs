@@ -1,6 +1,8 @@
 use sanskrit_common::model::*;
 use sanskrit_common::encoding::*;
 use sanskrit_common::errors::*;
+use sanskrit_common::names::{self, Names, NoNames, Component};
+use core::fmt;
 
 // AllocParsable is a workaround to Parsable as AllocLifetime stopped working (it takes the first generic param as AllocLifetime)
 //A Block
@@ -23,6 +25,7 @@ pub enum LitDesc {
     U64,
     I128,
     U128,
+    U256,
 }
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug, Parsable, Serializable, VirtualSize)]
@@ -38,7 +41,9 @@ pub enum Kind {
     U64,
     I128,
     U128,
-    Data
+    Data,
+    //Added after Data to keep the encoding of Data stable (U256 values are represented as 32 byte Data)
+    U256
 }
 
 #[derive(Copy, Clone, Debug, AllocParsable, Serializable, VirtualSize)]
@@ -154,6 +159,41 @@ pub enum RuntimeType</*#[AllocLifetime]*/ 'a> {
     }
 }
 
+impl<'a> RuntimeType<'a> {
+    //Renders the type as `Module.Name[Applies]` with the names from a source (runtime types do not know their capabilities)
+    pub fn write_named<W:fmt::Write, N:Names>(&self, f:&mut W, names:&N) -> fmt::Result {
+        match *self {
+            RuntimeType::Custom { ref module, offset, applies } => {
+                names::write_component(f, names, module, Component::Data, offset)?;
+                if applies.is_empty() {
+                    return Ok(())
+                }
+                write!(f, "[")?;
+                for (i, typ) in applies.iter().enumerate() {
+                    if i != 0 { write!(f, ",")? }
+                    typ.write_named(f, names)?;
+                }
+                write!(f, "]")
+            },
+            RuntimeType::Projection { depth, typ } => {
+                for _ in 0..depth { write!(f, "^")? }
+                typ.write_named(f, names)
+            },
+            RuntimeType::Virtual { ref id } => {
+                write!(f, "virtual(")?;
+                names::write_short_hash(f, id)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Display for RuntimeType<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_named(f, &NoNames)
+    }
+}
+
 #[derive(Copy, Clone, VirtualSize)]
 pub union Entry<'a> {
     pub data: SlicePtr<'a, u8>,
@@ -176,3 +216,30 @@ pub struct Adt<'a>(pub u8, pub SlicePtr<'a, Entry<'a>>);
 
 #[derive(Copy, Clone, VirtualSize)]
 pub struct Func<'a>(pub u16, pub SlicePtr<'a, Entry<'a>>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::{String, ToString};
+    use sanskrit_common::arena::Heap;
+
+    struct TestNames;
+    impl Names for TestNames {
+        fn module_name(&self, module:&Hash) -> Option<String> {
+            if *module == [1;20] { Some("Token".to_string()) } else { None }
+        }
+    }
+
+    #[test]
+    fn runtime_types_are_shown_by_name() {
+        let heap = Heap::new(4096, 2.0);
+        let alloc = heap.new_arena(1024);
+        let virt = alloc.alloc(RuntimeType::Virtual { id: [0xab;20] });
+        let inner = alloc.alloc(RuntimeType::Projection { depth: 1, typ: virt });
+        let typ = RuntimeType::Custom { module: [1;20], offset: 2, applies: alloc.copy_alloc_slice(&[inner]).unwrap() };
+        let mut named = String::new();
+        typ.write_named(&mut named, &TestNames).unwrap();
+        assert_eq!(named, "Token.data2[^virtual(abababab)]");
+        assert_eq!(typ.to_string(), "01010101.data2[^virtual(abababab)]");
+    }
+}
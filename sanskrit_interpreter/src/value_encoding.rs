@@ -39,6 +39,14 @@ impl<'a> ValueSchema<'a> {
             ValueSchema::Unsigned(4) => unsafe {value.u32}.serialize(s),
             ValueSchema::Unsigned(8) => unsafe {value.u64}.serialize(s),
             ValueSchema::Unsigned(16) => unsafe {value.u128}.serialize(s),
+            ValueSchema::Unsigned(32) => {
+                let data = unsafe {value.data};
+                if data.len() != 32 {
+                    return error(||"U256 value must have 32 bytes")
+                }
+                s.produce_bytes(&data);
+                Ok(())
+            },
             ValueSchema::Signed(1) => unsafe {value.i8}.serialize(s),
             ValueSchema::Signed(2) => unsafe {value.i16}.serialize(s),
             ValueSchema::Signed(4) => unsafe {value.i32}.serialize(s),
@@ -90,6 +98,14 @@ impl<'a> ValueSchema<'a> {
             ValueSchema::Unsigned(4) => Entry{u32: u32::parse(p, alloc)?},
             ValueSchema::Unsigned(8) => Entry{u64: u64::parse(p, alloc)?},
             ValueSchema::Unsigned(16) => Entry{u128: u128::parse(p, alloc)?},
+            //U256 is stored as 32 byte data in the EncodingByteOrder (big endian) like the serialized smaller integers
+            ValueSchema::Unsigned(32) => {
+                let mut builder = alloc.poly_slice_builder(32)?;
+                for _ in 0..32 {
+                    builder.push(u8::parse(p, alloc)?);
+                }
+                Entry{ data:builder.finish()}
+            },
             ValueSchema::Signed(1) => Entry{i8: i8::parse(p, alloc)?},
             ValueSchema::Signed(2) => Entry{i16: i16::parse(p, alloc)?},
             ValueSchema::Signed(4) => Entry{i32: i32::parse(p, alloc)?},
@@ -121,6 +137,10 @@ impl<'a> ValueSchema<'a> {
             ValueSchema::Unsigned(4) => Ok(Entry{u32:unsafe {value.u32}}),
             ValueSchema::Unsigned(8) => Ok(Entry{u64:unsafe {value.u64}}),
             ValueSchema::Unsigned(16) => Ok(Entry{u128:unsafe {value.u128}}),
+            ValueSchema::Unsigned(32) => {
+                let data = alloc.copy_alloc_slice(&unsafe {value.data})?;
+                Ok(Entry{data})
+            },
             ValueSchema::Signed(1) => Ok(Entry{i8:unsafe {value.i8}}),
             ValueSchema::Signed(2) => Ok(Entry{i16:unsafe {value.i16}}),
             ValueSchema::Signed(4) => Ok(Entry{i32:unsafe {value.i32}}),
@@ -162,6 +182,8 @@ impl<'a> ValueSchema<'a> {
                 (Entry::SIZE+(field_content_sizes as usize),pos)
             },
             ValueSchema::Data(size) => (Entry::SIZE+(size as usize),pos+(size as usize)),
+            //U256 needs its data on the heap
+            ValueSchema::Unsigned(32) => (Entry::SIZE+32, pos+32),
             ValueSchema::Unsigned(size) | ValueSchema::Signed(size) => (Entry::SIZE, pos+(size as usize))
         };
         if res.0 > (u16::max_value() as usize) {
@@ -189,6 +211,8 @@ impl<'a> ValueSchema<'a> {
                 Entry::SIZE+max_field_content_sizes
             },
             ValueSchema::Data(size) => Entry::SIZE+(size as usize),
+            //U256 needs its data on the heap
+            ValueSchema::Unsigned(32) => Entry::SIZE+32,
             ValueSchema::Unsigned(_) | ValueSchema::Signed(_) => Entry::SIZE
         };
         if res > (u16::max_value() as usize) {
@@ -223,4 +247,380 @@ impl<'a> ValueSchema<'a> {
             ValueSchema::Unsigned(size) | ValueSchema::Signed(size) => size as u16,
         }
     }
-}
\ No newline at end of file
+}
+//Converts between json and serialized values
+// the names of constructors and fields are only known for adts whose schema carries their type (the ones with indexed fields)
+#[cfg(feature = "json")]
+pub mod json {
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use alloc::format;
+    use core::fmt::Write;
+    use ethnum::U256;
+    use serde_json::{Map, Value};
+    use sanskrit_common::encoding::{Serializer, Serializable, Parser, Parsable, NoCustomAlloc};
+    use sanskrit_common::errors::*;
+    use sanskrit_common::model::Hash;
+    use crate::model::ValueSchema;
+
+    //Provides the names of the constructors and fields of an adt identified by module and offset
+    pub trait ValueNames {
+        fn ctr_name(&self, _typ:&(Hash,u8), _tag:u8) -> Option<String> { None }
+        fn field_name(&self, _typ:&(Hash,u8), _tag:u8, _field:u8) -> Option<String> { None }
+    }
+
+    //Used when no names are available (constructors are identified by tag and fields by position)
+    pub struct NoValueNames;
+    impl ValueNames for NoValueNames {}
+
+    fn to_hex(data:&[u8]) -> String {
+        let mut res = String::with_capacity(data.len()*2);
+        for b in data {
+            //writing to a string can not fail
+            let _ = write!(res, "{:02x}", b);
+        }
+        res
+    }
+
+    fn from_hex(text:&str) -> Result<Vec<u8>> {
+        let text = text.strip_prefix("0x").unwrap_or(text);
+        if !text.len().is_multiple_of(2) { return error(||"Hex data must have an even number of digits") }
+        let mut res = Vec::with_capacity(text.len()/2);
+        for i in (0..text.len()).step_by(2) {
+            match text.get(i..i+2).and_then(|digits|u8::from_str_radix(digits, 16).ok()) {
+                Some(b) => res.push(b),
+                None => return error(||"Data must be hex encoded")
+            }
+        }
+        Ok(res)
+    }
+
+    //Integers up to 32 bits are written as json numbers, wider ones as decimal strings
+    // json numbers are doubles in most parsers, which can not hold every 64 bit integer
+    // Both forms are accepted when reading (strings can be hex encoded with a 0x prefix if they are unsigned)
+    fn unsigned_from_json(json:&Value, size:u8) -> Result<u128> {
+        let value = match *json {
+            Value::Number(ref num) => match num.as_u64() {
+                Some(val) => val as u128,
+                None => return error(||"Expected an unsigned integer")
+            },
+            Value::String(ref text) => match text.strip_prefix("0x") {
+                Some(hex) => u128::from_str_radix(hex, 16),
+                None => text.parse::<u128>(),
+            }.or_else(|_|error(||"Expected an unsigned integer"))?,
+            _ => return error(||"Expected an unsigned integer")
+        };
+        if size < 16 && value >> (size as u32 * 8) != 0 {
+            return owned_error(||format!("Integer {} does not fit into {} bytes", value, size))
+        }
+        Ok(value)
+    }
+
+    fn signed_from_json(json:&Value, size:u8) -> Result<i128> {
+        let value = match *json {
+            Value::Number(ref num) => match num.as_i64() {
+                Some(val) => val as i128,
+                None => return error(||"Expected a signed integer")
+            },
+            Value::String(ref text) => text.parse::<i128>().or_else(|_|error(||"Expected a signed integer"))?,
+            _ => return error(||"Expected a signed integer")
+        };
+        if size < 16 {
+            let bound = 1i128 << (size as u32 * 8 - 1);
+            if value < -bound || value >= bound {
+                return owned_error(||format!("Integer {} does not fit into {} bytes", value, size))
+            }
+        }
+        Ok(value)
+    }
+
+    impl<'a> ValueSchema<'a> {
+
+        //Reads a serialized value and produces its json representation
+        pub fn to_json<N:ValueNames>(&self, data:&[u8], max_depth:usize, names:&N) -> Result<Value> {
+            let mut parser = Parser::new(data, max_depth);
+            let res = self.read_json(&mut parser, names)?;
+            if parser.index != data.len() {
+                return error(||"Value has trailing bytes")
+            }
+            Ok(res)
+        }
+
+        //Produces the serialized value from its json representation
+        pub fn from_json<N:ValueNames>(&self, json:&Value, max_depth:usize, names:&N) -> Result<Vec<u8>> {
+            let mut serializer = Serializer::new(max_depth);
+            self.write_json(json, &mut serializer, names)?;
+            Ok(serializer.extract())
+        }
+
+        fn read_json<N:ValueNames>(&self, p:&mut Parser, names:&N) -> Result<Value> {
+            let alloc = &NoCustomAlloc();
+            Ok(match *self {
+                ValueSchema::Adt(typ, ctrs) => {
+                    //if their is only 1 tag it was omitted
+                    let tag = if ctrs.len() != 1 {
+                        u8::parse(p, alloc)?
+                    } else {
+                        0
+                    };
+                    if tag as usize >= ctrs.len() {
+                        return error(||"Tag of parsed value is invalid")
+                    }
+                    let typ = typ.map(|t|*t.0);
+                    let ctr = ctrs[tag as usize];
+                    let field_names:Option<Vec<String>> = typ.and_then(|typ|(0..ctr.len()).map(|i|names.field_name(&typ, tag, i as u8)).collect());
+                    p.increment_depth()?;
+                    let mut values = Vec::with_capacity(ctr.len());
+                    for (_,f_schema) in ctr.iter() {
+                        values.push(f_schema.read_json(p, names)?);
+                    }
+                    p.decrement_depth();
+                    let fields = match field_names {
+                        Some(field_names) if !values.is_empty() => Value::Object(field_names.into_iter().zip(values).collect()),
+                        _ => Value::Array(values)
+                    };
+                    let mut obj = Map::new();
+                    obj.insert("tag".to_string(), Value::from(tag));
+                    if let Some(name) = typ.and_then(|typ|names.ctr_name(&typ, tag)) {
+                        obj.insert("ctr".to_string(), Value::String(name));
+                    }
+                    obj.insert("fields".to_string(), fields);
+                    Value::Object(obj)
+                },
+                ValueSchema::Data(size) => Value::String(to_hex(p.consume_bytes(size as usize)?)),
+                ValueSchema::Unsigned(1) => Value::from(u8::parse(p, alloc)?),
+                ValueSchema::Unsigned(2) => Value::from(u16::parse(p, alloc)?),
+                ValueSchema::Unsigned(4) => Value::from(u32::parse(p, alloc)?),
+                ValueSchema::Unsigned(8) => Value::String(u64::parse(p, alloc)?.to_string()),
+                ValueSchema::Unsigned(16) => Value::String(u128::parse(p, alloc)?.to_string()),
+                //U256 is stored as 32 byte big endian data
+                ValueSchema::Unsigned(32) => {
+                    let mut bytes = [0u8;32];
+                    bytes.copy_from_slice(p.consume_bytes(32)?);
+                    Value::String(U256::from_be_bytes(bytes).to_string())
+                },
+                ValueSchema::Signed(1) => Value::from(i8::parse(p, alloc)?),
+                ValueSchema::Signed(2) => Value::from(i16::parse(p, alloc)?),
+                ValueSchema::Signed(4) => Value::from(i32::parse(p, alloc)?),
+                ValueSchema::Signed(8) => Value::String(i64::parse(p, alloc)?.to_string()),
+                ValueSchema::Signed(16) => Value::String(i128::parse(p, alloc)?.to_string()),
+                _ => return error(||"Unsupported value schema")
+            })
+        }
+
+        //Adts are objects with a tag or ctr name (can be omitted if there is only one constructor) and the fields as array or object (by name)
+        fn write_json<N:ValueNames>(&self, json:&Value, s:&mut Serializer, names:&N) -> Result<()> {
+            match *self {
+                ValueSchema::Adt(typ, ctrs) => {
+                    let obj = match json.as_object() {
+                        Some(obj) => obj,
+                        None => return error(||"Expected an object with tag and fields")
+                    };
+                    let typ = typ.map(|t|*t.0);
+                    let tag = match (obj.get("tag"), obj.get("ctr")) {
+                        (Some(tag), _) => match tag.as_u64() {
+                            Some(tag) if (tag as usize) < ctrs.len() => tag as u8,
+                            _ => return error(||"Tag of value is invalid")
+                        },
+                        (None, Some(Value::String(name))) => {
+                            let found = typ.and_then(|typ|(0..ctrs.len() as u8).find(|t|names.ctr_name(&typ, *t).as_ref() == Some(name)));
+                            match found {
+                                Some(tag) => tag,
+                                None => return owned_error(||format!("Unknown constructor {}", name))
+                            }
+                        },
+                        (None, _) if ctrs.len() == 1 => 0,
+                        (None, _) => return error(||"Value needs a tag or ctr")
+                    };
+                    //if their is only 1 tag we omit the tag
+                    if ctrs.len() != 1 {
+                        tag.serialize(s)?;
+                    }
+                    let ctr = ctrs[tag as usize];
+                    let empty = Value::Array(Vec::new());
+                    let fields = obj.get("fields").unwrap_or(&empty);
+                    s.increment_depth()?;
+                    match *fields {
+                        Value::Array(ref values) => {
+                            if values.len() != ctr.len() {
+                                return owned_error(||format!("Expected {} fields but got {}", ctr.len(), values.len()))
+                            }
+                            for (value, (_,f_schema)) in values.iter().zip(ctr.iter()) {
+                                f_schema.write_json(value, s, names)?;
+                            }
+                        },
+                        Value::Object(ref values) => {
+                            if values.len() != ctr.len() {
+                                return owned_error(||format!("Expected {} fields but got {}", ctr.len(), values.len()))
+                            }
+                            for (i, (_,f_schema)) in ctr.iter().enumerate() {
+                                let name = typ.and_then(|typ|names.field_name(&typ, tag, i as u8));
+                                match name.as_ref().and_then(|name|values.get(name)) {
+                                    Some(value) => f_schema.write_json(value, s, names)?,
+                                    None => return owned_error(||format!("Field {} is missing", name.unwrap_or_else(||i.to_string())))
+                                }
+                            }
+                        },
+                        _ => return error(||"Fields must be an array or an object")
+                    }
+                    s.decrement_depth();
+                    Ok(())
+                },
+                ValueSchema::Data(size) => {
+                    let data = match json.as_str() {
+                        Some(text) => from_hex(text)?,
+                        None => return error(||"Expected hex encoded data")
+                    };
+                    if data.len() != size as usize {
+                        return owned_error(||format!("Expected {} bytes of data but got {}", size, data.len()))
+                    }
+                    s.produce_bytes(&data);
+                    Ok(())
+                },
+                ValueSchema::Unsigned(32) => {
+                    let value = match json {
+                        Value::Number(num) => num.as_u64().map(U256::from),
+                        Value::String(text) => match text.strip_prefix("0x") {
+                            Some(hex) => U256::from_str_radix(hex, 16).ok(),
+                            None => U256::from_str_radix(text, 10).ok(),
+                        },
+                        _ => None
+                    };
+                    match value {
+                        Some(value) => {
+                            s.produce_bytes(&value.to_be_bytes());
+                            Ok(())
+                        },
+                        None => error(||"Expected an unsigned integer")
+                    }
+                },
+                ValueSchema::Unsigned(1) => (unsigned_from_json(json, 1)? as u8).serialize(s),
+                ValueSchema::Unsigned(2) => (unsigned_from_json(json, 2)? as u16).serialize(s),
+                ValueSchema::Unsigned(4) => (unsigned_from_json(json, 4)? as u32).serialize(s),
+                ValueSchema::Unsigned(8) => (unsigned_from_json(json, 8)? as u64).serialize(s),
+                ValueSchema::Unsigned(16) => unsigned_from_json(json, 16)?.serialize(s),
+                ValueSchema::Signed(1) => (signed_from_json(json, 1)? as i8).serialize(s),
+                ValueSchema::Signed(2) => (signed_from_json(json, 2)? as i16).serialize(s),
+                ValueSchema::Signed(4) => (signed_from_json(json, 4)? as i32).serialize(s),
+                ValueSchema::Signed(8) => (signed_from_json(json, 8)? as i64).serialize(s),
+                ValueSchema::Signed(16) => signed_from_json(json, 16)?.serialize(s),
+                _ => error(||"Unsupported value schema")
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sanskrit_common::arena::Heap;
+
+    #[test]
+    fn u256_round_trips_in_the_encoding_byte_order() {
+        let heap = Heap::new(1024, 1.0);
+        let alloc = heap.new_virtual_arena(1024);
+        let mut bytes = [0u8; 32];
+        bytes[31] = 7;
+        let value = Entry{ data: alloc.copy_alloc_slice(&bytes).unwrap() };
+        let mut s = Serializer::new(1);
+        ValueSchema::Unsigned(32).serialize_value(value, &mut s).unwrap();
+        let data = s.extract();
+        assert_eq!(data, bytes.to_vec());
+        //the same order as a serialized u128
+        let mut s = Serializer::new(1);
+        ValueSchema::Unsigned(16).serialize_value(Entry{ u128: 7 }, &mut s).unwrap();
+        assert_eq!(s.extract()[..], data[16..]);
+        let parsed = ValueSchema::Unsigned(32).parse_value(&mut Parser::new(&data, 1), &alloc).unwrap();
+        assert_eq!(&*unsafe { parsed.data }, &bytes[..]);
+    }
+
+    #[test]
+    fn u256_with_wrong_size_is_rejected() {
+        let heap = Heap::new(1024, 1.0);
+        let alloc = heap.new_virtual_arena(1024);
+        let value = Entry{ data: alloc.copy_alloc_slice(&[1u8; 31]).unwrap() };
+        assert!(ValueSchema::Unsigned(32).serialize_value(value, &mut Serializer::new(1)).is_err());
+    }
+
+    #[cfg(feature = "json")]
+    mod json {
+        use crate::value_encoding::json::{ValueNames, NoValueNames};
+        use crate::model::ValueSchema;
+        use alloc::string::{String, ToString};
+        use sanskrit_common::arena::{Heap, HeapArena};
+        use sanskrit_common::model::Hash;
+        use serde_json::{json, Value};
+
+        const TYP:(Hash,u8) = ([1;20], 0);
+
+        struct TestNames;
+        impl ValueNames for TestNames {
+            fn ctr_name(&self, typ:&(Hash,u8), tag:u8) -> Option<String> {
+                if *typ == TYP && tag == 1 { Some("Some".to_string()) } else { None }
+            }
+        }
+
+        //None() | Some(u64, i64, u128, i128, u256, data2, u8, i32)
+        fn schema<'a>(alloc:&'a HeapArena) -> ValueSchema<'a> {
+            let none = alloc.copy_alloc_slice(&[]).unwrap();
+            let fields = [
+                ValueSchema::Unsigned(8), ValueSchema::Signed(8), ValueSchema::Unsigned(16), ValueSchema::Signed(16),
+                ValueSchema::Unsigned(32), ValueSchema::Data(2), ValueSchema::Unsigned(1), ValueSchema::Signed(4)
+            ].map(|f|(alloc.copy_alloc_slice(&[]).unwrap(), alloc.alloc(f)));
+            let some = alloc.copy_alloc_slice(&fields).unwrap();
+            ValueSchema::Adt(Some(alloc.alloc(TYP)), alloc.copy_alloc_slice(&[none, some]).unwrap())
+        }
+
+        fn fields(json:&Value) -> &[Value] {
+            json["fields"].as_array().unwrap()
+        }
+
+        #[test]
+        fn values_round_trip_with_wide_integers_as_strings() {
+            let heap = Heap::new(4096, 1.0);
+            let alloc = heap.new_arena(4096);
+            let schema = schema(&alloc);
+            let input = json!({"tag": 1, "fields": [
+                u64::MAX.to_string(), i64::MIN.to_string(), u128::MAX.to_string(), i128::MIN.to_string(),
+                "0x100", "0xbeef", 255, -5
+            ]});
+            let data = schema.from_json(&input, 10, &TestNames).unwrap();
+            let output = schema.to_json(&data, 10, &TestNames).unwrap();
+            assert_eq!(output["ctr"], json!("Some"));
+            assert_eq!(fields(&output), &[
+                json!(u64::MAX.to_string()), json!(i64::MIN.to_string()), json!(u128::MAX.to_string()), json!(i128::MIN.to_string()),
+                json!("256"), json!("beef"), json!(255), json!(-5)
+            ][..]);
+            assert_eq!(schema.from_json(&output, 10, &TestNames).unwrap(), data);
+        }
+
+        #[test]
+        fn numbers_and_strings_are_both_accepted() {
+            let heap = Heap::new(4096, 1.0);
+            let alloc = heap.new_arena(4096);
+            let schema = schema(&alloc);
+            let as_numbers = json!({"ctr": "Some", "fields": [5, -5, 5, -5, 5, "0001", 1, 1]});
+            let as_strings = json!({"tag": 1, "fields": ["5", "-5", "5", "-5", "5", "0001", "1", "1"]});
+            assert_eq!(schema.from_json(&as_numbers, 10, &TestNames).unwrap(), schema.from_json(&as_strings, 10, &TestNames).unwrap());
+            //without names the constructor can only be given by tag
+            assert!(schema.from_json(&as_numbers, 10, &NoValueNames).is_err());
+            let none = schema.from_json(&json!({"tag": 0}), 10, &NoValueNames).unwrap();
+            assert_eq!(schema.to_json(&none, 10, &NoValueNames).unwrap(), json!({"tag": 0, "fields": []}));
+        }
+
+        #[test]
+        fn invalid_values_are_rejected() {
+            let heap = Heap::new(4096, 1.0);
+            let alloc = heap.new_arena(4096);
+            assert!(ValueSchema::Unsigned(1).from_json(&json!(256), 1, &NoValueNames).is_err());
+            assert!(ValueSchema::Signed(1).from_json(&json!("128"), 1, &NoValueNames).is_err());
+            assert!(ValueSchema::Unsigned(8).from_json(&json!(-1), 1, &NoValueNames).is_err());
+            assert!(ValueSchema::Data(2).from_json(&json!("abc"), 1, &NoValueNames).is_err());
+            assert!(ValueSchema::Unsigned(1).to_json(&[1, 2], 1, &NoValueNames).is_err());
+            let schema = schema(&alloc);
+            assert!(schema.from_json(&json!({"tag": 2}), 10, &NoValueNames).is_err());
+            assert!(schema.from_json(&json!({"tag": 1, "fields": [1]}), 10, &NoValueNames).is_err());
+        }
+    }
+}
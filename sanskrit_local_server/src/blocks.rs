@@ -0,0 +1,300 @@
+//! Block production of the local server
+//!
+//! Bundles sent to the local server wait in the mempool until a block is produced (on demand or every block time).
+//! A block includes the pending bundles whose inclusion window contains its number, ordered by their earliest block and then by arrival.
+//! Bundles whose window has passed are dropped, bundles whose window has not started yet stay in the mempool.
+//! Every produced block (including the ones of directly executed bundles) is appended to the block log.
+//! The log also contains the deploys that happen outside of blocks and the state root after each entry, so it can be replayed (see replay).
+//! It starts with a version, logs of a different version are neither extended nor read, as their roots can not be compared.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
+use sanskrit_common::errors::*;
+use sanskrit_common::encoding::*;
+use sanskrit_common::model::{Hash, LargeVec};
+use sanskrit_common::arena::Heap;
+use sanskrit_runtime::CONFIG;
+use sanskrit_runtime::system::SystemContext;
+use sanskrit_client::partial::BundleData;
+use crate::externals::ServerSystem;
+use crate::manager::Receipt;
+use crate::convert_error;
+
+//A log entry only contains bundles or deploys (and their metadata), so it does not nest deeply
+const MAX_LOG_DEPTH:usize = 5;
+//Logs written before the version was introduced have no header (and roots that were hashed over all entries)
+// version 1 has the roots computed from the class digests
+// version 2 records the gas price of each block
+// version 3 charges deploys for their dependencies and imports (older deploys may exceed their gas_limit now)
+const LOG_MAGIC:&[u8] = b"SKBL";
+pub const LOG_VERSION:u8 = 3;
+//Keeps the time the state is locked by producing a block short, the remaining bundles go into the next block
+pub const MAX_BUNDLES_PER_BLOCK:usize = 255;
+
+pub struct PendingBundle {
+    pub hash:Hash,
+    pub earliest_block:u64,
+    pub data:Vec<u8>,
+}
+
+impl PendingBundle {
+    //Parses the bundle to learn its hash and inclusion window (it is verified when a block includes it)
+    pub fn parse(data:Vec<u8>) -> Result<Self> {
+        let heap = Heap::new(CONFIG.max_bundle_size*2,2.0);
+        let alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
+        let bundle = <ServerSystem>::parse_bundle(&data, &alloc)?;
+        Ok(PendingBundle { hash: bundle.bundle_hash, earliest_block: bundle.txt_bundle.core.earliest_block, data })
+    }
+}
+
+pub struct Mempool {
+    pending:Vec<PendingBundle>,
+}
+
+//What happened to the bundles when a block was produced
+pub struct BlockReport {
+    pub block_no:u64,
+    pub timestamp:u64,
+    pub included:Vec<(Hash, Receipt)>,
+    //bundles that failed verification (they are not part of the block)
+    pub rejected:Vec<(Hash, String)>,
+    //bundles whose inclusion window passed before they were included
+    pub dropped:Vec<Hash>,
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Mempool { pending: Vec::new() }
+    }
+
+    pub fn pending(&self) -> &[PendingBundle] {
+        &self.pending
+    }
+
+    pub fn contains(&self, hash:&Hash) -> bool {
+        self.pending.iter().any(|p|p.hash == *hash)
+    }
+
+    pub fn add(&mut self, bundle:PendingBundle) -> Result<Hash> {
+        let hash = bundle.hash;
+        if self.contains(&hash) {
+            return error(||"Bundle is already pending")
+        }
+        self.pending.push(bundle);
+        //stable, so bundles with the same earliest block stay in arrival order
+        self.pending.sort_by_key(|p|p.earliest_block);
+        Ok(hash)
+    }
+
+    //Removes the bundles for the block, returns them together with the hashes of the dropped bundles
+    pub fn take(&mut self, block_no:u64) -> (Vec<PendingBundle>, Vec<Hash>) {
+        let mut ready = Vec::new();
+        let mut dropped = Vec::new();
+        let mut waiting = Vec::new();
+        for bundle in self.pending.drain(..) {
+            if block_no >= bundle.earliest_block.saturating_add(CONFIG.block_inclusion_window) {
+                dropped.push(bundle.hash)
+            } else if block_no < bundle.earliest_block || ready.len() >= MAX_BUNDLES_PER_BLOCK {
+                waiting.push(bundle)
+            } else {
+                ready.push(bundle)
+            }
+        }
+        self.pending = waiting;
+        (ready, dropped)
+    }
+}
+
+//Everything that changed the state, in the order it happened
+#[derive(Debug, Parsable, Serializable)]
+pub enum LogEntry {
+    Block(LoggedBlock),
+    Deploy(LoggedDeploy),
+}
+
+//A produced block with the bundles it contains
+#[derive(Debug, Parsable, Serializable)]
+pub struct LoggedBlock {
+    pub block_no:u64,
+    pub timestamp:u64,
+    //the fees were charged with this price
+    pub gas_price:u64,
+    pub bundles:LargeVec<BundleData>,
+    //the root of the state after the block
+    pub state_root:Hash,
+}
+
+//A deploy that was executed directly (over the repl, the compiler port or the rpc interface) and not in a bundle
+#[derive(Debug, Parsable, Serializable)]
+pub struct LoggedDeploy {
+    //the serialized deploy transaction
+    pub data:BundleData,
+    pub system_mode:bool,
+    //the system module the deployed module was registered as
+    pub system_id:Option<u8>,
+    //the root of the state after the deploy
+    pub state_root:Hash,
+}
+
+//Append only file of the log entries after the version header, each entry is prefixed with its length
+pub struct BlockLog {
+    file:File,
+    path:PathBuf,
+}
+
+impl BlockLog {
+    //Opens the log to append to it, a new log gets the header of the current version
+    pub fn open(path:&Path) -> std::io::Result<Self> {
+        let mut file = OpenOptions::new().create(true).read(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            file.write_all(LOG_MAGIC)?;
+            file.write_all(&[LOG_VERSION])?;
+            file.sync_data()?;
+        } else if let Err(err) = Self::read_header(&mut file) {
+            let msg = format!("{} {}, move it away to start a new one", path.display(), error_to_string(&err));
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, msg))
+        }
+        Ok(BlockLog { file, path: path.to_owned() })
+    }
+
+    //Reads the entries appended so far
+    pub fn entries(&self) -> Result<Vec<LogEntry>> {
+        Self::read(&self.path)
+    }
+
+    fn read_header(file:&mut File) -> Result<()> {
+        let mut header = [0; 5];
+        if file.read_exact(&mut header).is_err() || &header[..4] != LOG_MAGIC {
+            return error(||"Block log has no version, it was written by an older server")
+        }
+        if header[4] != LOG_VERSION {
+            return owned_error(||format!("Block log version {} is not supported", header[4]))
+        }
+        Ok(())
+    }
+
+    pub fn append(&mut self, entry:&LogEntry) -> Result<()> {
+        let data = Serializer::serialize_fully(entry, MAX_LOG_DEPTH)?;
+        convert_error(self.file.write_u32::<NetworkEndian>(data.len() as u32))?;
+        convert_error(self.file.write_all(&data))?;
+        convert_error(self.file.sync_data())
+    }
+
+    //Reads all the entries in the log (in the order they were appended)
+    pub fn read(path:&Path) -> Result<Vec<LogEntry>> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) => return owned_error(||format!("Can not open block log {}: {}", path.display(), err))
+        };
+        Self::read_header(&mut file)?;
+        let mut entries = Vec::new();
+        loop {
+            let len = match file.read_u32::<NetworkEndian>() {
+                Ok(len) => len,
+                Err(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(entries),
+                Err(_) => return error(||"Reading the block log failed")
+            };
+            let mut data = vec![0; len as usize];
+            if file.read_exact(&mut data).is_err() {
+                return error(||"Block log ends in the middle of an entry")
+            }
+            entries.push(Parser::parse_fully(&data, MAX_LOG_DEPTH, &NoCustomAlloc())?);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(id:u8, earliest_block:u64) -> PendingBundle {
+        PendingBundle { hash: [id; 20], earliest_block, data: vec![id] }
+    }
+
+    fn hashes(bundles:&[PendingBundle]) -> Vec<Hash> {
+        bundles.iter().map(|p|p.hash).collect()
+    }
+
+    #[test]
+    fn bundles_are_taken_in_their_inclusion_window() {
+        let window = CONFIG.block_inclusion_window;
+        let mut pool = Mempool::new();
+        pool.add(pending(1, 10)).unwrap();
+        pool.add(pending(2, 5)).unwrap();
+        pool.add(pending(3, 10)).unwrap();
+        pool.add(pending(4, 0)).unwrap();
+        assert!(pool.add(pending(1, 10)).is_err());
+        //ordered by earliest block and then by arrival
+        assert_eq!(hashes(pool.pending()), vec![[4; 20], [2; 20], [1; 20], [3; 20]]);
+
+        let (ready, dropped) = pool.take(window);
+        assert_eq!(hashes(&ready), vec![[2; 20], [1; 20], [3; 20]]);
+        assert_eq!(dropped, vec![[4; 20]]);
+        assert!(pool.pending().is_empty());
+
+        pool.add(pending(5, window + 1)).unwrap();
+        let (ready, dropped) = pool.take(window);
+        assert!(ready.is_empty() && dropped.is_empty());
+        assert!(pool.contains(&[5; 20]));
+    }
+
+    #[test]
+    fn blocks_take_a_limited_number_of_bundles() {
+        let mut pool = Mempool::new();
+        for id in 0..=MAX_BUNDLES_PER_BLOCK {
+            let mut bundle = pending(0, 0);
+            bundle.hash[..8].copy_from_slice(&(id as u64).to_be_bytes());
+            pool.add(bundle).unwrap();
+        }
+        let (ready, dropped) = pool.take(0);
+        assert_eq!(ready.len(), MAX_BUNDLES_PER_BLOCK);
+        assert!(dropped.is_empty());
+        assert_eq!(pool.pending().len(), 1);
+    }
+
+    #[test]
+    fn late_windows_do_not_overflow() {
+        let mut pool = Mempool::new();
+        pool.add(pending(1, u64::MAX)).unwrap();
+        pool.add(pending(2, u64::MAX - 1)).unwrap();
+        let (ready, dropped) = pool.take(0);
+        assert!(ready.is_empty() && dropped.is_empty());
+        let (ready, dropped) = pool.take(u64::MAX - 1);
+        assert_eq!(hashes(&ready), vec![[2; 20]]);
+        assert!(dropped.is_empty());
+        assert!(pool.contains(&[1; 20]));
+    }
+
+    #[test]
+    fn logs_are_versioned() {
+        let path = std::env::temp_dir().join(format!("sanskrit_block_log_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let block = LogEntry::Block(LoggedBlock{ block_no: 1, timestamp: 2, gas_price: 4, bundles: LargeVec(vec![BundleData(vec![1, 2])]), state_root: [3; 20] });
+        BlockLog::open(&path).unwrap().append(&block).unwrap();
+        //a reopened log is extended
+        let deploy = LogEntry::Deploy(LoggedDeploy{ data: BundleData(vec![4]), system_mode: true, system_id: Some(0), state_root: [5; 20] });
+        BlockLog::open(&path).unwrap().append(&deploy).unwrap();
+        match &BlockLog::read(&path).unwrap()[..] {
+            [LogEntry::Block(block), LogEntry::Deploy(deploy)] => {
+                assert_eq!((block.block_no, block.timestamp, block.gas_price, block.state_root), (1, 2, 4, [3; 20]));
+                assert_eq!(block.bundles.0[0].0, vec![1, 2]);
+                assert_eq!((deploy.system_id, deploy.state_root), (Some(0), [5; 20]));
+            },
+            entries => panic!("unexpected entries {:?}", entries)
+        }
+
+        //logs from before the version and of other versions are not touched
+        let entry = Serializer::serialize_fully(&block, MAX_LOG_DEPTH).unwrap();
+        let mut unversioned = (entry.len() as u32).to_be_bytes().to_vec();
+        unversioned.extend_from_slice(&entry);
+        for data in [unversioned, b"SKBL\x02".to_vec(), b"SKBL\x04".to_vec()] {
+            std::fs::write(&path, &data).unwrap();
+            assert!(BlockLog::read(&path).is_err());
+            assert!(BlockLog::open(&path).is_err());
+            assert_eq!(std::fs::read(&path).unwrap(), data);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
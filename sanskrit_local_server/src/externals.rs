@@ -1,5 +1,7 @@
+use core::marker::PhantomData;
 use sanskrit_common::errors::*;
-use sanskrit_common::model::{Hash, Ptr};
+use sanskrit_common::store::Store;
+use sanskrit_common::model::{Hash, Ptr, hash_from_slice, HASH_SIZE};
 
 use sanskrit_common::arena::VirtualHeapArena;
 use sanskrit_interpreter::model::{Entry, Adt, RuntimeType};
@@ -10,7 +12,7 @@ use sanskrit_sled_store::SledStore;
 use sanskrit_runtime::direct_stored::{StatefulEntryStoreVerifier, SystemDataManager, StatefulEntryStoreExecutor};
 use sanskrit_common::encoding::{VirtualSize, ParserAllocator, Parser};
 use sanskrit_runtime::model::{BundleWithHash, BaseTransactionBundle};
-use sanskrit_runtime::CONFIG;
+use sanskrit_runtime::{CONFIG, BlockInfo};
 use sanskrit_interpreter::externals::{ExecutionInterface};
 use sanskrit_default_externals::{SYS_HASH, EDDSA_HASH, ServerExternals};
 
@@ -27,8 +29,9 @@ impl<'c> SystemDataManager<BundleWithHash<'c>> for ServerSystemDataManager {
             RuntimeType::Custom { module, offset, .. } if module == SYS_HASH.lock().get() && offset == 2 => {
                 Ok((Hash::SIZE + 2*Entry::SIZE) as u32)
             }
+            //BlockInfo(blockNo:U64, timestamp:U64)
             RuntimeType::Custom { module, offset, .. } if module == SYS_HASH.lock().get() && offset == 3 => {
-                error(||"Not supported by this runtime yet")
+                Ok((2*Entry::SIZE) as u32)
             }
 
             _ => return error(||"Provided value parameter must be of a supported type")
@@ -49,7 +52,8 @@ impl<'c> SystemDataManager<BundleWithHash<'c>> for ServerSystemDataManager {
                 Ok(hash_alloc + pack + hash_cost)
             }
             RuntimeType::Custom { module, offset, .. } if module == SYS_HASH.lock().get() && offset == 3 => {
-                error(||"Not supported by this runtime yet")
+                let pack:u64 = 13 + 4;
+                Ok(pack)
             }
             _ => return error(||"Provided value parameter must be of a supported type")
         }
@@ -72,12 +76,12 @@ impl<'c> SystemDataManager<BundleWithHash<'c>> for ServerSystemDataManager {
         }
     }
 
-    fn create_provided_value<'a, 'h>(bundle: &BundleWithHash, typ: Ptr<RuntimeType>, alloc: &'a VirtualHeapArena<'h>, block_no: u64, section_no:u8,  txt_no:u8, p_num:u8) -> Result<Entry<'a>> {
+    fn create_provided_value<'a, 'h>(bundle: &BundleWithHash, typ: Ptr<RuntimeType>, alloc: &'a VirtualHeapArena<'h>, block: BlockInfo, section_no:u8,  txt_no:u8, p_num:u8) -> Result<Entry<'a>> {
         match *typ {
             RuntimeType::Custom { module, offset, .. } if module == SYS_HASH.lock().get() && offset == 1 => {
                 Ok(Entry{adt: Adt(0,alloc.copy_alloc_slice(&[
                     Entry {data: alloc.copy_alloc_slice(&bundle.bundle_hash)?},
-                    Entry {u64: block_no},
+                    Entry {u64: block.block_no},
                     Entry {u8: section_no},
                     Entry {u8: txt_no},
                 ])?)})
@@ -95,22 +99,48 @@ impl<'c> SystemDataManager<BundleWithHash<'c>> for ServerSystemDataManager {
                 ])?)})
             },
             RuntimeType::Custom { module, offset, .. } if module == SYS_HASH.lock().get() && offset == 3 => {
-                error(||"Not supported by this runtime yet")
+                Ok(Entry{adt: Adt(0,alloc.copy_alloc_slice(&[
+                    Entry {u64: block.block_no},
+                    Entry {u64: block.timestamp},
+                ])?)})
             },
-            _ => error(||"Requested value is not providable")
+            _ => owned_error(||format!("Requested value of type {} is not providable", *typ))
+        }
+    }
+
+    //Fee(amount:U64, payer:Id)
+    fn is_fee(typ: Ptr<RuntimeType>) -> bool {
+        matches!(*typ, RuntimeType::Custom { module, offset, .. } if module == SYS_HASH.lock().get() && offset == 4)
+    }
+
+    fn fee_value(value: &Entry) -> Result<u64> {
+        match unsafe {value.adt.1.first()} {
+            Some(amount) => Ok(unsafe {amount.u64}),
+            None => error(||"Fee has to few fields")
+        }
+    }
+
+    //The payer only receives the refunds, it is not authenticated
+    fn fee_payer(value: &Entry) -> Result<Hash> {
+        match unsafe {value.adt.1.get(1)} {
+            Some(payer) if unsafe {payer.data.len()} == HASH_SIZE => Ok(hash_from_slice(unsafe {&payer.data})),
+            Some(_) => error(||"Fee payer is not an id"),
+            None => error(||"Fee has no payer")
         }
     }
 }
 
-pub struct ServerSystem;
-impl<'c> SystemContext<'c> for ServerSystem {
+//The store is a parameter so that blocks can be replayed into other stores than the one of the server
+pub struct ServerSystem<S = SledStore>(PhantomData<S>);
+impl<'c, S:Store> SystemContext<'c> for ServerSystem<S> {
     type RE = ServerExternals;
 
-    type S = SledStore;
+    type S = S;
 
     type B = BundleWithHash<'c>;
     type VC = StatefulEntryStoreVerifier<Self::B,ServerSystemDataManager>;
     type EC = StatefulEntryStoreExecutor<Self::B,ServerSystemDataManager>;
+    type CE = ServerExternals;
 
     fn parse_bundle<A: ParserAllocator>(data: &[u8], alloc: &'c A) -> Result<Self::B> {
         let txt_bundle:BaseTransactionBundle = Parser::parse_fully(data, CONFIG.max_structural_dept, alloc)?;
@@ -121,3 +151,59 @@ impl<'c> SystemContext<'c> for ServerSystem {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sanskrit_common::arena::Heap;
+    use sanskrit_common::model::SlicePtr;
+    use sanskrit_client::BundleBuilder;
+    use crate::fixtures::{bind_system_modules, BLOCK_INFO};
+    use crate::manager::{State, LOCAL_PRODUCER};
+
+    type Sdm = ServerSystemDataManager;
+
+    #[test]
+    fn block_info_is_provided() {
+        let sys = bind_system_modules();
+        let heap = Heap::new(CONFIG.calc_heap_size(2), 2.0);
+        let alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
+        let typ = alloc.alloc(RuntimeType::Custom{ module: sys, offset: BLOCK_INFO, applies: SlicePtr::empty() }).unwrap();
+        assert_eq!(<Sdm as SystemDataManager<BundleWithHash>>::providable_size(typ).unwrap(), 2*Entry::SIZE as u32);
+        assert_eq!(<Sdm as SystemDataManager<BundleWithHash>>::providable_gas(typ).unwrap(), 17);
+        assert!(<Sdm as SystemDataManager<BundleWithHash>>::provided_value_key(typ, 0, 0, 3).is_none());
+
+        let state = State::temporary("provided_block_info");
+        let bundle = BundleBuilder::new(0).build::<_, Sdm>(&state.store, &alloc).unwrap();
+        let block = BlockInfo{ block_no: 7, timestamp: 1_600_000_000, producer: LOCAL_PRODUCER, gas_price: CONFIG.gas_price };
+        let value = Sdm::create_provided_value(&bundle, typ, &alloc, block, 0, 0, 3).unwrap();
+        let Adt(tag, fields) = unsafe {value.adt};
+        assert_eq!(tag, 0);
+        assert_eq!(fields.iter().map(|field|unsafe {field.u64}).collect::<Vec<_>>(), vec![7, 1_600_000_000]);
+
+        //the offsets the node does not provide are rejected
+        let unknown = alloc.alloc(RuntimeType::Custom{ module: sys, offset: 4, applies: SlicePtr::empty() }).unwrap();
+        assert!(<Sdm as SystemDataManager<BundleWithHash>>::providable_size(unknown).is_err());
+        assert!(<Sdm as SystemDataManager<BundleWithHash>>::providable_gas(unknown).is_err());
+        assert!(Sdm::create_provided_value(&bundle, unknown, &alloc, block, 0, 0, 3).is_err());
+    }
+
+    #[test]
+    fn malformed_fees_are_errors() {
+        let heap = Heap::new(4096, 2.0);
+        let alloc = heap.new_virtual_arena(1024);
+        let payer = Entry{data: alloc.copy_alloc_slice(&[7u8; 20]).unwrap()};
+        let fee = Entry{adt: Adt(0, alloc.copy_alloc_slice(&[Entry{u64: 5}, payer]).unwrap())};
+        assert_eq!(<Sdm as SystemDataManager<BundleWithHash>>::fee_value(&fee).unwrap(), 5);
+        assert_eq!(<Sdm as SystemDataManager<BundleWithHash>>::fee_payer(&fee).unwrap(), [7; 20]);
+
+        //the layout without payer is not accepted anymore
+        let no_payer = Entry{adt: Adt(0, alloc.copy_alloc_slice(&[Entry{u64: 5}]).unwrap())};
+        assert!(<Sdm as SystemDataManager<BundleWithHash>>::fee_payer(&no_payer).is_err());
+        let short_payer = Entry{data: alloc.copy_alloc_slice(&[7u8; 3]).unwrap()};
+        let bad_payer = Entry{adt: Adt(0, alloc.copy_alloc_slice(&[Entry{u64: 5}, short_payer]).unwrap())};
+        assert!(<Sdm as SystemDataManager<BundleWithHash>>::fee_payer(&bad_payer).is_err());
+        let empty = Entry{adt: Adt(0, SlicePtr::empty())};
+        assert!(<Sdm as SystemDataManager<BundleWithHash>>::fee_value(&empty).is_err());
+    }
+}
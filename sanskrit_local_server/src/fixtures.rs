@@ -0,0 +1,297 @@
+//! Modules and bundles shared by the tests of the node
+//!
+//! The modules are built from the model and mirror the parts of the system modules the node depends on:
+//!   U64 (system module 8):   global external(8) primitive data U64
+//!   Data (system module 10): global external(20) primitive data Data20
+//!   System (system module 12, the node finds its types by offset):
+//!     0: Token(id:Data20, amount:U64)
+//!     1: TxData() & 2: UniqueId()   placeholders
+//!     3: BlockInfo(blockNo:U64, timestamp:U64)
+//!     4: Fee(amount:U64, payer:Data20)
+//!   Choice (not a system module): global primitive data Choice = Cheap | Expensive
+//! And the transactions:
+//!   pay(amount:U64, payer:Data20):(Fee)
+//!   mint(id:Data20, amount:U64):(Token)
+//!   burn(token:Token):()
+//!   fail():() which always rolls back
+//!   choose(choice:Choice, num:U64):() whose expensive branch costs more gas than the cheap one
+
+use sanskrit_common::encoding::Serializer;
+use sanskrit_common::model::{Hash, LargeVec, ModuleLink, Tag, ValueRef};
+use sanskrit_common::store::store_hash;
+use sanskrit_core::model::*;
+use sanskrit_core::model::bitsets::{CapSet, PermSet, BitSet};
+use sanskrit_common::arena::Heap;
+use sanskrit_default_externals::SYS_MODS;
+use sanskrit_client::{BundleBuilder, Input};
+use sanskrit_runtime::CONFIG;
+use crate::compiler::CompilerInstance;
+use crate::externals::ServerSystemDataManager;
+use crate::manager::{State, MAX_PARSE_DEPTH};
+
+//The offsets of the types in the system module
+pub const TOKEN:u8 = 0;
+pub const BLOCK_INFO:u8 = 3;
+pub const FEE:u8 = 4;
+
+fn module(system_module:bool, data:Vec<DataComponent>, functions:Vec<FunctionComponent>) -> Vec<u8> {
+    let module = Module {
+        byte_size: None,
+        system_module,
+        meta: LargeVec(vec![]),
+        data_sig_order: BitSerializedVec(vec![true; data.len()]),
+        data,
+        sigs: vec![],
+        fun_impl_order: BitSerializedVec(vec![true; functions.len()]),
+        functions,
+        implements: vec![],
+    };
+    Serializer::serialize_fully(&module, MAX_PARSE_DEPTH).unwrap()
+}
+
+fn data(provided_caps:CapSet, import:PublicImport, body:DataImpl) -> DataComponent {
+    DataComponent {
+        byte_size: None,
+        create_scope: Accessibility::Global,
+        consume_scope: Accessibility::Global,
+        inspect_scope: Accessibility::Global,
+        provided_caps,
+        generics: vec![],
+        import,
+        body,
+    }
+}
+
+fn no_imports() -> PublicImport {
+    PublicImport{ modules: vec![], types: vec![] }
+}
+
+fn caps(caps:&[Capability]) -> CapSet {
+    CapSet::empty().with_elems(caps.iter().cloned())
+}
+
+//The U64 (TypeRef 0) and Data20 (TypeRef 1) types imported as the modules 1 & 2 (0 is the importing module itself)
+fn value_imports() -> PublicImport {
+    PublicImport {
+        modules: vec![ModuleLink::Remote(store_hash(&[&u64_module()])), ModuleLink::Remote(store_hash(&[&data_module()]))],
+        types: vec![
+            TypeImport::Data{ link: DataLink{ module: ModRef(1), offset: 0 }, applies: vec![] },
+            TypeImport::Data{ link: DataLink{ module: ModRef(2), offset: 0 }, applies: vec![] },
+        ]
+    }
+}
+
+fn record(provided_caps:CapSet, fields:&[TypeRef]) -> DataComponent {
+    let fields = fields.iter().map(|typ|Field{ indexed: vec![], typ: *typ }).collect();
+    data(provided_caps, value_imports(), DataImpl::Internal{ constructors: vec![Case{ fields }] })
+}
+
+pub fn u64_module() -> Vec<u8> {
+    module(true, vec![data(CapSet::primitive(), no_imports(), DataImpl::External(8))], vec![])
+}
+
+pub fn data_module() -> Vec<u8> {
+    module(true, vec![data(CapSet::primitive(), no_imports(), DataImpl::External(20))], vec![])
+}
+
+pub fn system_module() -> Vec<u8> {
+    let (num, id) = (TypeRef(0), TypeRef(1));
+    let value = caps(&[Capability::Drop, Capability::Value, Capability::Unbound]);
+    module(true, vec![
+        record(caps(&[Capability::Drop, Capability::Persist, Capability::Value, Capability::Unbound]), &[id, num]),
+        record(value, &[]),
+        record(value, &[]),
+        record(value.union(caps(&[Capability::Copy])), &[num, num]),
+        record(value, &[num, id]),
+    ], vec![])
+}
+
+//Binds the system modules without deploying them (all tests bind the same hashes, so they can run in parallel)
+pub fn bind_system_modules() -> Hash {
+    SYS_MODS[8](store_hash(&[&u64_module()]));
+    SYS_MODS[10](store_hash(&[&data_module()]));
+    let sys = store_hash(&[&system_module()]);
+    SYS_MODS[12](sys);
+    sys
+}
+
+//Choice (TypeRef 0 of its module) has the function keep(num:U64):(U64) that just returns its input
+pub fn choice_module() -> Vec<u8> {
+    let choice = data(CapSet::primitive(), no_imports(), DataImpl::Internal{ constructors: vec![Case{ fields: vec![] }, Case{ fields: vec![] }] });
+    let keep = function(value_imports(), &[TypeRef(0)], &[TypeRef(0)], vec![], vec![], vec![OpCode::Return(vec![ValueRef(0)])]);
+    module(false, vec![choice], vec![keep])
+}
+
+fn function(import:PublicImport, params:&[TypeRef], returns:&[TypeRef], callables:Vec<CallableImport>, permissions:Vec<PermissionImport>, code:Vec<OpCode>) -> FunctionComponent {
+    FunctionComponent {
+        byte_size: None,
+        scope: Accessibility::Global,
+        shared: FunSigShared {
+            transactional: false,
+            generics: vec![],
+            import,
+            params: params.iter().map(|typ|Param{ consumes: true, typ: *typ }).collect(),
+            returns: returns.to_vec(),
+        },
+        body: CallableImpl::Internal {
+            byte_size: None,
+            imports: BodyImport{ public: no_imports(), callables, permissions },
+            code: Exp(LargeVec(code)),
+        }
+    }
+}
+
+//A transaction importing the type at sys_type of the system module (TypeRef 0), U64 (TypeRef 1) and Data20 (TypeRef 2)
+// without an own module the system module is ModRef 0 and U64 and Data are 1 & 2
+fn transaction(sys_type:u8, params:&[TypeRef], returns:&[TypeRef], permissions:Vec<PermissionImport>, code:Vec<OpCode>) -> Vec<u8> {
+    module_transaction(system_module(), sys_type, params, returns, vec![], permissions, code)
+}
+
+//As transaction but TypeRef 0 is the type at offset of type_module (ModRef 0) and it can import the functions of it
+fn module_transaction(type_module:Vec<u8>, offset:u8, params:&[TypeRef], returns:&[TypeRef], callables:Vec<CallableImport>, permissions:Vec<PermissionImport>, code:Vec<OpCode>) -> Vec<u8> {
+    let mut import = value_imports();
+    import.modules.insert(0, ModuleLink::Remote(store_hash(&[&type_module])));
+    import.types.insert(0, TypeImport::Data{ link: DataLink{ module: ModRef(0), offset }, applies: vec![] });
+    Serializer::serialize_fully(&function(import, params, returns, callables, permissions, code), MAX_PARSE_DEPTH).unwrap()
+}
+
+fn create(typ:TypeRef) -> PermissionImport {
+    PermissionImport::Type(PermSet::empty().with_elem(Permission::Create), typ)
+}
+
+//The last parameter is on top of the stack (ValueRef 0)
+pub fn pay_transaction() -> Vec<u8> {
+    transaction(FEE, &[TypeRef(1), TypeRef(2)], &[TypeRef(0)], vec![create(TypeRef(0))], vec![
+        OpCode::Pack(PermRef(0), Tag(0), vec![ValueRef(1), ValueRef(0)])
+    ])
+}
+
+pub fn mint_transaction() -> Vec<u8> {
+    transaction(TOKEN, &[TypeRef(2), TypeRef(1)], &[TypeRef(0)], vec![create(TypeRef(0))], vec![
+        OpCode::Pack(PermRef(0), Tag(0), vec![ValueRef(1), ValueRef(0)])
+    ])
+}
+
+pub fn burn_transaction() -> Vec<u8> {
+    transaction(TOKEN, &[TypeRef(0)], &[], vec![], vec![OpCode::Discard(ValueRef(0))])
+}
+
+pub fn fail_transaction() -> Vec<u8> {
+    transaction(TOKEN, &[], &[], vec![], vec![OpCode::RollBack(vec![], vec![])])
+}
+
+//The switch consumes the choice (ValueRef 1) and the branches the number (ValueRef 0), only the expensive one calls keep
+pub fn choose_transaction() -> Vec<u8> {
+    let consume = PermissionImport::Type(PermSet::empty().with_elem(Permission::Consume), TypeRef(0));
+    let call = PermissionImport::Callable(PermSet::empty().with_elem(Permission::Call), CallRef(0));
+    let keep = CallableImport::Function{ link: FuncLink{ module: ModRef(0), offset: 0 }, applies: vec![] };
+    module_transaction(choice_module(), 0, &[TypeRef(0), TypeRef(1)], &[], vec![keep], vec![consume, call], vec![
+        OpCode::Switch(ValueRef(1), PermRef(0), vec![
+            Exp(LargeVec(vec![OpCode::Discard(ValueRef(0))])),
+            Exp(LargeVec(vec![OpCode::Invoke(PermRef(1), vec![ValueRef(0)]), OpCode::Discard(ValueRef(0))])),
+        ])
+    ])
+}
+
+//As choose but the expensive branch calls keep with the choice instead of the number, so it does not type check
+pub fn mistyped_transaction() -> Vec<u8> {
+    let consume = PermissionImport::Type(PermSet::empty().with_elem(Permission::Consume), TypeRef(0));
+    let call = PermissionImport::Callable(PermSet::empty().with_elem(Permission::Call), CallRef(0));
+    let keep = CallableImport::Function{ link: FuncLink{ module: ModRef(0), offset: 0 }, applies: vec![] };
+    module_transaction(choice_module(), 0, &[TypeRef(0), TypeRef(1)], &[], vec![keep], vec![consume, call], vec![
+        OpCode::Switch(ValueRef(1), PermRef(0), vec![
+            Exp(LargeVec(vec![OpCode::Discard(ValueRef(0))])),
+            Exp(LargeVec(vec![OpCode::Discard(ValueRef(0)), OpCode::Invoke(PermRef(1), vec![ValueRef(1)])])),
+        ])
+    ])
+}
+
+//A transaction that only returns and imports the choice module once and then extra times again
+pub fn fan_out_transaction(extra:usize) -> Vec<u8> {
+    let mut import = value_imports();
+    let choice = ModuleLink::Remote(store_hash(&[&choice_module()]));
+    import.modules.extend(core::iter::repeat_n(choice, extra + 1));
+    Serializer::serialize_fully(&function(import, &[], &[], vec![], vec![], vec![OpCode::Return(vec![])]), MAX_PARSE_DEPTH).unwrap()
+}
+
+//A module without any components (it validates and can be deployed by anyone)
+pub fn empty_module() -> Vec<u8> {
+    module(false, vec![], vec![])
+}
+
+//The descriptors of the deployed transactions
+pub struct Transactions {
+    pub pay:Hash,
+    pub mint:Hash,
+    pub burn:Hash,
+    pub choose:Hash,
+    pub fail:Hash,
+}
+
+//A temporary node with the system modules and the transactions deployed
+pub fn chain(name:&str) -> (State, Transactions) {
+    let mut state = State::temporary(name);
+    let txts = CompilerInstance::with_compiler_result(|compiler|{
+        state.deploy_system_module(compiler, u64_module(), Some(8))?;
+        state.deploy_system_module(compiler, data_module(), Some(10))?;
+        state.deploy_system_module(compiler, system_module(), Some(12))?;
+        state.deploy_module(compiler, choice_module(), false, None)?;
+        Ok(Transactions {
+            pay: state.deploy_transaction(compiler, pay_transaction())?.1,
+            mint: state.deploy_transaction(compiler, mint_transaction())?.1,
+            burn: state.deploy_transaction(compiler, burn_transaction())?.1,
+            choose: state.deploy_transaction(compiler, choose_transaction())?.1,
+            fail: state.deploy_transaction(compiler, fail_transaction())?.1,
+        })
+    }).unwrap();
+    (state, txts)
+}
+
+//Builds a bundle against the store of the node and serializes it
+pub fn bundle(state:&State, builder:&BundleBuilder) -> Vec<u8> {
+    let heap = Heap::new(CONFIG.calc_heap_size(2),2.0);
+    let alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
+    let bundle = builder.build::<_, ServerSystemDataManager>(&state.store, &alloc).unwrap();
+    Serializer::serialize_fully(&bundle.txt_bundle, MAX_PARSE_DEPTH).unwrap()
+}
+
+//The literal inputs of the transactions
+pub fn num(val:u64) -> Input {
+    Input::Literal(Serializer::serialize_fully(&val, 1).unwrap())
+}
+
+pub fn id(val:u8) -> Input {
+    Input::Literal(vec![val; 20])
+}
+
+//The files of the fixtures that the wasm host deploys natively and in wasm
+pub fn fixture_files() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("u64.mod.sans", u64_module()),
+        ("data.mod.sans", data_module()),
+        ("system.mod.sans", system_module()),
+        ("empty.mod.sans", empty_module()),
+        ("pay.txt.sans", pay_transaction()),
+        ("mint.txt.sans", mint_transaction()),
+        ("burn.txt.sans", burn_transaction()),
+        ("fail.txt.sans", fail_transaction()),
+    ]
+}
+
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    //Run with SANSKRIT_UPDATE_FIXTURES=1 to rewrite the files after the model changed
+    #[test]
+    fn checked_in_fixtures_are_current() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../sanskrit_wasm_host/fixtures");
+        let update = std::env::var_os("SANSKRIT_UPDATE_FIXTURES").is_some();
+        for (name, data) in fixture_files() {
+            if update {
+                std::fs::write(dir.join(name), &data).unwrap();
+            }
+            assert_eq!(std::fs::read(dir.join(name)).unwrap(), data, "{} is outdated", name);
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! Initializes an empty node from a genesis file
+//!
+//! The genesis file is a json document listing what the network starts with:
+//!   {
+//!     "system_modules": [{"name": "I8", "file": "i8.bin", "id": 0}, {"name": "Base", "file": "base.bin"}],
+//!     "modules": [{"name": "Token", "file": "token.bin"}],
+//!     "transactions": [{"name": "mint", "file": "mint.bin"}],
+//!     "accounts": [{"name": "alice"}, {"name": "bob", "secret": "<hex>"}],
+//!     "entries": ["mint(pk(alice), u64(1000)):(store(alice_tokens))"]
+//!   }
+//! The files contain compiled modules and transactions, relative paths are resolved against the folder of the genesis file.
+//! The id of a system module is its index in SYS_MODS, system modules without an id are only deployed in system mode.
+//! Accounts without a secret get a key derived from their name, which is only acceptable for test networks.
+//! Entries are transaction invocations written like in the repl, each one is executed as a bundle of the genesis block.
+//! Everything is loaded in the order of the file and the genesis block has the timestamp 0,
+//! so every node that loads the same file ends up with the same state (and prints the same genesis state root).
+//! The root shows that two nodes loaded different files, it is not a commitment that authenticates the genesis (see state_root).
+//! The genesis is loaded into a staging node in the temp folder first and only moved into the state once all of it loaded,
+//! so a genesis that fails leaves the state as it was.
+//! The system module bindings are global, the staging node sets them to execute the entries,
+//! they are restored if the genesis fails and bound to the system modules of the state once it is adopted.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use serde_json::Value;
+use ed25519_dalek::SigningKey;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use sanskrit_common::errors::*;
+use sanskrit_common::encoding::*;
+use sanskrit_common::model::Hash;
+use sanskrit_common::arena::Heap;
+use sanskrit_common::hashing::HashingDomain;
+use sanskrit_common::store::{ListableStore, STORAGE_CLASSES};
+use sanskrit_common::archive::{export_state, import_state};
+use sled::Db;
+use sanskrit_runtime::{BlockInfo, Tracker, CONFIG};
+use sanskrit_default_externals::{SYS_HASH, EDDSA_HASH, SYS_MODS};
+use crate::manager::{State, ExecutionState, LOCAL_PRODUCER, MAX_PARSE_DEPTH};
+use crate::compiler::CompilerInstance;
+use crate::{build_tx, convert_error};
+
+pub struct GenesisFile {
+    pub name:String,
+    pub data:Vec<u8>,
+}
+
+pub struct GenesisSystemModule {
+    pub file:GenesisFile,
+    pub id:Option<u8>,
+}
+
+pub struct Genesis {
+    pub system_modules:Vec<GenesisSystemModule>,
+    pub modules:Vec<GenesisFile>,
+    pub transactions:Vec<GenesisFile>,
+    pub accounts:Vec<(String, SigningKey)>,
+    pub entries:Vec<String>,
+}
+
+fn string_field<'a>(value:&'a Value, name:&str) -> Result<&'a str> {
+    match value.get(name).and_then(Value::as_str) {
+        Some(field) => Ok(field),
+        None => owned_error(||format!("Genesis entry {} needs a string field {}", value, name))
+    }
+}
+
+//A missing section is treated as empty
+fn section<'a>(genesis:&'a Value, name:&str) -> Result<&'a [Value]> {
+    match genesis.get(name) {
+        None => Ok(&[]),
+        Some(Value::Array(items)) => Ok(items),
+        Some(_) => owned_error(||format!("Genesis section {} must be a list", name))
+    }
+}
+
+fn read_file(value:&Value, folder:&Path) -> Result<GenesisFile> {
+    let file = folder.join(string_field(value, "file")?);
+    match fs::read(&file) {
+        Ok(data) => Ok(GenesisFile{ name: string_field(value, "name")?.to_owned(), data }),
+        Err(err) => owned_error(||format!("Can not read {}: {}", file.display(), err))
+    }
+}
+
+//The key of an account without a secret, the same name always leads to the same key
+fn derived_key(name:&str) -> SigningKey {
+    let mut seed = [0; 32];
+    seed[..20].copy_from_slice(&HashingDomain::Derive.hash(name.as_bytes()));
+    SigningKey::generate(&mut ChaCha8Rng::from_seed(seed))
+}
+
+fn read_account(value:&Value) -> Result<(String, SigningKey)> {
+    let name = string_field(value, "name")?.to_owned();
+    let key = match value.get("secret") {
+        None => derived_key(&name),
+        Some(_) => {
+            let secret = string_field(value, "secret")?;
+            match hex::decode(secret.strip_prefix("0x").unwrap_or(secret)).ok().and_then(|data|data.try_into().ok()) {
+                Some(bytes) => SigningKey::from_bytes(&bytes),
+                None => return owned_error(||format!("Secret of account {} must be 32 hex encoded bytes", name))
+            }
+        }
+    };
+    Ok((name, key))
+}
+
+impl Genesis {
+    //Reads the genesis file and all the files it references
+    pub fn read(path:&Path) -> Result<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => return owned_error(||format!("Can not read genesis file {}: {}", path.display(), err))
+        };
+        let genesis:Value = match serde_json::from_str(&content) {
+            Ok(genesis) => genesis,
+            Err(err) => return owned_error(||format!("Genesis file is not valid json: {}", err))
+        };
+        let folder = path.parent().unwrap_or(Path::new("."));
+
+        let mut system_modules = Vec::new();
+        for value in section(&genesis, "system_modules")? {
+            let id = match value.get("id") {
+                None => None,
+                Some(id) => match id.as_u64() {
+                    Some(id) if id <= u8::MAX as u64 => Some(id as u8),
+                    _ => return owned_error(||format!("Genesis entry {} has an invalid system module id", value))
+                }
+            };
+            system_modules.push(GenesisSystemModule{ file: read_file(value, folder)?, id });
+        }
+        let modules = section(&genesis, "modules")?.iter().map(|value|read_file(value, folder)).collect::<Result<_>>()?;
+        let transactions = section(&genesis, "transactions")?.iter().map(|value|read_file(value, folder)).collect::<Result<_>>()?;
+        let accounts = section(&genesis, "accounts")?.iter().map(read_account).collect::<Result<_>>()?;
+        let mut entries = Vec::new();
+        for value in section(&genesis, "entries")? {
+            match value.as_str() {
+                Some(entry) => entries.push(entry.to_owned()),
+                None => return error(||"Genesis entries must be strings")
+            }
+        }
+        Ok(Genesis{ system_modules, modules, transactions, accounts, entries })
+    }
+}
+
+fn execute_entries(genesis:&Genesis, state:&mut State) -> Result<()> {
+    let block = BlockInfo{ block_no: state.block_no()?, timestamp: 0, producer: LOCAL_PRODUCER, gas_price: state.gas_price };
+    state.tracking.block_start(block.block_no);
+    let mut bundles = Vec::with_capacity(genesis.entries.len());
+    let mut deployed = false;
+    for entry in &genesis.entries {
+        //every entry is a bundle of its own, so its element names are tracked like in the repl
+        state.tracking.exec_state = ExecutionState::new();
+        let tx = build_tx(entry, state, &mut BTreeSet::new())?;
+        let heap = Heap::new(CONFIG.calc_heap_size(2),2.0);
+        let full_heap = heap.new_virtual_arena(CONFIG.max_bundle_size);
+        let (_, bundle) = state.build_transactions(&[tx], &full_heap, block.block_no)?;
+        let data = Serializer::serialize_fully(&bundle, MAX_PARSE_DEPTH)?;
+        let (_, receipt) = state.execute_in_block(&data, block)?;
+        if !receipt.success {
+            return owned_error(||format!("Genesis entry {} was rolled back", entry))
+        }
+        deployed |= !receipt.deployed.is_empty();
+        bundles.push(data);
+    }
+    state.finish_block(block, bundles, deployed)
+}
+
+fn load(genesis:&Genesis, state:&mut State, compiler:&mut CompilerInstance) -> Result<()> {
+    for module in &genesis.system_modules {
+        let hash = state.deploy_system_module(compiler, module.file.data.clone(), module.id)?;
+        convert_error(state.module_name_mapping.insert(module.file.name.as_bytes(), &hash))?;
+    }
+    for module in &genesis.modules {
+        let hash = state.deploy_module(compiler, module.data.clone(), false, None)?;
+        convert_error(state.module_name_mapping.insert(module.name.as_bytes(), &hash))?;
+    }
+    for transaction in &genesis.transactions {
+        let (_, desc_hash) = state.deploy_transaction(compiler, transaction.data.clone())?;
+        convert_error(state.transaction_name_mapping.insert(transaction.name.as_bytes(), &desc_hash))?;
+    }
+    convert_error(state.module_name_mapping.flush())?;
+    convert_error(state.transaction_name_mapping.flush())?;
+    for (name, key) in &genesis.accounts {
+        state.add_account(name, key)?;
+    }
+    if !genesis.entries.is_empty() {
+        execute_entries(genesis, state)?;
+    }
+    Ok(())
+}
+
+fn copy_db(from:&Db, to:&Db) -> Result<()> {
+    for entry in from.iter() {
+        let (key, value) = convert_error(entry)?;
+        convert_error(to.insert(key, value))?;
+    }
+    convert_error(to.flush())?;
+    Ok(())
+}
+
+//Moves the content of the staging node into the state and returns the state root
+fn adopt(staged:&State, state:&mut State) -> Result<Hash> {
+    //the archive import verifies the content against the staged root and keeps nothing if it fails
+    let root = import_state(&export_state(&staged.store)?.0, &state.store)?;
+    for class in STORAGE_CLASSES {
+        state.store.flush(class);
+    }
+    copy_db(&staged.accounts, &state.accounts)?;
+    copy_db(&staged.system_entries, &state.system_entries)?;
+    copy_db(&staged.module_name_mapping, &state.module_name_mapping)?;
+    copy_db(&staged.transaction_name_mapping, &state.transaction_name_mapping)?;
+    copy_db(&staged.tracking.active_elems, &state.tracking.active_elems)?;
+    copy_db(&staged.tracking.element_data, &state.tracking.element_data)?;
+    copy_db(&staged.tracking.data_names, &state.tracking.data_names)?;
+    copy_db(&staged.tracking.entry_schemas, &state.tracking.entry_schemas)?;
+    copy_db(&staged.receipts, &state.receipts)?;
+    copy_db(&staged.meta_data, &state.meta_data)?;
+    for entry in staged.block_log.entries()? {
+        state.block_log.append(&entry)?;
+    }
+    Ok(root)
+}
+
+//Binds the system modules registered in the state
+fn bind_system_modules(state:&State) -> Result<()> {
+    for entry in state.system_entries.iter() {
+        let (key, value) = convert_error(entry)?;
+        match (SYS_MODS.get(key[0] as usize), value.as_ref().try_into()) {
+            (Some(sys_impl), Ok(hash)) => sys_impl(hash),
+            _ => return error(||"State has an invalid system module binding")
+        }
+    }
+    Ok(())
+}
+
+//Loads the genesis into the state and returns the genesis state root
+// a state that was initialized before keeps its genesis, everything else has to be empty
+pub fn init(genesis:&Genesis, state:&mut State, compiler:&mut CompilerInstance) -> Result<Hash> {
+    if let Some(root) = convert_error(state.meta_data.get("genesis_root"))? {
+        println!("State was already initialized from a genesis");
+        return Parser::parse_fully(&root, 1, &NoCustomAlloc())
+    }
+    for class in STORAGE_CLASSES {
+        if state.store.digest(class)?.entries != 0 {
+            return error(||"A genesis can only be loaded into an empty state")
+        }
+    }
+
+    let staging = std::env::temp_dir().join(format!("sanskrit_genesis_staging_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&staging);
+    //the other system modules only add implementations for their own hashes and do not need to be restored
+    let bindings = (SYS_HASH.lock().get(), EDDSA_HASH.lock().get());
+    let res = match State::open(&staging, BTreeSet::new()) {
+        Ok(mut staged) => load(genesis, &mut staged, compiler).and_then(|_|adopt(&staged, state)),
+        Err(err) => owned_error(||format!("Can not open the genesis staging folder {}: {}", staging.display(), err))
+    };
+    let _ = fs::remove_dir_all(&staging);
+    let root = match res.and_then(|root|bind_system_modules(state).map(|_|root)) {
+        Ok(root) => root,
+        Err(err) => {
+            SYS_HASH.lock().set(bindings.0);
+            EDDSA_HASH.lock().set(bindings.1);
+            return Err(err)
+        }
+    };
+    //written last, a state without it is not initialized
+    convert_error(state.meta_data.insert("genesis_root", Serializer::serialize_fully(&root, 1)?))?;
+    convert_error(state.meta_data.flush())?;
+    println!("Loaded genesis with {} system modules, {} modules, {} transactions, {} accounts and {} entries",
+             genesis.system_modules.len(), genesis.modules.len(), genesis.transactions.len(), genesis.accounts.len(), genesis.entries.len());
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sanskrit_common::store::{StorageClass, state_root};
+    use crate::blocks::LogEntry;
+    use crate::fixtures::empty_module;
+
+    fn genesis(modules:Vec<GenesisFile>) -> Genesis {
+        Genesis {
+            system_modules: vec![],
+            modules,
+            transactions: vec![],
+            accounts: vec![("alice".to_owned(), derived_key("alice"))],
+            entries: vec![],
+        }
+    }
+
+    fn is_untouched(state:&State) -> bool {
+        STORAGE_CLASSES.iter().all(|class|state.store.digest(*class).unwrap().entries == 0)
+            && state.accounts.is_empty()
+            && state.module_name_mapping.is_empty()
+            && state.meta_data.is_empty()
+            && state.block_log.entries().unwrap().is_empty()
+    }
+
+    #[test]
+    fn failed_genesis_keeps_the_system_bindings() {
+        let mut state = State::temporary("genesis_bindings");
+        let before = EDDSA_HASH.lock().get();
+        let mut broken = genesis(vec![GenesisFile{ name: "Broken".to_owned(), data: vec![1, 2, 3] }]);
+        //the module is staged and bound as the eddsa module before the broken module fails
+        broken.system_modules.push(GenesisSystemModule{ file: GenesisFile{ name: "EdDsa".to_owned(), data: empty_module() }, id: Some(15) });
+        CompilerInstance::with_compiler_result(|compiler|{
+            assert!(init(&broken, &mut state, compiler).is_err());
+            Ok(())
+        }).unwrap();
+        assert!(is_untouched(&state));
+        assert_eq!(EDDSA_HASH.lock().get(), before);
+        assert_ne!(before, sanskrit_common::store::store_hash(&[&empty_module()]));
+    }
+
+    #[test]
+    fn genesis_is_loaded_completely_or_not_at_all() {
+        let mut state = State::temporary("genesis_target");
+        let valid = GenesisFile{ name: "Empty".to_owned(), data: empty_module() };
+        let broken = GenesisFile{ name: "Broken".to_owned(), data: vec![1, 2, 3] };
+        CompilerInstance::with_compiler_result(|compiler|{
+            //the valid module and the account are loaded before the broken module fails
+            assert!(init(&genesis(vec![valid, broken]), &mut state, compiler).is_err());
+            assert!(is_untouched(&state));
+
+            let valid = GenesisFile{ name: "Empty".to_owned(), data: empty_module() };
+            let root = init(&genesis(vec![valid]), &mut state, compiler)?;
+            assert_eq!(root, state_root(&state.store)?);
+            assert_eq!(state.store.digest(StorageClass::Module)?.entries, 1);
+            assert!(state.module_name_mapping.get("Empty").unwrap().is_some());
+            assert_eq!(state.get_accounts()?.len(), 1);
+            match &state.block_log.entries()?[..] {
+                [LogEntry::Deploy(deploy)] => assert_eq!(deploy.state_root, root),
+                _ => panic!("genesis should have logged one deploy")
+            }
+
+            //a loaded genesis is kept
+            assert_eq!(init(&genesis(vec![]), &mut state, compiler)?, root);
+            Ok(())
+        }).unwrap();
+    }
+}
@@ -4,6 +4,7 @@ extern crate sanskrit_common;
 extern crate sanskrit_core;
 extern crate sanskrit_interpreter;
 extern crate sanskrit_sled_store; //for now later use an ethereum or substrate based one
+extern crate sanskrit_memory_store;
 
 extern crate sanskrit_default_externals;
 extern crate sled;
@@ -12,6 +13,7 @@ extern crate ed25519_dalek;
 extern crate sha2;
 extern crate rand;
 extern crate rand_chacha;
+extern crate ethnum;
 extern crate hex;
 extern crate arrayref;
 extern crate byteorder;
@@ -22,12 +24,21 @@ extern crate sanskrit_derive;
 extern crate lalrpop_util;
 extern crate fluid_let;
 extern crate sanskrit_compile;
+extern crate serde_json;
+extern crate tiny_http;
 
 
 mod manager;
 mod parser_model;
 mod compiler;
 mod externals;
+mod rpc;
+mod blocks;
+mod script;
+mod replay;
+mod genesis;
+#[cfg(test)]
+mod fixtures;
 
 lalrpop_mod!(pub parser);
 
@@ -35,8 +46,6 @@ lalrpop_mod!(pub parser);
 
 use std::path::Path;
 
-use sanskrit_sled_store::SledStore;
-
 use sled::Db;
 
 use std::{env, thread};
@@ -45,7 +54,7 @@ use sanskrit_common::errors::*;
 
 use std::net::TcpListener;
 use byteorder::{ NetworkEndian, ReadBytesExt, WriteBytesExt};
-use manager::{State, TrackingState, ExecutionState, ModuleNames, Tx};
+use manager::{State, ExecutionState, ModuleNames, Tx};
 use std::io::{Read, Write};
 use std::error::Error;
 use sanskrit_common::model::{Hash, hash_from_slice};
@@ -55,21 +64,24 @@ use rustyline::error::ReadlineError;
 use parser_model::Execute;
 use sanskrit_common::arena::{Heap, VirtualHeapArena};
 use sanskrit_interpreter::model::Entry;
+use sanskrit_runtime::model::DeployType;
 use sanskrit_common::encoding::{VirtualSize, Parser, NoCustomAlloc};
 use std::collections::BTreeSet;
 use std::cell::RefCell;
 use std::rc::Rc;
 use rand::prelude::*;
-use rand_chacha::ChaCha8Rng;
 use sanskrit_default_externals::{SYS_MODS, External};
 
 use compiler::CompilerInstance;
+use blocks::BlockReport;
+use std::time::Duration;
 use sanskrit_common::store::{CachedStore, StorageClass};
 use sanskrit_core::model::Module;
 
 pub const MODULE_COMMAND:u8 = 0;
 pub const TRANSACTION_COMMAND:u8 = 1;
 pub const SYS_MODULE_COMMAND:u8 = 2;
+pub const PACKAGE_COMMAND:u8 = 3;
 
 pub const SUCCESS_RETURN:u8 = 0;
 pub const ERROR_RETURN:u8 = 1;
@@ -114,21 +126,14 @@ fn handle_data<R:Read>(state: &mut State, compiler:&mut CompilerInstance, reader
             let name = (data.0).0;
             let (hash, e_hash) = if convert_error(reader.read_u8())? != 0 {
                 let sys_id = convert_error(reader.read_u8())?;
-                if sys_id as usize >= SYS_MODS.len() {
-                    return error(||"unknown system module identifier")
-                }
                 let bytes = read_length_prefixed_array(reader)?;
-                let hash = state.deploy_module(compiler,bytes, true, Some(sys_id))?;
-                let sys_impl = SYS_MODS[sys_id as usize];
-                sys_impl(hash.clone());
-                convert_error(state.system_entries.insert(&[sys_id], &hash))?;
-                convert_error(state.system_entries.flush())?;
+                let hash = state.deploy_system_module(compiler, bytes, Some(sys_id))?;
                 let e_hash = encode(&hash);
                 //println!("Registered System Module {} with Hash {:?} with System Number {:?}",name, e_hash,sys_id);
                 (hash, e_hash)
             } else {
                 let bytes = read_length_prefixed_array(reader)?;
-                let hash = state.deploy_module(compiler,bytes, true, None)?;
+                let hash = state.deploy_system_module(compiler, bytes, None)?;
                 let e_hash = encode(&hash);
                 //println!("Registered System Module {} with Hash {:?}",name, e_hash);
                 (hash, e_hash)
@@ -152,6 +157,40 @@ fn handle_data<R:Read>(state: &mut State, compiler:&mut CompilerInstance, reader
             convert_error(state.transaction_name_mapping.flush())?;
             Ok(vec![f_hash, d_hash])
         },
+        //A count followed by (module metadata, module) pairs and a count followed by (transaction name, transaction) pairs
+        PACKAGE_COMMAND => {
+            let num_modules = convert_error(reader.read_u32::<NetworkEndian>())?;
+            let mut module_names = Vec::new();
+            let mut modules = Vec::new();
+            for _ in 0..num_modules {
+                let meta_data_bytes = read_length_prefixed_array(reader)?;
+                let data:ModuleNames = Parser::parse_fully(&meta_data_bytes,6,&NoCustomAlloc())?;
+                module_names.push(((data.0).0, meta_data_bytes));
+                modules.push(read_length_prefixed_array(reader)?);
+            }
+            let num_transactions = convert_error(reader.read_u32::<NetworkEndian>())?;
+            let mut transaction_names = Vec::new();
+            let mut transactions = Vec::new();
+            for _ in 0..num_transactions {
+                transaction_names.push(convert_error(String::from_utf8(read_length_prefixed_array(reader)?))?);
+                transactions.push(read_length_prefixed_array(reader)?);
+            }
+            let hashes = state.deploy_package(compiler, modules, transactions)?;
+            let (module_hashes, desc_hashes) = hashes.split_at(module_names.len());
+            for ((name, meta_data_bytes), hash) in module_names.into_iter().zip(module_hashes) {
+                println!("{}",&name);
+                convert_error(state.tracking.data_names.insert(hash,meta_data_bytes))?;
+                convert_error(state.module_name_mapping.insert(name,hash))?;
+            }
+            for (name, d_hash) in transaction_names.into_iter().zip(desc_hashes) {
+                println!("{}",&name);
+                convert_error(state.transaction_name_mapping.insert(name,d_hash))?;
+            }
+            convert_error(state.tracking.data_names.flush())?;
+            convert_error(state.module_name_mapping.flush())?;
+            convert_error(state.transaction_name_mapping.flush())?;
+            Ok(hashes)
+        },
         _ => error(||"Unknown Command"),
     }
 }
@@ -172,14 +211,19 @@ fn extract_command(line:String) -> (String,String) {
     }
 }
 
-fn process_txt_line(input:String, shared_state:Arc<Mutex<State>>, mut bindings: &mut BTreeSet<String>) -> Result<Tx> {
-    let txt:Execute = convert_error(parser::ExecuteParser::new().parse(&input))?;
+fn process_txt_line(input:String, shared_state:Arc<Mutex<State>>, bindings: &mut BTreeSet<String>) -> Result<Tx> {
     let mut local_state =   convert_error(shared_state.lock())?;
-    txt.build_param_names(&mut local_state);
-    txt.build_return_names(&mut local_state);
-    let desc = txt.txt_hash(&mut local_state)?;
+    build_tx(&input, &mut local_state, bindings)
+}
+
+//Parses a transaction invocation (name(params):(returns)) and prepares the tracking of its element names
+fn build_tx(input:&str, local_state:&mut State, mut bindings: &mut BTreeSet<String>) -> Result<Tx> {
+    let txt:Execute = convert_error(parser::ExecuteParser::new().parse(input))?;
+    txt.build_param_names(local_state);
+    txt.build_return_names(local_state);
+    let desc = txt.txt_hash(local_state)?;
     let returns = txt.build_returns(&mut bindings);
-    let params = txt.build_params(&mut local_state, &bindings)?;
+    let params = txt.build_params(local_state, &bindings)?;
     Ok(Tx { desc, params, returns })
 }
 
@@ -274,6 +318,40 @@ fn process_line(line:String, shared_state:Arc<Mutex<State>>, full_heap:&VirtualH
             println!("{}",elem)
         }
 
+        //worst case resources of the functions and implements of a module
+        "bounds" => convert_error(shared_state.lock())?.module_bounds(&input)?,
+
+        //the interface of a module as json (types, constructors, signatures and functions)
+        "abi" => convert_error(shared_state.lock())?.module_abi(&input)?,
+
+        //writes the state of the store to an archive file: export <file>
+        "export" => {
+            let root = convert_error(shared_state.lock())?.export_state(Path::new(&input))?;
+            println!("Exported state with root 0x{} to {}", encode(root), input)
+        },
+
+        //validates a module or transaction file without deploying it: dryrun module|transaction <file>
+        "dryrun" => {
+            let (typ, file) = extract_command(input);
+            let typ = match typ.as_ref() {
+                "module" => DeployType::Module,
+                "transaction" | "txt" => DeployType::Transaction,
+                _ => return error(||"Expected module or transaction")
+            };
+            let data = convert_error(std::fs::read(file))?;
+            convert_error(shared_state.lock())?.dry_run_deploy(typ, data)?;
+        },
+
+        //produces a block with the bundles waiting in the mempool
+        "block" => {
+            let report = convert_error(shared_state.lock())?.produce_block()?;
+            print_block_report(&report);
+        },
+
+        "mempool" => for bundle in convert_error(shared_state.lock())?.mempool.pending() {
+            println!("0x{} (earliest block {})", encode(bundle.hash), bundle.earliest_block)
+        },
+
         "exit" => return Ok(ProcRes::End),
 
         x if x.len() != 0 =>  println!("Unknown Command"),
@@ -283,6 +361,19 @@ fn process_line(line:String, shared_state:Arc<Mutex<State>>, full_heap:&VirtualH
 }
 
 
+fn print_block_report(report:&BlockReport) {
+    println!("Produced block {} with {} bundles", report.block_no, report.included.len());
+    for (hash, receipt) in &report.included {
+        println!("  - 0x{} {} (charged {} gas)", encode(hash), if receipt.success {"succeeded"} else {"was rolled back"}, receipt.used_gas);
+    }
+    for (hash, err) in &report.rejected {
+        println!("  - 0x{} was rejected: {}", encode(hash), err);
+    }
+    for hash in &report.dropped {
+        println!("  - 0x{} was dropped (its inclusion window passed)", encode(hash));
+    }
+}
+
 fn process_bundle_line(line:String, bundle_state:Rc<RefCell<(BTreeSet<String>,Vec<Tx>)>>, shared_state:Arc<Mutex<State>>) -> Result<ProcRes> {
     let (command, input) = extract_command(line);
     match command.to_lowercase().as_ref() {
@@ -348,49 +439,144 @@ static MODE:&str = "embedded";
 
 
 pub fn main() -> std::io::Result<()> {
-    let args: Vec<String> = env::args().collect();
+    //usage: sanskrit_local_server [db folder] [--genesis <file>] [--script <file>] [--block-time <seconds>] [--gas-price <units>]
+    //       sanskrit_local_server --replay <block log> [--in-memory]
+    //       sanskrit_local_server [db folder] --export <archive> | --import <archive>
+    let mut args = env::args().skip(1);
+    let mut db_arg = None;
+    let mut script = None;
+    let mut block_time = None;
+    let mut replay_log = None;
+    let mut in_memory = false;
+    let mut gas_price = None;
+    let mut genesis_file = None;
+    let mut export_file = None;
+    let mut import_file = None;
+    while let Some(arg) = args.next() {
+        if arg == "--script" {
+            match args.next() {
+                Some(file) => script = Some(Path::new(&file).to_owned()),
+                None => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--script needs a file"))
+            }
+        } else if arg == "--block-time" {
+            match args.next().and_then(|secs|secs.parse::<u64>().ok()) {
+                Some(secs) if secs > 0 => block_time = Some(Duration::from_secs(secs)),
+                _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--block-time needs a number of seconds"))
+            }
+        } else if arg == "--gas-price" {
+            match args.next().and_then(|units|units.parse::<u64>().ok()) {
+                Some(units) => gas_price = Some(units),
+                None => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--gas-price needs a number of fee units"))
+            }
+        } else if arg == "--replay" {
+            match args.next() {
+                Some(file) => replay_log = Some(Path::new(&file).to_owned()),
+                None => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--replay needs a block log"))
+            }
+        } else if arg == "--genesis" {
+            match args.next() {
+                Some(file) => genesis_file = Some(Path::new(&file).to_owned()),
+                None => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--genesis needs a file"))
+            }
+        } else if arg == "--export" || arg == "--import" {
+            let file = match args.next() {
+                Some(file) => Path::new(&file).to_owned(),
+                None => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--export and --import need an archive"))
+            };
+            if arg == "--export" { export_file = Some(file) } else { import_file = Some(file) }
+        } else if arg == "--in-memory" {
+            in_memory = true;
+        } else {
+            db_arg = Some(arg);
+        }
+    }
+
+    //replays do not need the state of the server, they start from an empty store
+    if let Some(log) = replay_log {
+        //the blocks are replayed with the gas price they were produced with
+        if gas_price.is_some() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--replay uses the gas prices recorded in the block log"))
+        }
+        match replay::run(&log, in_memory) {
+            Ok(true) => return Ok(()),
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                println!("Error: {}", error_to_string(&err));
+                std::process::exit(1)
+            }
+        }
+    }
     let work_dir =  env::current_dir()?;
 
-    let db_folder = if args.len() >= 2 {
-        Path::new(&args[1]).to_owned()
-    } else {
-        work_dir.join("db")
+    let db_folder = match db_arg {
+        Some(folder) => Path::new(&folder).to_owned(),
+        None => work_dir.join("db")
     };
 
-    let account_db = db_folder.join("accounts").with_extension("db");
-    let module_name_db = db_folder.join("module_names").with_extension("db");
-    let transaction_name_db = db_folder.join("transaction_names").with_extension("db");
-    let elem_tracker_db = db_folder.join("elem_tracker").with_extension("db");
-    let data_tracker_db = db_folder.join("data_tracker").with_extension("db");
-    let data_names_tracker_db = db_folder.join("data_name_tracker").with_extension("db");
-    let sys_entry_db = db_folder.join("system_modules").with_extension("db");
-    let meta_db = db_folder.join("meta_data").with_extension("db");
-
     let history = work_dir.join("history").with_extension("txt");
 
-    let mut auto_flushes = BTreeSet::new();
+    let auto_flushes = BTreeSet::new();
     //auto_flushes.insert(StorageClass::Transaction);
     //auto_flushes.insert(StorageClass::Module);
     //auto_flushes.insert(StorageClass::Descriptor);
 
-    let state = State {
-        csprng: ChaCha8Rng::seed_from_u64(10), //ChaCha8Rng::from_entropy(),
-        store: CachedStore::<Module,SledStore>::new(SledStore::new(&db_folder, auto_flushes), StorageClass::Module),
-        accounts:sled::open(account_db)?,
-        system_entries:sled::open(sys_entry_db)?,
-        module_name_mapping:sled::open(module_name_db)?,
-        transaction_name_mapping:sled::open(transaction_name_db)?,
-        tracking: TrackingState {
-            exec_state: ExecutionState::new(),
-            active_elems:sled::open(elem_tracker_db)?,
-            element_data:sled::open(data_tracker_db)?,
-            data_names:sled::open(data_names_tracker_db)?,
-        },
-        meta_data:sled::open(meta_db)?,
-    };
+    let mut state = State::open(&db_folder, auto_flushes)?;
+    if let Some(price) = gas_price {
+        state.gas_price = price;
+    }
+
+    //moving a state between nodes is done without starting the node
+    if export_file.is_some() || import_file.is_some() {
+        let res = match (export_file, import_file) {
+            (Some(file), None) => state.export_state(&file).map(|root|println!("Exported state with root 0x{} to {}", encode(root), file.display())),
+            (None, Some(file)) => state.import_state(&file).map(|root|println!("Imported state with root 0x{} from {}", encode(root), file.display())),
+            _ => error(||"--export and --import can not be combined")
+        };
+        match res {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                println!("Error: {}", error_to_string(&err));
+                std::process::exit(1)
+            }
+        }
+    }
 
     let shared_state = Arc::new(Mutex::new(state));
+
+    //the genesis is loaded before anything else can change the state
+    if let Some(genesis_file) = genesis_file {
+        let res = genesis::Genesis::read(&genesis_file).and_then(|genesis|CompilerInstance::with_compiler_result(|compiler|{
+            let mut state = convert_error(shared_state.lock())?;
+            genesis::init(&genesis, &mut state, compiler)
+        }));
+        match res {
+            Ok(root) => println!("Genesis state root: 0x{}", encode(root)),
+            Err(err) => {
+                println!("Error: {}", error_to_string(&err));
+                std::process::exit(1)
+            }
+        }
+    }
+
+    //scripts run without the listeners, so that several of them can run at the same time (with different db folders)
+    if let Some(script) = script {
+        let res = CompilerInstance::with_compiler_result(|compiler|{
+            convert_error(register_system_modules(&shared_state.lock().unwrap(), compiler))?;
+            script::run(&script, shared_state.clone())
+        });
+        match res {
+            Ok(true) => return Ok(()),
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                println!("Error: {}", error_to_string(&err));
+                std::process::exit(1)
+            }
+        }
+    }
+
     let listener_state = Arc::clone(&shared_state);
+    let rpc_state = Arc::clone(&shared_state);
+    let producer_state = Arc::clone(&shared_state);
     // accept connections and process them serially
     println!("Started Local VM in {} mode", MODE);
     #[cfg(feature = "dynamic_gas")]
@@ -423,6 +609,25 @@ pub fn main() -> std::io::Result<()> {
 
     });
 
+    thread::spawn(move || {
+        if let Err(err) = rpc::serve(rpc::RPC_ADDRESS, rpc_state) {
+            println!("JSON-RPC interface stopped: {}", error_to_string(&err));
+        }
+    });
+
+    //without a block time, blocks with bundles from the mempool are only produced on demand
+    if let Some(block_time) = block_time {
+        thread::spawn(move || loop {
+            thread::sleep(block_time);
+            match producer_state.lock().unwrap().produce_block() {
+                //empty blocks are not reported, they would flood the repl
+                Ok(report) if report.included.is_empty() && report.rejected.is_empty() && report.dropped.is_empty() => {},
+                Ok(report) => print_block_report(&report),
+                Err(err) => println!("Producing block failed: {}", error_to_string(&err)),
+            }
+        });
+    }
+
     let mut rl = rustyline::DefaultEditor::new().unwrap();
     if rl.load_history(&history).is_err() {
         println!("No previous history.");
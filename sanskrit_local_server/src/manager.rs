@@ -1,798 +1,1488 @@
-//extern crate blake2_rfc;
-use sanskrit_sled_store::SledStore;
-use sled::Db;
-
-use std::collections::{BTreeMap, BTreeSet, VecDeque};
-use sanskrit_runtime::{execute, Tracker, CONFIG, read_transaction_desc, Context, verify, TransactionBundle};
-use sanskrit_runtime::deploy;
-use sanskrit_common::store::*;
-use sanskrit_common::encoding::*;
-use sanskrit_common::model::*;
-use sanskrit_common::errors::*;
-
-use ed25519_dalek::{SigningKey, Signature, Signer, SECRET_KEY_LENGTH};
-
-use hex::encode;
-use sanskrit_common::arena::{Heap, VirtualHeapArena};
-use sanskrit_common::hashing::HashingDomain;
-
-use sanskrit_runtime::model::{ParamRef, ParamMode, RetType, BundleSection, SectionType, Transaction, TransactionBundleCore, BaseTransactionBundle, BundleWithHash};
-use sanskrit_runtime::model::{DeployTransaction, DeployType};
-use sanskrit_interpreter::model::{Entry, TxTParam, TxTReturn, TransactionDescriptor, ValueSchema, Adt};
-use crate::externals::{ServerSystem, ServerSystemDataManager, get_ed_dsa_module};
-use sanskrit_default_externals::ServerExternals;
-use std::time::Instant;
-use std::ops::{Deref, Add};
-use std::convert::TryInto;
-use std::str::from_utf8;
-use sanskrit_core::model::Module;
-use crate::convert_error;
-use ed25519_dalek::SIGNATURE_LENGTH;
-use sanskrit_runtime::system::SystemContext;
-use sanskrit_runtime::direct_stored::SystemDataManager;
-use sanskrit_default_externals::crypto::{raw_plain_hash, raw_join_hash};
-use crate::compiler::CompilerInstance;
-use sanskrit_interpreter::interpreter::InterpreterResult;
-use rand::prelude::*;
-use rand_chacha::ChaCha8Rng;
-
-pub struct Tx {
-    pub desc:Hash,
-    pub params:Vec<Param>,
-    pub returns:Vec<Ret>
-}
-
-pub enum Param {
-    Lit(Vec<u8>),
-    Sig(String),       //Signs with account with id x
-    Pk(String),        //Produces a Pk literal for account with id x
-    Subject(String),   //Produces a Subject literal for account with id x
-    Consume(Hash),
-    Borrow(Hash),
-    Copy(Hash),
-    LocalConsume(String),
-    LocalBorrow(String),
-    LocalCopy(String),
-    Provided
-}
-
-pub enum Ret {
-    Log,
-    Elem,
-    Drop,
-    Assign(String),
-}
-
-#[derive(Clone)]
-pub struct ExecutionState {
-    pub consumed_elems:BTreeSet<String>,
-    pub produced_elems:BTreeMap<String, (Hash,String)>,
-    pub param_names:VecDeque<String>,
-    pub return_names:VecDeque<String>,
-    pub success:bool,
-}
-
-//NOTE: THIS WORKS ONLY WITH A SINGLE TXT
-// IF WE HAVE FULL BUNDLE IT NEEDS TO BE IMPROVED
-pub struct TrackingState {
-    pub exec_state:ExecutionState,
-    pub active_elems:Db,
-    pub element_data:Db,
-    pub data_names:Db,
-}
-
-pub struct State {
-    pub csprng: ChaCha8Rng,
-    pub store: CachedStore<Module,SledStore>,
-    pub accounts: Db,
-    pub system_entries: Db,
-    pub module_name_mapping: Db,
-    pub transaction_name_mapping: Db,
-    pub tracking: TrackingState,
-    pub meta_data: Db,
-}
-
-
-#[derive(Debug, Parsable, Serializable)]
-pub struct CtrName(pub EncString, pub u8);
-#[derive(Debug, Parsable, Serializable)]
-pub struct DataNames(pub EncString, pub Vec<CtrName>);
-#[derive(Debug, Parsable, Serializable)]
-pub struct ModuleNames(pub EncString, pub Vec<DataNames>);
-
-#[derive(Debug)]
-pub struct EncString(pub String);
-impl<'a> Parsable<'a> for EncString{
-    fn parse<A: ParserAllocator>(p: &mut Parser, alloc:&'a A) -> Result<Self> {
-        let len = u16::parse(p,alloc)?;
-        let string_data = p.consume_bytes(len as usize)?;
-        Ok(EncString(convert_error(String::from_utf8(string_data.to_owned()))?))
-    }
-}
-
-impl Serializable for EncString{
-    fn serialize(&self, s:&mut Serializer) -> Result<()> {
-        assert!(self.0.len() <= u16::MAX as usize);
-        (self.0.len() as u16).serialize(s)?;
-        s.produce_bytes(self.0.as_bytes());
-        Ok(())
-    }
-}
-
-impl ExecutionState {
-    pub fn new() -> Self {
-        ExecutionState {
-            consumed_elems: BTreeSet::new(),
-            produced_elems:  BTreeMap::new(),
-            param_names: VecDeque::new(),
-            return_names: VecDeque::new(),
-            success:false
-        }
-    }
-}
-
-fn pretty_print_data(value:&Entry, desc:&ValueSchema) -> String {
-    match *desc {
-        ValueSchema::Adt(_,ctrs) => {
-            let Adt(tag, fields) = unsafe {value.adt};
-
-            //if their are zero fields we omit the fields
-            let ctr =  ctrs[tag as usize];
-            assert_eq!(fields.len(), ctr.len());
-            let mut string = String::new();
-            if ctr.len() != 1 {
-                string.push_str(&format!("({}|", tag))
-            } else {
-                string.push_str(&format!("(|"))
-            }
-            let mut first = true;
-            for (f_value, (_,f_schema)) in fields.iter().zip(ctr.iter()) {
-                if !first {
-                    string.push_str(", ")
-                }
-                string.push_str(&pretty_print_data(f_value, f_schema));
-                first = false;
-            }
-            string.push_str(")");
-            string
-        },
-        ValueSchema::Data(_) =>  format!("0x{}",encode(unsafe {value.data}.deref())),
-        ValueSchema::Unsigned(1) => format!("{}",unsafe {value.u8}),
-        ValueSchema::Unsigned(2) => format!("{}",unsafe {value.u16}),
-        ValueSchema::Unsigned(4) => format!("{}",unsafe {value.u32}),
-        ValueSchema::Unsigned(8) => format!("{}",unsafe {value.u64}),
-        ValueSchema::Unsigned(16) => format!("{}",unsafe {value.u128}),
-        ValueSchema::Signed(1) => format!("{}",unsafe {value.i8}),
-        ValueSchema::Signed(2) => format!("{}",unsafe {value.i16}),
-        ValueSchema::Signed(4) => format!("{}",unsafe {value.i32}),
-        ValueSchema::Signed(8) => format!("{}",unsafe {value.i64}),
-        ValueSchema::Signed(16) => format!("{}",unsafe {value.i128}),
-        _ => unreachable!()
-    }
-}
-
-
-impl Tracker for TrackingState {
-    fn block_start(&mut self, _block_no: u64) {  }
-    fn bundle_start<T:TransactionBundle>(&mut self, _bundle: &T) { }
-    fn section_start(&mut self, _section: &BundleSection) {  }
-    fn transaction_start(&mut self, _transaction: &Transaction) { }
-    fn parameter_load(&mut self, p_ref: &ParamRef, _p_desc: &TxTParam, _value: &Entry) {
-        let name = self.exec_state.param_names.pop_front().unwrap();
-        match p_ref {
-            ParamRef::Load(ParamMode::Consume, _) => {
-                self.exec_state.consumed_elems.insert(name);
-            },
-            _ => {},
-        };
-    }
-
-    fn return_value(&mut self, r_typ:&RetType, r_desc:&TxTReturn, value:&Entry){
-        let name = self.exec_state.return_names.pop_front().unwrap();
-        match r_typ {
-            RetType::Store => {
-                let id = hash_from_slice(unsafe {value.adt.1.get(0).unwrap().data.deref()});
-                let pretty = pretty_print_data(value, &r_desc.desc);
-                self.exec_state.produced_elems.insert(name, (id, pretty));
-            },
-            RetType::Put(_) => {}
-            RetType::Drop => {},
-            RetType::Log => {},
-        };
-    }
-
-    fn transaction_finish(&mut self, _transaction: &Transaction, _success: bool) { }
-    fn section_finish(&mut self, _section: &BundleSection, success: bool) {
-        self.exec_state.success = success;
-        if success  {
-            for elem in &self.exec_state.consumed_elems {
-                self.active_elems.remove(elem).unwrap();
-                self.element_data.remove(elem).unwrap();
-            }
-            for (name,(id,pretty)) in &self.exec_state.produced_elems {
-                let data = pretty.clone().into_bytes();
-                match self.active_elems.insert(name.clone(), id) {
-                    Ok(None) => {},
-                    Ok(Some(_)) => {}
-                    Err(x) => Err(x).unwrap(),
-                }
-                self.element_data.insert(name.clone(), data).unwrap();
-            }
-            self.exec_state.consumed_elems = BTreeSet::new();
-            self.exec_state.produced_elems = BTreeMap::new();
-        }
-    }
-    fn bundle_finish<T:TransactionBundle>(&mut self, _bundle: &T, _success: bool) { }
-    fn block_finish(&mut self, _block_no: u64, _success: bool) {
-        self.active_elems.flush().unwrap();
-        self.element_data.flush().unwrap();
-    }
-}
-
-const MAX_PARSE_DEPTH:usize = 1024;
-
-impl State {
-
-    pub fn verify_bundle(&mut self, txt_bundle:&BundleWithHash, block_no:u64, heap:&Heap) -> Result<()> {
-        //Todo: a wasm version would probably have its own heap internally
-        let ctx = Context {
-            store: &self.store,
-            txt_bundle
-        };
-        verify::<ServerSystem>(&ctx,block_no, &heap)
-    }
-
-    //Todo: can we have a wasm version of this
-    //      Can we bundle runtime together with compile?
-    pub fn execute_bundle(&mut self, txt_bundle:&BundleWithHash, block_no:u64, heap:&Heap, commit:bool) -> InterpreterResult {
-        //Todo: a wasm version would probably have its own heap internally
-        let ctx = Context {
-            store: &self.store,
-            txt_bundle
-        };
-        execute::<_, ServerSystem>(ctx,block_no, &heap, &mut self.tracking, commit)
-    }
-
-    pub fn execute_deploy(&mut self, bundle:&[u8], system_mode_on:bool) -> Result<Hash> {
-        let heap = Heap::new(CONFIG.calc_heap_size(2),2.0);
-        deploy::<_, ServerExternals>(&self.store, &bundle, &heap, system_mode_on)
-    }
-
-    pub fn deploy_module(&mut self, _compiler:&mut CompilerInstance, module:Vec<u8>, system_mode_on:bool, _system_id:Option<u8>) -> Result<Hash> {
-        let now = Instant::now();
-        let hash1 = store_hash(&[&module]);
-        //if !self.store.contains(StorageClass::Module, &hash1){
-        let txt = DeployTransaction{
-            typ: DeployType::Module,
-            data: SlicePtr::wrap(&module)
-        };
-        let mut s = Serializer::new(usize::max_value());
-        txt.serialize(&mut s)?;
-        self.execute_deploy(&s.extract(), system_mode_on)?;
-        let end = now.elapsed().as_micros();
-        self.store.flush(StorageClass::Module);
-        let flush_end = now.elapsed().as_micros();
-        println!("deployed Module with hash {:?} of size {:?} in {:?} us", encode(&hash1),module.len(),end);
-        //println!("module {:?} {:?} {:?} {:?}", encode(&hash1),module.len(),end, flush_end);
-        //}
-        Ok(hash1)
-    }
-
-    pub fn deploy_transaction(&mut self, _compiler:&mut CompilerInstance, transaction:Vec<u8>) -> Result<(Hash,Hash)> {
-        let now = Instant::now();
-        let hash = store_hash(&[&transaction]);
-        let txt = DeployTransaction{
-            typ: DeployType::Transaction,
-            data: SlicePtr::wrap(&transaction)
-        };
-        let mut s = Serializer::new(usize::max_value());
-        txt.serialize(&mut s)?;
-        let t_hash = self.execute_deploy(&s.extract(), false)?;
-        let end = now.elapsed().as_micros();
-        println!("deployed transaction with hash {:?} of size {:?} in {:?} us",  encode(&hash), transaction.len(), end);
-        self.store.flush(StorageClass::Transaction);
-        self.store.flush(StorageClass::Descriptor);
-        let flush_end = now.elapsed().as_micros();
-        //println!("transaction {:?} {:?} {:?} {:?}",  encode(&hash), transaction.len(), end, flush_end);
-        let desc_size = self.store.get( StorageClass::Descriptor, &t_hash,|d|d.len())?;
-        println!("  - resulting descriptor has hash {:?} and size {:?}", encode(&t_hash), desc_size);
-
-        Ok((hash,t_hash))
-    }
-
-
-    fn build_core<'c>(
-        gas:u64,
-        param_heap_limit:u16,
-        descs:&[TransactionDescriptor],
-        sections:SlicePtr<'c, BundleSection<'c>>,
-        descriptors:SlicePtr<'c, Hash>,
-        stored: SlicePtr<'c, Hash>,
-        literal:SlicePtr<'c, SlicePtr<'c,u8>>,
-        scratch_pad_limit:u8,
-        meta:SlicePtr<'c, u8>,
-        earliest_block:u64,
-    ) -> TransactionBundleCore<'c> {
-        let mut transaction_heap_limit = SlicePtr::<TransactionDescriptor>::SIZE as u32;
-        let mut stack_elem_limit:u16 = 0;
-        let mut stack_frame_limit:u16 = 0;
-        let mut runtime_heap_limit:u16 = 0;
-        for txt_desc in descs {
-            transaction_heap_limit = transaction_heap_limit.add(txt_desc.virt_size.unwrap() as u32);
-            stack_elem_limit = stack_elem_limit.max(txt_desc.max_stack);
-            stack_frame_limit = stack_frame_limit.max(txt_desc.max_frames);
-            runtime_heap_limit = runtime_heap_limit.max(txt_desc.max_mem);
-        }
-
-        TransactionBundleCore {
-            //The correct info will be filled in later
-            essential_gas_cost: gas,
-            total_gas_cost: gas,
-
-            byte_size: None,
-            meta,
-            earliest_block,
-            param_heap_limit,
-            //This is to low why -- oh I see, this is byte size we need parsed size -- can we inject in derive -- ?
-            // Alla alloc Size??
-            scratch_pad_limit,
-            transaction_heap_limit,
-            stack_elem_limit,
-            stack_frame_limit,
-            runtime_heap_limit,
-
-            sections,
-            descriptors,
-            stored,
-            literal,
-        }
-    }
-
-    fn print_bundle_stats(bundle:&BaseTransactionBundle){
-        println!("Transaction Bundle Stats:");
-        println!("Size: {} byte", bundle.byte_size.unwrap());
-        println!("Essential gas: {}", bundle.core.essential_gas_cost);
-        println!("Total gas: {}", bundle.core.total_gas_cost);
-        println!("Virtual runtime heap memory required: {} bytes", bundle.core.runtime_heap_limit);
-        println!("Virtual transaction storage memory required: {} bytes", bundle.core.transaction_heap_limit);
-        println!("Maximum stack slots: {} (required memory: {} bytes)", bundle.core.stack_elem_limit, ( bundle.core.stack_elem_limit as usize) * Entry::SIZE);
-        println!("Maximum frame slots: {} (required memory: ~{} bytes)", bundle.core.stack_frame_limit, ( bundle.core.stack_frame_limit as usize) * (5*8));
-    }
-
-
-    pub fn bench_transaction(&mut self, txts:&[Tx]) -> Result<()> {
-        let n = 5000;
-        //todo make softer exits
-        //todo: improve overall heap management only alloc 1 Heap
-        //2*Because heap is not reset between verify and execute
-        let block_no = match convert_error(self.meta_data.get("block_no"))? {
-            None => 0,
-            Some(val) => Parser::parse_fully(&val, 1, &NoCustomAlloc())?
-        };
-        let mut heap = Heap::new(2*CONFIG.calc_heap_size(2),2.0);
-        let mut verify_elapsed = 0;
-        let mut elapsed = 0;
-        let mut size = 0;
-        let base_exec_state = self.tracking.exec_state.clone();
-        for i in 0..n {
-            self.store.rollback(StorageClass::EntryValue);
-            self.store.rollback(StorageClass::EntryHash);
-            self.tracking.exec_state = base_exec_state.clone();
-            let full_heap = heap.new_virtual_arena(100000 as usize);
-            let (_,bundle) = self.build_transactions(txts, &full_heap, block_no+i)?;
-            let ser = Serializer::serialize_fully(&bundle,MAX_PARSE_DEPTH)?;
-            size = ser.len();
-            heap = heap.reuse();
-            self.tracking.block_start(block_no+i);
-            let now = Instant::now();
-            let txt_bundle_alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
-            let bundle = ServerSystem::parse_bundle(&ser,&txt_bundle_alloc)?;
-            self.verify_bundle(&bundle, block_no+i, &heap)?;
-            verify_elapsed += now.elapsed().as_micros();
-            self.execute_bundle(&bundle,block_no+i, &heap, false)?;
-            elapsed += now.elapsed().as_micros();
-            self.tracking.block_finish(block_no+i, true);
-            heap = heap.reuse();
-        }
-        self.store.commit(StorageClass::EntryValue);
-        self.store.commit(StorageClass::EntryHash);
-        //we flush manually as this would be done once per block and not per txt
-        let parse = (verify_elapsed as f64)/(n as f64);
-        let exact = (elapsed as f64)/(n as f64);
-        let now = Instant::now();
-        self.store.flush(StorageClass::EntryValue);
-        self.store.flush(StorageClass::EntryHash);
-        println!("Bundle executed in {}us and flushed in {}us", exact , now.elapsed().as_micros() as f64);
-        //println!("Bundle {} {} {} {}", size, parse, exact ,exact + now.elapsed().as_millis() as f64);
-        let new_block_no = block_no+n;
-        convert_error(self.meta_data.insert("block_no", Serializer::serialize_fully(&new_block_no, 1)?))?;
-        convert_error(self.meta_data.flush())?;
-        Ok(())
-    }
-
-    pub fn execute_transaction(&mut self, txts:&[Tx]) -> Result<()> {
-        //todo make softer exits
-        //todo: improve overall heap management only alloc 1 Heap
-        //2*Because heap is not reset between verify and execute
-        let mut heap = Heap::new(2*CONFIG.calc_heap_size(2),2.0);
-        let full_heap = heap.new_virtual_arena(100000 as usize);
-        let block_no = match convert_error(self.meta_data.get("block_no"))? {
-            None => 0,
-            Some(val) => Parser::parse_fully(&val, 1, &NoCustomAlloc())?
-        };
-        let (exec_gas,bundle) = self.build_transactions(txts, &full_heap, block_no)?;
-        #[cfg(feature = "dynamic_gas")]
-        let total_gas = bundle.core.total_gas_cost;
-        Self::print_bundle_stats(&bundle);
-        let ser = Serializer::serialize_fully(&bundle,MAX_PARSE_DEPTH)?;
-        heap = heap.reuse();
-        self.tracking.block_start(block_no);
-        println!("Starting bundle execution");
-        let now = Instant::now();
-        let txt_bundle_alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
-        let bundle = ServerSystem::parse_bundle(&ser,&txt_bundle_alloc)?;
-        self.verify_bundle(&bundle,block_no, &heap)?;
-        //let t0 = now.elapsed().as_micros();
-        let res = self.execute_bundle( &bundle,block_no, &heap, true)?;
-        #[cfg(feature = "dynamic_gas")]
-        assert!(res <= total_gas);
-        #[cfg(feature = "dynamic_gas")]
-        println!("Interpreter execution used {} of the available {} gas", res, exec_gas);
-        //we flush manually as this would be done once per block and not per txt
-        let t1 = now.elapsed().as_micros();
-        self.store.flush(StorageClass::EntryValue);
-        self.store.flush(StorageClass::EntryHash);
-        println!("Bundle executed in {}us and flushed in {}us", t1, now.elapsed().as_micros());
-        //println!("Bundle {} {} {} {}", ser.len(), t0, t1, now.elapsed().as_micros());
-        self.tracking.block_finish(block_no, true);
-        let new_block_no = block_no+1;
-        convert_error(self.meta_data.insert("block_no", Serializer::serialize_fully(&new_block_no, 1)?))?;
-        convert_error(self.meta_data.flush())?;
-        Ok(())
-    }
-
-    pub fn build_transactions<'c,'h>(&mut self, txts:&[Tx], full_heap:&'c VirtualHeapArena<'h>, block_no:u64) -> Result<(u64, BaseTransactionBundle<'c>)>{
-        let mut transactions:Vec<Transaction> = Vec::with_capacity(txts.len());
-
-        //todo: build on Heap?
-        let mut lits:Vec<SlicePtr<u8>> = Vec::new();
-        let mut lit_dedup:BTreeMap<Vec<u8>,u16> = BTreeMap::new();
-
-        let mut sigs:Vec<String> = Vec::new();
-        let mut sigs_dedup:BTreeMap<String,u16> = BTreeMap::new();
-
-        let mut stores:Vec<Hash> = Vec::new();
-        let mut stores_dedup:BTreeMap<Hash,u16> = BTreeMap::new();
-
-        let mut param_heap = 0;
-        let mut ret_assigns:BTreeMap<String,u8> = BTreeMap::new();
-
-        //Todo: Later do dedup
-        let mut descs:Vec<Hash> = Vec::new();
-        let mut txt_descs:Vec<TransactionDescriptor> = Vec::new();
-        let mut desc_dedup:BTreeMap<Hash,u16> = BTreeMap::new();
-        let mut exec_gas:u64 = 0;
-
-        let mut gas = CONFIG.bundle_base_cost;
-        for Tx{ref desc, ref params, ref returns} in txts {
-
-            let txt_desc = if desc_dedup.contains_key(desc) {
-                let txt_index = desc_dedup.get(desc).unwrap();
-                &txt_descs[*txt_index as usize]
-            } else {
-                let l_desc = read_transaction_desc(desc,&self.store, full_heap)?;
-                let txt_index = txt_descs.len();
-                gas += l_desc.gas_cost as u64;
-                exec_gas += l_desc.gas_cost as u64;
-
-                txt_descs.push(l_desc);
-                descs.push(*desc);
-                desc_dedup.insert(*desc, txt_index as u16);
-                &txt_descs[txt_index]
-            };
-
-
-            if txt_desc.params.len() != params.len() {
-                return owned_error(||format!("Expected {} params, provided {}",txt_desc.params.len(), params.len()));
-            }
-            if txt_desc.returns.len() != returns.len() {
-                return owned_error(||format!("Expected {} returns, received {}",txt_desc.returns.len(), returns.len()))
-            }
-
-            let mut txt_params:Vec<ParamRef> = Vec::with_capacity(params.len());
-
-            gas += CONFIG.entry_load_cost.compute(txt_desc.byte_size.unwrap() as u64);
-
-            for (p, txt_p) in params.iter().zip(txt_desc.params.iter()) {
-                match p {
-                    Param::Lit(data) => {
-                        let max_size = txt_p.desc.max_runtime_size()?;
-                        param_heap += max_size as usize;
-                        if lit_dedup.contains_key(data) {
-                            txt_params.push(ParamRef::Literal(*lit_dedup.get(data).unwrap()))
-                        } else {
-                            gas += CONFIG.parsing_cost.compute(max_size as u64);
-                            lits.push(full_heap.copy_alloc_slice(&data)?);
-                            lit_dedup.insert(data.clone(), (lits.len()-1) as u16);
-                            txt_params.push(ParamRef::Literal((lits.len()-1) as u16))
-                        }
-                    },
-
-                    Param::Pk(account) => {
-                        let max_size = txt_p.desc.max_runtime_size()?;
-                        param_heap += max_size as usize;
-                        let data = self.get_account(account)?.verifying_key().to_bytes().to_vec();
-                        if lit_dedup.contains_key(&data) {
-                            txt_params.push(ParamRef::Literal(*lit_dedup.get(&data).unwrap()))
-                        } else {
-                            gas += CONFIG.parsing_cost.compute(max_size as u64);
-                            lits.push(full_heap.copy_alloc_slice(&data)?);
-                            lit_dedup.insert(data, (lits.len()-1) as u16);
-                            txt_params.push(ParamRef::Literal((lits.len()-1) as u16))
-                        }
-                    },
-
-                    Param::Subject(account) => {
-                        let max_size = txt_p.desc.max_runtime_size()?;
-                        param_heap += max_size as usize;
-                        let pk = self.get_account(account)?.verifying_key().to_bytes().to_vec();
-                        //compute the edDsaSubject
-                        let subject = Self::calc_subject(&pk,&full_heap)?;
-                        //vectorized to store in lit dedup
-                        let data = subject.to_vec();
-                        if lit_dedup.contains_key(&data) {
-                            txt_params.push(ParamRef::Literal(*lit_dedup.get(&data).unwrap()))
-                        } else {
-                            gas += CONFIG.parsing_cost.compute(max_size as u64);
-                            lits.push(subject);
-                            lit_dedup.insert(data, (lits.len()-1) as u16);
-                            txt_params.push(ParamRef::Literal((lits.len()-1) as u16))
-                        }
-                    },
-
-                    Param::Sig(account) => {
-                        let max_size = txt_p.desc.max_runtime_size()?;
-                        param_heap += max_size as usize;
-                        if sigs_dedup.contains_key(account) {
-                            txt_params.push(ParamRef::Witness(*sigs_dedup.get(account).unwrap()))
-                        } else {
-                            gas += CONFIG.parsing_cost.compute(max_size as u64);
-                            sigs.push(account.clone());
-                            sigs_dedup.insert(account.clone(), (sigs.len()-1) as u16);
-                            txt_params.push(ParamRef::Witness((sigs.len()-1) as u16))
-                        }
-                    }
-
-                    Param::Provided => {
-                        gas += ServerSystemDataManager::providable_gas(txt_p.typ)?;
-                        param_heap += ServerSystemDataManager::providable_size(txt_p.typ)? as usize;
-                        txt_params.push(ParamRef::Provided)
-                    },
-
-                    //todo: share common stuff
-                    Param::Consume(id) => {
-                        param_heap += txt_p.desc.max_runtime_size()? as usize;
-                        if stores_dedup.contains_key(id) {
-                            txt_params.push(ParamRef::Load(ParamMode::Consume,*stores_dedup.get(id).unwrap()))
-                        } else {
-                            gas += CONFIG.entry_load_cost.compute(txt_p.desc.max_serialized_size() as u64);
-                            gas += CONFIG.entry_store_cost.compute(0);
-                            stores.push(id.clone());
-                            stores_dedup.insert(id.clone(), (stores.len()-1) as u16);
-                            txt_params.push(ParamRef::Load(ParamMode::Consume,(stores.len()-1) as u16))
-                        }
-                    },
-                    Param::Borrow(id) => {
-                        param_heap += txt_p.desc.max_runtime_size()? as usize;
-                        if stores_dedup.contains_key(id) {
-                            txt_params.push(ParamRef::Load(ParamMode::Borrow,*stores_dedup.get(id).unwrap()))
-                        } else {
-                            gas += CONFIG.entry_load_cost.compute(txt_p.desc.max_serialized_size() as u64);
-                            stores.push(id.clone());
-                            stores_dedup.insert(id.clone(), (stores.len()-1) as u16);
-                            txt_params.push(ParamRef::Load(ParamMode::Borrow,(stores.len()-1) as u16))
-                        }
-                    },
-                    Param::Copy(id) => {
-                        param_heap += txt_p.desc.max_runtime_size()? as usize;
-                        if stores_dedup.contains_key(id) {
-                            txt_params.push(ParamRef::Load(ParamMode::Copy,*stores_dedup.get(id).unwrap()))
-                        } else {
-                            gas += CONFIG.entry_load_cost.compute(txt_p.desc.max_serialized_size() as u64);
-                            stores.push(id.clone());
-                            stores_dedup.insert(id.clone(), (stores.len()-1) as u16);
-                            txt_params.push(ParamRef::Load(ParamMode::Copy,(stores.len()-1) as u16))
-                        }
-                    }
-
-                    Param::LocalConsume(key) => {
-                        if !ret_assigns.contains_key(key) {
-                            return error(||"Element name unknown")
-                        }
-                        txt_params.push(ParamRef::Fetch(ParamMode::Consume,*ret_assigns.get(key).unwrap() as u8))
-
-                    }
-                    Param::LocalBorrow(key) => {
-                        if !ret_assigns.contains_key(key) {
-                            return error(||"Element name unknown")
-                        }
-                        txt_params.push(ParamRef::Fetch(ParamMode::Borrow,*ret_assigns.get(key).unwrap() as u8))
-                    }
-                    Param::LocalCopy(key) => {
-                        if !ret_assigns.contains_key(key) {
-                            return error(||"Element name unknown")
-                        }
-                        txt_params.push(ParamRef::Fetch(ParamMode::Copy,*ret_assigns.get(key).unwrap() as u8))
-                    }
-                }
-            }
-
-            let mut txt_rets:Vec<RetType> = Vec::with_capacity(returns.len());
-            for (r, txt_r) in returns.iter().zip(txt_desc.returns.iter()) {
-                match r {
-                    Ret::Log => {
-                        //Logs may cost something in the future
-                        txt_rets.push(RetType::Log)
-                    },
-                    Ret::Assign(name) => {
-                        if !ret_assigns.contains_key(name) {
-                            ret_assigns.insert(name.clone(), ret_assigns.len() as u8);
-                        }
-                        txt_rets.push(RetType::Put(*ret_assigns.get(name).unwrap()));
-                        let runtime_size = txt_r.desc.max_runtime_size()?;
-                        gas += CONFIG.copy_cost.compute(runtime_size as u64);
-                        param_heap += runtime_size as usize;
-                    }
-
-                    Ret::Elem => {
-                        gas += CONFIG.entry_store_cost.compute(txt_r.desc.max_serialized_size() as u64);
-                        txt_rets.push(RetType::Store)
-                    },
-                    Ret::Drop => txt_rets.push(RetType::Drop),
-                }
-            }
-
-            transactions.push(Transaction {
-                txt_desc: (descs.len()-1) as u16,
-                params: full_heap.copy_alloc_slice(&txt_params)?,
-                returns: full_heap.copy_alloc_slice(&txt_rets)?,
-            });
-        }
-
-        //Todo: Allow many Sections
-        let section = BundleSection {
-            typ: SectionType::Essential,
-            txts: full_heap.copy_alloc_slice(&transactions)?
-        };
-
-        let meta = Serializer::serialize_fully(&block_no,1).unwrap();
-
-        let bundle_core = Self::build_core(
-            0,
-            param_heap as u16,
-            &txt_descs,
-            full_heap.copy_alloc_slice(&[section])?,
-            full_heap.copy_alloc_slice(&descs)?,
-            full_heap.copy_alloc_slice(&stores)?,
-            full_heap.copy_alloc_slice(&lits)?,
-             ret_assigns.len() as u8,
-            full_heap.copy_alloc_slice(&meta)?,
-            block_no
-        );
-
-        let bundle_core_data = &Serializer::serialize_fully(&bundle_core,MAX_PARSE_DEPTH)?;  //todo: is the max here correct
-        //this is not nice but the cyclic dependency requires it
-        let mut core_reparsed:TransactionBundleCore = Parser::parse_fully(&bundle_core_data,MAX_PARSE_DEPTH, full_heap)?;  //todo: is the max here correct
-        let witness_size = (sigs.len() * (SIGNATURE_LENGTH + 2)) + 2;
-        let full_size = core_reparsed.byte_size.unwrap() + witness_size;
-
-        gas += CONFIG.parsing_cost.compute(full_size as u64);
-        core_reparsed.essential_gas_cost = gas;
-        core_reparsed.total_gas_cost = gas;
-
-        let bundle_hash = HashingDomain::Bundle.hash(&Serializer::serialize_fully(&core_reparsed,MAX_PARSE_DEPTH)?); //todo: is the max here correct
-
-        let mut witness:Vec<SlicePtr<u8>> = Vec::with_capacity(sigs.len());
-
-        for w in &sigs {
-            let keys = self.get_account(w)?;
-            let signature: Signature = keys.sign(&bundle_hash);
-            witness.push(full_heap.copy_alloc_slice(&signature.to_bytes())?);
-        };
-        let witness_slice  = full_heap.copy_alloc_slice(&witness)?;
-        //Todo: only compute and return exec gas in dynamic case
-        Ok((exec_gas, BaseTransactionBundle {
-            byte_size: Some(core_reparsed.byte_size.unwrap() + witness_slice.len()*(64+2) +2), //cheat here a bit so we can do stats
-            core: core_reparsed,
-            witness: witness_slice,
-        }))
-    }
-
-    pub fn get_account(&mut self, ident:&str) -> Result<SigningKey> {
-        let key = ident.as_bytes();
-        if convert_error(self.accounts.contains_key(&key))? {
-            let sig_key:&[u8] = &convert_error(self.accounts.get(&key))?.unwrap();
-            if sig_key.len() != SECRET_KEY_LENGTH {
-                return owned_error(||format!("Wrong Key Size: {} vs. {}",sig_key.len(), SECRET_KEY_LENGTH));
-            }
-            return Ok(SigningKey::from_bytes(sig_key.try_into().unwrap()))
-        }
-        let kp = SigningKey::generate(&mut self.csprng);
-        convert_error(self.accounts.insert(key,kp.to_bytes().to_vec()))?;
-        convert_error(self.accounts.flush())?;
-        return Ok(kp);
-    }
-
-    pub fn get_accounts(&mut self) -> Result<Vec<(String,SigningKey)>> {
-        let mut res = Vec::with_capacity(self.accounts.len());
-        for account in self.accounts.iter() {
-            let (name_bytes,key_bytes) = convert_error(account)?;
-            let name = convert_error(from_utf8(&name_bytes))?;
-            let sig_key:&[u8] = &key_bytes;
-            if sig_key.len() != SECRET_KEY_LENGTH {
-                return owned_error(||format!("Wrong Key Size: {} vs. {}",sig_key.len(), SECRET_KEY_LENGTH));
-            }
-            let sig_key = SigningKey::from_bytes(sig_key.try_into().unwrap());
-            res.push((name.to_owned(), sig_key))
-        }
-        return Ok(res)
-    }
-
-    pub fn get_elems(&mut self) -> Result<Vec<(String,String)>> {
-        let mut res = Vec::with_capacity(self.tracking.element_data.len());
-        for account in self.tracking.element_data.iter() {
-            let (name_bytes,data_bytes) = convert_error(account)?;
-            let name = convert_error(from_utf8(&name_bytes))?;
-            let data = convert_error(from_utf8(&data_bytes))?;
-            res.push((name.to_owned(), data.to_owned()))
-        }
-        return Ok(res)
-    }
-
-    pub fn get_elem(&mut self, ident:&str) -> Result<String> {
-        let data_bytes = convert_error(self.tracking.element_data.get(ident))?.unwrap();
-        let data = convert_error(from_utf8(&data_bytes))?;
-        return Ok(data.to_owned())
-    }
-
-    pub fn get_transactions(&mut self) -> Result<Vec<String>> {
-        let mut res = Vec::with_capacity(self.transaction_name_mapping.len());
-        for transaction in self.transaction_name_mapping.iter() {
-            let (name_bytes,_) = convert_error(transaction)?;
-            let name = convert_error(from_utf8(&name_bytes))?;
-            res.push(name.to_owned())
-        }
-        return Ok(res)
-    }
-
-    pub fn get_transaction<'a:'b, 'b, A:ParserAllocator>(&'b mut self, ident:&str,  heap:&'a A) -> Result<TransactionDescriptor> {
-        let hash_bytes = convert_error(self.transaction_name_mapping.get(ident))?.unwrap();
-        let id = hash_from_slice(&hash_bytes);
-        read_transaction_desc(&id, &self.store,  heap)
-    }
-
-    pub fn get_modules(&mut self) -> Result<Vec<String>> {
-        let mut res = Vec::with_capacity(self.module_name_mapping.len());
-        for module in self.module_name_mapping.iter() {
-            let (name_bytes,_) = convert_error(module)?;
-            let name = convert_error(from_utf8(&name_bytes))?;
-            res.push(name.to_owned())
-        }
-        return Ok(res)
-    }
-
-    pub fn get_module<'a:'b, 'b, A:ParserAllocator>(&'b mut self, ident:&str,  heap:&'a A) -> Result<Module> {
-        let hash_bytes = convert_error(self.module_name_mapping.get(ident))?.unwrap();
-        let id = hash_from_slice(&hash_bytes);
-        self.store.parsed_get(StorageClass::Module, &id, CONFIG.max_structural_dept, heap)
-    }
-
-    pub fn calc_subject<'a,'h>(pk:&[u8], full_heap:&'a VirtualHeapArena<'h>) -> Result<SlicePtr<'a,u8>>{
-        //compute the edDsaSubject
-        let id = raw_plain_hash(pk, &full_heap)?;
-        //compute the subject Manager Subject
-        raw_join_hash(&get_ed_dsa_module(), &id, HashingDomain::Derive, &full_heap)
-    }
-
-}
-
+//extern crate blake2_rfc;
+use sanskrit_sled_store::SledStore;
+use sled::Db;
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use sanskrit_runtime::{execute, Tracker, CONFIG, read_transaction_desc, Context, verify, TransactionBundle, BlockInfo};
+use sanskrit_runtime::deploy;
+use sanskrit_runtime::dry_run::{dry_run, DeployReport};
+use sanskrit_compile::bounds::{module_bounds, CallableBounds};
+use sanskrit_common::limiter::Limiter;
+use sanskrit_common::store::*;
+use sanskrit_common::archive::{export_state, import_state};
+use sanskrit_common::encoding::*;
+use sanskrit_common::model::*;
+use sanskrit_common::errors::*;
+
+use ed25519_dalek::{SigningKey, SECRET_KEY_LENGTH};
+
+use hex::encode;
+use sanskrit_common::arena::{Heap, VirtualHeapArena};
+use sanskrit_common::hashing::HashingDomain;
+
+use sanskrit_runtime::model::{ParamRef, ParamMode, RetType, BundleSection, SectionType, Transaction, BaseTransactionBundle, BundleWithHash};
+use sanskrit_runtime::model::{DeployTransaction, DeployType, DeployPackage};
+use sanskrit_interpreter::model::{Entry, TxTParam, TxTReturn, TransactionDescriptor, ValueSchema, Adt};
+use crate::externals::{ServerSystem, ServerSystemDataManager, get_ed_dsa_module};
+use sanskrit_default_externals::{ServerExternals, SYS_MODS};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::ops::Deref;
+use std::convert::TryInto;
+use std::str::from_utf8;
+use std::path::Path;
+use sanskrit_core::model::Module;
+use sanskrit_core::model::display::Names;
+use sanskrit_core::metadata::{ModuleMetadata, DataMetadata, CtrMetadata, Name};
+use sanskrit_core::abi::export_abi;
+use sanskrit_interpreter::value_encoding::json::ValueNames;
+use crate::convert_error;
+use sanskrit_runtime::system::SystemContext;
+use sanskrit_default_externals::crypto::{raw_plain_hash, raw_join_hash};
+use crate::compiler::CompilerInstance;
+use crate::blocks::{Mempool, PendingBundle, BlockLog, BlockReport, LogEntry, LoggedBlock, LoggedDeploy};
+use sanskrit_client::partial::BundleData;
+use sanskrit_client::{BundleBuilder, Input, Output, Slot, WitnessRef};
+use sanskrit_interpreter::interpreter::InterpreterResult;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use ethnum::U256;
+
+pub struct Tx {
+    pub desc:Hash,
+    pub params:Vec<Param>,
+    pub returns:Vec<Ret>
+}
+
+pub enum Param {
+    Lit(Vec<u8>),
+    Sig(String),       //Signs with account with id x
+    Pk(String),        //Produces a Pk literal for account with id x
+    Subject(String),   //Produces a Subject literal for account with id x
+    Consume(Hash),
+    Borrow(Hash),
+    Copy(Hash),
+    LocalConsume(String),
+    LocalBorrow(String),
+    LocalCopy(String),
+    Provided
+}
+
+pub enum Ret {
+    Log,
+    Elem,
+    Drop,
+    Assign(String),
+    Fee,
+}
+
+#[derive(Clone)]
+pub struct ExecutionState {
+    pub consumed_elems:BTreeSet<String>,
+    pub produced_elems:BTreeMap<String, (Hash,String)>,
+    pub param_names:VecDeque<String>,
+    pub return_names:VecDeque<String>,
+    pub success:bool,
+    //the essential section was committed, so the fee is paid even if a later section fails
+    pub paid:bool,
+    pub used_gas:u64,
+    pub refunded_gas:u64,
+    //modules and descriptors deployed by the committed sections of the bundle
+    pub deployed:Vec<Hash>,
+    //modules and descriptors deployed by the current section, they are dropped if it fails
+    pub section_deployed:Vec<Hash>,
+    //the schemas of the entries stored by the bundle and the entries it consumed (by id), used to show entries as json
+    pub stored_schemas:BTreeMap<Hash, Vec<u8>>,
+    pub consumed_ids:BTreeSet<Hash>,
+}
+
+//NOTE: THIS WORKS ONLY WITH A SINGLE TXT
+// IF WE HAVE FULL BUNDLE IT NEEDS TO BE IMPROVED
+pub struct TrackingState {
+    pub exec_state:ExecutionState,
+    pub active_elems:Db,
+    pub element_data:Db,
+    pub data_names:Db,
+    //the serialized schema of each stored entry by id (for entries stored by bundles executed on this node)
+    pub entry_schemas:Db,
+}
+
+pub struct State {
+    pub csprng: ChaCha8Rng,
+    pub store: SledStore,
+    pub accounts: Db,
+    pub system_entries: Db,
+    pub module_name_mapping: Db,
+    pub transaction_name_mapping: Db,
+    pub tracking: TrackingState,
+    pub meta_data: Db,
+    pub receipts: Db,
+    //the receipt of the bundle executed most recently
+    pub last_receipt: Option<Receipt>,
+    pub mempool: Mempool,
+    pub block_log: BlockLog,
+    //the gas price of the blocks produced by this node (--gas-price)
+    pub gas_price: u64,
+}
+
+//The outcome of an executed bundle, kept under the bundle hash
+#[derive(Clone, Debug, Parsable, Serializable)]
+pub struct Receipt {
+    pub block_no:u64,
+    pub success:bool,
+    pub used_gas:u64,
+    pub refunded_gas:u64,
+    pub deployed:LargeVec<Hash>,
+}
+
+
+#[derive(Debug, Parsable, Serializable)]
+pub struct CtrName(pub EncString, pub u8);
+#[derive(Debug, Parsable, Serializable)]
+pub struct DataNames(pub EncString, pub Vec<CtrName>);
+#[derive(Debug, Parsable, Serializable)]
+pub struct ModuleNames(pub EncString, pub Vec<DataNames>);
+
+#[derive(Debug)]
+pub struct EncString(pub String);
+impl<'a> Parsable<'a> for EncString{
+    fn parse<A: ParserAllocator>(p: &mut Parser, alloc:&'a A) -> Result<Self> {
+        let len = u16::parse(p,alloc)?;
+        let string_data = p.consume_bytes(len as usize)?;
+        Ok(EncString(convert_error(String::from_utf8(string_data.to_owned()))?))
+    }
+}
+
+impl Serializable for EncString{
+    fn serialize(&self, s:&mut Serializer) -> Result<()> {
+        assert!(self.0.len() <= u16::MAX as usize);
+        (self.0.len() as u16).serialize(s)?;
+        s.produce_bytes(self.0.as_bytes());
+        Ok(())
+    }
+}
+
+//Names of modules and their data types as provided with their deployment
+impl Names for TrackingState {
+    fn module_name(&self, module:&Hash) -> Option<String> {
+        self.module_names(module).map(|names|(names.0).0)
+    }
+
+    fn data_name(&self, module:&Hash, offset:u8) -> Option<String> {
+        self.module_names(module)
+            .and_then(|names|names.1.into_iter().nth(offset as usize))
+            .map(|data|(data.0).0)
+    }
+}
+
+//Constructor names of the data types (field names are not provided with the deployment)
+impl ValueNames for TrackingState {
+    fn ctr_name(&self, typ:&(Hash,u8), tag:u8) -> Option<String> {
+        self.module_names(&typ.0)
+            .and_then(|names|names.1.into_iter().nth(typ.1 as usize))
+            .and_then(|data|data.1.into_iter().nth(tag as usize))
+            .map(|ctr|(ctr.0).0)
+    }
+}
+
+impl TrackingState {
+    fn module_names(&self, module:&Hash) -> Option<ModuleNames> {
+        let data = self.data_names.get(module).ok()??;
+        Parser::parse_fully(&data, 6, &NoCustomAlloc()).ok()
+    }
+
+    //The names provided with the deployments in the standard metadata layout (field, callable and generic names are not provided)
+    fn known_metadata(&self) -> BTreeMap<Hash, ModuleMetadata> {
+        let mut res = BTreeMap::new();
+        for (key, data) in self.data_names.iter().flatten() {
+            if let Ok(names) = Parser::parse_fully::<ModuleNames,_>(&data, 6, &NoCustomAlloc()) {
+                res.insert(hash_from_slice(&key), ModuleMetadata {
+                    version: ModuleMetadata::VERSION,
+                    name: Name((names.0).0),
+                    data: names.1.into_iter().map(|data| DataMetadata {
+                        name: Name((data.0).0),
+                        generics: Vec::new(),
+                        ctrs: data.1.into_iter().map(|ctr|CtrMetadata{ name: Name((ctr.0).0), fields: Vec::new() }).collect()
+                    }).collect(),
+                    sigs: Vec::new(),
+                    functions: Vec::new(),
+                    implements: Vec::new(),
+                });
+            }
+        }
+        res
+    }
+}
+
+impl ExecutionState {
+    pub fn new() -> Self {
+        ExecutionState {
+            consumed_elems: BTreeSet::new(),
+            produced_elems:  BTreeMap::new(),
+            param_names: VecDeque::new(),
+            return_names: VecDeque::new(),
+            success:false,
+            paid:false,
+            used_gas:0,
+            refunded_gas:0,
+            deployed:Vec::new(),
+            section_deployed:Vec::new(),
+            stored_schemas: BTreeMap::new(),
+            consumed_ids: BTreeSet::new(),
+        }
+    }
+}
+
+fn pretty_print_data(value:&Entry, desc:&ValueSchema) -> Result<String> {
+    Ok(match *desc {
+        ValueSchema::Adt(_,ctrs) => {
+            let Adt(tag, fields) = unsafe {value.adt};
+
+            //if their are zero fields we omit the fields
+            let ctr = match ctrs.get(tag as usize) {
+                Some(ctr) if ctr.len() == fields.len() => ctr,
+                _ => return error(||"Value does not match its schema")
+            };
+            let mut string = String::new();
+            if ctr.len() != 1 {
+                string.push_str(&format!("({}|", tag))
+            } else {
+                string.push_str(&format!("(|"))
+            }
+            let mut first = true;
+            for (f_value, (_,f_schema)) in fields.iter().zip(ctr.iter()) {
+                if !first {
+                    string.push_str(", ")
+                }
+                string.push_str(&pretty_print_data(f_value, f_schema)?);
+                first = false;
+            }
+            string.push_str(")");
+            string
+        },
+        ValueSchema::Data(_) =>  format!("0x{}",encode(unsafe {value.data}.deref())),
+        ValueSchema::Unsigned(1) => format!("{}",unsafe {value.u8}),
+        ValueSchema::Unsigned(2) => format!("{}",unsafe {value.u16}),
+        ValueSchema::Unsigned(4) => format!("{}",unsafe {value.u32}),
+        ValueSchema::Unsigned(8) => format!("{}",unsafe {value.u64}),
+        ValueSchema::Unsigned(16) => format!("{}",unsafe {value.u128}),
+        ValueSchema::Unsigned(32) => match unsafe {value.data}.deref().try_into() {
+            Ok(bytes) => format!("{}",U256::from_be_bytes(bytes)),
+            Err(_) => return error(||"U256 value must have 32 bytes")
+        },
+        ValueSchema::Signed(1) => format!("{}",unsafe {value.i8}),
+        ValueSchema::Signed(2) => format!("{}",unsafe {value.i16}),
+        ValueSchema::Signed(4) => format!("{}",unsafe {value.i32}),
+        ValueSchema::Signed(8) => format!("{}",unsafe {value.i64}),
+        ValueSchema::Signed(16) => format!("{}",unsafe {value.i128}),
+        _ => return error(||"Unsupported value schema")
+    })
+}
+
+
+//The id of a stored entry is its first field
+fn entry_id(value:&Entry) -> Option<Hash> {
+    let id = unsafe {value.adt.1.first()?.data};
+    if id.len() == HASH_SIZE { Some(hash_from_slice(&id)) } else { None }
+}
+
+impl Tracker for TrackingState {
+    fn block_start(&mut self, _block_no: u64) {  }
+    fn bundle_start<T:TransactionBundle>(&mut self, _bundle: &T) {  }
+    fn section_start(&mut self, _section: &BundleSection) {  }
+    fn transaction_start(&mut self, _transaction: &Transaction) { }
+    fn parameter_load(&mut self, p_ref: &ParamRef, _p_desc: &TxTParam, value: &Entry) {
+        //only loaded values are stored entries, literals and provided values are not adts
+        if let ParamRef::Load(ParamMode::Consume, _) = p_ref {
+            if let Some(id) = entry_id(value) {
+                self.exec_state.consumed_ids.insert(id);
+            }
+        }
+        //bundles that were not built by the repl have no names
+        let name = match self.exec_state.param_names.pop_front() {
+            Some(name) => name,
+            None => return
+        };
+        match p_ref {
+            ParamRef::Load(ParamMode::Consume, _) => {
+                self.exec_state.consumed_elems.insert(name);
+            },
+            _ => {},
+        };
+    }
+
+    fn return_value(&mut self, r_typ:&RetType, r_desc:&TxTReturn, value:&Entry){
+        if let RetType::Store = r_typ {
+            if let (Some(id), Ok(schema)) = (entry_id(value), Serializer::serialize_fully(&*r_desc.desc, CONFIG.max_structural_dept)) {
+                self.exec_state.stored_schemas.insert(id, schema);
+            }
+        }
+        let name = match self.exec_state.return_names.pop_front() {
+            Some(name) => name,
+            None => return
+        };
+        match r_typ {
+            RetType::Store => {
+                let id = hash_from_slice(unsafe {value.adt.1.get(0).unwrap().data.deref()});
+                //the tracker can not fail the bundle, so a value that can not be printed is stored with the reason
+                let pretty = pretty_print_data(value, &r_desc.desc).unwrap_or_else(|err|format!("<{}>", error_to_string(&err)));
+                self.exec_state.produced_elems.insert(name, (id, pretty));
+            },
+            RetType::Put(_) => {}
+            RetType::Drop => {},
+            RetType::Log => {},
+            RetType::Fee => {},
+        };
+    }
+
+    fn deployed(&mut self, _deploy: &DeployTransaction, hash: &Hash) {
+        self.exec_state.section_deployed.push(*hash);
+    }
+
+    fn transaction_finish(&mut self, _transaction: &Transaction, _success: bool) { }
+    fn section_finish(&mut self, section: &BundleSection, success: bool) {
+        self.exec_state.success = success;
+        let section_deployed = std::mem::take(&mut self.exec_state.section_deployed);
+        if success  {
+            if section.typ == SectionType::Essential {
+                self.exec_state.paid = true;
+            }
+            self.exec_state.deployed.extend(section_deployed);
+            for elem in &self.exec_state.consumed_elems {
+                self.active_elems.remove(elem).unwrap();
+                self.element_data.remove(elem).unwrap();
+            }
+            for (name,(id,pretty)) in &self.exec_state.produced_elems {
+                let data = pretty.clone().into_bytes();
+                match self.active_elems.insert(name.clone(), id) {
+                    Ok(None) => {},
+                    Ok(Some(_)) => {}
+                    Err(x) => Err(x).unwrap(),
+                }
+                self.element_data.insert(name.clone(), data).unwrap();
+            }
+            for id in &self.exec_state.consumed_ids {
+                self.entry_schemas.remove(id).unwrap();
+            }
+            for (id, schema) in &self.exec_state.stored_schemas {
+                self.entry_schemas.insert(id, schema.clone()).unwrap();
+            }
+            self.exec_state.consumed_elems = BTreeSet::new();
+            self.exec_state.produced_elems = BTreeMap::new();
+            self.exec_state.consumed_ids = BTreeSet::new();
+            self.exec_state.stored_schemas = BTreeMap::new();
+        }
+    }
+    fn gas_used<T:TransactionBundle>(&mut self, _bundle: &T, used_gas: u64, refunded_gas: u64) {
+        self.exec_state.used_gas = used_gas;
+        self.exec_state.refunded_gas = refunded_gas;
+    }
+    fn bundle_finish<T:TransactionBundle>(&mut self, _bundle: &T, _success: bool) { }
+    fn block_finish(&mut self, _block_no: u64, _success: bool) {
+        self.active_elems.flush().unwrap();
+        self.element_data.flush().unwrap();
+        self.entry_schemas.flush().unwrap();
+    }
+}
+
+pub const MAX_PARSE_DEPTH:usize = 1024;
+//The local server produces all blocks and collects the fees under this id
+pub const LOCAL_PRODUCER:Hash = [0; 20];
+
+impl State {
+
+    //Opens (or creates) the databases of a node in the folder
+    pub fn open(db_folder:&Path, auto_flushes:BTreeSet<StorageClass>) -> std::io::Result<State> {
+        let db = |name:&str|sled::open(db_folder.join(name).with_extension("db"));
+        Ok(State {
+            csprng: ChaCha8Rng::seed_from_u64(10), //ChaCha8Rng::from_entropy(),
+            store: SledStore::new(db_folder, auto_flushes),
+            accounts: db("accounts")?,
+            system_entries: db("system_modules")?,
+            module_name_mapping: db("module_names")?,
+            transaction_name_mapping: db("transaction_names")?,
+            tracking: TrackingState {
+                exec_state: ExecutionState::new(),
+                active_elems: db("elem_tracker")?,
+                element_data: db("data_tracker")?,
+                data_names: db("data_name_tracker")?,
+                entry_schemas: db("entry_schemas")?,
+            },
+            meta_data: db("meta_data")?,
+            receipts: db("receipts")?,
+            last_receipt: None,
+            mempool: Mempool::new(),
+            block_log: BlockLog::open(&db_folder.join("blocks").with_extension("log"))?,
+            gas_price: CONFIG.gas_price,
+        })
+    }
+
+    //A node with empty databases in the temp folder
+    #[cfg(test)]
+    pub fn temporary(name:&str) -> State {
+        let folder = std::env::temp_dir().join(format!("sanskrit_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&folder);
+        State::open(&folder, BTreeSet::new()).unwrap()
+    }
+
+    pub fn verify_bundle(&mut self, txt_bundle:&BundleWithHash, block:BlockInfo, heap:&Heap) -> Result<()> {
+        //Todo: a wasm version would probably have its own heap internally
+        let ctx = Context {
+            store: &self.store,
+            txt_bundle
+        };
+        verify::<ServerSystem>(&ctx,block, &heap)
+    }
+
+    //Todo: can we have a wasm version of this
+    //      Can we bundle runtime together with compile?
+    pub fn execute_bundle(&mut self, txt_bundle:&BundleWithHash, block:BlockInfo, heap:&Heap, commit:bool) -> InterpreterResult {
+        //Todo: a wasm version would probably have its own heap internally
+        let ctx = Context {
+            store: &self.store,
+            txt_bundle
+        };
+        execute::<_, ServerSystem>(ctx,block, &heap, &mut self.tracking, commit)
+    }
+
+    //The local server is its own block producer and uses the local time (in seconds)
+    // The last timestamp is remembered so that block timestamps never decrease
+    pub fn block_timestamp(&self) -> Result<u64> {
+        let last = match convert_error(self.meta_data.get("block_timestamp"))? {
+            None => 0,
+            Some(val) => Parser::parse_fully(&val, 1, &NoCustomAlloc())?
+        };
+        let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(dur) => dur.as_secs(),
+            Err(_) => 0
+        };
+        Ok(u64::max(now, last))
+    }
+
+    //Deploys outside of a block, successful deploys are logged so that a replay sees them
+    pub fn execute_deploy(&mut self, bundle:&[u8], system_mode_on:bool, system_id:Option<u8>) -> Result<Vec<Hash>> {
+        let heap = Heap::new(CONFIG.calc_heap_size(2),2.0);
+        let store = CachedStore::<Module,_>::new(&self.store, StorageClass::Module);
+        let hashes = deploy::<_, ServerExternals>(&store, &bundle, &heap, system_mode_on)?;
+        self.block_log.append(&LogEntry::Deploy(LoggedDeploy{
+            data: BundleData(bundle.to_vec()),
+            system_mode: system_mode_on,
+            system_id,
+            state_root: state_root(&self.store)?,
+        }))?;
+        Ok(hashes)
+    }
+
+    pub fn deploy_module(&mut self, _compiler:&mut CompilerInstance, module:Vec<u8>, system_mode_on:bool, system_id:Option<u8>) -> Result<Hash> {
+        let now = Instant::now();
+        let hash1 = store_hash(&[&module]);
+        //if !self.store.contains(StorageClass::Module, &hash1){
+        let txt = DeployTransaction{
+            gas_limit: CONFIG.max_deploy_gas,
+            typ: DeployType::Module,
+            data: SlicePtr::wrap(&module)
+        };
+        let mut s = Serializer::new(usize::max_value());
+        txt.serialize(&mut s)?;
+        self.execute_deploy(&s.extract(), system_mode_on, system_id)?;
+        let end = now.elapsed().as_micros();
+        self.store.flush(StorageClass::Module);
+        let flush_end = now.elapsed().as_micros();
+        println!("deployed Module with hash {:?} of size {:?} in {:?} us", encode(&hash1),module.len(),end);
+        //println!("module {:?} {:?} {:?} {:?}", encode(&hash1),module.len(),end, flush_end);
+        //}
+        Ok(hash1)
+    }
+
+    //Deploys a module in system mode and binds it to the system module implementation with the id (if one is given)
+    pub fn deploy_system_module(&mut self, compiler:&mut CompilerInstance, module:Vec<u8>, system_id:Option<u8>) -> Result<Hash> {
+        if let Some(sys_id) = system_id {
+            if sys_id as usize >= SYS_MODS.len() {
+                return error(||"unknown system module identifier")
+            }
+        }
+        let hash = self.deploy_module(compiler, module, true, system_id)?;
+        if let Some(sys_id) = system_id {
+            SYS_MODS[sys_id as usize](hash);
+            convert_error(self.system_entries.insert([sys_id], &hash))?;
+            convert_error(self.system_entries.flush())?;
+        }
+        Ok(hash)
+    }
+
+    pub fn deploy_transaction(&mut self, _compiler:&mut CompilerInstance, transaction:Vec<u8>) -> Result<(Hash,Hash)> {
+        let now = Instant::now();
+        let hash = store_hash(&[&transaction]);
+        let txt = DeployTransaction{
+            gas_limit: CONFIG.max_deploy_gas,
+            typ: DeployType::Transaction,
+            data: SlicePtr::wrap(&transaction)
+        };
+        let mut s = Serializer::new(usize::max_value());
+        txt.serialize(&mut s)?;
+        let t_hash = self.execute_deploy(&s.extract(), false, None)?[0];
+        let end = now.elapsed().as_micros();
+        println!("deployed transaction with hash {:?} of size {:?} in {:?} us",  encode(&hash), transaction.len(), end);
+        self.store.flush(StorageClass::Transaction);
+        self.store.flush(StorageClass::Descriptor);
+        let flush_end = now.elapsed().as_micros();
+        //println!("transaction {:?} {:?} {:?} {:?}",  encode(&hash), transaction.len(), end, flush_end);
+        let desc_size = self.store.get( StorageClass::Descriptor, &t_hash,|d|d.len())?;
+        println!("  - resulting descriptor has hash {:?} and size {:?}", encode(&t_hash), desc_size);
+
+        Ok((hash,t_hash))
+    }
+
+    //Deploys the modules (in dependency order) and the transactions together, if one fails none is deployed
+    // returns the module hashes followed by the descriptor hashes
+    pub fn deploy_package(&mut self, _compiler:&mut CompilerInstance, modules:Vec<Vec<u8>>, transactions:Vec<Vec<u8>>) -> Result<Vec<Hash>> {
+        let now = Instant::now();
+        let num_modules = modules.len();
+        let package = Serializer::serialize_fully(&DeployPackage{ modules, transactions }, MAX_PARSE_DEPTH)?;
+        let txt = DeployTransaction{
+            gas_limit: CONFIG.max_deploy_gas,
+            typ: DeployType::Package,
+            data: SlicePtr::wrap(&package)
+        };
+        let hashes = self.execute_deploy(&Serializer::serialize_fully(&txt, MAX_PARSE_DEPTH)?, false, None)?;
+        let end = now.elapsed().as_micros();
+        self.store.flush(StorageClass::Module);
+        self.store.flush(StorageClass::Transaction);
+        self.store.flush(StorageClass::Descriptor);
+        println!("deployed package with {} modules and {} transactions of size {:?} in {:?} us", num_modules, hashes.len() - num_modules, package.len(), end);
+        for hash in &hashes {
+            println!("  - {:?}", encode(hash));
+        }
+        Ok(hashes)
+    }
+
+    //Validates and compiles a module or transaction without deploying it
+    pub fn dry_run_reports(&self, typ:DeployType, data:&[u8]) -> Result<Vec<DeployReport>> {
+        let txt = DeployTransaction{
+            gas_limit: CONFIG.max_deploy_gas,
+            typ,
+            data: SlicePtr::wrap(data)
+        };
+        //2*Because the dry run keeps the parsed deploy transaction and the descriptors alive at the same time
+        let heap = Heap::new(2*CONFIG.calc_heap_size(2),2.0);
+        dry_run::<_, ServerExternals>(&self.store, &Serializer::serialize_fully(&txt, MAX_PARSE_DEPTH)?, &heap, false)
+    }
+
+    //Dry runs a module or transaction and prints what was found
+    pub fn dry_run_deploy(&mut self, typ:DeployType, data:Vec<u8>) -> Result<()> {
+        for report in self.dry_run_reports(typ, &data)? {
+            println!("{:?} with hash {:?} of size {:?}", report.typ, encode(report.hash), report.size);
+            for dep in &report.dependencies {
+                println!("  - depends on {:?}", encode(dep));
+            }
+            for comp in &report.components {
+                println!("  - {:?} at {}: {}", comp.kind, comp.offset, if comp.valid {"valid"} else {"invalid"});
+            }
+            if let Some(desc) = report.descriptor {
+                println!("  - resulting descriptor has hash {:?} and size {:?}", encode(desc.hash), desc.size);
+                println!("  - max stack slots: {}, max frame slots: {}, max consumed memory: {} bytes", desc.max_stack, desc.max_frames, desc.max_mem);
+                println!("  - gas cost: {}", desc.gas_cost);
+            }
+            println!("  - deploy gas: {} (in a bundle: {})", report.deploy_gas, report.bundle_gas);
+            if let Some(failure) = report.failure {
+                let mut location = String::new();
+                convert_error(failure.write_named(&mut location, &self.tracking))?;
+                println!("  - type checking failed in {}", location);
+            }
+            match report.error {
+                None => println!("  - would deploy successfully"),
+                Some(err) => println!("  - would fail: {}", error_to_string(&err)),
+            }
+        }
+        Ok(())
+    }
+
+    fn print_bundle_stats(bundle:&BaseTransactionBundle){
+        println!("Transaction Bundle Stats:");
+        println!("Size: {} byte", bundle.byte_size.unwrap());
+        println!("Essential gas: {}", bundle.core.essential_gas_cost);
+        println!("Total gas: {}", bundle.core.total_gas_cost);
+        println!("Virtual runtime heap memory required: {} bytes", bundle.core.runtime_heap_limit);
+        println!("Virtual transaction storage memory required: {} bytes", bundle.core.transaction_heap_limit);
+        println!("Maximum stack slots: {} (required memory: {} bytes)", bundle.core.stack_elem_limit, ( bundle.core.stack_elem_limit as usize) * Entry::SIZE);
+        println!("Maximum frame slots: {} (required memory: ~{} bytes)", bundle.core.stack_frame_limit, ( bundle.core.stack_frame_limit as usize) * (5*8));
+    }
+
+
+    pub fn bench_transaction(&mut self, txts:&[Tx]) -> Result<()> {
+        let n = 5000;
+        //todo make softer exits
+        //todo: improve overall heap management only alloc 1 Heap
+        //2*Because heap is not reset between verify and execute
+        let block_no = match convert_error(self.meta_data.get("block_no"))? {
+            None => 0,
+            Some(val) => Parser::parse_fully(&val, 1, &NoCustomAlloc())?
+        };
+        let timestamp = self.block_timestamp()?;
+        let mut heap = Heap::new(2*CONFIG.calc_heap_size(2),2.0);
+        let mut verify_elapsed = 0;
+        let mut elapsed = 0;
+        let mut size = 0;
+        let base_exec_state = self.tracking.exec_state.clone();
+        for i in 0..n {
+            self.store.rollback(StorageClass::EntryValue);
+            self.store.rollback(StorageClass::EntryHash);
+            self.store.rollback(StorageClass::Balance);
+            self.tracking.exec_state = base_exec_state.clone();
+            let full_heap = heap.new_virtual_arena(100000 as usize);
+            let (_,bundle) = self.build_transactions(txts, &full_heap, block_no+i)?;
+            let ser = Serializer::serialize_fully(&bundle,MAX_PARSE_DEPTH)?;
+            size = ser.len();
+            heap = heap.reuse();
+            self.tracking.block_start(block_no+i);
+            let now = Instant::now();
+            let txt_bundle_alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
+            let bundle = <ServerSystem>::parse_bundle(&ser,&txt_bundle_alloc)?;
+            let block = BlockInfo{block_no:block_no+i, timestamp, producer:LOCAL_PRODUCER, gas_price:self.gas_price};
+            self.verify_bundle(&bundle, block, &heap)?;
+            verify_elapsed += now.elapsed().as_micros();
+            self.execute_bundle(&bundle, block, &heap, false)?;
+            elapsed += now.elapsed().as_micros();
+            self.tracking.block_finish(block_no+i, true);
+            heap = heap.reuse();
+        }
+        self.store.commit(StorageClass::EntryValue);
+        self.store.commit(StorageClass::EntryHash);
+        self.store.commit(StorageClass::Balance);
+        //we flush manually as this would be done once per block and not per txt
+        let parse = (verify_elapsed as f64)/(n as f64);
+        let exact = (elapsed as f64)/(n as f64);
+        let now = Instant::now();
+        self.store.flush(StorageClass::EntryValue);
+        self.store.flush(StorageClass::EntryHash);
+        self.store.flush(StorageClass::Balance);
+        println!("Bundle executed in {}us and flushed in {}us", exact , now.elapsed().as_micros() as f64);
+        //println!("Bundle {} {} {} {}", size, parse, exact ,exact + now.elapsed().as_millis() as f64);
+        let new_block_no = block_no+n;
+        convert_error(self.meta_data.insert("block_no", Serializer::serialize_fully(&new_block_no, 1)?))?;
+        convert_error(self.meta_data.insert("block_timestamp", Serializer::serialize_fully(&timestamp, 1)?))?;
+        convert_error(self.meta_data.flush())?;
+        Ok(())
+    }
+
+    pub fn block_no(&self) -> Result<u64> {
+        match convert_error(self.meta_data.get("block_no"))? {
+            None => Ok(0),
+            Some(val) => Parser::parse_fully(&val, 1, &NoCustomAlloc())
+        }
+    }
+
+    pub fn execute_transaction(&mut self, txts:&[Tx]) -> Result<()> {
+        //todo make softer exits
+        let heap = Heap::new(CONFIG.calc_heap_size(2),2.0);
+        let full_heap = heap.new_virtual_arena(100000 as usize);
+        let block_no = self.block_no()?;
+        let (exec_gas,bundle) = self.build_transactions(txts, &full_heap, block_no)?;
+        #[cfg(feature = "dynamic_gas")]
+        println!("Transactions have {} gas available for the interpreter", exec_gas);
+        Self::print_bundle_stats(&bundle);
+        let ser = Serializer::serialize_fully(&bundle,MAX_PARSE_DEPTH)?;
+        println!("Starting bundle execution");
+        let (_, receipt) = self.submit_bundle(&ser)?;
+        for hash in receipt.deployed.iter() {
+            println!("Bundle deployed {}", encode(hash));
+        }
+        Ok(())
+    }
+
+    //The block that is produced next
+    fn next_block(&self) -> Result<BlockInfo> {
+        Ok(BlockInfo{block_no:self.block_no()?, timestamp:self.block_timestamp()?, producer:LOCAL_PRODUCER, gas_price:self.gas_price})
+    }
+
+    //Verifies and executes a serialized bundle as the only one of the next block
+    pub fn submit_bundle(&mut self, data:&[u8]) -> Result<(Hash, Receipt)> {
+        let block = self.next_block()?;
+        self.tracking.block_start(block.block_no);
+        let now = Instant::now();
+        let (hash, receipt) = self.execute_in_block(data, block)?;
+        //we flush manually as this would be done once per block and not per txt
+        let t1 = now.elapsed().as_micros();
+        self.finish_block(block, vec![data.to_vec()], !receipt.deployed.is_empty())?;
+        println!("Bundle executed in {}us and flushed in {}us", t1, now.elapsed().as_micros());
+        //println!("Bundle {} {} {} {}", data.len(), t0, t1, now.elapsed().as_micros());
+        Ok((hash, receipt))
+    }
+
+    //Adds a bundle to the mempool, it is executed when the next block that is in its inclusion window is produced
+    pub fn send_bundle(&mut self, data:Vec<u8>) -> Result<Hash> {
+        let bundle = PendingBundle::parse(data)?;
+        if self.get_receipt(&bundle.hash)?.is_some() {
+            return error(||"Bundle was already included")
+        }
+        self.mempool.add(bundle)
+    }
+
+    //Produces a block with the bundles from the mempool
+    pub fn produce_block(&mut self) -> Result<BlockReport> {
+        let block = self.next_block()?;
+        let (bundles, dropped) = self.mempool.take(block.block_no);
+        self.tracking.block_start(block.block_no);
+        let mut included = Vec::with_capacity(bundles.len());
+        let mut rejected = Vec::new();
+        let mut logged = Vec::with_capacity(bundles.len());
+        for bundle in bundles {
+            //the bundles were not built by the repl, so there are no names to track
+            self.tracking.exec_state = ExecutionState::new();
+            match self.execute_in_block(&bundle.data, block) {
+                Ok(res) => {
+                    included.push(res);
+                    logged.push(bundle.data);
+                },
+                Err(err) => rejected.push((bundle.hash, error_to_string(&err).to_owned()))
+            }
+        }
+        let deployed = included.iter().any(|(_, receipt)|!receipt.deployed.is_empty());
+        self.finish_block(block, logged, deployed)?;
+        Ok(BlockReport{ block_no: block.block_no, timestamp: block.timestamp, included, rejected, dropped })
+    }
+
+    //Verifies and executes a serialized bundle in the current block and records its receipt under the bundle hash
+    pub fn execute_in_block(&mut self, data:&[u8], block:BlockInfo) -> Result<(Hash, Receipt)> {
+        //a bundle that fails before it gets a receipt must not leave the receipt of the previous one behind
+        self.last_receipt = None;
+        //todo: improve overall heap management only alloc 1 Heap
+        //2*Because heap is not reset between verify and execute
+        let heap = Heap::new(2*CONFIG.calc_heap_size(2),2.0);
+        let txt_bundle_alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
+        let bundle = <ServerSystem>::parse_bundle(data,&txt_bundle_alloc)?;
+        if self.get_receipt(&bundle.bundle_hash)?.is_some() {
+            return error(||"Bundle was already included")
+        }
+        self.verify_bundle(&bundle,block, &heap)?;
+        //let t0 = now.elapsed().as_micros();
+        //the runtime rejects bundles that use more than their total gas cost before committing them
+        let res = self.execute_bundle( &bundle,block, &heap, true);
+        #[cfg(feature = "dynamic_gas")]
+        if let Ok(gas) = res {
+            println!("Interpreter execution used {} gas", gas);
+        }
+        let exec_state = &self.tracking.exec_state;
+        let receipt = match res {
+            Ok(_) => Receipt {
+                block_no: block.block_no,
+                success: true,
+                used_gas: exec_state.used_gas,
+                refunded_gas: exec_state.refunded_gas,
+                deployed: LargeVec(exec_state.deployed.clone()),
+            },
+            //the sections before the failing one stay committed and the fee stays paid
+            // so the bundle is included (and can not be executed again) with a failed receipt
+            Err(err) if exec_state.paid => {
+                println!("Bundle failed after its essential section: {}", error_to_string(&err));
+                Receipt {
+                    block_no: block.block_no,
+                    success: false,
+                    used_gas: bundle.total_gas_cost(),
+                    refunded_gas: 0,
+                    deployed: LargeVec(exec_state.deployed.clone()),
+                }
+            },
+            //nothing was committed, the bundle is not included
+            Err(err) => return Err(err),
+        };
+        convert_error(self.receipts.insert(bundle.bundle_hash, Serializer::serialize_fully(&receipt, MAX_PARSE_DEPTH)?))?;
+        convert_error(self.receipts.flush())?;
+        self.last_receipt = Some(receipt.clone());
+        Ok((bundle.bundle_hash, receipt))
+    }
+
+    //Persists the changes of the block, appends it to the block log and advances the block number
+    pub fn finish_block(&mut self, block:BlockInfo, bundles:Vec<Vec<u8>>, deployed:bool) -> Result<()> {
+        self.store.flush(StorageClass::EntryValue);
+        self.store.flush(StorageClass::EntryHash);
+        self.store.flush(StorageClass::Balance);
+        if deployed {
+            self.store.flush(StorageClass::Module);
+            self.store.flush(StorageClass::Transaction);
+            self.store.flush(StorageClass::Descriptor);
+        }
+        self.tracking.block_finish(block.block_no, true);
+        self.block_log.append(&LogEntry::Block(LoggedBlock{
+            block_no: block.block_no,
+            timestamp: block.timestamp,
+            gas_price: block.gas_price,
+            bundles: LargeVec(bundles.into_iter().map(BundleData).collect()),
+            state_root: state_root(&self.store)?,
+        }))?;
+        let new_block_no = block.block_no+1;
+        convert_error(self.meta_data.insert("block_no", Serializer::serialize_fully(&new_block_no, 1)?))?;
+        convert_error(self.meta_data.insert("block_timestamp", Serializer::serialize_fully(&block.timestamp, 1)?))?;
+        convert_error(self.meta_data.flush())?;
+        Ok(())
+    }
+
+    //Writes the committed store content to an archive file and returns its state root
+    // names, accounts, receipts and the block number are not part of the store and are not archived
+    pub fn export_state(&self, path:&Path) -> Result<Hash> {
+        let (archive, root) = export_state(&self.store)?;
+        if let Err(err) = std::fs::write(path, &archive) {
+            return owned_error(||format!("Can not write archive {}: {}", path.display(), err))
+        }
+        Ok(root)
+    }
+
+    //Imports an archive file into the store, which has to be empty, and returns the state root of the imported state
+    pub fn import_state(&mut self, path:&Path) -> Result<Hash> {
+        for class in STORAGE_CLASSES {
+            if self.store.digest(class)?.entries != 0 {
+                return error(||"An archive can only be imported into an empty state")
+            }
+        }
+        let archive = match std::fs::read(path) {
+            Ok(archive) => archive,
+            Err(err) => return owned_error(||format!("Can not read archive {}: {}", path.display(), err))
+        };
+        let root = import_state(&archive, &self.store)?;
+        for class in STORAGE_CLASSES {
+            self.store.flush(class);
+        }
+        Ok(root)
+    }
+
+    pub fn get_receipt(&self, bundle_hash:&Hash) -> Result<Option<Receipt>> {
+        match convert_error(self.receipts.get(bundle_hash))? {
+            None => Ok(None),
+            Some(data) => Ok(Some(Parser::parse_fully(&data, MAX_PARSE_DEPTH, &NoCustomAlloc())?))
+        }
+    }
+
+    pub fn build_transactions<'c,'h>(&mut self, txts:&[Tx], full_heap:&'c VirtualHeapArena<'h>, block_no:u64) -> Result<(u64, BaseTransactionBundle<'c>)>{
+        let mut builder = BundleBuilder::new(block_no);
+        builder.meta(Serializer::serialize_fully(&block_no,1)?);
+
+        let mut witnesses:BTreeMap<String,WitnessRef> = BTreeMap::new();
+        let mut slots:BTreeMap<String,Slot> = BTreeMap::new();
+        let fetch_slot = |slots:&BTreeMap<String,Slot>, key:&String| match slots.get(key) {
+            Some(slot) => Ok(*slot),
+            None => error(||"Element name unknown")
+        };
+
+        for Tx{ref desc, ref params, ref returns} in txts {
+            let mut inputs = Vec::with_capacity(params.len());
+            for p in params {
+                inputs.push(match p {
+                    Param::Lit(data) => Input::Literal(data.clone()),
+                    Param::Pk(account) => Input::Literal(self.get_account(account)?.verifying_key().to_bytes().to_vec()),
+                    Param::Subject(account) => {
+                        let pk = self.get_account(account)?.verifying_key().to_bytes().to_vec();
+                        //compute the edDsaSubject
+                        Input::Literal(Self::calc_subject(&pk,&full_heap)?.to_vec())
+                    },
+                    Param::Sig(account) => Input::Witness(match witnesses.get(account) {
+                        Some(witness) => *witness,
+                        None => {
+                            let witness = builder.signer(Box::new(self.get_account(account)?))?;
+                            witnesses.insert(account.clone(), witness);
+                            witness
+                        }
+                    }),
+                    Param::Provided => Input::Provided,
+                    Param::Consume(id) => Input::Load(ParamMode::Consume, *id),
+                    Param::Borrow(id) => Input::Load(ParamMode::Borrow, *id),
+                    Param::Copy(id) => Input::Load(ParamMode::Copy, *id),
+                    Param::LocalConsume(key) => Input::Fetch(ParamMode::Consume, fetch_slot(&slots, key)?),
+                    Param::LocalBorrow(key) => Input::Fetch(ParamMode::Borrow, fetch_slot(&slots, key)?),
+                    Param::LocalCopy(key) => Input::Fetch(ParamMode::Copy, fetch_slot(&slots, key)?),
+                })
+            }
+
+            let mut outputs = Vec::with_capacity(returns.len());
+            for r in returns {
+                outputs.push(match r {
+                    Ret::Log => Output::Log,
+                    Ret::Assign(name) => Output::Put(match slots.get(name) {
+                        Some(slot) => *slot,
+                        None => {
+                            let slot = builder.slot()?;
+                            slots.insert(name.clone(), slot);
+                            slot
+                        }
+                    }),
+                    Ret::Elem => Output::Store,
+                    Ret::Drop => Output::Drop,
+                    Ret::Fee => Output::Fee,
+                })
+            }
+            builder.invoke(*desc, &inputs, &outputs)?;
+        }
+
+        let bundle = builder.build::<_, ServerSystemDataManager>(&self.store, full_heap)?;
+        //Todo: only compute and return exec gas in dynamic case
+        let mut exec_gas:u64 = 0;
+        for desc in builder.descriptors() {
+            exec_gas += read_transaction_desc(desc, &self.store, full_heap)?.gas_cost as u64;
+        }
+        Ok((exec_gas, bundle.txt_bundle))
+    }
+
+    pub fn get_account(&mut self, ident:&str) -> Result<SigningKey> {
+        let key = ident.as_bytes();
+        if convert_error(self.accounts.contains_key(&key))? {
+            let sig_key:&[u8] = &convert_error(self.accounts.get(&key))?.unwrap();
+            if sig_key.len() != SECRET_KEY_LENGTH {
+                return owned_error(||format!("Wrong Key Size: {} vs. {}",sig_key.len(), SECRET_KEY_LENGTH));
+            }
+            return Ok(SigningKey::from_bytes(sig_key.try_into().unwrap()))
+        }
+        let kp = SigningKey::generate(&mut self.csprng);
+        convert_error(self.accounts.insert(key,kp.to_bytes().to_vec()))?;
+        convert_error(self.accounts.flush())?;
+        return Ok(kp);
+    }
+
+    //Registers an account with a given key (instead of a generated one)
+    pub fn add_account(&mut self, ident:&str, key:&SigningKey) -> Result<()> {
+        convert_error(self.accounts.insert(ident.as_bytes(), key.to_bytes().to_vec()))?;
+        convert_error(self.accounts.flush())?;
+        Ok(())
+    }
+
+    pub fn get_accounts(&mut self) -> Result<Vec<(String,SigningKey)>> {
+        let mut res = Vec::with_capacity(self.accounts.len());
+        for account in self.accounts.iter() {
+            let (name_bytes,key_bytes) = convert_error(account)?;
+            let name = convert_error(from_utf8(&name_bytes))?;
+            let sig_key:&[u8] = &key_bytes;
+            if sig_key.len() != SECRET_KEY_LENGTH {
+                return owned_error(||format!("Wrong Key Size: {} vs. {}",sig_key.len(), SECRET_KEY_LENGTH));
+            }
+            let sig_key = SigningKey::from_bytes(sig_key.try_into().unwrap());
+            res.push((name.to_owned(), sig_key))
+        }
+        return Ok(res)
+    }
+
+    pub fn get_elems(&mut self) -> Result<Vec<(String,String)>> {
+        let mut res = Vec::with_capacity(self.tracking.element_data.len());
+        for account in self.tracking.element_data.iter() {
+            let (name_bytes,data_bytes) = convert_error(account)?;
+            let name = convert_error(from_utf8(&name_bytes))?;
+            let data = convert_error(from_utf8(&data_bytes))?;
+            res.push((name.to_owned(), data.to_owned()))
+        }
+        return Ok(res)
+    }
+
+    pub fn get_elem(&mut self, ident:&str) -> Result<String> {
+        let data_bytes = match convert_error(self.tracking.element_data.get(ident))? {
+            Some(data_bytes) => data_bytes,
+            None => return error(||"Element not found")
+        };
+        let data = convert_error(from_utf8(&data_bytes))?;
+        return Ok(data.to_owned())
+    }
+
+    //The json form of a stored entry, if its schema is known
+    pub fn entry_json(&self, id:&Hash, data:&[u8]) -> Result<Option<serde_json::Value>> {
+        let schema = match convert_error(self.tracking.entry_schemas.get(id))? {
+            Some(schema) => schema,
+            None => return Ok(None)
+        };
+        let heap = Heap::new(2*CONFIG.max_structural_dept*1024, 2.0);
+        let alloc = heap.new_virtual_arena(CONFIG.max_structural_dept*1024);
+        let schema:ValueSchema = Parser::parse_fully(&schema, CONFIG.max_structural_dept, &alloc)?;
+        Ok(Some(schema.to_json(data, CONFIG.max_structural_dept, &self.tracking)?))
+    }
+
+    pub fn get_transactions(&mut self) -> Result<Vec<String>> {
+        let mut res = Vec::with_capacity(self.transaction_name_mapping.len());
+        for transaction in self.transaction_name_mapping.iter() {
+            let (name_bytes,_) = convert_error(transaction)?;
+            let name = convert_error(from_utf8(&name_bytes))?;
+            res.push(name.to_owned())
+        }
+        return Ok(res)
+    }
+
+    pub fn get_transaction<'a:'b, 'b, A:ParserAllocator>(&'b mut self, ident:&str,  heap:&'a A) -> Result<TransactionDescriptor> {
+        let hash_bytes = convert_error(self.transaction_name_mapping.get(ident))?.unwrap();
+        let id = hash_from_slice(&hash_bytes);
+        read_transaction_desc(&id, &self.store,  heap)
+    }
+
+    pub fn get_modules(&mut self) -> Result<Vec<String>> {
+        let mut res = Vec::with_capacity(self.module_name_mapping.len());
+        for module in self.module_name_mapping.iter() {
+            let (name_bytes,_) = convert_error(module)?;
+            let name = convert_error(from_utf8(&name_bytes))?;
+            res.push(name.to_owned())
+        }
+        return Ok(res)
+    }
+
+    pub fn get_module<'a:'b, 'b, A:ParserAllocator>(&'b mut self, ident:&str,  heap:&'a A) -> Result<Module> {
+        let hash_bytes = convert_error(self.module_name_mapping.get(ident))?.unwrap();
+        let id = hash_from_slice(&hash_bytes);
+        self.store.parsed_get(StorageClass::Module, &id, CONFIG.max_structural_dept, heap)
+    }
+
+    //Prints the worst case resources of the functions and implements a module offers to others
+    pub fn module_bounds(&mut self, ident:&str) -> Result<()> {
+        let hash_bytes = match convert_error(self.module_name_mapping.get(ident))? {
+            Some(hash_bytes) => hash_bytes,
+            None => return error(||"Module not found")
+        };
+        for callable in self.callable_bounds(hash_from_slice(&hash_bytes))? {
+            let res = callable.resources;
+            println!("{:?} {}: gas {}, memory {} bytes, stack slots {}, frames {}", callable.kind, callable.offset, res.gas, res.mem, res.manifest_stack, res.frames);
+            for branch in callable.branches {
+                let path:Vec<String> = branch.path.iter().enumerate().map(|(i,(b, op))| if i == 0 {
+                    format!("opcode {}", op)
+                } else {
+                    format!("branch {} opcode {}", b, op)
+                }).collect();
+                println!("  - branch {} at {}: gas {}, memory {} bytes", branch.branch, path.join(" / "), branch.gas, branch.mem);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn callable_bounds(&self, module_hash:Hash) -> Result<Vec<CallableBounds>> {
+        let store = CachedStore::<Module,_>::new(&self.store, StorageClass::Module);
+        let heap = Heap::new(CONFIG.max_bounds_alloc, 1.0);
+        let alloc = heap.new_arena(CONFIG.max_bounds_alloc);
+        module_bounds::<_, ServerExternals>(&store, module_hash, &alloc, &Limiter::unlimited())
+    }
+
+    pub fn module_abi(&mut self, ident:&str) -> Result<()> {
+        let hash_bytes = match convert_error(self.module_name_mapping.get(ident))? {
+            Some(hash_bytes) => hash_bytes,
+            None => return error(||"Module not found")
+        };
+        println!("{:#}", self.export_module_abi(hash_from_slice(&hash_bytes))?);
+        Ok(())
+    }
+
+    pub fn export_module_abi(&self, module_hash:Hash) -> Result<serde_json::Value> {
+        let store = CachedStore::<Module,_>::new(&self.store, StorageClass::Module);
+        export_abi(&store, module_hash, self.tracking.known_metadata())
+    }
+
+    pub fn calc_subject<'a,'h>(pk:&[u8], full_heap:&'a VirtualHeapArena<'h>) -> Result<SlicePtr<'a,u8>>{
+        //compute the edDsaSubject
+        let id = raw_plain_hash(pk, &full_heap)?;
+        //compute the subject Manager Subject
+        raw_join_hash(&get_ed_dsa_module(), &id, HashingDomain::Derive, &full_heap)
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sanskrit_runtime::direct_stored::read_fee_balance;
+    use sanskrit_client::{Input, Output};
+    use sanskrit_core::loader::Loader;
+    use sanskrit_deploy::{ComponentKind, ExpStep, Status};
+    use sanskrit_core::model::resolved::ResolvedType;
+    use crate::fixtures::{empty_module, choice_module, u64_module, fan_out_transaction, mistyped_transaction, chain, bundle, num, id};
+
+    //Records the progress reported by the runtime
+    struct Events(Vec<String>);
+    impl Tracker for Events {
+        fn block_start(&mut self, _block_no: u64) {}
+        fn bundle_start<T:TransactionBundle>(&mut self, _bundle: &T) { self.0.push("bundle_start".to_owned()) }
+        fn section_start(&mut self, _section: &BundleSection) { self.0.push("section_start".to_owned()) }
+        fn transaction_start(&mut self, _transaction: &Transaction) { self.0.push("transaction_start".to_owned()) }
+        fn parameter_load(&mut self, _p_ref: &ParamRef, _p_desc: &TxTParam, _value: &Entry) {}
+        fn return_value(&mut self, _r_typ: &RetType, _r_desc: &TxTReturn, _value: &Entry) {}
+        fn transaction_finish(&mut self, _transaction: &Transaction, success: bool) { self.0.push(format!("transaction_finish {}", success)) }
+        fn section_finish(&mut self, _section: &BundleSection, success: bool) { self.0.push(format!("section_finish {}", success)) }
+        fn bundle_finish<T:TransactionBundle>(&mut self, _bundle: &T, success: bool) { self.0.push(format!("bundle_finish {}", success)) }
+        fn block_finish(&mut self, _block_no: u64, _success: bool) {}
+    }
+
+    #[test]
+    fn failing_bundles_are_executed_once() {
+        let mut state = State::temporary("single_execution");
+        let heap = Heap::new(2*CONFIG.calc_heap_size(2),2.0);
+        let alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
+        let mut builder = BundleBuilder::new(0);
+        builder.deploy(DeployType::Module, CONFIG.max_deploy_gas, empty_module()).unwrap();
+        builder.section(SectionType::Custom).unwrap().deploy(DeployType::Module, CONFIG.max_deploy_gas, vec![1, 2, 3]).unwrap();
+        let bundle = builder.build::<_, ServerSystemDataManager>(&state.store, &alloc).unwrap();
+        let block = BlockInfo{ block_no: 0, timestamp: 0, producer: LOCAL_PRODUCER, gas_price: CONFIG.gas_price };
+        state.verify_bundle(&bundle, block, &heap).unwrap();
+
+        let mut events = Events(vec![]);
+        let ctx = Context{ store: &state.store, txt_bundle: &bundle };
+        assert!(execute::<_, ServerSystem>(ctx, block, &heap, &mut events, true).is_err());
+        //every event is reported once and in order, the essential section stays committed
+        assert_eq!(events.0, vec![
+            "bundle_start",
+            "section_start", "transaction_start", "transaction_finish true", "section_finish true",
+            "section_start", "transaction_start", "transaction_finish false", "section_finish false",
+            "bundle_finish false",
+        ]);
+        assert_eq!(state.store.digest(StorageClass::Module).unwrap().entries, 1);
+    }
+
+    #[test]
+    fn bundles_failing_after_their_essential_section_are_included() {
+        let mut state = State::temporary("failed_inclusion");
+        let heap = Heap::new(2*CONFIG.calc_heap_size(2),2.0);
+        let alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
+        let mut builder = BundleBuilder::new(0);
+        builder.deploy(DeployType::Module, CONFIG.max_deploy_gas, empty_module()).unwrap();
+        builder.section(SectionType::Custom).unwrap().deploy(DeployType::Module, CONFIG.max_deploy_gas, vec![1, 2, 3]).unwrap();
+        let bundle = builder.build::<_, ServerSystemDataManager>(&state.store, &alloc).unwrap();
+        let data = Serializer::serialize_fully(&bundle.txt_bundle, MAX_PARSE_DEPTH).unwrap();
+        let hash = state.send_bundle(data.clone()).unwrap();
+        let report = state.produce_block().unwrap();
+        assert!(report.rejected.is_empty());
+        //the module of the essential section stays deployed and is charged the full gas
+        let (included, receipt) = &report.included[0];
+        assert_eq!(*included, hash);
+        assert!(!receipt.success);
+        assert_eq!(receipt.used_gas, bundle.total_gas_cost());
+        assert_eq!(receipt.deployed.len(), 1);
+        assert!(!state.get_receipt(&hash).unwrap().unwrap().success);
+        //the essential section can not run a second time
+        assert_eq!(error_to_string(&state.submit_bundle(&data).unwrap_err()), "Bundle was already included");
+
+        //the logged block reproduces the state with the committed essential section
+        let entries = state.block_log.entries().unwrap();
+        match &entries[..] {
+            [LogEntry::Block(block)] => assert_eq!(block.bundles.len(), 1),
+            entries => panic!("unexpected entries {:?}", entries)
+        }
+        let replayed = crate::replay::replay(&entries, &sanskrit_memory_store::BTreeMapStore::new()).unwrap();
+        assert_eq!((replayed.bundles, replayed.divergence), (1, None));
+    }
+
+    #[test]
+    fn blocks_carry_the_gas_price_of_the_node() {
+        let mut state = State::temporary("block_gas_price");
+        state.gas_price = 3;
+        //a bundle without a fee is rejected once the node charges fees
+        let mut builder = BundleBuilder::new(0);
+        builder.deploy(DeployType::Module, CONFIG.max_deploy_gas, empty_module()).unwrap();
+        let heap = Heap::new(2*CONFIG.calc_heap_size(2),2.0);
+        let alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
+        let bundle = Serializer::serialize_fully(&builder.build::<_, ServerSystemDataManager>(&state.store, &alloc).unwrap().txt_bundle, MAX_PARSE_DEPTH).unwrap();
+        let err = state.submit_bundle(&bundle).unwrap_err();
+        assert_eq!(error_to_string(&err), "Bundle must pay a fee in its essential section");
+        state.produce_block().unwrap();
+        match &state.block_log.entries().unwrap()[..] {
+            [LogEntry::Block(block)] => assert_eq!(block.gas_price, 3),
+            entries => panic!("unexpected entries {:?}", entries)
+        }
+    }
+
+    #[test]
+    fn block_timestamps_never_decrease() {
+        let mut state = State::temporary("block_timestamps");
+        let first = state.produce_block().unwrap();
+        let second = state.produce_block().unwrap();
+        assert_eq!((first.block_no, second.block_no), (0, 1));
+        assert!(first.timestamp > 0);
+        assert!(second.timestamp >= first.timestamp);
+        assert!(state.block_timestamp().unwrap() >= second.timestamp);
+
+        //a block produced after the clock went backwards keeps the timestamp of the last one
+        let future = second.timestamp + 3600;
+        state.meta_data.insert("block_timestamp", Serializer::serialize_fully(&future, 1).unwrap()).unwrap();
+        let third = state.produce_block().unwrap();
+        let fourth = state.produce_block().unwrap();
+        assert_eq!((third.timestamp, fourth.timestamp), (future, future));
+    }
+
+    #[test]
+    fn fees_are_never_taken_from_balances() {
+        let (mut state, txts) = chain("fee_no_drain");
+        state.gas_price = 1;
+        let victim = [7u8; 20];
+        state.store.set(StorageClass::Balance, victim, Serializer::serialize_fully(&100u64, 1).unwrap()).unwrap();
+        state.store.commit(StorageClass::Balance);
+        //a fee naming someone else as payer does not make its balance pay the missing part
+        let mut builder = BundleBuilder::new(0);
+        builder.invoke(txts.pay, &[num(1), id(7)], &[Output::Fee]).unwrap();
+        let err = state.submit_bundle(&bundle(&state, &builder)).unwrap_err();
+        assert_eq!(error_to_string(&err), "Paid fee does not cover the declared gas cost");
+        assert_eq!(read_fee_balance(&state.store, &victim).unwrap(), 100);
+    }
+
+    fn deploy_limited(state:&mut State, typ:DeployType, data:&[u8], gas_limit:u64) -> Result<Vec<Hash>> {
+        let txt = DeployTransaction{ gas_limit, typ, data: SlicePtr::wrap(data) };
+        state.execute_deploy(&Serializer::serialize_fully(&txt, MAX_PARSE_DEPTH)?, false, None)
+    }
+
+    #[test]
+    fn modules_larger_than_the_deploy_gas_limit_are_aborted() {
+        let (mut state, _) = chain("deploy_oversized");
+        let module = empty_module();
+        //every byte of the module costs a work unit before the validation even starts
+        let err = deploy_limited(&mut state, DeployType::Module, &module, module.len() as u64 - 1).unwrap_err();
+        assert_eq!(error_to_string(&err), "Work limit exceeded");
+        assert!(state.store.get(StorageClass::Module, &store_hash(&[&module]), |_| ()).is_err());
+        deploy_limited(&mut state, DeployType::Module, &module, CONFIG.max_deploy_gas).unwrap();
+        assert!(state.store.get(StorageClass::Module, &store_hash(&[&module]), |_| ()).is_ok());
+    }
+
+    #[test]
+    fn dependency_fan_out_is_charged_against_the_deploy_gas_limit() {
+        let (mut state, _) = chain("deploy_fan_out");
+        let gas = |state:&mut State, extra:usize| state.dry_run_reports(DeployType::Transaction, &fan_out_transaction(extra)).unwrap()[0].deploy_gas;
+        let (narrow, wide) = (gas(&mut state, 0), gas(&mut state, 200));
+        //each import is resolved during the validation and again during the compilation
+        assert!(wide >= narrow + 2*200*10);
+        let txt = fan_out_transaction(200);
+        let err = deploy_limited(&mut state, DeployType::Transaction, &txt, wide - 1).unwrap_err();
+        assert_eq!(error_to_string(&err), "Work limit exceeded");
+        deploy_limited(&mut state, DeployType::Transaction, &txt, wide).unwrap();
+    }
+
+    #[test]
+    fn dependencies_are_charged_by_their_size_even_when_cached() {
+        let (state, _) = chain("deploy_dependency_size");
+        let store = CachedStore::<Module,_>::new(&state.store, StorageClass::Module);
+        let hash = store_hash(&[&choice_module()]);
+        let charge = || {
+            let limiter = Limiter::unlimited();
+            Loader::new_complete(&store, &limiter).get_module(hash).unwrap();
+            limiter.used()
+        };
+        //the first loader parses it from the store, the second one gets it from the cache of the store
+        let (cold, cached) = (charge(), charge());
+        assert_eq!(cold, cached);
+        assert!(cold > choice_module().len() as u64);
+    }
+
+    #[test]
+    fn empty_deploys_are_rejected() {
+        let (mut state, _) = chain("deploy_empty");
+        let heap = Heap::new(CONFIG.calc_heap_size(2),2.0);
+        let alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
+        let mut builder = BundleBuilder::new(0);
+        builder.deploy(DeployType::Module, CONFIG.max_deploy_gas, vec![]).unwrap();
+        let err = builder.build::<_, ServerSystemDataManager>(&state.store, &alloc).err().unwrap();
+        assert_eq!(error_to_string(&err), "Deploy data is empty");
+        //the node rejects them from other clients as well
+        let mut builder = BundleBuilder::new(0);
+        builder.deploy(DeployType::Module, CONFIG.max_deploy_gas, vec![0]).unwrap();
+        let mut built = builder.build::<_, ServerSystemDataManager>(&state.store, &alloc).unwrap();
+        let empty = Transaction::Deploy(DeployTransaction{ gas_limit: CONFIG.max_deploy_gas, typ: DeployType::Module, data: SlicePtr::empty() });
+        let section = BundleSection{ typ: SectionType::Essential, txts: alloc.copy_alloc_slice(&[empty]).unwrap() };
+        built.txt_bundle.core.sections = alloc.copy_alloc_slice(&[section]).unwrap();
+        let err = state.submit_bundle(&Serializer::serialize_fully(&built.txt_bundle, MAX_PARSE_DEPTH).unwrap()).unwrap_err();
+        assert_eq!(error_to_string(&err), "Deploy data is empty");
+        //outside of bundles and inside of packages the deployer rejects them
+        let err = deploy_limited(&mut state, DeployType::Module, &[], CONFIG.max_deploy_gas).unwrap_err();
+        assert_eq!(error_to_string(&err), "Module is empty");
+        let package = DeployPackage{ modules: vec![empty_module(), vec![]], transactions: vec![] };
+        let err = deploy_limited(&mut state, DeployType::Package, &Serializer::serialize_fully(&package, MAX_PARSE_DEPTH).unwrap(), CONFIG.max_deploy_gas).unwrap_err();
+        assert_eq!(error_to_string(&err), "Module is empty");
+        assert!(state.store.get(StorageClass::Module, &store_hash(&[&empty_module()]), |_| ()).is_err());
+        assert!(deploy_limited(&mut state, DeployType::Transaction, &[], CONFIG.max_deploy_gas).is_err());
+    }
+
+    #[test]
+    fn packages_are_deployed_all_or_nothing() {
+        let (mut state, _) = chain("deploy_package");
+        let stored = |state:&State, class:StorageClass, data:&[u8]| state.store.get(class, &store_hash(&[data]), |_| ()).is_ok();
+        CompilerInstance::with_compiler_result(|compiler|{
+            //a broken module or transaction keeps the valid parts out of the store
+            assert!(state.deploy_package(compiler, vec![empty_module(), vec![1, 2, 3]], vec![]).is_err());
+            assert!(state.deploy_package(compiler, vec![empty_module()], vec![vec![1, 2, 3]]).is_err());
+            assert!(!stored(&state, StorageClass::Module, &empty_module()));
+
+            let hashes = state.deploy_package(compiler, vec![empty_module()], vec![fan_out_transaction(0)])?;
+            assert_eq!(hashes.len(), 2);
+            assert!(stored(&state, StorageClass::Module, &empty_module()));
+            assert!(stored(&state, StorageClass::Transaction, &fan_out_transaction(0)));
+            assert!(state.store.get(StorageClass::Descriptor, &hashes[1], |_| ()).is_ok());
+            Ok(())
+        }).unwrap();
+    }
+
+    #[test]
+    fn failed_deploys_roll_back_their_section() {
+        let (mut state, _) = chain("deploy_rollback");
+        let mut builder = BundleBuilder::new(0);
+        builder.section(SectionType::Custom).unwrap()
+            .deploy(DeployType::Module, CONFIG.max_deploy_gas, empty_module()).unwrap()
+            .deploy(DeployType::Module, CONFIG.max_deploy_gas, vec![1, 2, 3]).unwrap();
+        let modules = state.store.digest(StorageClass::Module).unwrap().entries;
+        assert!(state.submit_bundle(&bundle(&state, &builder)).is_err());
+        assert!(state.store.get(StorageClass::Module, &store_hash(&[&empty_module()]), |_| ()).is_err());
+        assert_eq!(state.store.digest(StorageClass::Module).unwrap().entries, modules);
+    }
+
+    #[test]
+    fn deploy_gas_limits_are_paid_on_top_of_the_gas() {
+        let (mut state, txts) = chain("deploy_gas");
+        state.gas_price = 1;
+        let limit = 50_000;
+        let paid = |fee:u64| {
+            let mut builder = BundleBuilder::new(0);
+            builder.invoke(txts.pay, &[num(fee), id(9)], &[Output::Fee]).unwrap();
+            builder.section(SectionType::Custom).unwrap().deploy(DeployType::Module, limit, empty_module()).unwrap();
+            builder
+        };
+        let heap = Heap::new(CONFIG.calc_heap_size(2),2.0);
+        let alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
+        let gas = paid(0).build::<_, ServerSystemDataManager>(&state.store, &alloc).unwrap().txt_bundle.core.total_gas_cost;
+        //the work units of the deploy are not part of the gas of the bundle
+        assert!(gas < limit);
+        let err = state.submit_bundle(&bundle(&state, &paid(gas + limit - 1))).unwrap_err();
+        assert_eq!(error_to_string(&err), "Paid fee does not cover the declared gas cost");
+        let (_, receipt) = state.submit_bundle(&bundle(&state, &paid(gas + limit))).unwrap();
+        assert!(receipt.success);
+        assert!(state.store.get(StorageClass::Module, &store_hash(&[&empty_module()]), |_| ()).is_ok());
+        //only gas can be refunded, the deploy gas limit is always earned by the producer
+        assert!(read_fee_balance(&state.store, &LOCAL_PRODUCER).unwrap() >= limit);
+        assert_eq!(read_fee_balance(&state.store, &LOCAL_PRODUCER).unwrap() + read_fee_balance(&state.store, &[9u8; 20]).unwrap(), gas + limit);
+    }
+
+    #[test]
+    fn dry_runs_describe_type_errors() {
+        let (state, _) = chain("dry_run_type_error");
+        let report = &state.dry_run_reports(DeployType::Transaction, &mistyped_transaction()).unwrap()[0];
+        let failure = report.failure.as_ref().unwrap();
+        assert_eq!(failure.kind, ComponentKind::Transaction);
+        //the invoke is the second opcode of the expensive case of the switch
+        assert_eq!(failure.path, vec![ExpStep{ branch: 0, opcode: 0 }, ExpStep{ branch: 1, opcode: 1 }]);
+        let module = |typ:&ResolvedType| match *typ {
+            ResolvedType::Data{ ref module, .. } | ResolvedType::Lit{ ref module, .. } => **module,
+            _ => panic!("not an imported type"),
+        };
+        let (expected, actual) = failure.mismatch.as_ref().unwrap();
+        assert_eq!(module(expected), ModuleLink::Remote(store_hash(&[&u64_module()])));
+        assert_eq!(module(actual), ModuleLink::Remote(store_hash(&[&choice_module()])));
+        //the choice was consumed by the switch and the number discarded by the case
+        let stack:Vec<_> = failure.stack.iter().map(|slot|(module(&slot.typ), slot.status, slot.locked)).collect();
+        assert_eq!(stack, vec![
+            (ModuleLink::Remote(store_hash(&[&choice_module()])), Status::Consumed, false),
+            (ModuleLink::Remote(store_hash(&[&u64_module()])), Status::Consumed, false),
+        ]);
+        //the location is named once in the error
+        let error = report.error.as_ref().unwrap();
+        assert!(error.starts_with("Parameter for function call has wrong type ("));
+        assert_eq!(error.matches(&failure.to_string()).count(), 1);
+    }
+
+    #[test]
+    fn dry_runs_leave_the_store_untouched() {
+        let (state, _) = chain("dry_run_overlay");
+        let module = store_hash(&[&empty_module()]);
+        let report = &state.dry_run_reports(DeployType::Module, &empty_module()).unwrap()[0];
+        assert!(report.error.is_none());
+        assert!(state.store.get(StorageClass::Module, &module, |_| ()).is_err());
+
+        //uncommitted changes of the node survive a dry run and stay uncommitted
+        state.store.set(StorageClass::Module, module, empty_module()).unwrap();
+        state.store.set(StorageClass::EntryValue, [4; 20], vec![1, 2]).unwrap();
+        let report = &state.dry_run_reports(DeployType::Transaction, &fan_out_transaction(0)).unwrap()[0];
+        assert!(report.error.is_none());
+        assert!(state.store.get(StorageClass::Transaction, &store_hash(&[&fan_out_transaction(0)]), |_| ()).is_err());
+        assert_eq!(state.store.get(StorageClass::Module, &module, |d| d.to_vec()).unwrap(), empty_module());
+        assert_eq!(state.store.get(StorageClass::EntryValue, &[4; 20], |d| d.to_vec()).unwrap(), vec![1, 2]);
+        state.store.rollback(StorageClass::Module);
+        state.store.rollback(StorageClass::EntryValue);
+        assert!(state.store.get(StorageClass::Module, &module, |_| ()).is_err());
+        assert!(state.store.get(StorageClass::EntryValue, &[4; 20], |_| ()).is_err());
+    }
+
+    #[test]
+    fn built_bundles_declare_the_verified_costs() {
+        let (mut state, txts) = chain("built_costs");
+        let mut builder = BundleBuilder::new(0);
+        builder.invoke(txts.pay, &[num(1_000_000), id(9)], &[Output::Fee]).unwrap();
+        builder.section(SectionType::Custom).unwrap().invoke(txts.mint, &[id(1), num(5)], &[Output::Store]).unwrap();
+        assert!(state.submit_bundle(&bundle(&state, &builder)).unwrap().1.success);
+
+        //loads, stores, literals used twice and invokes in both sections
+        let mut builder = BundleBuilder::new(0);
+        builder.invoke(txts.pay, &[num(1_000_000), id(9)], &[Output::Fee]).unwrap();
+        builder.section(SectionType::Custom).unwrap()
+            .invoke(txts.burn, &[Input::Load(ParamMode::Consume, [1; 20])], &[]).unwrap()
+            .invoke(txts.mint, &[id(9), num(5)], &[Output::Store]).unwrap()
+            .invoke(txts.choose, &[Input::Literal(vec![1]), num(5)], &[]).unwrap();
+        let heap = Heap::new(2*CONFIG.calc_heap_size(2),2.0);
+        let alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
+        let mut built = builder.build::<_, ServerSystemDataManager>(&state.store, &alloc).unwrap();
+        let block = state.next_block().unwrap();
+        verify::<ServerSystem<_>>(&Context{ store: &state.store, txt_bundle: &built }, block, &heap).unwrap();
+
+        //the declared costs are exactly the required ones
+        built.txt_bundle.core.total_gas_cost -= 1;
+        let err = verify::<ServerSystem<_>>(&Context{ store: &state.store, txt_bundle: &built }, block, &heap).unwrap_err();
+        assert_eq!(error_to_string(&err), "Bundle has declared wrong total gas cost");
+        built.txt_bundle.core.total_gas_cost += 1;
+        built.txt_bundle.core.essential_gas_cost -= 1;
+        let err = verify::<ServerSystem<_>>(&Context{ store: &state.store, txt_bundle: &built }, block, &heap).unwrap_err();
+        assert_eq!(error_to_string(&err), "Bundle has declared wrong essential gas cost");
+        built.txt_bundle.core.essential_gas_cost += 1;
+        built.txt_bundle.core.param_heap_limit -= 1;
+        let err = verify::<ServerSystem<_>>(&Context{ store: &state.store, txt_bundle: &built }, block, &heap).unwrap_err();
+        assert_eq!(error_to_string(&err), "Bundle has not reserved enough parameter heap space");
+
+        let (_, receipt) = state.submit_bundle(&bundle(&state, &builder)).unwrap();
+        assert!(receipt.success);
+        assert!(state.store.get(StorageClass::EntryValue, &[1; 20], |_| ()).is_err());
+        assert!(state.store.get(StorageClass::EntryValue, &[9; 20], |_| ()).is_ok());
+    }
+
+    #[test]
+    fn paid_fees_go_to_the_payer_and_the_producer() {
+        let (mut state, txts) = chain("fee_split");
+        state.gas_price = 1;
+        let payer = [9u8; 20];
+        let mut builder = BundleBuilder::new(0);
+        builder.invoke(txts.pay, &[num(1_000_000), id(9)], &[Output::Fee]).unwrap();
+        builder.section(SectionType::Custom).unwrap()
+            .invoke(txts.mint, &[id(1), num(5)], &[Output::Store]).unwrap()
+            .invoke(txts.choose, &[Input::Literal(vec![0]), num(5)], &[]).unwrap();
+        let (_, receipt) = state.submit_bundle(&bundle(&state, &builder)).unwrap();
+        assert!(receipt.success);
+        //whatever is not refunded to the payer is earned by the producer
+        let refunded = read_fee_balance(&state.store, &payer).unwrap();
+        assert_eq!(read_fee_balance(&state.store, &LOCAL_PRODUCER).unwrap(), 1_000_000 - refunded);
+        assert_eq!(state.store.digest(StorageClass::EntryValue).unwrap().entries, 1);
+    }
+
+    #[cfg(feature = "dynamic_gas")]
+    #[test]
+    fn unused_gas_is_refunded_to_the_payer() {
+        let (mut state, txts) = chain("fee_refund");
+        state.gas_price = 2;
+        let payer = [9u8; 20];
+        let mut builder = BundleBuilder::new(0);
+        builder.invoke(txts.pay, &[num(1_000_000), id(9)], &[Output::Fee]).unwrap();
+        //the cheap branch of choose uses less gas than the verification reserved for it
+        builder.section(SectionType::Custom).unwrap().invoke(txts.choose, &[Input::Literal(vec![0]), num(5)], &[]).unwrap();
+        let (_, receipt) = state.submit_bundle(&bundle(&state, &builder)).unwrap();
+        assert!(receipt.success);
+        assert!(receipt.refunded_gas > 0);
+        let refund = receipt.refunded_gas*2;
+        assert_eq!(read_fee_balance(&state.store, &payer).unwrap(), refund);
+        assert_eq!(read_fee_balance(&state.store, &LOCAL_PRODUCER).unwrap(), 1_000_000 - refund);
+    }
+
+    #[cfg(feature = "dynamic_gas")]
+    #[test]
+    fn bundles_running_out_of_gas_are_aborted() {
+        let (state, txts) = chain("out_of_gas");
+        let mut builder = BundleBuilder::new(0);
+        builder.invoke(txts.mint, &[id(1), num(5)], &[Output::Store]).unwrap();
+        let data = bundle(&state, &builder);
+        let heap = Heap::new(2*CONFIG.calc_heap_size(2),2.0);
+        let alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
+        let mut txt_bundle = <ServerSystem>::parse_bundle(&data, &alloc).unwrap();
+        //the declared gas is far below what the interpreter needs (execute does not verify the bundle)
+        txt_bundle.txt_bundle.core.total_gas_cost = 1;
+        let block = BlockInfo{ block_no: 0, timestamp: 0, producer: LOCAL_PRODUCER, gas_price: 0 };
+        let ctx = Context{ store: &state.store, txt_bundle: &txt_bundle };
+        let mut events = Events(vec![]);
+        let err = execute::<_, ServerSystem>(ctx, block, &heap, &mut events, true).unwrap_err();
+        assert_eq!(error_to_string(&err), "Out of gas");
+        assert_eq!(events.0.last().unwrap(), "bundle_finish false");
+        assert_eq!(state.store.digest(StorageClass::EntryValue).unwrap().entries, 0);
+    }
+}
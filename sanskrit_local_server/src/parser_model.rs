@@ -6,6 +6,7 @@ use hex::decode;
 use crate::convert_error;
 use sanskrit_common::hashing::HashingDomain;
 use std::collections::BTreeSet;
+use ethnum::U256;
 
 #[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Debug)]
 pub struct Execute{
@@ -31,7 +32,8 @@ pub enum RetInput {
     Log(String),
     Store(String),
     Assign(String),
-    Drop
+    Drop,
+    Fee
 }
 
 #[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Debug)]
@@ -46,6 +48,7 @@ pub enum LitInput {
     U32(u32),
     U64(u64),
     U128(u128),
+    U256(U256),
     I8(i8),
     I16(i16),
     I32(i32),
@@ -100,6 +103,7 @@ impl Execute {
                 LitInput::U32(num) => num.serialize(s)?,
                 LitInput::U64(num) => num.serialize(s)?,
                 LitInput::U128(num) => num.serialize(s)?,
+                LitInput::U256(num) => s.produce_bytes(&num.to_be_bytes()),
                 LitInput::I8(num) => num.serialize(s)?,
                 LitInput::I16(num) => num.serialize(s)?,
                 LitInput::I32(num) => num.serialize(s)?,
@@ -152,6 +156,7 @@ impl Execute {
             RetInput::Log(_) => Ret::Log,
             RetInput::Store(_) => Ret::Elem,
             RetInput::Drop => Ret::Drop,
+            RetInput::Fee => Ret::Fee,
             RetInput::Assign(name) => {
                 bindings.insert(name.clone());
                 Ret::Assign(name.clone())
@@ -181,7 +186,23 @@ impl Execute {
                 RetInput::Store(name) => name.clone(),
                 RetInput::Assign(name) => name.clone(),
                 RetInput::Drop => "".to_owned(),
+                RetInput::Fee => "".to_owned(),
             })
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ExecuteParser;
+
+    #[test]
+    fn out_of_range_numbers_are_errors() {
+        let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+        let txt = ExecuteParser::new().parse(&format!("big(u256({})):()", max)).unwrap();
+        assert_eq!(txt.params, vec![ParamInput::Lit(LitInput::U256(U256::MAX))]);
+        assert!(ExecuteParser::new().parse(&format!("big(u256({}6)):()", max)).is_err());
+        assert!(ExecuteParser::new().parse("big(u256(-1)):()").is_err());
+        assert!(ExecuteParser::new().parse("small(u8(256)):()").is_err());
+    }
+}
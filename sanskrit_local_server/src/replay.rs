@@ -0,0 +1,136 @@
+//! Re-executes a block log from genesis to check that execution is deterministic
+//!
+//! Every entry of the log carries the state root the server had after it.
+//! Replaying the log into an empty store has to reproduce these roots, a difference means that the build,
+//! the enabled features (for example advanced or dynamic_gas) or the store backend changed the outcome of an execution.
+//! The replay stops at the first entry that fails or leads to a different root, as all later roots would differ anyway.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use hex::encode;
+use sanskrit_common::errors::*;
+use sanskrit_common::arena::Heap;
+use sanskrit_common::store::{CachedStore, ListableStore, StorageClass, state_root};
+use sanskrit_core::model::Module;
+use sanskrit_runtime::{deploy, execute, verify, BlockInfo, Context, Tracker, TransactionBundle, CONFIG};
+use sanskrit_runtime::model::{BundleSection, SectionType, Transaction, ParamRef, RetType};
+use sanskrit_interpreter::model::{Entry, TxTParam, TxTReturn};
+use sanskrit_runtime::system::SystemContext;
+use sanskrit_default_externals::{ServerExternals, SYS_MODS};
+use sanskrit_sled_store::SledStore;
+use sanskrit_memory_store::BTreeMapStore;
+use crate::blocks::{BlockLog, LogEntry, LoggedBlock, LoggedDeploy};
+use crate::externals::ServerSystem;
+use crate::manager::LOCAL_PRODUCER;
+
+pub struct ReplayReport {
+    //entries that were replayed (including the diverging one)
+    pub entries:usize,
+    pub blocks:usize,
+    pub bundles:usize,
+    //describes the first entry that did not reproduce the logged state
+    pub divergence:Option<String>,
+}
+
+fn replay_deploy<S:ListableStore>(deploy_entry:&LoggedDeploy, store:&S) -> Result<()> {
+    let heap = Heap::new(CONFIG.calc_heap_size(2),2.0);
+    let cached = CachedStore::<Module,_>::new(store, StorageClass::Module);
+    let hashes = deploy::<_, ServerExternals>(&cached, &deploy_entry.data.0, &heap, deploy_entry.system_mode)?;
+    //later entries may depend on the system module (like the server did when it was deployed)
+    if let Some(sys_id) = deploy_entry.system_id {
+        match (SYS_MODS.get(sys_id as usize), hashes.first()) {
+            (Some(sys_impl), Some(hash)) => sys_impl(*hash),
+            _ => return error(||"Deploy is registered as an unknown system module")
+        }
+    }
+    Ok(())
+}
+
+//Remembers if the essential section of the bundle was committed
+struct Paid(bool);
+impl Tracker for Paid {
+    fn block_start(&mut self, _block_no:u64) {}
+    fn bundle_start<T:TransactionBundle>(&mut self, _bundle:&T) {}
+    fn section_start(&mut self, _section:&BundleSection) {}
+    fn transaction_start(&mut self, _transaction:&Transaction) {}
+    fn parameter_load(&mut self, _p_ref:&ParamRef, _p_desc:&TxTParam, _value:&Entry) {}
+    fn return_value(&mut self, _r_typ:&RetType, _r_desc:&TxTReturn, _value:&Entry) {}
+    fn transaction_finish(&mut self, _transaction:&Transaction, _success:bool) {}
+    fn section_finish(&mut self, section:&BundleSection, success:bool) {
+        if success && section.typ == SectionType::Essential { self.0 = true }
+    }
+    fn bundle_finish<T:TransactionBundle>(&mut self, _bundle:&T, _success:bool) {}
+    fn block_finish(&mut self, _block_no:u64, _success:bool) {}
+}
+
+fn replay_block<S:ListableStore>(block:&LoggedBlock, store:&S) -> Result<()> {
+    let info = BlockInfo{block_no:block.block_no, timestamp:block.timestamp, producer:LOCAL_PRODUCER, gas_price:block.gas_price};
+    for bundle in block.bundles.iter() {
+        //same sizes as the server uses when it executes a bundle
+        let heap = Heap::new(2*CONFIG.calc_heap_size(2),2.0);
+        let alloc = heap.new_virtual_arena(CONFIG.max_bundle_size);
+        let txt_bundle = <ServerSystem<S>>::parse_bundle(&bundle.0, &alloc)?;
+        let ctx = Context { store, txt_bundle: &txt_bundle };
+        verify::<ServerSystem<S>>(&ctx, info, &heap)?;
+        //the server includes bundles that fail after their essential section with a failed receipt
+        // their committed sections are part of the logged state, so only earlier failures break the replay
+        let mut paid = Paid(false);
+        if let Err(err) = execute::<_, ServerSystem<S>>(ctx, info, &heap, &mut paid, true) {
+            if !paid.0 { return Err(err) }
+        }
+    }
+    Ok(())
+}
+
+//Replays the entries into the store, which has to be empty
+pub fn replay<S:ListableStore>(entries:&[LogEntry], store:&S) -> Result<ReplayReport> {
+    let mut report = ReplayReport{ entries: 0, blocks: 0, bundles: 0, divergence: None };
+    for (index, entry) in entries.iter().enumerate() {
+        report.entries += 1;
+        let (name, expected_root, res) = match *entry {
+            LogEntry::Deploy(ref deploy_entry) => (format!("deploy (entry {})", index), deploy_entry.state_root, replay_deploy(deploy_entry, store)),
+            LogEntry::Block(ref block) => {
+                report.blocks += 1;
+                report.bundles += block.bundles.len();
+                (format!("block {} (entry {})", block.block_no, index), block.state_root, replay_block(block, store))
+            }
+        };
+        if let Err(err) = res {
+            report.divergence = Some(format!("{} failed: {}", name, error_to_string(&err)));
+            return Ok(report)
+        }
+        let root = state_root(store)?;
+        if root != expected_root {
+            report.divergence = Some(format!("{} has state root {} instead of {}", name, encode(root), encode(expected_root)));
+            return Ok(report)
+        }
+    }
+    Ok(report)
+}
+
+//Replays the log into a fresh store of the backend and prints the outcome, returns true if all roots matched
+pub fn run(log:&Path, in_memory:bool) -> Result<bool> {
+    let entries = BlockLog::read(log)?;
+    let (backend, report) = if in_memory {
+        ("memory", replay(&entries, &BTreeMapStore::new())?)
+    } else {
+        //a fresh folder, the db folder of the server is not touched
+        let folder = std::env::temp_dir().join(format!("sanskrit_replay_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        let report = replay(&entries, &SledStore::new(&folder, BTreeSet::new()));
+        let _ = fs::remove_dir_all(&folder);
+        ("sled", report?)
+    };
+    println!("Replayed {} of {} log entries ({} blocks with {} bundles) into a {} store", report.entries, entries.len(), report.blocks, report.bundles, backend);
+    match report.divergence {
+        None => {
+            println!("All state roots match");
+            Ok(true)
+        },
+        Some(divergence) => {
+            println!("Diverged at {}", divergence);
+            Ok(false)
+        }
+    }
+}
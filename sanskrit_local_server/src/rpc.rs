@@ -0,0 +1,458 @@
+//! JSON-RPC interface of the local server
+//!
+//! Requests are JSON-RPC 2.0 objects posted over HTTP, they are processed one after the other while holding the state.
+//! Modules, transactions, bundles, entries and hashes are hex encoded (the 0x prefix is optional).
+//! Wherever a hash is expected the name the module, transaction or element was registered under can be given instead.
+
+use std::sync::{Arc, Mutex};
+use serde_json::{json, Value, Map};
+use tiny_http::{Server, Request, Response, Header, Method};
+use hex::encode;
+use sled::Db;
+use sanskrit_common::errors::*;
+use sanskrit_common::model::{Hash, hash_from_slice};
+use sanskrit_common::store::{Store, StorageClass};
+use sanskrit_common::arena::Heap;
+use sanskrit_runtime::{CONFIG, read_transaction_desc};
+use sanskrit_common::encoding::{Parser, NoCustomAlloc};
+use sanskrit_runtime::model::{DeployType, DeployPackage};
+use sanskrit_runtime::dry_run::DeployReport;
+use crate::manager::{State, ExecutionState, Receipt, MAX_PARSE_DEPTH};
+use crate::compiler::CompilerInstance;
+use crate::blocks::BlockReport;
+use crate::convert_error;
+
+pub const RPC_ADDRESS:&str = "127.0.0.1:6001";
+
+const PARSE_ERROR:i64 = -32700;
+const INVALID_REQUEST:i64 = -32600;
+const METHOD_NOT_FOUND:i64 = -32601;
+const SERVER_ERROR:i64 = -32000;
+
+enum Failure {
+    Rpc(i64, String),
+    Sanskrit(ErrorType),
+}
+
+impl From<ErrorType> for Failure {
+    fn from(err:ErrorType) -> Self {
+        Failure::Sanskrit(err)
+    }
+}
+
+fn param<'a>(params:&'a Value, name:&str) -> Result<&'a Value> {
+    match params.get(name) {
+        Some(value) => Ok(value),
+        None => owned_error(||format!("Missing parameter {}", name))
+    }
+}
+
+fn string_param<'a>(params:&'a Value, name:&str) -> Result<&'a str> {
+    match param(params, name)?.as_str() {
+        Some(value) => Ok(value),
+        None => owned_error(||format!("Parameter {} must be a string", name))
+    }
+}
+
+fn hex_param(params:&Value, name:&str) -> Result<Vec<u8>> {
+    let value = string_param(params, name)?;
+    match hex::decode(value.strip_prefix("0x").unwrap_or(value)) {
+        Ok(data) => Ok(data),
+        Err(_) => owned_error(||format!("Parameter {} must be hex encoded", name))
+    }
+}
+
+fn to_hash(data:&[u8]) -> Result<Hash> {
+    if data.len() != 20 {
+        return error(||"Hashes must have 20 bytes")
+    }
+    Ok(hash_from_slice(data))
+}
+
+//Accepts {"hash": <hex>} or {"name": <name registered in names>}
+fn hash_or_name(params:&Value, names:&Db) -> Result<Hash> {
+    if params.get("hash").is_some() {
+        return to_hash(&hex_param(params, "hash")?)
+    }
+    match convert_error(names.get(string_param(params, "name")?))? {
+        Some(hash) => to_hash(&hash),
+        None => error(||"Name not found")
+    }
+}
+
+fn deploy_type(params:&Value) -> Result<DeployType> {
+    match string_param(params, "type")? {
+        "module" => Ok(DeployType::Module),
+        "transaction" => Ok(DeployType::Transaction),
+        "package" => Ok(DeployType::Package),
+        _ => error(||"Type must be module, transaction or package")
+    }
+}
+
+fn receipt_json(hash:&Hash, receipt:&Receipt) -> Value {
+    json!({
+        "hash": encode(hash),
+        "block_no": receipt.block_no,
+        "success": receipt.success,
+        "used_gas": receipt.used_gas,
+        "refunded_gas": receipt.refunded_gas,
+        "deployed": receipt.deployed.iter().map(encode).collect::<Vec<_>>()
+    })
+}
+
+fn report_json(state:&State, report:DeployReport) -> Result<Value> {
+    let failure = match report.failure {
+        None => None,
+        Some(failure) => {
+            let mut location = String::new();
+            convert_error(failure.write_named(&mut location, &state.tracking))?;
+            Some(location)
+        }
+    };
+    Ok(json!({
+        "type": format!("{:?}", report.typ),
+        "hash": encode(report.hash),
+        "size": report.size,
+        "dependencies": report.dependencies.iter().map(encode).collect::<Vec<_>>(),
+        "components": report.components.iter().map(|comp|json!({
+            "kind": format!("{:?}", comp.kind),
+            "offset": comp.offset,
+            "valid": comp.valid
+        })).collect::<Vec<_>>(),
+        "descriptor": report.descriptor.map(|desc|json!({
+            "hash": encode(desc.hash),
+            "size": desc.size,
+            "max_stack": desc.max_stack,
+            "max_frames": desc.max_frames,
+            "max_mem": desc.max_mem,
+            "gas_cost": desc.gas_cost
+        })),
+        "deploy_gas": report.deploy_gas,
+        "bundle_gas": report.bundle_gas,
+        "failure": failure,
+        "error": report.error.map(|err|error_to_string(&err).to_owned())
+    }))
+}
+
+fn deploy(state:&mut State, compiler:&mut CompilerInstance, params:&Value) -> Result<Value> {
+    let data = hex_param(params, "data")?;
+    let name = params.get("name").and_then(|name|name.as_str());
+    match deploy_type(params)? {
+        DeployType::Module => {
+            let hash = state.deploy_module(compiler, data, false, None)?;
+            if let Some(name) = name {
+                convert_error(state.module_name_mapping.insert(name, &hash))?;
+                convert_error(state.module_name_mapping.flush())?;
+            }
+            Ok(json!({"hash": encode(hash)}))
+        },
+        //the data is an encoded DeployPackage, its parts are deployed together
+        DeployType::Package => {
+            if name.is_some() { return error(||"Packages can not be registered under a name") }
+            let package:DeployPackage = Parser::parse_fully(&data, MAX_PARSE_DEPTH, &NoCustomAlloc())?;
+            let hashes = state.deploy_package(compiler, package.modules, package.transactions)?;
+            Ok(json!({"hashes": hashes.iter().map(encode).collect::<Vec<_>>()}))
+        },
+        DeployType::Transaction => {
+            let (hash, desc_hash) = state.deploy_transaction(compiler, data)?;
+            if let Some(name) = name {
+                convert_error(state.transaction_name_mapping.insert(name, &desc_hash))?;
+                convert_error(state.transaction_name_mapping.flush())?;
+            }
+            Ok(json!({"hash": encode(hash), "descriptor": encode(desc_hash)}))
+        }
+    }
+}
+
+fn submit_bundle(state:&mut State, params:&Value) -> Result<Value> {
+    let data = hex_param(params, "bundle")?;
+    //the bundle was not built by the repl, so there are no names to track
+    state.tracking.exec_state = ExecutionState::new();
+    let (hash, receipt) = state.submit_bundle(&data)?;
+    Ok(receipt_json(&hash, &receipt))
+}
+
+fn block_json(report:&BlockReport) -> Value {
+    json!({
+        "block_no": report.block_no,
+        "timestamp": report.timestamp,
+        "included": report.included.iter().map(|(hash, receipt)|receipt_json(hash, receipt)).collect::<Vec<_>>(),
+        "rejected": report.rejected.iter().map(|(hash, err)|json!({"hash": encode(hash), "error": err})).collect::<Vec<_>>(),
+        "dropped": report.dropped.iter().map(encode).collect::<Vec<_>>()
+    })
+}
+
+fn get_entry(state:&mut State, params:&Value) -> Result<Value> {
+    let hash = hash_or_name(params, &state.tracking.active_elems)?;
+    let data = state.store.get(StorageClass::EntryValue, &hash, |d|d.to_vec())?;
+    //the schema is only known for entries stored by bundles that were executed on this node
+    let value = state.entry_json(&hash, &data)?;
+    Ok(json!({"hash": encode(hash), "data": encode(data), "value": value}))
+}
+
+fn get_descriptor(state:&State, params:&Value) -> Result<Value> {
+    let hash = hash_or_name(params, &state.transaction_name_mapping)?;
+    let heap = Heap::new(CONFIG.calc_heap_size(2),2.0);
+    let alloc = heap.new_virtual_arena(CONFIG.max_structural_dept*1024);
+    let desc = read_transaction_desc(&hash, &state.store, &alloc)?;
+    Ok(json!({
+        "hash": encode(hash),
+        "size": desc.byte_size,
+        "virtual_size": desc.virt_size,
+        "gas_cost": desc.gas_cost,
+        "max_stack": desc.max_stack,
+        "max_frames": desc.max_frames,
+        "max_mem": desc.max_mem,
+        "params": desc.params.iter().map(|p|json!({
+            "consumes": p.consumes,
+            "primitive": p.primitive,
+            "copy": p.copy,
+            "drop": p.drop
+        })).collect::<Vec<_>>(),
+        "returns": desc.returns.iter().map(|r|json!({
+            "primitive": r.primitive,
+            "copy": r.copy,
+            "drop": r.drop
+        })).collect::<Vec<_>>()
+    }))
+}
+
+fn get_bounds(state:&State, params:&Value) -> Result<Value> {
+    let hash = hash_or_name(params, &state.module_name_mapping)?;
+    let bounds = state.callable_bounds(hash)?;
+    Ok(Value::Array(bounds.into_iter().map(|callable|json!({
+        "kind": format!("{:?}", callable.kind),
+        "offset": callable.offset,
+        "gas": callable.resources.gas,
+        "mem": callable.resources.mem,
+        "stack": callable.resources.manifest_stack,
+        "frames": callable.resources.frames,
+        "branches": callable.branches.iter().map(|branch|json!({
+            "path": branch.path.iter().map(|(b, op)|json!({"branch": b, "opcode": op})).collect::<Vec<_>>(),
+            "branch": branch.branch,
+            "gas": branch.gas,
+            "mem": branch.mem
+        })).collect::<Vec<_>>()
+    })).collect()))
+}
+
+fn get_receipt(state:&State, params:&Value) -> Result<Value> {
+    let hash = to_hash(&hex_param(params, "hash")?)?;
+    Ok(match state.get_receipt(&hash)? {
+        Some(receipt) => receipt_json(&hash, &receipt),
+        None => Value::Null
+    })
+}
+
+fn dispatch(state:&mut State, compiler:&mut CompilerInstance, method:&str, params:&Value) -> core::result::Result<Value, Failure> {
+    Ok(match method {
+        "deploy" => deploy(state, compiler, params)?,
+        "submit_bundle" => submit_bundle(state, params)?,
+        "send_bundle" => json!({"hash": encode(state.send_bundle(hex_param(params, "bundle")?)?)}),
+        "produce_block" => block_json(&state.produce_block()?),
+        "mempool" => Value::Array(state.mempool.pending().iter().map(|bundle|json!({
+            "hash": encode(bundle.hash),
+            "earliest_block": bundle.earliest_block
+        })).collect()),
+        "dry_run" => {
+            let typ = deploy_type(params)?;
+            let data = hex_param(params, "data")?;
+            let mut reports = Vec::new();
+            for report in state.dry_run_reports(typ, &data)? {
+                reports.push(report_json(state, report)?)
+            }
+            Value::Array(reports)
+        },
+        "get_entry" => get_entry(state, params)?,
+        "get_module" => {
+            let hash = hash_or_name(params, &state.module_name_mapping)?;
+            state.export_module_abi(hash)?
+        },
+        "get_descriptor" => get_descriptor(state, params)?,
+        "get_bounds" => get_bounds(state, params)?,
+        "get_receipt" => get_receipt(state, params)?,
+        "block_no" => json!(state.block_no()?),
+        _ => return Err(Failure::Rpc(METHOD_NOT_FOUND, format!("Unknown method {}", method)))
+    })
+}
+
+fn process(shared_state:&Arc<Mutex<State>>, compiler:&mut CompilerInstance, body:&str) -> Value {
+    let request:Value = match serde_json::from_str(body) {
+        Ok(request) => request,
+        Err(err) => return json!({"jsonrpc": "2.0", "id": null, "error": {"code": PARSE_ERROR, "message": err.to_string()}})
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = match request.get("method").and_then(|m|m.as_str()) {
+        Some(method) => method,
+        None => return json!({"jsonrpc": "2.0", "id": id, "error": {"code": INVALID_REQUEST, "message": "Request has no method"}})
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Object(Map::new()));
+    let res = match shared_state.lock() {
+        Ok(mut state) => dispatch(&mut state, compiler, method, &params),
+        Err(_) => Err(Failure::Rpc(SERVER_ERROR, "State is poisoned".to_owned()))
+    };
+    match res {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(Failure::Rpc(code, message)) => json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}}),
+        Err(Failure::Sanskrit(err)) => json!({"jsonrpc": "2.0", "id": id, "error": {"code": SERVER_ERROR, "message": error_to_string(&err)}}),
+    }
+}
+
+fn respond(mut request:Request, shared_state:&Arc<Mutex<State>>, compiler:&mut CompilerInstance) -> std::io::Result<()> {
+    if *request.method() != Method::Post {
+        return request.respond(Response::from_string("Only POST is supported").with_status_code(405))
+    }
+    let mut body = String::new();
+    let answer = match request.as_reader().read_to_string(&mut body) {
+        Ok(_) => process(shared_state, compiler, &body),
+        Err(err) => json!({"jsonrpc": "2.0", "id": null, "error": {"code": PARSE_ERROR, "message": err.to_string()}})
+    };
+    //static header that is always valid
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    request.respond(Response::from_string(answer.to_string()).with_header(header))
+}
+
+//Serves requests for as long as the server runs
+pub fn serve(address:&str, shared_state:Arc<Mutex<State>>) -> Result<()> {
+    let server = match Server::http(address) {
+        Ok(server) => server,
+        Err(err) => return owned_error(||err.to_string())
+    };
+    println!("JSON-RPC interface listens on http://{}", address);
+    CompilerInstance::with_compiler_result(|compiler|{
+        for request in server.incoming_requests() {
+            //a client that went away does not concern the others
+            if let Err(err) = respond(request, &shared_state, compiler) {
+                println!("Answering JSON-RPC request failed: {}", err);
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sanskrit_common::encoding::Serializer;
+    use sanskrit_common::store::store_hash;
+    use sanskrit_runtime::model::SectionType;
+    use sanskrit_client::{BundleBuilder, Output};
+    use crate::fixtures::{chain, bundle, num, id, empty_module, choice_module, fan_out_transaction, mistyped_transaction};
+
+    //Processes a request and returns its result (or panics with its error)
+    fn call(shared_state:&Arc<Mutex<State>>, compiler:&mut CompilerInstance, method:&str, params:Value) -> Value {
+        let request = json!({"jsonrpc": "2.0", "id": 7, "method": method, "params": params});
+        let mut res = process(shared_state, compiler, &request.to_string());
+        assert_eq!(res["id"], json!(7));
+        match res.get("error") {
+            None => res["result"].take(),
+            Some(err) => panic!("{} failed with {}", method, err)
+        }
+    }
+
+    #[test]
+    fn failed_bundles_do_not_poison_the_state() {
+        let shared_state = Arc::new(Mutex::new(State::temporary("rpc_failed_bundle")));
+        CompilerInstance::with_compiler_result(|compiler|{
+            let res = process(&shared_state, compiler, r#"{"jsonrpc": "2.0", "id": 1, "method": "submit_bundle", "params": {"bundle": "00ff"}}"#);
+            assert_eq!(res["id"], json!(1));
+            assert_eq!(res["error"]["code"], json!(SERVER_ERROR));
+            assert!(res.get("result").is_none());
+
+            assert!(!shared_state.is_poisoned());
+            let res = process(&shared_state, compiler, r#"{"jsonrpc": "2.0", "id": 2, "method": "block_no"}"#);
+            assert_eq!(res["result"], json!(0));
+
+            let res = process(&shared_state, compiler, r#"{"jsonrpc": "2.0", "id": 3, "method": "no_such_method"}"#);
+            assert_eq!(res["error"]["code"], json!(METHOD_NOT_FOUND));
+            Ok(())
+        }).unwrap();
+    }
+
+    #[test]
+    fn packages_can_be_deployed() {
+        let shared_state = Arc::new(Mutex::new(chain("rpc_package").0));
+        let package = DeployPackage{ modules: vec![empty_module()], transactions: vec![fan_out_transaction(0)] };
+        let data = encode(Serializer::serialize_fully(&package, MAX_PARSE_DEPTH).unwrap());
+        CompilerInstance::with_compiler_result(|compiler|{
+            let request = format!(r#"{{"jsonrpc": "2.0", "id": 1, "method": "deploy", "params": {{"type": "package", "data": "{}"}}}}"#, data);
+            let res = process(&shared_state, compiler, &request);
+            let hashes = res["result"]["hashes"].as_array().unwrap();
+            assert_eq!(hashes[0], json!(encode(store_hash(&[&empty_module()]))));
+            assert_eq!(hashes.len(), 2);
+            Ok(())
+        }).unwrap();
+    }
+
+    #[test]
+    fn bundles_are_submitted_and_their_receipts_kept() {
+        let (state, txts) = chain("rpc_submit");
+        let mut builder = BundleBuilder::new(0);
+        builder.invoke(txts.pay, &[num(1_000_000), id(9)], &[Output::Fee]).unwrap();
+        builder.section(SectionType::Custom).unwrap().invoke(txts.mint, &[id(1), num(5)], &[Output::Store]).unwrap();
+        let data = encode(bundle(&state, &builder));
+        let shared_state = Arc::new(Mutex::new(state));
+        CompilerInstance::with_compiler_result(|compiler|{
+            let receipt = call(&shared_state, compiler, "submit_bundle", json!({"bundle": data}));
+            assert_eq!(receipt["success"], json!(true));
+            assert_eq!(receipt["block_no"], json!(0));
+            assert!(receipt["used_gas"].as_u64().unwrap() > 0);
+            assert_eq!(receipt["deployed"], json!([]));
+            assert_eq!(call(&shared_state, compiler, "get_receipt", json!({"hash": receipt["hash"]})), receipt);
+            assert_eq!(call(&shared_state, compiler, "block_no", json!({})), json!(1));
+
+            //the minted token is stored under its id
+            let entry = call(&shared_state, compiler, "get_entry", json!({"hash": encode([1u8; 20])}));
+            assert_eq!(entry["hash"], json!(encode([1u8; 20])));
+            assert_eq!(entry["value"]["fields"][0], json!(encode([1u8; 20])));
+            //integers wider than 32 bits are strings
+            assert_eq!(entry["value"]["fields"][1], json!("5"));
+            Ok(())
+        }).unwrap();
+    }
+
+    #[test]
+    fn bounds_are_reported_by_hash_and_name() {
+        let shared_state = Arc::new(Mutex::new(chain("rpc_bounds").0));
+        CompilerInstance::with_compiler_result(|compiler|{
+            let bounds = call(&shared_state, compiler, "get_bounds", json!({"hash": encode(store_hash(&[&choice_module()]))}));
+            let bounds = bounds.as_array().unwrap();
+            assert_eq!(bounds.len(), 1);
+            assert_eq!(bounds[0]["kind"], json!("Function"));
+            assert_eq!(bounds[0]["offset"], json!(0));
+            assert!(bounds[0]["gas"].as_u64().unwrap() > 0);
+            assert_eq!(bounds[0]["branches"], json!([]));
+
+            let deployed = call(&shared_state, compiler, "deploy", json!({"type": "module", "data": encode(empty_module()), "name": "empty"}));
+            assert_eq!(deployed["hash"], json!(encode(store_hash(&[&empty_module()]))));
+            assert_eq!(call(&shared_state, compiler, "get_bounds", json!({"name": "empty"})), json!([]));
+            Ok(())
+        }).unwrap();
+    }
+
+    #[test]
+    fn dry_runs_report_without_deploying() {
+        let shared_state = Arc::new(Mutex::new(chain("rpc_dry_run").0));
+        CompilerInstance::with_compiler_result(|compiler|{
+            let reports = call(&shared_state, compiler, "dry_run", json!({"type": "transaction", "data": encode(fan_out_transaction(0))}));
+            let report = &reports.as_array().unwrap()[0];
+            assert_eq!(report["type"], json!("Transaction"));
+            assert_eq!(report["hash"], json!(encode(store_hash(&[&fan_out_transaction(0)]))));
+            assert_eq!(report["components"], json!([{"kind": "Transaction", "offset": 0, "valid": true}]));
+            assert!(report["descriptor"]["gas_cost"].as_u64().is_some());
+            assert_eq!(report["failure"], Value::Null);
+            assert_eq!(report["error"], Value::Null);
+
+            let reports = call(&shared_state, compiler, "dry_run", json!({"type": "transaction", "data": encode(mistyped_transaction())}));
+            let report = &reports.as_array().unwrap()[0];
+            assert_eq!(report["components"], json!([{"kind": "Transaction", "offset": 0, "valid": false}]));
+            assert!(report["failure"].as_str().unwrap().starts_with("Transaction 0 at opcode 0 / branch 1 opcode 1, expected "));
+            assert_eq!(report["descriptor"], Value::Null);
+
+            //nothing was deployed
+            let state = shared_state.lock().unwrap();
+            assert!(state.store.get(StorageClass::Transaction, &store_hash(&[&fan_out_transaction(0)]), |_| ()).is_err());
+            Ok(())
+        }).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+//! Runs a file of repl commands without interaction
+//!
+//! Besides the repl commands a script can state what it expects from the last executed bundle and from the tracked elements
+//! (commands that do not execute a bundle keep the last one, so several expectations can follow a bundle):
+//!   expect success              the bundle was executed and all its sections succeeded
+//!   expect rollback             the bundle was executed but a section was rolled back
+//!   expect gas <max>            the bundle was charged at most max gas
+//!   expect elem <name> <value>  the element exists and has the value (as printed by the elem command)
+//!   expect missing <name>       the element does not exist (anymore)
+//! Empty lines and lines starting with # are ignored.
+//! Failing commands and expectations do not stop the script, they are collected and make the server exit with a non-zero status.
+
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use sanskrit_common::errors::*;
+use sanskrit_common::arena::{Heap, VirtualHeapArena};
+use crate::manager::State;
+use crate::{convert_error, extract_command, process_line, LineProcessor, ProcRes};
+
+//Splits of the first word (keeps the case, element names are case sensitive)
+fn split_word(input:&str) -> (&str, &str) {
+    match input.trim().split_once(char::is_whitespace) {
+        None => (input.trim(), ""),
+        Some((word, rest)) => (word, rest.trim())
+    }
+}
+
+fn check(expectation:&str, state:&mut State) -> core::result::Result<(), String> {
+    let (kind, args) = split_word(expectation);
+    match kind.to_lowercase().as_ref() {
+        "success" | "rollback" => {
+            let expected = kind.eq_ignore_ascii_case("success");
+            match state.last_receipt {
+                None => Err("no bundle was executed".to_owned()),
+                Some(ref receipt) if receipt.success != expected => Err(format!("bundle {}", if receipt.success {"succeeded"} else {"was rolled back"})),
+                Some(_) => Ok(())
+            }
+        },
+        "gas" => {
+            let max:u64 = match args.parse() {
+                Ok(max) => max,
+                Err(_) => return Err(format!("{} is not a gas amount", args))
+            };
+            match state.last_receipt {
+                None => Err("no bundle was executed".to_owned()),
+                Some(ref receipt) if receipt.used_gas > max => Err(format!("bundle was charged {} gas", receipt.used_gas)),
+                Some(_) => Ok(())
+            }
+        },
+        "elem" => {
+            let (name, expected) = split_word(args);
+            match state.get_elem(name) {
+                Ok(ref value) if value == expected => Ok(()),
+                Ok(value) => Err(format!("{} is {}", name, value)),
+                Err(_) => Err(format!("{} does not exist", name))
+            }
+        },
+        "missing" => match state.get_elem(args) {
+            Ok(value) => Err(format!("{} exists with value {}", args, value)),
+            Err(_) => Ok(())
+        },
+        _ => Err(format!("unknown expectation {}", kind))
+    }
+}
+
+//Returns true if all commands and expectations succeeded
+pub fn run(path:&Path, shared_state:Arc<Mutex<State>>) -> Result<bool> {
+    let script = match fs::read_to_string(path) {
+        Ok(script) => script,
+        Err(err) => return owned_error(||format!("Can not read script {}: {}", path.display(), err))
+    };
+    let heap = Heap::new(100000000,2.0);
+    let mut full_heap = heap.new_virtual_arena(10000000);
+
+    let repl_state = shared_state.clone();
+    let mut processor:Box<LineProcessor> = Box::new(move |line:String, heap:&VirtualHeapArena|process_line(line, repl_state.clone(), heap));
+    let mut stack:Vec<Box<LineProcessor>> = Vec::new();
+
+    let mut commands = 0;
+    let mut expectations = 0;
+    let mut failures = Vec::new();
+    for (index, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue }
+        println!(">> {}", line);
+        let (command, input) = extract_command(line.to_owned());
+        if command == "expect" {
+            expectations += 1;
+            let mut state = convert_error(shared_state.lock())?;
+            if let Err(msg) = check(&input, &mut state) {
+                println!("Expectation failed: {}", msg);
+                failures.push(format!("line {}: expect {} ({})", index + 1, input, msg));
+            }
+            continue
+        }
+
+        commands += 1;
+        match processor(line.to_owned(), &full_heap) {
+            Err(err) => {
+                println!("Error: {}", error_to_string(&err));
+                failures.push(format!("line {}: {} ({})", index + 1, line, error_to_string(&err)));
+            }
+            Ok(ProcRes::End) if stack.is_empty() => break,
+            Ok(ProcRes::End) => processor = stack.pop().unwrap(),
+            Ok(ProcRes::Continue) => {},
+            Ok(ProcRes::Switch(proc)) => {
+                stack.push(processor);
+                processor = proc
+            }
+        }
+        full_heap = full_heap.reuse();
+    }
+    if !stack.is_empty() {
+        failures.push("script ended inside of a bundle".to_owned());
+    }
+
+    println!("Script {}: {} commands, {} expectations, {} failures", path.display(), commands, expectations, failures.len());
+    for failure in &failures {
+        println!("  - {}", failure);
+    }
+    Ok(failures.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sanskrit_common::model::LargeVec;
+    use crate::manager::Receipt;
+    use crate::fixtures::{chain, bundle, num, id};
+    use sanskrit_client::{BundleBuilder, Output};
+    use sanskrit_runtime::model::SectionType;
+
+    fn receipt(success:bool, used_gas:u64) -> Option<Receipt> {
+        Some(Receipt{ block_no: 0, success, used_gas, refunded_gas: 0, deployed: LargeVec(Vec::new()) })
+    }
+
+    #[test]
+    fn expectations_check_the_receipt_and_the_elements() {
+        let mut state = State::temporary("script_expectations");
+        assert!(check("success", &mut state).is_err());
+        assert!(check("gas 10", &mut state).is_err());
+
+        state.last_receipt = receipt(true, 10);
+        assert!(check("success", &mut state).is_ok());
+        assert!(check("rollback", &mut state).is_err());
+        assert!(check("gas 10", &mut state).is_ok());
+        assert!(check("gas 9", &mut state).is_err());
+        assert!(check("gas many", &mut state).is_err());
+
+        state.tracking.element_data.insert("Coin", "Coin(5)").unwrap();
+        assert!(check("elem Coin Coin(5)", &mut state).is_ok());
+        assert!(check("elem Coin Coin(6)", &mut state).is_err());
+        assert!(check("elem coin Coin(5)", &mut state).is_err());
+        assert!(check("missing Coin", &mut state).is_err());
+        assert!(check("missing Other", &mut state).is_ok());
+        assert!(check("anything", &mut state).is_err());
+    }
+
+    #[test]
+    fn bundles_failing_after_the_fee_are_rolled_back() {
+        let (mut state, txts) = chain("script_rollback");
+        let mut builder = BundleBuilder::new(0);
+        builder.invoke(txts.pay, &[num(0), id(9)], &[Output::Fee]).unwrap();
+        builder.section(SectionType::Custom).unwrap().invoke(txts.fail, &[], &[]).unwrap();
+        let (_, receipt) = state.submit_bundle(&bundle(&state, &builder)).unwrap();
+        assert!(check("Rollback", &mut state).is_ok());
+        assert!(check("success", &mut state).is_err());
+        assert!(check(&format!("gas {}", receipt.used_gas), &mut state).is_ok());
+    }
+
+    #[test]
+    fn only_executed_bundles_replace_the_receipt() {
+        let shared_state = Arc::new(Mutex::new(State::temporary("script_receipts")));
+        shared_state.lock().unwrap().last_receipt = receipt(true, 10);
+
+        //commands that do not execute a bundle keep the receipt
+        let script = std::env::temp_dir().join(format!("sanskrit_script_receipts_{}.txt", std::process::id()));
+        fs::write(&script, "expect success\naccounts\nexpect success\nexpect gas 10\n").unwrap();
+        assert!(run(&script, shared_state.clone()).unwrap());
+        fs::write(&script, "accounts\nexpect gas 9\n").unwrap();
+        assert!(!run(&script, shared_state.clone()).unwrap());
+        fs::remove_file(&script).unwrap();
+
+        //a bundle that fails before it is executed has no receipt
+        assert!(shared_state.lock().unwrap().submit_bundle(&[0, 255]).is_err());
+        assert!(shared_state.lock().unwrap().last_receipt.is_none());
+    }
+}
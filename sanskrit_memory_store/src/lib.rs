@@ -15,14 +15,17 @@ use core::mem;
 #[derive(Clone, Default, Debug)]
 struct Container {
     persisted:BTreeMap<Hash, Vec<u8>>,
-    pending:BTreeMap<Hash, Option<Vec<u8>>>
+    pending:BTreeMap<Hash, Option<Vec<u8>>>,
+    //digest of the persisted entries
+    digest:ClassDigest,
 }
 
 impl Container {
     pub fn new()-> Self{
         Container{
             persisted: BTreeMap::new(),
-            pending: BTreeMap::new()
+            pending: BTreeMap::new(),
+            digest: ClassDigest::default(),
         }
     }
 
@@ -76,22 +79,33 @@ impl Container {
     pub fn clear(&mut self) {
         self.persisted.clear();
         self.pending.clear();
+        self.digest = ClassDigest::default();
     }
 
     pub fn commit(&mut self) {
         let mut res = BTreeMap::new();
         mem::swap(&mut res, &mut self.pending);
         for (key, value) in res {
-            match value {
+            let old = match value {
                 None => self.persisted.remove(&key),
-                Some(data) => self.persisted.insert(key, data)
+                Some(data) => {
+                    self.digest.insert(&key, &data);
+                    self.persisted.insert(key, data)
+                }
             };
+            if let Some(old) = old {
+                self.digest.remove(&key, &old);
+            }
         }
     }
 
     pub fn rollback(&mut self){
         self.pending.clear();
     }
+
+    pub fn list(&self) -> Vec<(Hash, Vec<u8>)> {
+        self.persisted.iter().map(|(key, value)|(*key, value.clone())).collect()
+    }
 }
 
 //A container for the different storage sections
@@ -103,6 +117,7 @@ pub struct InnerBTreeMapStore {
     funs:Container,      //All the top level Functions(Serialized)
     descs:Container,     //All the top level Functions(Serialized)
     elems:Container,     //All the Elements (Serialized)
+    balances:Container,  //All the fee balances (Serialized u64)
 }
 
 //A BTreeMap backed store for development
@@ -118,6 +133,7 @@ impl BTreeMapStore {
             funs: Container::new(),
             descs: Container::new(),
             elems: Container::new(),
+            balances: Container::new(),
         }))
     }
 
@@ -133,6 +149,7 @@ impl BTreeMapStore {
             StorageClass::Descriptor => process(&mut self.0.borrow_mut().descs),
             StorageClass::EntryValue => process(&mut self.0.borrow_mut().elems),
             StorageClass::EntryHash => process(&mut self.0.borrow_mut().hashs),
+            StorageClass::Balance => process(&mut self.0.borrow_mut().balances),
         }
 
     }
@@ -153,6 +170,7 @@ impl Store for BTreeMapStore {
             StorageClass::Descriptor => process(&mut self.0.borrow_mut().descs, key),
             StorageClass::EntryValue => process(&mut self.0.borrow_mut().elems, key),
             StorageClass::EntryHash => process(&mut self.0.borrow_mut().hashs, key),
+            StorageClass::Balance => process(&mut self.0.borrow_mut().balances, key),
         }
     }
 
@@ -170,6 +188,7 @@ impl Store for BTreeMapStore {
             StorageClass::Descriptor => process(&self.0.borrow().descs, key, f),
             StorageClass::EntryValue => process(&self.0.borrow().elems, key, f),
             StorageClass::EntryHash => process(&self.0.borrow().hashs, key, f),
+            StorageClass::Balance => process(&self.0.borrow().balances, key, f),
         }
     }
 
@@ -187,6 +206,7 @@ impl Store for BTreeMapStore {
             StorageClass::Descriptor => process(&mut self.0.borrow_mut().descs, key, data),
             StorageClass::EntryValue => process(&mut self.0.borrow_mut().elems, key, data),
             StorageClass::EntryHash => process(&mut self.0.borrow_mut().hashs, key, data),
+            StorageClass::Balance => process(&mut self.0.borrow_mut().balances, key, data),
         }
     }
 
@@ -203,6 +223,7 @@ impl Store for BTreeMapStore {
             StorageClass::Descriptor => process(&mut self.0.borrow_mut().descs),
             StorageClass::EntryValue => process(&mut self.0.borrow_mut().elems),
             StorageClass::EntryHash =>  process(&mut self.0.borrow_mut().hashs),
+            StorageClass::Balance =>  process(&mut self.0.borrow_mut().balances),
         }
     }
 
@@ -217,6 +238,34 @@ impl Store for BTreeMapStore {
             StorageClass::Descriptor => process(&mut self.0.borrow_mut().descs),
             StorageClass::EntryValue => process(&mut self.0.borrow_mut().elems),
             StorageClass::EntryHash => process(&mut self.0.borrow_mut().hashs),
+            StorageClass::Balance => process(&mut self.0.borrow_mut().balances),
         }
     }
 }
+
+
+impl ListableStore for BTreeMapStore {
+    fn list(&self, class:StorageClass) -> Result<Vec<(Hash, Vec<u8>)>> {
+        let inner = self.0.borrow();
+        Ok(match class {
+            StorageClass::Module => inner.modules.list(),
+            StorageClass::Transaction => inner.funs.list(),
+            StorageClass::Descriptor => inner.descs.list(),
+            StorageClass::EntryValue => inner.elems.list(),
+            StorageClass::EntryHash => inner.hashs.list(),
+            StorageClass::Balance => inner.balances.list(),
+        })
+    }
+
+    fn digest(&self, class:StorageClass) -> Result<ClassDigest> {
+        let inner = self.0.borrow();
+        Ok(match class {
+            StorageClass::Module => inner.modules.digest,
+            StorageClass::Transaction => inner.funs.digest,
+            StorageClass::Descriptor => inner.descs.digest,
+            StorageClass::EntryValue => inner.elems.digest,
+            StorageClass::EntryHash => inner.hashs.digest,
+            StorageClass::Balance => inner.balances.digest,
+        })
+    }
+}
\ No newline at end of file
@@ -17,6 +17,7 @@ use sanskrit_core::model::Module;
 use sanskrit_common::store::{CachedStore, StorageClass, store_hash};
 use crate::pre_load_store::PreStore;
 use sanskrit_common::encoding::*;
+use sanskrit_common::limiter::Limiter;
 
 #[macro_use]
 extern crate sanskrit_derive;
@@ -61,12 +62,14 @@ pub fn process_preloaded_deploy(modules:Vec<Vec<u8>>, transactions:Vec<Vec<u8>>,
         open_deps.insert(hash);
     }
 
+    //Preloaded modules are trusted and thus not metered
+    let limiter = Limiter::unlimited();
     for h in &mod_compiles {
-        deploy_stored_module(&store,h.module_hash.clone(),system_mode_on)?;
+        deploy_stored_module(&store,h.module_hash.clone(),system_mode_on,&limiter)?;
     }
 
     for h in transactions {
-        validate_function(&store,&h )?;
+        validate_function(&store,&h,&limiter)?;
         let transaction_hash = store_hash(&[&h]);
         txt_compiles.push(ValidatedTransaction{transaction_hash})
     }
@@ -1,255 +1,412 @@
-use sanskrit_common::errors::*;
-use sanskrit_common::encoding::{Parser, ParserAllocator};
-use crate::model::{Transaction, ParamRef, RetType, ParamMode};
-use sanskrit_common::model::{Hash, Ptr, SlicePtr};
-//use ed25519_dalek::*;
-//use sha2::{Sha512};
-use sanskrit_common::arena::*;
-use sanskrit_interpreter::interpreter::{Frame, ExecutionContext, InterpreterResult};
-use sanskrit_interpreter::model::{Entry, TransactionDescriptor, TxTParam, TxTReturn, RuntimeType};
-use alloc::vec::Vec;
-
-use crate::{Tracker, CONFIG};
-use core::cell::RefCell;
-use sanskrit_common::store::Store;
-use crate::{Context, TransactionBundle};
-use crate::system::SystemContext;
-
-//A struct holding context information of the current transaction
-pub struct ExecutionEnvironment<'a, 'b, 'c> {
-    parameter_heap:&'b VirtualHeapArena<'c>,
-    descs:SlicePtr<'a,TransactionDescriptor<'a>>,
-
-    structural_arena:HeapArena<'c>,
-    runtime_heap:VirtualHeapArena<'c>,
-    //Caches to parse each param just once
-    entry_cache:RefCell<Vec<Option<Entry<'b>>>>,
-    literal_cache:RefCell<Vec<Option<Entry<'b>>>>,
-    witness_cache:RefCell<Vec<Option<Entry<'b>>>>,
-    scratch_pad:RefCell<Vec<Option<Entry<'b>>>>,
-
-}
-
-pub trait TransactionExecutionContext<S:Store,B:TransactionBundle> {
-    fn new() -> Self;
-    //reads the desc
-    fn read_transaction_desc<'d, A:ParserAllocator>(&self, ctx:&Context<S,B>, target:&Hash, heap:&'d A) -> Result<TransactionDescriptor<'d>>;
-    //create a provided value
-    //Todo: Do we need some extra info??? in order to select
-    fn create_provided_value<'a,'h>(&self, ctx:&Context<S,B>, typ:Ptr<RuntimeType>, alloc:&'a VirtualHeapArena<'h>, block_no:u64, section_no:u8,  txt_no:u8, p_num:u8) -> Result<Entry<'a>>;
-    //loads an entry
-    fn chain_value_load<'b>(&self, ctx:&Context<S,B>, index:u16, param:TxTParam, parameter_heap:&'b VirtualHeapArena) -> Result<Entry<'b>>;
-    //deletes an entry
-    fn chain_value_delete(&self, ctx:&Context<S,B>, index:u16) -> Result<()>;
-    //stores an entry
-    fn chain_value_store(&self, ctx:&Context<S,B>, entry:&Entry, ret:TxTReturn) -> Result<()>;
-    //commits changes to backend
-    fn commit(&self, ctx:&Context<S,B>);
-    //reverts changes to last commit
-    fn revert(&self, ctx:&Context<S,B>);
-}
-
-//Executes a transaction
-pub fn execute_once<'c, L: Tracker, SYS:SystemContext<'c>>(exec_store:&SYS::EC, ctx:&Context<SYS::S, SYS::B>, block_no:u64, heap:&Heap, tracker:&mut L, commit:bool) -> InterpreterResult {
-    //Create Allocator
-    //create heaps: based on bundle input
-    let structural_arena = heap.new_arena(
-        Heap::max_elems_space::<Entry>(ctx.txt_bundle.stack_elem_limit() as usize)
-            + Heap::max_elems_space::<Frame>(ctx.txt_bundle.stack_frame_limit() as usize)
-            + Heap::max_elems_space::<Entry>(CONFIG.return_stack)
-    );
-
-    let parameter_heap = heap.new_virtual_arena(ctx.txt_bundle.param_heap_limit() as usize);
-    let runtime_heap = heap.new_virtual_arena(ctx.txt_bundle.runtime_heap_limit() as usize);
-
-    let entry_cache = RefCell::new(alloc::vec::from_elem(Option::None, ctx.txt_bundle.stored().len()));
-    let literal_cache = RefCell::new(alloc::vec::from_elem(Option::None, ctx.txt_bundle.literal().len()));
-    let witness_cache = RefCell::new(alloc::vec::from_elem(Option::None,ctx.txt_bundle.witness().len()));
-    let scratch_pad = RefCell::new(alloc::vec::from_elem(Option::None,ctx.txt_bundle.scratch_pad_slots() as usize));
-
-    //Todo: Shall we do lazy? -- currently all the txt loads count to essential cost
-    let desc_alloc = heap.new_virtual_arena(ctx.txt_bundle.transaction_heap_limit() as usize);
-    let mut desc_builder = desc_alloc.slice_builder(ctx.txt_bundle.descriptors().len())?;
-    for desc_hash in ctx.txt_bundle.descriptors().iter() {
-        desc_builder.push(exec_store.read_transaction_desc(ctx, desc_hash, &desc_alloc)?);
-    }
-
-    let mut exec_env = ExecutionEnvironment {
-        descs: desc_builder.finish(),
-        structural_arena,
-        parameter_heap: &parameter_heap,
-        runtime_heap,
-        entry_cache,
-        literal_cache,
-        witness_cache,
-        scratch_pad
-    };
-
-    #[cfg(feature = "dynamic_gas")]
-    let mut used_gas:u64 = 0;
-    tracker.bundle_start(ctx.txt_bundle);
-    let mut sec_no = 0;
-    for txt_section in ctx.txt_bundle.sections().iter() {
-        tracker.section_start(txt_section);
-        let mut txt_no = 0;
-        for txt in txt_section.txts.iter() {
-            tracker.transaction_start(txt);
-            match execute_transaction::<_, SYS>(&exec_env, exec_store, ctx, txt, block_no, sec_no, txt_no, tracker) {
-                #[cfg(feature = "dynamic_gas")]
-                Ok(gas) => used_gas = u64::saturating_add(used_gas, gas),
-                #[cfg(not(feature = "dynamic_gas"))]
-                Ok(_) => {},
-                Err(err) => {
-                    exec_store.revert(ctx);
-                    tracker.transaction_finish(txt, false);
-                    tracker.section_finish(txt_section, false);
-                    tracker.bundle_finish(ctx.txt_bundle, false);
-                    return Err(err);
-                }
-            };
-            txt_no +=1;
-            //release all the memory so it does not leak into the next transaction
-            exec_env.structural_arena = exec_env.structural_arena.reuse();
-            exec_env.runtime_heap = exec_env.runtime_heap.reuse();
-            tracker.transaction_finish(txt, true);
-        }
-        //commit -- making this optional is needed for benchmarking
-        if commit { exec_store.commit(ctx); }
-        tracker.section_finish(txt_section, true);
-        sec_no+=1;
-    }
-    tracker.bundle_finish(ctx.txt_bundle, true);
-
-    #[cfg(feature = "dynamic_gas")]
-    return Ok(used_gas);
-    #[cfg(not(feature = "dynamic_gas"))]
-    return Ok(());
-}
-
-fn execute_transaction<'c, L: Tracker, SYS:SystemContext<'c>>(env:&ExecutionEnvironment, exec_store:&SYS::EC, ctx:&Context<SYS::S, SYS::B>, txt:&Transaction, block_no:u64, sec_no:u8, txt_no:u8,  tracker:&mut L) -> InterpreterResult{
-
-    //Prepare all the Memory
-    let txt_desc:TransactionDescriptor = env.descs[txt.txt_desc as usize];
-    let mut interpreter_stack = env.structural_arena.alloc_stack::<Entry>(txt_desc.max_stack as usize);
-    let mut frame_stack = env.structural_arena.alloc_stack::<Frame>(txt_desc.max_frames as usize);
-    let mut return_stack = env.structural_arena.alloc_stack::<Entry>(CONFIG.return_stack);
-
-    //push everything required onto the stack
-    let mut deletes = Vec::with_capacity(txt_desc.params.len());
-
-    for (p_num, (p,p_typ)) in txt_desc.params.iter().zip(txt.params.iter()).enumerate() {
-        match p_typ {
-            ParamRef::Load(ParamMode::Consume,index) => {
-                //We delete at end so others can copy and in case it produces an error it must still be their
-                deletes.push(index);
-                let data = load_from_store::<SYS>(env, exec_store, ctx, *index, *p)?;
-                tracker.parameter_load(p_typ, p, &data);
-                interpreter_stack.push(data)?;
-            }
-            ParamRef::Load(ParamMode::Copy, index)
-            | ParamRef::Load(ParamMode::Borrow, index) => {
-                let data = load_from_store::<SYS>(env, exec_store, ctx, *index, *p)?;
-                tracker.parameter_load(p_typ, p, &data);
-                interpreter_stack.push(data)?;
-            },
-
-            ParamRef::Provided => {
-                let data = exec_store.create_provided_value(ctx, p.typ, &env.parameter_heap, block_no, sec_no, txt_no, p_num as u8)?;
-                tracker.parameter_load(p_typ, p, &data);
-                interpreter_stack.push(data)?;
-            },
-
-            ParamRef::Fetch(_, index) => {
-                let data = env.scratch_pad.borrow()[*index as usize].unwrap();
-                tracker.parameter_load(p_typ, p, &data);
-                interpreter_stack.push(data)?;
-            }
-
-            ParamRef::Literal(index) => {
-                let data = load_from_literal::<SYS>(env, ctx,*index,*p)?;
-                tracker.parameter_load(p_typ, p, &data);
-                interpreter_stack.push(data)?;
-            },
-            ParamRef::Witness(index) => {
-                let data = load_from_witness::<SYS>(env, ctx, *index,*p)?;
-                tracker.parameter_load(p_typ, p, &data);
-                interpreter_stack.push(data)?;
-            },
-        };
-    }
-
-    let res = ExecutionContext::interpret::<SYS::RE>(&txt_desc.functions, &mut interpreter_stack, &mut frame_stack, &mut return_stack, &env.runtime_heap)?;
-    #[cfg(feature = "dynamic_gas")]
-    if res > txt_desc.gas_cost as u64 {
-        panic!("used gas:{} - max gas:{} - txt:{:?}",res,txt_desc.gas_cost,&txt_desc.functions);
-    }
-    #[cfg(feature = "dynamic_gas")]
-    assert!(res <= txt_desc.gas_cost as u64);
-
-    //Now that we know it succeeds we can modify the store
-    for index in deletes {
-        exec_store.chain_value_delete(ctx, *index)?;
-    }
-
-    assert_eq!(interpreter_stack.len(), txt.returns.len(), "Transaction Return Information missmatched Stack");
-    assert_eq!(interpreter_stack.len(), txt_desc.returns.len(), "Transaction Description Return Information missmatched Stack");
-
-    for ((ret_entry, r), r_typ) in interpreter_stack.as_slice().iter().zip(txt_desc.returns.iter()).zip(txt.returns.iter()) {
-        match r_typ {
-            RetType::Store => {
-                tracker.return_value(r_typ, r, ret_entry);
-                exec_store.chain_value_store(ctx, ret_entry, *r)?
-            },
-            RetType::Put(index) => {
-                tracker.return_value(r_typ, r, ret_entry);
-                let copy = r.desc.move_value(*ret_entry, env.parameter_heap)?;
-                env.scratch_pad.borrow_mut()[*index as usize] = Some(copy)
-            }
-            RetType::Drop => tracker.return_value(r_typ, r, ret_entry),
-            RetType::Log => tracker.return_value(r_typ, r, ret_entry),
-        }
-    }
-    Ok(res)
-}
-
-
-fn load_from_literal<'a, 'b,'c, 'd, SYS:SystemContext<'d>>(env:&ExecutionEnvironment<'a, 'b, 'c>, ctx:&Context<SYS::S, SYS::B>, index:u16, param:TxTParam) -> Result<Entry<'b>> {
-    let entry_copy = env.literal_cache.borrow()[index as usize];
-    Ok(match entry_copy {
-        None => {
-            let data = ctx.txt_bundle.literal()[index as usize];
-            let mut parser = Parser::new(&data, CONFIG.max_structural_dept);
-            let entry = param.desc.parse_value(&mut parser, env.parameter_heap)?;
-            env.literal_cache.borrow_mut()[index as usize] = Some(entry,);
-            entry
-        },
-        Some(entry) => entry
-    })
-}
-
-fn load_from_witness<'a, 'b,'c, 'd, SYS:SystemContext<'d>>(env:&ExecutionEnvironment<'a, 'b, 'c>, ctx:&Context<SYS::S, SYS::B>, index:u16, param:TxTParam) -> Result<Entry<'b>> {
-    let entry_copy = env.witness_cache.borrow()[index as usize];
-    Ok(match entry_copy {
-        None => {
-            let data = ctx.txt_bundle.witness()[index as usize];
-            let mut parser = Parser::new(&data, CONFIG.max_structural_dept);
-            let entry = param.desc.parse_value(&mut parser, env.parameter_heap)?;
-            env.witness_cache.borrow_mut()[index as usize] = Some(entry,);
-            entry
-        },
-        Some(entry) => entry
-    })
-}
-
-
-fn load_from_store<'a, 'b,'c, 'd, SYS:SystemContext<'d>>(env:&ExecutionEnvironment<'a,'b, 'c>, exec_store: &SYS::EC, ctx:&Context<SYS::S, SYS::B>, index:u16, param:TxTParam) -> Result<Entry<'b>> {
-    let entry_copy = env.entry_cache.borrow()[index as usize];
-    Ok(match entry_copy {
-        None => {
-            let entry = exec_store.chain_value_load(ctx, index, param, env.parameter_heap)?;
-            env.entry_cache.borrow_mut()[index as usize] = Some(entry);
-            entry
-        },
-        Some(entry) => entry
-    })
-}
+use sanskrit_common::errors::*;
+use sanskrit_common::encoding::{Parser, ParserAllocator};
+use crate::model::{Transaction, Invoke, DeployTransaction, ParamRef, RetType, ParamMode, SectionType};
+use sanskrit_common::model::{Hash, Ptr, SlicePtr};
+//use ed25519_dalek::*;
+//use sha2::{Sha512};
+use sanskrit_common::arena::*;
+use sanskrit_interpreter::interpreter::{Frame, ExecutionContext, InterpreterResult};
+use sanskrit_interpreter::model::{Entry, TransactionDescriptor, TxTParam, TxTReturn, RuntimeType};
+use alloc::vec::Vec;
+
+use crate::{Tracker, CONFIG, BlockInfo, deploy_gas_limit};
+use core::cell::{Cell, RefCell};
+use sanskrit_common::store::Store;
+use crate::{Context, TransactionBundle};
+use crate::system::SystemContext;
+use sanskrit_interpreter::externals::{RuntimeExternals, SignatureBatch};
+#[cfg(feature = "deployer")]
+use sanskrit_common::store::{CachedStore, StorageClass};
+#[cfg(feature = "deployer")]
+use sanskrit_common::limiter::Limiter;
+#[cfg(feature = "deployer")]
+use sanskrit_core::model::Module;
+#[cfg(feature = "deployer")]
+use crate::deploy_content;
+
+//A struct holding context information of the current transaction
+pub struct ExecutionEnvironment<'a, 'b, 'c> {
+    parameter_heap:&'b VirtualHeapArena<'c>,
+    descs:SlicePtr<'a,TransactionDescriptor<'a>>,
+
+    structural_arena:HeapArena<'c>,
+    runtime_heap:VirtualHeapArena<'c>,
+    //Caches to parse each param just once
+    entry_cache:RefCell<Vec<Option<Entry<'b>>>>,
+    literal_cache:RefCell<Vec<Option<Entry<'b>>>>,
+    witness_cache:RefCell<Vec<Option<Entry<'b>>>>,
+    scratch_pad:RefCell<Vec<Option<Entry<'b>>>>,
+    //Sum of the fees returned in the essential section
+    fee:Cell<u64>,
+    //Receives the refund for unused gas
+    fee_payer:Cell<Option<Hash>>,
+}
+
+pub trait TransactionExecutionContext<S:Store,B:TransactionBundle> {
+    fn new() -> Self;
+    //reads the desc
+    fn read_transaction_desc<'d, A:ParserAllocator>(&self, ctx:&Context<S,B>, target:&Hash, heap:&'d A) -> Result<TransactionDescriptor<'d>>;
+    //create a provided value
+    //Todo: Do we need some extra info??? in order to select
+    fn create_provided_value<'a,'h>(&self, ctx:&Context<S,B>, typ:Ptr<RuntimeType>, alloc:&'a VirtualHeapArena<'h>, block:BlockInfo, section_no:u8,  txt_no:u8, p_num:u8) -> Result<Entry<'a>>;
+    //loads an entry
+    fn chain_value_load<'b>(&self, ctx:&Context<S,B>, index:u16, param:TxTParam, parameter_heap:&'b VirtualHeapArena) -> Result<Entry<'b>>;
+    //deletes an entry
+    fn chain_value_delete(&self, ctx:&Context<S,B>, index:u16) -> Result<()>;
+    //stores an entry
+    fn chain_value_store(&self, ctx:&Context<S,B>, entry:&Entry, ret:TxTReturn) -> Result<()>;
+    //extracts the amount from a fee value
+    fn fee_value(&self, ctx:&Context<S,B>, entry:&Entry, ret:TxTReturn) -> Result<u64>;
+    //extracts the account that receives the refunds of a fee value
+    fn fee_payer(&self, ctx:&Context<S,B>, entry:&Entry, ret:TxTReturn) -> Result<Hash>;
+    //credits the fee paid by the bundle to the block producer
+    fn credit_fee(&self, ctx:&Context<S,B>, block:BlockInfo, amount:u64) -> Result<()>;
+    //moves a part of the credited fee from the block producer back to the payer
+    fn refund_fee(&self, ctx:&Context<S,B>, block:BlockInfo, payer:&Hash, amount:u64) -> Result<()>;
+    //commits changes to backend
+    fn commit(&self, ctx:&Context<S,B>);
+    //reverts changes to last commit
+    fn revert(&self, ctx:&Context<S,B>);
+}
+
+//Executes a bundle
+// The signature checks of a section are collected and answered with true, they are verified together at the end of the section
+// As the answers are only known to hold after the verification, a section is not committed before its batch verified
+// A batch that does not verify fails the section like any other error, the bundle is never executed a second time
+pub fn execute_bundle<'c, L: Tracker, SYS:SystemContext<'c>>(ctx:&Context<SYS::S, SYS::B>, block:BlockInfo, heap:&Heap, tracker:&mut L, commit:bool) -> InterpreterResult {
+    let exec_store = &SYS::EC::new();
+    //Create Allocator
+    //create heaps: based on bundle input
+    let structural_arena = heap.new_arena(
+        Heap::max_elems_space::<Entry>(ctx.txt_bundle.stack_elem_limit() as usize)
+            + Heap::max_elems_space::<Frame>(ctx.txt_bundle.stack_frame_limit() as usize)
+            + Heap::max_elems_space::<Entry>(CONFIG.return_stack)
+    );
+
+    let parameter_heap = heap.new_virtual_arena(ctx.txt_bundle.param_heap_limit() as usize);
+    let runtime_heap = heap.new_virtual_arena(ctx.txt_bundle.runtime_heap_limit() as usize);
+
+    let entry_cache = RefCell::new(alloc::vec::from_elem(Option::None, ctx.txt_bundle.stored().len()));
+    let literal_cache = RefCell::new(alloc::vec::from_elem(Option::None, ctx.txt_bundle.literal().len()));
+    let witness_cache = RefCell::new(alloc::vec::from_elem(Option::None,ctx.txt_bundle.witness().len()));
+    let scratch_pad = RefCell::new(alloc::vec::from_elem(Option::None,ctx.txt_bundle.scratch_pad_slots() as usize));
+
+    //Todo: Shall we do lazy? -- currently all the txt loads count to essential cost
+    let desc_alloc = heap.new_virtual_arena(ctx.txt_bundle.transaction_heap_limit() as usize);
+    let mut desc_builder = desc_alloc.slice_builder(ctx.txt_bundle.descriptors().len())?;
+    for desc_hash in ctx.txt_bundle.descriptors().iter() {
+        desc_builder.push(exec_store.read_transaction_desc(ctx, desc_hash, &desc_alloc)?);
+    }
+
+    let mut exec_env = ExecutionEnvironment {
+        descs: desc_builder.finish(),
+        structural_arena,
+        parameter_heap: &parameter_heap,
+        runtime_heap,
+        entry_cache,
+        literal_cache,
+        witness_cache,
+        scratch_pad,
+        fee: Cell::new(0),
+        fee_payer: Cell::new(None),
+    };
+
+    //gas used by the interpreter
+    #[cfg(feature = "dynamic_gas")]
+    let mut used_gas:u64 = 0;
+    //gas the interpreter could have used according to the static bounds
+    #[cfg(feature = "dynamic_gas")]
+    let mut static_gas:u64 = 0;
+    #[cfg(feature = "dynamic_gas")]
+    let num_sections = ctx.txt_bundle.sections().len();
+    tracker.bundle_start(ctx.txt_bundle);
+    let mut sec_no = 0;
+    for txt_section in ctx.txt_bundle.sections().iter() {
+        tracker.section_start(txt_section);
+        let mut signatures = SignatureBatch::new();
+        let mut txt_no = 0;
+        for txt in txt_section.txts.iter() {
+            tracker.transaction_start(txt);
+            //the interpreter aborts if the bundle runs out of gas
+            #[cfg(feature = "dynamic_gas")]
+            let gas_limit = ctx.txt_bundle.total_gas_cost().saturating_sub(used_gas);
+            let res = match txt {
+                Transaction::Invoke(invoke) => execute_transaction::<_, SYS>(&exec_env, exec_store, ctx, invoke, block, sec_no, txt_no, tracker, &mut signatures, #[cfg(feature = "dynamic_gas")] gas_limit),
+                Transaction::Deploy(deploy) => execute_deploy::<_, SYS>(ctx, deploy, tracker),
+            };
+            //checked before anything is committed, so a broken bound rolls the bundle back
+            #[cfg(feature = "dynamic_gas")]
+            let res = res.and_then(|gas|if u64::saturating_add(used_gas, gas) > ctx.txt_bundle.total_gas_cost() {
+                error(||"Bundle used more gas than its declared total gas cost")
+            } else {
+                Ok(gas)
+            });
+            match res {
+                #[cfg(feature = "dynamic_gas")]
+                Ok(gas) => {
+                    used_gas = u64::saturating_add(used_gas, gas);
+                    static_gas = u64::saturating_add(static_gas, reserved_gas(&exec_env, txt));
+                },
+                #[cfg(not(feature = "dynamic_gas"))]
+                Ok(_) => {},
+                Err(err) => {
+                    exec_store.revert(ctx);
+                    tracker.transaction_finish(txt, false);
+                    tracker.section_finish(txt_section, false);
+                    tracker.bundle_finish(ctx.txt_bundle, false);
+                    return Err(err);
+                }
+            };
+            txt_no +=1;
+            //release all the memory so it does not leak into the next transaction
+            exec_env.structural_arena = exec_env.structural_arena.reuse();
+            exec_env.runtime_heap = exec_env.runtime_heap.reuse();
+            tracker.transaction_finish(txt, true);
+        }
+        let res = if txt_section.typ == SectionType::Essential {
+            pay_fee::<SYS>(&exec_env, exec_store, ctx, block)
+        } else {
+            Ok(())
+        };
+        //the gas used is known after the last section, the refund is committed together with it
+        // Note: if the bundle fails the fee stays with the block producer
+        #[cfg(feature = "dynamic_gas")]
+        let res = res.and_then(|_|if sec_no as usize + 1 == num_sections {
+            refund_fee::<SYS>(&exec_env, exec_store, ctx, block, static_gas.saturating_sub(used_gas))
+        } else {
+            Ok(())
+        });
+        //the only verification of the checks collected in the section
+        let res = res.and_then(|_|if SYS::RE::verify_signature_batch(&signatures) {
+            Ok(())
+        } else {
+            error(||"A signature checked in the section is not valid")
+        });
+        if let Err(err) = res {
+            exec_store.revert(ctx);
+            tracker.section_finish(txt_section, false);
+            tracker.bundle_finish(ctx.txt_bundle, false);
+            return Err(err);
+        }
+        //commit -- making this optional is needed for benchmarking
+        if commit { exec_store.commit(ctx); }
+        tracker.section_finish(txt_section, true);
+        sec_no+=1;
+    }
+
+    #[cfg(feature = "dynamic_gas")]
+    let refunded_gas = static_gas.saturating_sub(used_gas);
+    #[cfg(not(feature = "dynamic_gas"))]
+    let refunded_gas = 0;
+    let total_gas = ctx.txt_bundle.total_gas_cost();
+    tracker.gas_used(ctx.txt_bundle, total_gas.saturating_sub(refunded_gas), refunded_gas);
+    tracker.bundle_finish(ctx.txt_bundle, true);
+
+    #[cfg(feature = "dynamic_gas")]
+    return Ok(used_gas);
+    #[cfg(not(feature = "dynamic_gas"))]
+    return Ok(());
+}
+
+
+//The part of the gas reserved by the verification that can be refunded if unused
+#[cfg(feature = "dynamic_gas")]
+fn reserved_gas(env:&ExecutionEnvironment, txt:&Transaction) -> u64 {
+    match txt {
+        Transaction::Invoke(invoke) => env.descs[invoke.txt_desc as usize].gas_cost as u64,
+        Transaction::Deploy(deploy) => CONFIG.deploy_store_cost.compute(deploy.data.len() as u64),
+    }
+}
+
+//Validates and stores a module or a transaction (in which case the descriptor is compiled as well)
+// the changes are committed or reverted together with the section
+#[cfg(feature = "deployer")]
+fn execute_deploy<'c, L: Tracker, SYS:SystemContext<'c>>(ctx:&Context<SYS::S, SYS::B>, deploy:&DeployTransaction, tracker:&mut L) -> InterpreterResult {
+    let store = CachedStore::<Module,_>::new(ctx.store, StorageClass::Module);
+    let limiter = Limiter::new(deploy.gas_limit);
+    //System modules can only be deployed outside of bundles
+    for hash in deploy_content::<_,SYS::CE>(&store, deploy, false, &limiter)? {
+        tracker.deployed(deploy, &hash);
+    }
+    //The limiter counts validation and compilation work, which is paid separately and never refunded
+    // the gas of a deploy is the cost of storing its input
+    #[cfg(feature = "dynamic_gas")]
+    return Ok(CONFIG.deploy_store_cost.compute(deploy.data.len() as u64));
+    #[cfg(not(feature = "dynamic_gas"))]
+    return Ok(());
+}
+
+#[cfg(not(feature = "deployer"))]
+fn execute_deploy<'c, L: Tracker, SYS:SystemContext<'c>>(_ctx:&Context<SYS::S, SYS::B>, _deploy:&DeployTransaction, _tracker:&mut L) -> InterpreterResult {
+    error(||"Deploys are not supported by this runtime")
+}
+
+fn execute_transaction<'c, L: Tracker, SYS:SystemContext<'c>>(env:&ExecutionEnvironment, exec_store:&SYS::EC, ctx:&Context<SYS::S, SYS::B>, txt:&Invoke, block:BlockInfo, sec_no:u8, txt_no:u8,  tracker:&mut L, signatures:&mut SignatureBatch, #[cfg(feature = "dynamic_gas")] gas_limit:u64) -> InterpreterResult{
+
+    //Prepare all the Memory
+    let txt_desc:TransactionDescriptor = env.descs[txt.txt_desc as usize];
+    let mut interpreter_stack = env.structural_arena.alloc_stack::<Entry>(txt_desc.max_stack as usize);
+    let mut frame_stack = env.structural_arena.alloc_stack::<Frame>(txt_desc.max_frames as usize);
+    let mut return_stack = env.structural_arena.alloc_stack::<Entry>(CONFIG.return_stack);
+
+    //push everything required onto the stack
+    let mut deletes = Vec::with_capacity(txt_desc.params.len());
+
+    for (p_num, (p,p_typ)) in txt_desc.params.iter().zip(txt.params.iter()).enumerate() {
+        match p_typ {
+            ParamRef::Load(ParamMode::Consume,index) => {
+                //We delete at end so others can copy and in case it produces an error it must still be their
+                deletes.push(index);
+                let data = load_from_store::<SYS>(env, exec_store, ctx, *index, *p)?;
+                tracker.parameter_load(p_typ, p, &data);
+                interpreter_stack.push(data)?;
+            }
+            ParamRef::Load(ParamMode::Copy, index)
+            | ParamRef::Load(ParamMode::Borrow, index) => {
+                let data = load_from_store::<SYS>(env, exec_store, ctx, *index, *p)?;
+                tracker.parameter_load(p_typ, p, &data);
+                interpreter_stack.push(data)?;
+            },
+
+            ParamRef::Provided => {
+                let data = exec_store.create_provided_value(ctx, p.typ, &env.parameter_heap, block, sec_no, txt_no, p_num as u8)?;
+                tracker.parameter_load(p_typ, p, &data);
+                interpreter_stack.push(data)?;
+            },
+
+            ParamRef::Fetch(_, index) => {
+                let data = env.scratch_pad.borrow()[*index as usize].unwrap();
+                tracker.parameter_load(p_typ, p, &data);
+                interpreter_stack.push(data)?;
+            }
+
+            ParamRef::Literal(index) => {
+                let data = load_from_literal::<SYS>(env, ctx,*index,*p)?;
+                tracker.parameter_load(p_typ, p, &data);
+                interpreter_stack.push(data)?;
+            },
+            ParamRef::Witness(index) => {
+                let data = load_from_witness::<SYS>(env, ctx, *index,*p)?;
+                tracker.parameter_load(p_typ, p, &data);
+                interpreter_stack.push(data)?;
+            },
+        };
+    }
+
+    let res = ExecutionContext::interpret::<SYS::RE>(&txt_desc.functions, &mut interpreter_stack, &mut frame_stack, &mut return_stack, &env.runtime_heap, Some(signatures), #[cfg(feature = "dynamic_gas")] gas_limit)?;
+    #[cfg(feature = "dynamic_gas")]
+    if res > txt_desc.gas_cost as u64 {
+        return error(||"Transaction used more gas than its descriptor allows");
+    }
+
+    //Now that we know it succeeds we can modify the store
+    for index in deletes {
+        exec_store.chain_value_delete(ctx, *index)?;
+    }
+
+    if interpreter_stack.len() != txt.returns.len() {
+        return error(||"Transaction Return Information missmatched Stack")
+    }
+    if interpreter_stack.len() != txt_desc.returns.len() {
+        return error(||"Transaction Description Return Information missmatched Stack")
+    }
+
+    for ((ret_entry, r), r_typ) in interpreter_stack.as_slice().iter().zip(txt_desc.returns.iter()).zip(txt.returns.iter()) {
+        match r_typ {
+            RetType::Store => {
+                tracker.return_value(r_typ, r, ret_entry);
+                exec_store.chain_value_store(ctx, ret_entry, *r)?
+            },
+            RetType::Put(index) => {
+                tracker.return_value(r_typ, r, ret_entry);
+                let copy = r.desc.move_value(*ret_entry, env.parameter_heap)?;
+                env.scratch_pad.borrow_mut()[*index as usize] = Some(copy)
+            }
+            RetType::Drop => tracker.return_value(r_typ, r, ret_entry),
+            RetType::Log => tracker.return_value(r_typ, r, ret_entry),
+            RetType::Fee => {
+                tracker.return_value(r_typ, r, ret_entry);
+                let amount = exec_store.fee_value(ctx, ret_entry, *r)?;
+                env.fee.set(env.fee.get().saturating_add(amount));
+                //the payer of the first fee gets the refund
+                if env.fee_payer.get().is_none() {
+                    env.fee_payer.set(Some(exec_store.fee_payer(ctx, ret_entry, *r)?));
+                }
+            }
+        }
+    }
+    Ok(res)
+}
+
+//Checks that the fees returned in the essential section cover the declared gas and the deploy gas limits and credits them to the block producer
+// balances are never spent on fees: the payer of a fee value is not authenticated, any transaction that can create one can name any account
+fn pay_fee<'c, SYS:SystemContext<'c>>(env:&ExecutionEnvironment, exec_store:&SYS::EC, ctx:&Context<SYS::S, SYS::B>, block:BlockInfo) -> Result<()> {
+    let gas = ctx.txt_bundle.total_gas_cost().checked_add(deploy_gas_limit(ctx.txt_bundle));
+    let required = match gas.and_then(|gas|gas.checked_mul(block.gas_price)) {
+        Some(required) => required,
+        None => return error(||"Required fee is to big")
+    };
+    let fee = env.fee.get();
+    if fee < required {
+        return error(||"Paid fee does not cover the declared gas cost")
+    }
+    if fee != 0 {
+        exec_store.credit_fee(ctx, block, fee)?;
+    }
+    Ok(())
+}
+
+//Refunds the fee for the gas that was reserved but not used
+#[cfg(feature = "dynamic_gas")]
+fn refund_fee<'c, SYS:SystemContext<'c>>(env:&ExecutionEnvironment, exec_store:&SYS::EC, ctx:&Context<SYS::S, SYS::B>, block:BlockInfo, refunded_gas:u64) -> Result<()> {
+    if let Some(payer) = env.fee_payer.get() {
+        //the refund is bounded by the required fee as the static gas is part of the total gas
+        let amount = refunded_gas.saturating_mul(block.gas_price);
+        if amount != 0 {
+            exec_store.refund_fee(ctx, block, &payer, amount)?;
+        }
+    }
+    Ok(())
+}
+
+
+fn load_from_literal<'a, 'b,'c, 'd, SYS:SystemContext<'d>>(env:&ExecutionEnvironment<'a, 'b, 'c>, ctx:&Context<SYS::S, SYS::B>, index:u16, param:TxTParam) -> Result<Entry<'b>> {
+    let entry_copy = env.literal_cache.borrow()[index as usize];
+    Ok(match entry_copy {
+        None => {
+            let data = ctx.txt_bundle.literal()[index as usize];
+            let mut parser = Parser::new(&data, CONFIG.max_structural_dept);
+            let entry = param.desc.parse_value(&mut parser, env.parameter_heap)?;
+            env.literal_cache.borrow_mut()[index as usize] = Some(entry,);
+            entry
+        },
+        Some(entry) => entry
+    })
+}
+
+fn load_from_witness<'a, 'b,'c, 'd, SYS:SystemContext<'d>>(env:&ExecutionEnvironment<'a, 'b, 'c>, ctx:&Context<SYS::S, SYS::B>, index:u16, param:TxTParam) -> Result<Entry<'b>> {
+    let entry_copy = env.witness_cache.borrow()[index as usize];
+    Ok(match entry_copy {
+        None => {
+            let data = ctx.txt_bundle.witness()[index as usize];
+            let mut parser = Parser::new(&data, CONFIG.max_structural_dept);
+            let entry = param.desc.parse_value(&mut parser, env.parameter_heap)?;
+            env.witness_cache.borrow_mut()[index as usize] = Some(entry,);
+            entry
+        },
+        Some(entry) => entry
+    })
+}
+
+
+fn load_from_store<'a, 'b,'c, 'd, SYS:SystemContext<'d>>(env:&ExecutionEnvironment<'a,'b, 'c>, exec_store: &SYS::EC, ctx:&Context<SYS::S, SYS::B>, index:u16, param:TxTParam) -> Result<Entry<'b>> {
+    let entry_copy = env.entry_cache.borrow()[index as usize];
+    Ok(match entry_copy {
+        None => {
+            let entry = exec_store.chain_value_load(ctx, index, param, env.parameter_heap)?;
+            env.entry_cache.borrow_mut()[index as usize] = Some(entry);
+            entry
+        },
+        Some(entry) => entry
+    })
+}
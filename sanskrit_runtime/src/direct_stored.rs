@@ -1,7 +1,7 @@
 use crate::CONFIG;
 use sanskrit_common::store::{Store, StorageClass};
 use core::cell::{Cell, RefCell};
-use sanskrit_common::encoding::{ParserAllocator, Serializer, Parser};
+use sanskrit_common::encoding::{ParserAllocator, Serializer, Parser, NoCustomAlloc};
 use sanskrit_interpreter::model::{TransactionDescriptor, TxTParam, TxTReturn, Entry, RuntimeType};
 use crate::verify::TransactionVerificationContext;
 use sanskrit_common::errors::*;
@@ -12,9 +12,10 @@ use core::convert::TryInto;
 use sanskrit_common::model::{Hash, hash_from_slice, Ptr};
 use sanskrit_common::arena::VirtualHeapArena;
 use core::marker::PhantomData;
-use crate::{Context, TransactionBundle};
+use crate::{Context, TransactionBundle, BlockInfo};
 use alloc::vec::Vec;
 use alloc::collections::BTreeSet;
+use alloc::format;
 
 pub enum UniquenessScope{
     Bundle,
@@ -27,7 +28,10 @@ pub trait SystemDataManager<B:TransactionBundle> {
     fn providable_gas(typ:Ptr<RuntimeType>) -> Result<u64>;
     fn is_chain_value(typ:Ptr<RuntimeType>) -> bool;
     fn provided_value_key(typ:Ptr<RuntimeType>, section_no:u8,  txt_no:u8, p_num:u8) -> Option<Vec<u8>>;
-    fn create_provided_value<'a,'h>(bundle:&B, typ:Ptr<RuntimeType>, alloc: &'a VirtualHeapArena<'h>, block_no: u64, section_no:u8,  txt_no:u8, p_num:u8) -> Result<Entry<'a>>;
+    fn create_provided_value<'a,'h>(bundle:&B, typ:Ptr<RuntimeType>, alloc: &'a VirtualHeapArena<'h>, block: BlockInfo, section_no:u8,  txt_no:u8, p_num:u8) -> Result<Entry<'a>>;
+    fn is_fee(typ:Ptr<RuntimeType>) -> bool;
+    fn fee_value(value:&Entry) -> Result<u64>;
+    fn fee_payer(value:&Entry) -> Result<Hash>;
 }
 
 pub struct StatefulEntryStoreVerifier<B:TransactionBundle, SDM: SystemDataManager<B>>{
@@ -42,6 +46,42 @@ pub fn read_transaction_desc<'d, S:Store, A:ParserAllocator>(target:&Hash, store
     store.parsed_get(StorageClass::Descriptor, target, CONFIG.max_structural_dept, heap)
 }
 
+//Updates a fee balance
+// balances live in their own storage class under the account, so they can not collide with entries
+fn update_fee_balance<S:Store, F:FnOnce(u64) -> Result<u64>>(store:&S, account:&Hash, f:F) -> Result<()> {
+    let balance = match store.get(StorageClass::Balance, account, |d|Parser::parse_fully::<u64,_>(d, 1, &NoCustomAlloc())) {
+        Ok(balance) => {
+            store.delete(StorageClass::Balance, account)?;
+            balance?
+        },
+        Err(_) => 0
+    };
+    store.set(StorageClass::Balance, *account, Serializer::serialize_fully(&f(balance)?, 1)?)
+}
+
+//Takes an amount from a fee balance
+fn spend_fee_balance<S:Store>(store:&S, account:&Hash, amount:u64) -> Result<()> {
+    update_fee_balance(store, account, |balance|match balance.checked_sub(amount) {
+        Some(res) => Ok(res),
+        None => error(||"Fee balance is to small")
+    })
+}
+
+//Moves a part of a fee balance to another account
+fn move_fee_balance<S:Store>(store:&S, from:&Hash, to:&Hash, amount:u64) -> Result<()> {
+    spend_fee_balance(store, from, amount)?;
+    update_fee_balance(store, to, |balance|Ok(balance.saturating_add(amount)))
+}
+
+//Reads the fees earned by a block producer (or refunded to a payer)
+pub fn read_fee_balance<S:Store>(store:&S, producer:&Hash) -> Result<u64> {
+    match store.get(StorageClass::Balance, producer, |d|Parser::parse_fully(d, 1, &NoCustomAlloc())) {
+        Ok(res) => res,
+        //No fees were earned yet
+        Err(_) => Ok(0)
+    }
+}
+
 
 impl<S:Store,B:TransactionBundle, SDM: SystemDataManager<B>> TransactionVerificationContext<S,B> for StatefulEntryStoreVerifier<B,SDM>  {
 
@@ -98,6 +138,10 @@ impl<S:Store,B:TransactionBundle, SDM: SystemDataManager<B>> TransactionVerifica
         let gas = SDM::providable_gas(typ)?;
         Ok((gas,size))
     }
+
+    fn is_fee(&self, _ctx:&Context<S,B>, typ:Ptr<RuntimeType>) -> bool {
+        SDM::is_fee(typ)
+    }
 }
 
 
@@ -129,8 +173,8 @@ impl<S:Store,B:TransactionBundle, SDM: SystemDataManager<B>> TransactionExecutio
         ctx.store.parsed_get(StorageClass::Descriptor, target, CONFIG.max_structural_dept, heap)
     }
 
-    fn create_provided_value<'a,'h>(&self, ctx: &Context<S,B>, typ: Ptr<RuntimeType>, alloc: &'a VirtualHeapArena<'h>, block_no: u64, section_no:u8,  txt_no:u8, p_num:u8) -> Result<Entry<'a>> {
-        SDM::create_provided_value(ctx.txt_bundle, typ, alloc, block_no, section_no, txt_no, p_num)
+    fn create_provided_value<'a,'h>(&self, ctx: &Context<S,B>, typ: Ptr<RuntimeType>, alloc: &'a VirtualHeapArena<'h>, block: BlockInfo, section_no:u8,  txt_no:u8, p_num:u8) -> Result<Entry<'a>> {
+        SDM::create_provided_value(ctx.txt_bundle, typ, alloc, block, section_no, txt_no, p_num)
     }
 
 
@@ -145,7 +189,7 @@ impl<S:Store,B:TransactionBundle, SDM: SystemDataManager<B>> TransactionExecutio
         let expected_hash = entry_hash(&control_type,&value_hash);
 
         let control_hash = ctx.store.get(StorageClass::EntryHash, key_hash,  |d|hash_from_slice(d))?;
-        if control_hash != expected_hash { return error(||"stored value had wrong type")}
+        if control_hash != expected_hash { return owned_error(||format!("stored value had wrong type, expected {}", *param.typ))}
 
         let mut parser = Parser::new(&data, CONFIG.max_structural_dept);
         param.desc.parse_value(&mut parser, parameter_heap)
@@ -175,13 +219,71 @@ impl<S:Store,B:TransactionBundle, SDM: SystemDataManager<B>> TransactionExecutio
         Ok(())
     }
 
+    fn fee_value(&self, _ctx:&Context<S,B>, ret_entry: &Entry, _ret: TxTReturn) -> Result<u64> {
+        SDM::fee_value(ret_entry)
+    }
+
+    fn fee_payer(&self, _ctx:&Context<S,B>, ret_entry: &Entry, _ret: TxTReturn) -> Result<Hash> {
+        SDM::fee_payer(ret_entry)
+    }
+
+    fn credit_fee(&self, ctx:&Context<S,B>, block:BlockInfo, amount:u64) -> Result<()> {
+        update_fee_balance(ctx.store, &block.producer, |balance|Ok(balance.saturating_add(amount)))
+    }
+
+    fn refund_fee(&self, ctx:&Context<S,B>, block:BlockInfo, payer:&Hash, amount:u64) -> Result<()> {
+        move_fee_balance(ctx.store, &block.producer, payer, amount)
+    }
+
+    //Deploys inside a bundle write modules, transactions and descriptors
     fn commit(&self, ctx:&Context<S,B>)  {
         ctx.store.commit(StorageClass::EntryValue);
         ctx.store.commit(StorageClass::EntryHash);
+        ctx.store.commit(StorageClass::Module);
+        ctx.store.commit(StorageClass::Transaction);
+        ctx.store.commit(StorageClass::Descriptor);
+        ctx.store.commit(StorageClass::Balance);
     }
 
     fn revert(&self, ctx:&Context<S,B>) {
         ctx.store.rollback(StorageClass::EntryValue);
         ctx.store.rollback(StorageClass::EntryHash);
+        ctx.store.rollback(StorageClass::Module);
+        ctx.store.rollback(StorageClass::Transaction);
+        ctx.store.rollback(StorageClass::Descriptor);
+        ctx.store.rollback(StorageClass::Balance);
+    }
+}
+#[cfg(test)]
+mod tests {
+    extern crate sanskrit_memory_store;
+    use super::*;
+    use sanskrit_common::store::ListableStore;
+    use self::sanskrit_memory_store::BTreeMapStore;
+    use alloc::vec;
+
+    const PRODUCER:Hash = [1; 20];
+    const PAYER:Hash = [2; 20];
+
+    #[test]
+    fn fee_balances_use_their_own_class() {
+        let store = BTreeMapStore::new();
+        update_fee_balance(&store, &PRODUCER, |balance|Ok(balance + 30)).unwrap();
+        update_fee_balance(&store, &PRODUCER, |balance|Ok(balance + 12)).unwrap();
+        store.commit(StorageClass::Balance);
+        assert_eq!(read_fee_balance(&store, &PRODUCER).unwrap(), 42);
+        assert_eq!(read_fee_balance(&store, &PAYER).unwrap(), 0);
+        assert!(store.list(StorageClass::EntryValue).unwrap().is_empty());
+        assert_eq!(store.list(StorageClass::Balance).unwrap(), vec![(PRODUCER, 42u64.to_be_bytes().to_vec())]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn refunds_are_bounded_by_the_credited_fee() {
+        let store = BTreeMapStore::new();
+        update_fee_balance(&store, &PRODUCER, |_|Ok(40)).unwrap();
+        move_fee_balance(&store, &PRODUCER, &PAYER, 15).unwrap();
+        assert_eq!(read_fee_balance(&store, &PRODUCER).unwrap(), 25);
+        assert_eq!(read_fee_balance(&store, &PAYER).unwrap(), 15);
+        assert!(move_fee_balance(&store, &PRODUCER, &PAYER, 26).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! Runs a deploy without keeping its results
+//!  This allows to check a module or transaction and to get the gas it needs before paying for the deploy
+//!
+
+use alloc::vec::Vec;
+use alloc::collections::BTreeSet;
+use sanskrit_common::errors::*;
+use sanskrit_common::model::{Hash, ModuleLink};
+use sanskrit_common::store::{CachedStore, Store, StorageClass, OverlayStore, store_hash};
+use sanskrit_common::encoding::{Parser, NoCustomAlloc};
+use sanskrit_common::arena::{Heap, VirtualHeapArena};
+use sanskrit_common::limiter::Limiter;
+use sanskrit_core::model::{Module, FunctionComponent, PublicImport, CallableImpl};
+use sanskrit_deploy::{stage_module, stage_function, ComponentKind, ValidationTracker, CheckFailure};
+use sanskrit_compile::create_descriptor;
+use sanskrit_compile::externals::CompilationExternals;
+use sanskrit_interpreter::model::TransactionDescriptor;
+use crate::model::{DeployTransaction, DeployType, DeployPackage};
+use crate::CONFIG;
+
+//The validation status of a single component
+#[derive(Copy, Clone, Debug)]
+pub struct ComponentReport {
+    pub kind:ComponentKind,
+    pub offset:usize,
+    pub valid:bool,
+}
+
+//The properties of the descriptor compiled for a transaction
+#[derive(Copy, Clone, Debug)]
+pub struct DescriptorReport {
+    pub hash:Hash,
+    pub size:usize,
+    pub max_stack:u16,
+    pub max_frames:u16,
+    pub max_mem:u16,
+    pub gas_cost:u32,
+}
+
+//The result for a single module or transaction
+#[derive(Clone, Debug)]
+pub struct DeployReport {
+    //Module or Transaction (the parts of a package are reported one by one)
+    pub typ:DeployType,
+    pub hash:Hash,
+    pub size:usize,
+    //the modules imported by the input
+    pub dependencies:Vec<Hash>,
+    //the components in validation order (on error the last one is the failing one)
+    pub components:Vec<ComponentReport>,
+    //where the type checking failed (if it did)
+    pub failure:Option<CheckFailure>,
+    pub descriptor:Option<DescriptorReport>,
+    //gas used by the validation and compilation (the gas_limit the deploy needs)
+    pub deploy_gas:u64,
+    //gas a bundle has to reserve when the deploy is part of it (the deploy gas is paid on top of it)
+    pub bundle_gas:u64,
+    pub error:Option<ErrorType>,
+}
+
+struct ComponentCollector(Vec<ComponentReport>, Option<CheckFailure>);
+
+impl ValidationTracker for ComponentCollector {
+    fn component_start(&mut self, kind:ComponentKind, offset:usize) {
+        self.0.push(ComponentReport{ kind, offset, valid: false })
+    }
+
+    fn component_finish(&mut self, _kind:ComponentKind, _offset:usize) {
+        if let Some(last) = self.0.last_mut() {
+            last.valid = true
+        }
+    }
+
+    fn check_failed(&mut self, failure:&CheckFailure) {
+        self.1 = Some(failure.clone())
+    }
+}
+
+//Runs the deploy on an overlay of the store that is dropped afterwards
+// the run stops at the first failing part, as a package is only deployed if all parts are valid
+// the store (including its uncommitted changes) is only read
+pub fn dry_run<S:Store, CE:CompilationExternals>(store:&S, deploy_data:&[u8], heap:&Heap, system_mode_on:bool) -> Result<Vec<DeployReport>> {
+    let store = CachedStore::<Module,_>::new(OverlayStore::new(store), StorageClass::Module);
+    //Check that it is inside limit
+    if deploy_data.len() > CONFIG.max_bundle_size { return error(||"Transaction Bundle to big")}
+    let deploy_txt_alloc = heap.new_virtual_arena(CONFIG.max_txt_alloc);
+    let deploy_txt:DeployTransaction = Parser::parse_fully(deploy_data, CONFIG.max_structural_dept, &deploy_txt_alloc)?;
+    let parts:Vec<(DeployType, Vec<u8>)> = match deploy_txt.typ {
+        DeployType::Module => alloc::vec![(DeployType::Module, deploy_txt.data.to_vec())],
+        DeployType::Transaction => alloc::vec![(DeployType::Transaction, deploy_txt.data.to_vec())],
+        DeployType::Package => {
+            let package:DeployPackage = Parser::parse_fully(&deploy_txt.data, CONFIG.max_structural_dept, &NoCustomAlloc())?;
+            package.modules.into_iter().map(|m|(DeployType::Module, m))
+                .chain(package.transactions.into_iter().map(|t|(DeployType::Transaction, t)))
+                .collect()
+        }
+    };
+
+    //The gas limit of the deploy is ignored so that the report shows what is needed
+    let limiter = Limiter::new(CONFIG.max_deploy_gas);
+    let desc_alloc = heap.new_virtual_arena(CONFIG.max_txt_alloc);
+    let mut reports = Vec::with_capacity(parts.len());
+    for (typ, data) in parts {
+        let report = dry_run_part::<_,CE>(&store, typ, data, system_mode_on, &limiter, &desc_alloc);
+        let failed = report.error.is_some();
+        reports.push(report);
+        if failed { break }
+    }
+    Ok(reports)
+}
+
+fn dry_run_part<S:Store, CE:CompilationExternals>(store:&CachedStore<Module,S>, typ:DeployType, data:Vec<u8>, system_mode_on:bool, limiter:&Limiter, desc_alloc:&VirtualHeapArena) -> DeployReport {
+    let start_gas = limiter.used();
+    let mut components = ComponentCollector(Vec::new(), None);
+    let hash = store_hash(&[&data]);
+    let size = data.len();
+    let dependencies = dependencies(typ, &data);
+    let res = match typ {
+        DeployType::Module => stage_module(store, data, system_mode_on, limiter, &mut components).map(|_|None),
+        DeployType::Transaction => stage_function(store, data, limiter, &mut components)
+            .and_then(|target|describe::<_,CE>(store, target, limiter, desc_alloc))
+            .map(Some),
+        DeployType::Package => error(||"Packages can not be nested")
+    };
+    let deploy_gas = limiter.used() - start_gas;
+    let (descriptor, error) = match res {
+        Ok(descriptor) => (descriptor, None),
+        Err(err) => (None, Some(err))
+    };
+    DeployReport {
+        typ,
+        hash,
+        size,
+        dependencies,
+        components: components.0,
+        failure: components.1,
+        descriptor,
+        deploy_gas,
+        bundle_gas: CONFIG.deploy_store_cost.compute(size as u64),
+        error,
+    }
+}
+
+fn describe<S:Store, CE:CompilationExternals>(store:&CachedStore<Module,S>, target:Hash, limiter:&Limiter, desc_alloc:&VirtualHeapArena) -> Result<DescriptorReport> {
+    let (hash, data) = create_descriptor::<_,CE>(store, target, limiter)?;
+    let desc:TransactionDescriptor = Parser::parse_fully(&data, CONFIG.max_structural_dept, desc_alloc)?;
+    Ok(DescriptorReport {
+        hash,
+        size: data.len(),
+        max_stack: desc.max_stack,
+        max_frames: desc.max_frames,
+        max_mem: desc.max_mem,
+        gas_cost: desc.gas_cost,
+    })
+}
+
+//The modules imported by a module or transaction (an input that can not be parsed has none)
+fn dependencies(typ:DeployType, data:&[u8]) -> Vec<Hash> {
+    let mut deps = BTreeSet::new();
+    let mut add = |import:&PublicImport| for link in &import.modules {
+        if let ModuleLink::Remote(hash) = link {
+            deps.insert(*hash);
+        }
+    };
+    match typ {
+        DeployType::Module => if let Ok(module) = Parser::parse_fully::<Module,_>(data, usize::MAX, &NoCustomAlloc()) {
+            for d in &module.data { add(&d.import) }
+            for s in &module.sigs { add(&s.shared.import) }
+            for f in &module.functions {
+                add(&f.shared.import);
+                if let CallableImpl::Internal{ref imports, ..} = f.body { add(&imports.public) }
+            }
+            for i in &module.implements {
+                add(&i.import);
+                if let CallableImpl::Internal{ref imports, ..} = i.body { add(&imports.public) }
+            }
+        },
+        DeployType::Transaction => if let Ok(fun) = Parser::parse_fully::<FunctionComponent,_>(data, usize::MAX, &NoCustomAlloc()) {
+            add(&fun.shared.import);
+            if let CallableImpl::Internal{ref imports, ..} = fun.body { add(&imports.public) }
+        },
+        DeployType::Package => {}
+    }
+    deps.into_iter().collect()
+}
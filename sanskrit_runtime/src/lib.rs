@@ -24,15 +24,19 @@ use sanskrit_common::errors::*;
 #[cfg(feature = "deployer")]
 use sanskrit_common::encoding::Parser;
 use sanskrit_common::encoding::ParserAllocator;
-use model::{Transaction, ParamRef, RetType, BundleSection, BundleWithHash};
+use model::{Transaction, ParamRef, RetType, BundleSection, DeployTransaction};
 #[cfg(feature = "deployer")]
-use model::{DeployTransaction, DeployType};
+use model::{DeployType, DeployPackage};
+#[cfg(feature = "deployer")]
+use sanskrit_common::encoding::NoCustomAlloc;
+#[cfg(feature = "deployer")]
+use alloc::vec::Vec;
 use sanskrit_common::arena::*;
 use sanskrit_interpreter::model::{Entry, TxTParam, TxTReturn, TransactionDescriptor};
 
 use system::SystemContext;
 use verify::{verify_repeated, verify_once, TransactionVerificationContext};
-use compute::{execute_once, TransactionExecutionContext};
+use compute::execute_bundle;
 use sanskrit_common::model::{Hash, SlicePtr};
 use sanskrit_interpreter::interpreter::{Frame, InterpreterResult};
 #[cfg(feature = "deployer")]
@@ -41,6 +45,8 @@ use sanskrit_deploy::{deploy_module, deploy_function};
 use sanskrit_compile::compile_function;
 #[cfg(feature = "deployer")]
 use sanskrit_compile::externals::CompilationExternals;
+#[cfg(feature = "deployer")]
+use sanskrit_common::limiter::Limiter;
 use sanskrit_core::model::Module;
 
 pub mod model;
@@ -48,6 +54,8 @@ pub mod system;
 pub mod verify;
 pub mod direct_stored;
 pub mod compute;
+#[cfg(feature = "deployer")]
+pub mod dry_run;
 
 pub struct DataProcessingCost {
     cost_constant:u64,
@@ -61,45 +69,46 @@ impl DataProcessingCost {
     }
 }
 
-//TODO: Measure, these are guessed
-//Try to get approximately: 1000000 = 1ms / 1000gas == 1us / 1gas == 1ns (Use My Lenovo as referenz)
-// We need to remeasure the primitives
+//Note we rounded up - No fractions
+// Based on sanskrit_bench - main
+//  Generated with 0.6 gas per ns (11 runs of 256 repetitions per sample)
 pub const STORE_LOAD_COST: DataProcessingCost = DataProcessingCost {
-    cost_constant:  20000,
-    cost_multiplier: 10,
-    cost_divider: 1
+    cost_constant: 3888,
+    cost_multiplier: 45,
+    cost_divider: 100
 };
 
 pub const STORE_WRITE_COST: DataProcessingCost = DataProcessingCost {
-    cost_constant:  20000,
-    cost_multiplier: 50,
-    cost_divider: 1
+    cost_constant: 14107,
+    cost_multiplier: 20,
+    cost_divider: 10
 };
 
 pub const STORE_LOAD_AND_ENCODE_COST: DataProcessingCost = DataProcessingCost {
-    cost_constant:  2000,
-    cost_multiplier: 60,
-    cost_divider: 5
+    cost_constant: 3945,
+    cost_multiplier: 58,
+    cost_divider: 100
 };
 
 pub const STORE_WRITE_AND_ENCODE_COST: DataProcessingCost = DataProcessingCost {
-    cost_constant:  20000,
-    cost_multiplier: 52,
-    cost_divider: 1
+    cost_constant: 15494,
+    cost_multiplier: 30,
+    cost_divider: 10
 };
 
 pub const ENCODING_COST: DataProcessingCost = DataProcessingCost {
-    cost_constant: 0,
-    cost_multiplier: 2,
-    cost_divider: 1
+    cost_constant: 58,
+    cost_multiplier: 14,
+    cost_divider: 100
 };
 
 pub const COPYING_COST: DataProcessingCost = DataProcessingCost {
-    cost_constant: 0,
-    cost_multiplier: 2,
-    cost_divider: 1
+    cost_constant: 12,
+    cost_multiplier: 10,
+    cost_divider: 100
 };
 
+
 pub struct Configuration {
     pub max_stack_depth:usize,
     pub max_frame_depth:usize,
@@ -114,7 +123,15 @@ pub struct Configuration {
     pub txt_desc_load_cost:DataProcessingCost,
     pub parsing_cost: DataProcessingCost,
     pub copy_cost: DataProcessingCost,
+    //Cost of storing a deployed module or transaction (per byte of its input)
+    pub deploy_store_cost: DataProcessingCost,
     pub block_inclusion_window:u64,
+    //Gas price of the blocks of a producer that does not choose its own (see BlockInfo::gas_price)
+    pub gas_price:u64,
+    //Upper bound for the gas_limit of a deploy transaction
+    pub max_deploy_gas:u64,
+    //Arena for computing the bounds of a single function or implement of a deployed module
+    pub max_bounds_alloc:usize,
 }
 
 pub const CONFIG: Configuration = Configuration {
@@ -131,7 +148,11 @@ pub const CONFIG: Configuration = Configuration {
     txt_desc_load_cost: STORE_LOAD_COST,
     parsing_cost: ENCODING_COST,
     copy_cost: COPYING_COST,
+    deploy_store_cost: STORE_WRITE_COST,
     block_inclusion_window: 100,
+    gas_price: 0,
+    max_deploy_gas: 10_000_000,
+    max_bounds_alloc: 64 * 1024,
 };
 
 impl Configuration {
@@ -142,6 +163,8 @@ impl Configuration {
             + (self.max_heap_size * virt_factor)
             + (self.max_bundle_size * virt_factor)
             + (self.max_txt_alloc * virt_factor)
+            //the structural arena, the bundle, the transactions, the parameters and the runtime heap start aligned
+            + 5*Heap::ARENA_ALIGN
     }
 }
 
@@ -164,11 +187,38 @@ pub trait TransactionBundle {
     fn witness(&self) -> SlicePtr<SlicePtr<u8>>;
 }
 
+//The work units the deploys of a bundle may use for validation and compilation
+// they are a separate budget from the gas of the bundle, the fee has to cover both
+pub fn deploy_gas_limit<T:TransactionBundle>(txt_bundle:&T) -> u64 {
+    let mut limit:u64 = 0;
+    for section in txt_bundle.sections().iter() {
+        for txt in section.txts.iter() {
+            if let Transaction::Deploy(deploy) = txt {
+                limit = limit.saturating_add(deploy.gas_limit);
+            }
+        }
+    }
+    limit
+}
+
 pub struct Context<'a,'b, S:Store, T:TransactionBundle> {
     pub store:&'a S,
     pub txt_bundle:&'b T
 }
 
+//The block a bundle is executed in
+#[derive(Copy, Clone, Debug)]
+pub struct BlockInfo {
+    pub block_no:u64,
+    //Chosen by the block producer and part of the block so all nodes compute the same values (it is not the local time)
+    pub timestamp:u64,
+    //Receives the fees paid by the bundles in the block
+    pub producer:Hash,
+    //Fee units charged per unit of declared total gas (0 disables fee payment)
+    // part of the block like the timestamp, so replays charge what the producer charged
+    pub gas_price:u64,
+}
+
 pub trait Tracker {
     fn block_start(&mut self, block_no:u64);
     fn bundle_start<T:TransactionBundle>(&mut self, bundle:&T);
@@ -176,30 +226,48 @@ pub trait Tracker {
     fn transaction_start(&mut self, transaction:&Transaction);
     fn parameter_load(&mut self, p_ref:&ParamRef, p_desc:&TxTParam, value:&Entry);
     fn return_value(&mut self, r_typ:&RetType, r_desc:&TxTReturn, value:&Entry);
+    //reports the hash of a deployed module or the descriptor hash of a deployed transaction (once for each part of a package)
+    fn deployed(&mut self, _deploy:&DeployTransaction, _hash:&Hash) {}
     fn transaction_finish(&mut self, transaction:&Transaction, success:bool);
     fn section_finish(&mut self, section:&BundleSection, success:bool);
+    //reports the gas charged for a successful bundle and the part of the declared total gas that was refunded
+    fn gas_used<T:TransactionBundle>(&mut self, _bundle:&T, _used_gas:u64, _refunded_gas:u64) {}
     fn bundle_finish<T:TransactionBundle>(&mut self, bundle:&T, success:bool);
     fn block_finish(&mut self, block_no:u64, success:bool);
 }
 
+//Used when no one is interested in the progress (for example when replaying blocks)
+impl Tracker for () {
+    fn block_start(&mut self, _block_no:u64) {}
+    fn bundle_start<T:TransactionBundle>(&mut self, _bundle:&T) {}
+    fn section_start(&mut self, _section:&BundleSection) {}
+    fn transaction_start(&mut self, _transaction:&Transaction) {}
+    fn parameter_load(&mut self, _p_ref:&ParamRef, _p_desc:&TxTParam, _value:&Entry) {}
+    fn return_value(&mut self, _r_typ:&RetType, _r_desc:&TxTReturn, _value:&Entry) {}
+    fn transaction_finish(&mut self, _transaction:&Transaction, _success:bool) {}
+    fn section_finish(&mut self, _section:&BundleSection, _success:bool) {}
+    fn bundle_finish<T:TransactionBundle>(&mut self, _bundle:&T, _success:bool) {}
+    fn block_finish(&mut self, _block_no:u64, _success:bool) {}
+}
+
 pub fn read_transaction_desc<'d, S:Store, A:ParserAllocator>(target:&Hash, store:&S, heap: &'d A) -> Result<TransactionDescriptor<'d>> {
     direct_stored::read_transaction_desc(target, store, heap)
 }
 
-pub fn verify<'c, 'd:'c, SYS:SystemContext<'c>>(ctx:&Context<SYS::S, SYS::B>, block_no:u64, heap:&'d Heap) -> Result<()> {
+pub fn verify<'c, 'd:'c, SYS:SystemContext<'c>>(ctx:&Context<SYS::S, SYS::B>, block:BlockInfo, heap:&'d Heap) -> Result<()> {
     //Check that it is inside limit
     if ctx.txt_bundle.byte_size() > CONFIG.max_bundle_size { return error(||"Transaction Bundle to big")}
-    verify_repeated::<SYS>(ctx, block_no)?;
-    verify_once::<SYS>(&SYS::VC::new(), ctx, heap)
+    verify_repeated::<SYS>(ctx, block.block_no)?;
+    verify_once::<SYS>(&SYS::VC::new(), ctx, heap, block.gas_price)
 }
 
 //Executes a transaction
-pub fn execute<'c, 'd:'c, L: Tracker,SYS:SystemContext<'c>>(ctx:Context<SYS::S, SYS::B>, block_no:u64, heap:&'d Heap, tracker:&mut L, commit:bool) -> InterpreterResult {
-    execute_once::<_,SYS>(&SYS::EC::new(), &ctx, block_no, heap, tracker, commit)
+pub fn execute<'c, 'd:'c, L: Tracker,SYS:SystemContext<'c>>(ctx:Context<SYS::S, SYS::B>, block:BlockInfo, heap:&'d Heap, tracker:&mut L, commit:bool) -> InterpreterResult {
+    execute_bundle::<_,SYS>(&ctx, block, heap, tracker, commit)
 }
 
 #[cfg(feature = "deployer")]
-pub fn deploy<'c, S:Store, CE:CompilationExternals>(store:&CachedStore<Module,S>, deploy_data:&[u8], heap:&Heap, system_mode_on:bool) -> Result<Hash> {
+pub fn deploy<'c, S:Store, CE:CompilationExternals>(store:&CachedStore<Module,S>, deploy_data:&[u8], heap:&Heap, system_mode_on:bool) -> Result<Vec<Hash>> {
     //Check that it is inside limit
     if deploy_data.len() > CONFIG.max_bundle_size { return error(||"Transaction Bundle to big")}
     //Static allocations (could be done once)
@@ -207,24 +275,44 @@ pub fn deploy<'c, S:Store, CE:CompilationExternals>(store:&CachedStore<Module,S>
     let deploy_txt_alloc = heap.new_virtual_arena(CONFIG.max_txt_alloc);
     //Parse the transaction
     let deploy_txt:DeployTransaction = Parser::parse_fully(deploy_data, CONFIG.max_structural_dept, &deploy_txt_alloc)?;
+    //Validation and compilation abort when they exceed the gas limit
+    let limiter = Limiter::new(deploy_txt.gas_limit.min(CONFIG.max_deploy_gas));
+    let res = deploy_content::<_,CE>(store, &deploy_txt, system_mode_on, &limiter);
+    //Nothing is kept if a part of the deploy failed
+    for class in [StorageClass::Module, StorageClass::Transaction, StorageClass::Descriptor] {
+        if res.is_ok() {
+            store.get_store().commit(class);
+        } else {
+            store.get_store().rollback(class);
+        }
+    }
+    res
+}
 
+//Validates and stores the content of a deploy without committing it
+// returns the hashes of the deployed modules followed by the descriptor hashes of the deployed transactions
+#[cfg(feature = "deployer")]
+pub(crate) fn deploy_content<S:Store, CE:CompilationExternals>(store:&CachedStore<Module,S>, deploy_txt:&DeployTransaction, system_mode_on:bool, limiter:&Limiter) -> Result<Vec<Hash>> {
     Ok(match deploy_txt.typ {
-        DeployType::Module => {
-            //todo: I do not like the to_vec here (as we have it in memory twice now)
-            //but without having seperate Transaction type it is hard not to do this
-            //todo: we may consider passing &[u8] into store and copy there if necessary (but this gives lifetime hell)
-            let res = deploy_module(store, deploy_txt.data.to_vec(), system_mode_on, true)?;
-            store.get_store().commit(StorageClass::Module);
-            res
-        },
+        //todo: I do not like the to_vec here (as we have it in memory twice now)
+        //but without having seperate Transaction type it is hard not to do this
+        //todo: we may consider passing &[u8] into store and copy there if necessary (but this gives lifetime hell)
+        DeployType::Module => alloc::vec![deploy_module(store, deploy_txt.data.to_vec(), system_mode_on, false, limiter)?],
         DeployType::Transaction => {
-            //todo: I do not like the to_vec here (as we have it in memory twice now)
-            //but without having seperate Transaction type it is hard not to do this
-            //todo: we may consider passing &[u8] into store and copy there if necessary (but this gives lifetime hell)
-            let target = deploy_function(store, deploy_txt.data.to_vec(), true)?;
-            let (res,_) = compile_function::<_,CE>(store.get_store(), target, true)?;
-            store.get_store().commit(StorageClass::Transaction);
-            store.get_store().commit(StorageClass::Descriptor);
+            let target = deploy_function(store, deploy_txt.data.to_vec(), false, limiter)?;
+            alloc::vec![compile_function::<_,CE>(store, target, false, limiter)?.0]
+        },
+        DeployType::Package => {
+            let package:DeployPackage = Parser::parse_fully(&deploy_txt.data, CONFIG.max_structural_dept, &NoCustomAlloc())?;
+            let mut res = Vec::with_capacity(package.modules.len() + package.transactions.len());
+            //The uncommitted modules are visible to the ones deployed after them
+            for module in package.modules {
+                res.push(deploy_module(store, module, system_mode_on, false, limiter)?);
+            }
+            for transaction in package.transactions {
+                let target = deploy_function(store, transaction, false, limiter)?;
+                res.push(compile_function::<_,CE>(store, target, false, limiter)?.0);
+            }
             res
         }
     })
@@ -2,12 +2,15 @@ use sanskrit_common::model::*;
 use sanskrit_common::encoding::*;
 use sanskrit_common::errors::*;
 use crate::TransactionBundle;
+use alloc::vec::Vec;
 
-#[cfg(feature = "deployer")]
-#[derive(Clone, Debug, AllocParsable, Serializable, VirtualSize)]
+#[derive(Clone, Copy, Debug, AllocParsable, Serializable, VirtualSize)]
 pub struct DeployTransaction</*#[AllocLifetime]*/ 'c> {
-    //todo: add max_loads & max_byte_loads
     //todo: add max_stores & max_byte_stores
+    //Maximal work units the validation and compilation may use (capped by the configuration)
+    // it covers the bytes of the input, the loaded dependencies, the resolved imports, the components and the opcodes
+    // Note: since version 0.2.0 it is the first field of the encoding, older deploys started with the type
+    pub gas_limit:u64,
     pub typ:DeployType,
     pub data: SlicePtr<'c, u8>,
 }
@@ -73,11 +76,20 @@ pub enum SectionType {
     Custom
 }
 
-#[cfg(feature = "deployer")]
 #[derive(Clone, Copy, Debug, Parsable, Serializable, VirtualSize)]
 pub enum DeployType {
     Module,
-    Transaction
+    Transaction,
+    //A serialized DeployPackage
+    Package
+}
+
+//Modules and transactions that are deployed together (either all succeed or nothing is deployed)
+// the modules must be ordered so that each comes after the modules it depends on
+#[derive(Clone, Debug, Parsable, Serializable)]
+pub struct DeployPackage {
+    pub modules:Vec<Vec<u8>>,
+    pub transactions:Vec<Vec<u8>>,
 }
 
 //A section of transactions
@@ -92,7 +104,16 @@ pub struct BundleSection</*#[AllocLifetime]*/ 'c> {
 
 //A transaction
 #[derive(Clone, Copy, Debug, AllocParsable, Serializable, VirtualSize)]
-pub struct Transaction</*#[AllocLifetime]*/ 'c> {
+pub enum Transaction</*#[AllocLifetime]*/ 'c> {
+    //Calls a deployed transaction
+    Invoke(Invoke<'c>),
+    //Deploys a module or a transaction (it can be invoked from the next bundle on)
+    Deploy(DeployTransaction<'c>),
+}
+
+//A call to a deployed transaction
+#[derive(Clone, Copy, Debug, AllocParsable, Serializable, VirtualSize)]
+pub struct Invoke</*#[AllocLifetime]*/ 'c> {
     //transaction type
     pub txt_desc: u16,
     //parameter source & fetch mode
@@ -123,7 +144,9 @@ pub enum RetType {
     Store,
     Put(u8),
     Drop,
-    Log
+    Log,
+    //Pays the bundle fee (only allowed in the essential section)
+    Fee
 }
 
 
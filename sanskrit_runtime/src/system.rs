@@ -5,6 +5,8 @@ use crate::compute::TransactionExecutionContext;
 use crate::TransactionBundle;
 use sanskrit_common::encoding::ParserAllocator;
 use sanskrit_common::errors::*;
+#[cfg(feature = "deployer")]
+use sanskrit_compile::externals::CompilationExternals;
 
 
 
@@ -14,6 +16,9 @@ pub trait SystemContext<'c> {
     type B:TransactionBundle;
     type VC:TransactionVerificationContext<Self::S, Self::B>;
     type EC:TransactionExecutionContext<Self::S, Self::B>;
+    //Used to compile the transactions deployed inside a bundle
+    #[cfg(feature = "deployer")]
+    type CE:CompilationExternals;
 
     fn parse_bundle<A: ParserAllocator>(data:&[u8], alloc:&'c A) -> Result<Self::B>;
 }
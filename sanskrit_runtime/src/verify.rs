@@ -1,7 +1,7 @@
 
 use sanskrit_common::errors::*;
 use sanskrit_common::encoding::ParserAllocator;
-use crate::model::{Transaction, ParamRef, RetType, ParamMode, SectionType};
+use crate::model::{Transaction, Invoke, DeployTransaction, ParamRef, RetType, ParamMode, SectionType};
 use sanskrit_common::model::{Hash, Ptr, SlicePtr};
 use sanskrit_common::arena::*;
 use sanskrit_interpreter::model::{TransactionDescriptor, TxTReturn, RuntimeType, TxTParam};
@@ -26,6 +26,8 @@ pub struct VerificationEnvironment<'a> {
     //indicates the number of scratch pad entries that lack drop
     num_non_drop_scratch_pad_entries:Cell<u8>,
     param_heap:Cell<u32>,
+    //indicates that the essential section returns a fee
+    pays_fee:Cell<bool>,
 }
 
 
@@ -46,6 +48,8 @@ pub trait TransactionVerificationContext<S:Store, B:TransactionBundle> {
     fn is_chain_value(&self, ctx:&Context<S,B>, typ:Ptr<RuntimeType>) -> bool;
     //checks if a type represents a providable entry (returns gas to create & size on haep)
     fn verify_providable(&self, ctx:&Context<S,B>, typ:Ptr<RuntimeType>, section_no:u8,  txt_no:u8, p_num:u8) -> Result<(u64,u32)>;
+    //checks if a type represents a fee payment
+    fn is_fee(&self, ctx:&Context<S,B>, typ:Ptr<RuntimeType>) -> bool;
 
 }
 
@@ -61,7 +65,7 @@ pub trait TransactionVerificationContext<S:Store, B:TransactionBundle> {
 
 pub fn verify_repeated<'c, SYS:SystemContext<'c>>(ctx:&Context<SYS::S,SYS::B>,  block_no:u64) -> Result<()> {
     //check that it is in window
-    if block_no < ctx.txt_bundle.earliest_block() || block_no >= ctx.txt_bundle.earliest_block() + CONFIG.block_inclusion_window {
+    if block_no < ctx.txt_bundle.earliest_block() || block_no >= ctx.txt_bundle.earliest_block().saturating_add(CONFIG.block_inclusion_window) {
         return error(||"Transaction not allowed in current block")
     }
 
@@ -82,8 +86,64 @@ pub fn verify_repeated<'c, SYS:SystemContext<'c>>(ctx:&Context<SYS::S,SYS::B>,
     Ok(())
 }
 
+//The resources a bundle needs, independent of what it declares
+pub struct RequiredResources {
+    pub essential_gas:u64,
+    pub total_gas:u64,
+    pub param_heap:u32,
+    //the essential section returns a fee
+    pays_fee:bool,
+}
+
 //Executes a transaction
-pub fn verify_once<'c, SYS:SystemContext<'c>>(acc_ctx:&SYS::VC, ctx:&Context<SYS::S, SYS::B>, heap:&Heap) -> Result<()> {
+pub fn verify_once<'c, SYS:SystemContext<'c>>(acc_ctx:&SYS::VC, ctx:&Context<SYS::S, SYS::B>, heap:&Heap, gas_price:u64) -> Result<()> {
+    if !cfg!(feature = "deployer") && has_deploys(ctx.txt_bundle) {
+        return error(||"Deploys are not supported by this runtime")
+    }
+
+    if CONFIG.max_txt_alloc < ctx.txt_bundle.transaction_heap_limit() as usize {
+        return error(||"Transaction Descriptors use to much memory")
+    }
+
+    //Todo: Shall we do lazy? -- currently all the txt loads count to essential cost
+    let desc_alloc = heap.new_virtual_arena(ctx.txt_bundle.transaction_heap_limit() as usize);
+    let required = required_resources(acc_ctx, ctx, &desc_alloc)?;
+
+    //Todo: Check that this is in the limit specified by the miner (or globally agreed on)
+    //      If miner the check needs to be done specially in order that sender does not get removed from network layer
+    if required.essential_gas > ctx.txt_bundle.essential_gas_cost() {
+        return error(||"Bundle has declared wrong essential gas cost")
+    }
+
+    if required.total_gas > ctx.txt_bundle.total_gas_cost() {
+        return error(||"Bundle has declared wrong total gas cost")
+    }
+
+    //the fee is checked against the total gas cost, so it must cover the essential part as well
+    if ctx.txt_bundle.essential_gas_cost() > ctx.txt_bundle.total_gas_cost() {
+        return error(||"Bundle has declared an essential gas cost above its total gas cost")
+    }
+
+    //Fees can only be returned in the essential section, so this ensures the bundle starts with one
+    if gas_price != 0 && !required.pays_fee {
+        return error(||"Bundle must pay a fee in its essential section")
+    }
+
+    if required.param_heap > ctx.txt_bundle.param_heap_limit() as u32 {
+        return error(||"Bundle has not reserved enough parameter heap space")
+    }
+
+    Ok(())
+}
+
+fn has_deploys<B:TransactionBundle>(txt_bundle:&B) -> bool {
+    txt_bundle.sections().iter().any(|section|section.txts.iter().any(|txt|matches!(txt, Transaction::Deploy(_))))
+}
+
+//Checks the structure of a bundle and computes the gas and parameter heap it needs
+// the declared costs and limits are not checked (except the ones for the descriptors), this allows clients to compute them
+// the descriptors are allocated in desc_alloc
+pub fn required_resources<S:Store, B:TransactionBundle, VC:TransactionVerificationContext<S,B>>(acc_ctx:&VC, ctx:&Context<S, B>, desc_alloc:&VirtualHeapArena) -> Result<RequiredResources> {
     //Calculate the payment information
     //Starts with the parsing costs which are already done mostly but this is inevitable (a miner could have a size limit)
     //This includes encoding the parameter witnesses
@@ -93,15 +153,9 @@ pub fn verify_once<'c, SYS:SystemContext<'c>>(acc_ctx:&SYS::VC, ctx:&Context<SYS
     let witness_types  = RefCell::new(alloc::vec::from_elem(Option::None,ctx.txt_bundle.witness().len()));
     let scratch_pad_types  = RefCell::new(alloc::vec::from_elem(Option::None,ctx.txt_bundle.scratch_pad_slots() as usize));
 
-    if CONFIG.max_txt_alloc < ctx.txt_bundle.transaction_heap_limit() as usize {
-        return error(||"Transaction Descriptors use to much memory")
-    }
-
-    //Todo: Shall we do lazy? -- currently all the txt loads count to essential cost
-    let desc_alloc = heap.new_virtual_arena(ctx.txt_bundle.transaction_heap_limit() as usize);
     let mut desc_builder = desc_alloc.slice_builder(ctx.txt_bundle.descriptors().len())?;
     for desc_hash in ctx.txt_bundle.descriptors().iter() {
-        desc_builder.push(acc_ctx.read_transaction_desc(ctx, desc_hash, &desc_alloc)?);
+        desc_builder.push(acc_ctx.read_transaction_desc(ctx, desc_hash, desc_alloc)?);
     }
 
 
@@ -113,7 +167,8 @@ pub fn verify_once<'c, SYS:SystemContext<'c>>(acc_ctx:&SYS::VC, ctx:&Context<SYS
         witness_types,
         scratch_pad_types,
         num_non_drop_scratch_pad_entries: Cell::new(0),
-        param_heap: Cell::new(0)
+        param_heap: Cell::new(0),
+        pays_fee: Cell::new(false),
     };
 
     let mut required_gas = CONFIG.bundle_base_cost + CONFIG.parsing_cost.compute(ctx.txt_bundle.byte_size() as u64);
@@ -126,7 +181,10 @@ pub fn verify_once<'c, SYS:SystemContext<'c>>(acc_ctx:&SYS::VC, ctx:&Context<SYS
     for txt_section in ctx.txt_bundle.sections().iter() {
         let mut txt_no = 0;
         for txt in txt_section.txts.iter() {
-            required_gas += verify_transaction::<SYS>(&verify_env, acc_ctx, ctx, txt, txt_section.typ, sec_no, txt_no)? as u64;
+            required_gas += match txt {
+                Transaction::Invoke(invoke) => verify_transaction(&verify_env, acc_ctx, ctx, invoke, txt_section.typ, sec_no, txt_no)?,
+                Transaction::Deploy(deploy) => verify_deploy(deploy)?,
+            };
             if txt_no == u8::max_value() {
                 //Check Txt Limit
                 return error(||"to many transactions in a section only 256 are allowed")
@@ -159,26 +217,25 @@ pub fn verify_once<'c, SYS:SystemContext<'c>>(acc_ctx:&SYS::VC, ctx:&Context<SYS
         }
     }
 
-    //Todo: Check that this is in the limit specified by the miner (or globally agreed on)
-    //      If miner the check needs to be done specially in order that sender does not get removed from network layer
-    if essential_gas > ctx.txt_bundle.essential_gas_cost() {
-        return error(||"Bundle has declared wrong essential gas cost")
-    }
+    Ok(RequiredResources {
+        essential_gas,
+        total_gas: required_gas,
+        param_heap: verify_env.param_heap.get(),
+        pays_fee: verify_env.pays_fee.get(),
+    })
+}
 
-    if required_gas > ctx.txt_bundle.total_gas_cost() {
-        return error(||"Bundle has declared wrong total gas cost")
-    }
 
-    if verify_env.param_heap.get() > ctx.txt_bundle.param_heap_limit() as u32 {
-        return error(||"Bundle has not reserved enough parameter heap space")
-    }
 
-    Ok(())
+//Deploys are charged with the gas for storing the input
+// their gas limit counts validation and compilation work units, which are paid on top of the gas (see deploy_gas_limit)
+fn verify_deploy(deploy:&DeployTransaction) -> Result<u64> {
+    if deploy.data.is_empty() { return error(||"Deploy data is empty") }
+    if deploy.gas_limit > CONFIG.max_deploy_gas { return error(||"Deploy gas limit is above the allowed maximum") }
+    Ok(CONFIG.deploy_store_cost.compute(deploy.data.len() as u64))
 }
 
-
-
-fn verify_transaction<'c, SYS:SystemContext<'c>>(env:&VerificationEnvironment, acc_ctx:&SYS::VC, ctx:&Context<SYS::S, SYS::B>, txt:&Transaction, sec_typ:SectionType, sec_no:u8, txt_no:u8) -> Result<u64>{
+fn verify_transaction<S:Store, B:TransactionBundle, VC:TransactionVerificationContext<S,B>>(env:&VerificationEnvironment, acc_ctx:&VC, ctx:&Context<S, B>, txt:&Invoke, sec_typ:SectionType, sec_no:u8, txt_no:u8) -> Result<u64>{
     //Prepare all the Memory
     if  env.descs.len() <= txt.txt_desc as usize { return error(||"Descriptor index out of range")  }
     let txt_desc = env.descs[txt.txt_desc as usize];
@@ -318,6 +375,12 @@ fn verify_transaction<'c, SYS:SystemContext<'c>>(env:&VerificationEnvironment, a
                         //Todo: Logs can be costly we should charge
                         if !drop { return error(||"Returns without drop capability must be stored or scratched") }
                     },
+                    RetType::Fee => {
+                        //The fee is consumed by the runtime and credited to the block producer
+                        if sec_typ != SectionType::Essential { return error(||"Fees can only be paid in the essential section") }
+                        if !acc_ctx.is_fee(ctx, typ) { return error(||"Fee returns must be of the fee type") }
+                        env.pays_fee.set(true);
+                    },
                 }
             },
         }
@@ -4,7 +4,7 @@ extern crate arrayref;
 extern crate core;
 
 use sled::Db;
-use sanskrit_common::model::Hash;
+use sanskrit_common::model::{Hash, hash_from_slice};
 use sanskrit_common::store::*;
 use sanskrit_common::errors::*;
 use std::path::{Path, PathBuf};
@@ -16,16 +16,23 @@ use std::cell::RefCell;
 struct Container {
     persisted:Db,
     pending:BTreeMap<Hash, Option<Vec<u8>>>,
-    auto_flush:bool
+    auto_flush:bool,
+    //digest of the persisted entries, computed once on open and then updated on commit
+    digest:ClassDigest,
 }
 
 impl Container {
     pub fn new(path:PathBuf, auto_flush:bool)-> Self{
-        Container{
+        let mut container = Container{
             persisted: sled::open(path).unwrap(),
             pending: BTreeMap::new(),
-            auto_flush
+            auto_flush,
+            digest: ClassDigest::default(),
+        };
+        for (key, value) in container.list().unwrap() {
+            container.digest.insert(&key, &value);
         }
+        container
     }
 
     pub fn contains_key(&self, key:&Hash) -> bool {
@@ -79,10 +86,16 @@ impl Container {
         let mut res = BTreeMap::new();
         mem::swap(&mut res, &mut self.pending);
         for (key, value) in res {
-            match value {
+            let old = match value {
                 None => self.persisted.remove(&key).unwrap(),
-                Some(data) => self.persisted.insert(key, data).unwrap()
+                Some(data) => {
+                    self.digest.insert(&key, &data);
+                    self.persisted.insert(key, data).unwrap()
+                }
             };
+            if let Some(old) = old {
+                self.digest.remove(&key, &old);
+            }
         }
         if self.auto_flush {
             self.persisted.flush().unwrap();
@@ -96,6 +109,18 @@ impl Container {
     pub fn flush(&mut self) {
         self.persisted.flush().unwrap();
     }
+
+    pub fn list(&self) -> Result<Vec<(Hash, Vec<u8>)>> {
+        let mut res = Vec::new();
+        for entry in self.persisted.iter() {
+            match entry {
+                Ok((key, value)) if key.len() == 20 => res.push((hash_from_slice(&key), value.to_vec())),
+                Ok(_) => return error(||"Store contains a key that is not a hash"),
+                Err(_) => return error(||"Reading the store failed")
+            }
+        }
+        Ok(res)
+    }
 }
 
 pub struct InnerSledStore {
@@ -104,6 +129,7 @@ pub struct InnerSledStore {
     funs: Container,
     descs: Container,
     elems: Container,
+    balances: Container,
 }
 
 pub struct SledStore(RefCell<InnerSledStore>);
@@ -117,6 +143,7 @@ impl SledStore {
 
         let desc_p = folder.join("desc").with_extension("db");
         let elem_p = folder.join("elem").with_extension("db");
+        let balance_p = folder.join("balance").with_extension("db");
 
 
         SledStore(RefCell::new(InnerSledStore {
@@ -125,6 +152,7 @@ impl SledStore {
             funs: Container::new(fun_p, auto_flush.contains( &StorageClass::Transaction)),
             descs: Container::new(desc_p, auto_flush.contains( &StorageClass::Descriptor)),
             elems: Container::new(elem_p, auto_flush.contains( &StorageClass::EntryValue)),
+            balances: Container::new(balance_p, auto_flush.contains( &StorageClass::Balance)),
         }))
     }
 
@@ -139,6 +167,7 @@ impl SledStore {
             StorageClass::Descriptor => process(&mut self.0.borrow_mut().descs),
             StorageClass::EntryValue => process(&mut self.0.borrow_mut().elems),
             StorageClass::EntryHash =>  process(&mut self.0.borrow_mut().hashs),
+            StorageClass::Balance =>  process(&mut self.0.borrow_mut().balances),
         }
     }
 }
@@ -158,6 +187,7 @@ impl Store for SledStore {
             StorageClass::Descriptor => process(&mut self.0.borrow_mut().descs, key),
             StorageClass::EntryValue => process(&mut self.0.borrow_mut().elems, key),
             StorageClass::EntryHash => process(&mut self.0.borrow_mut().hashs, key),
+            StorageClass::Balance => process(&mut self.0.borrow_mut().balances, key),
         }
     }
 
@@ -172,6 +202,7 @@ impl Store for SledStore {
             StorageClass::Descriptor => process(&self.0.borrow().descs, key),
             StorageClass::EntryValue => process(&self.0.borrow().elems, key),
             StorageClass::EntryHash => process(&self.0.borrow().hashs, key),
+            StorageClass::Balance => process(&self.0.borrow().balances, key),
         }
     }*/
 
@@ -188,6 +219,7 @@ impl Store for SledStore {
             StorageClass::Descriptor => process(&self.0.borrow().descs, key, f),
             StorageClass::EntryValue => process(&self.0.borrow().elems, key, f),
             StorageClass::EntryHash => process(&self.0.borrow().hashs, key, f),
+            StorageClass::Balance => process(&self.0.borrow().balances, key, f),
         }
     }
 
@@ -205,6 +237,7 @@ impl Store for SledStore {
             StorageClass::Descriptor => process(&mut self.0.borrow_mut().descs, key, data),
             StorageClass::EntryValue => process(&mut self.0.borrow_mut().elems, key, data),
             StorageClass::EntryHash => process(&mut self.0.borrow_mut().hashs, key, data),
+            StorageClass::Balance => process(&mut self.0.borrow_mut().balances, key, data),
         }
     }
 
@@ -220,6 +253,7 @@ impl Store for SledStore {
             StorageClass::Descriptor => process(&mut self.0.borrow_mut().descs),
             StorageClass::EntryValue => process(&mut self.0.borrow_mut().elems),
             StorageClass::EntryHash =>  process(&mut self.0.borrow_mut().hashs),
+            StorageClass::Balance =>  process(&mut self.0.borrow_mut().balances),
         }
     }
 
@@ -234,27 +268,157 @@ impl Store for SledStore {
             StorageClass::Descriptor => process(&mut self.0.borrow_mut().descs),
             StorageClass::EntryValue => process(&mut self.0.borrow_mut().elems),
             StorageClass::EntryHash => process(&mut self.0.borrow_mut().hashs),
+            StorageClass::Balance => process(&mut self.0.borrow_mut().balances),
         }
     }
 
+}
 
-    /*
-    fn list(&self, class: StorageClass) -> Vec<(Hash, Vec<u8>)> {
-        fn process(map:&Tree) -> Vec<(Hash, Vec<u8>)> {
-            map.iter().map(|r|{
-                let (hash, val) = r.unwrap();
-                let hash_data_ref = array_ref!(hash,0,20);
-                (hash_data_ref.to_owned(), val.to_owned())
-            }).collect()
+impl ListableStore for SledStore {
+    fn list(&self, class:StorageClass) -> Result<Vec<(Hash, Vec<u8>)>> {
+        let inner = self.0.borrow();
+        match class {
+            StorageClass::Module => inner.modules.list(),
+            StorageClass::Transaction => inner.funs.list(),
+            StorageClass::Descriptor => inner.descs.list(),
+            StorageClass::EntryValue => inner.elems.list(),
+            StorageClass::EntryHash => inner.hashs.list(),
+            StorageClass::Balance => inner.balances.list(),
         }
+    }
 
-        match class {
-            StorageClass::AdtDesc => process(&self.adt_descs),
-            StorageClass::FunDesc => process(&self.fun_descs),
-            StorageClass::Module => process(&self.0.borrow().modules),
-            StorageClass::Function => process(&self.0.borrow().funs),
-            StorageClass::Elem => process(&self.0.borrow().elems),
+    fn digest(&self, class:StorageClass) -> Result<ClassDigest> {
+        let inner = self.0.borrow();
+        Ok(match class {
+            StorageClass::Module => inner.modules.digest,
+            StorageClass::Transaction => inner.funs.digest,
+            StorageClass::Descriptor => inner.descs.digest,
+            StorageClass::EntryValue => inner.elems.digest,
+            StorageClass::EntryHash => inner.hashs.digest,
+            StorageClass::Balance => inner.balances.digest,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate sanskrit_memory_store;
+
+    use super::*;
+    use sanskrit_common::archive::{export_state, import_state};
+    use self::sanskrit_memory_store::BTreeMapStore;
+
+    fn listed_digest<S:ListableStore>(store:&S, class:StorageClass) -> ClassDigest {
+        let mut digest = ClassDigest::default();
+        for (key, value) in store.list(class).unwrap() {
+            digest.insert(&key, &value);
+        }
+        digest
+    }
+
+    fn write<S:Store>(store:&S) {
+        for i in 0..10u8 {
+            store.set(StorageClass::EntryValue, [i; 20], vec![i; i as usize]).unwrap();
+        }
+        store.set(StorageClass::Balance, [1; 20], 5u64.to_be_bytes().to_vec()).unwrap();
+        store.commit(StorageClass::EntryValue);
+        store.commit(StorageClass::Balance);
+        //replaced values and deletes
+        store.delete(StorageClass::Balance, &[1; 20]).unwrap();
+        store.set(StorageClass::Balance, [1; 20], 7u64.to_be_bytes().to_vec()).unwrap();
+        store.delete(StorageClass::EntryValue, &[3; 20]).unwrap();
+        store.commit(StorageClass::EntryValue);
+        store.commit(StorageClass::Balance);
+        //pending changes are not part of the digest
+        store.set(StorageClass::Module, [9; 20], vec![9]).unwrap();
+        store.delete(StorageClass::EntryValue, &[4; 20]).unwrap();
+    }
+
+    #[test]
+    fn digests_follow_the_committed_entries() {
+        let folder = std::env::temp_dir().join(format!("sanskrit_sled_digest_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&folder);
+        let sled = SledStore::new(&folder, BTreeSet::new());
+        let memory = BTreeMapStore::new();
+        write(&sled);
+        write(&memory);
+
+        for class in STORAGE_CLASSES {
+            assert_eq!(sled.digest(class).unwrap(), listed_digest(&sled, class));
+            assert_eq!(memory.digest(class).unwrap(), sled.digest(class).unwrap());
+        }
+        assert_eq!(sled.digest(StorageClass::EntryValue).unwrap().entries, 9);
+        assert_eq!(sled.digest(StorageClass::Module).unwrap(), ClassDigest::default());
+        let root = state_root(&sled).unwrap();
+        assert_eq!(state_root(&memory).unwrap(), root);
+
+        //rolled back changes leave the digest as it was
+        sled.rollback(StorageClass::Module);
+        sled.rollback(StorageClass::EntryValue);
+        assert_eq!(state_root(&sled).unwrap(), root);
+        //deleting all entries returns to the empty digest
+        for (key, _) in sled.list(StorageClass::EntryValue).unwrap() {
+            sled.delete(StorageClass::EntryValue, &key).unwrap();
+        }
+        sled.commit(StorageClass::EntryValue);
+        assert_eq!(sled.digest(StorageClass::EntryValue).unwrap(), ClassDigest::default());
+        let root = state_root(&sled).unwrap();
+
+        //the digest of a reopened store is computed from its content
+        for class in STORAGE_CLASSES {
+            sled.flush(class);
+        }
+        drop(sled);
+        let sled = SledStore::new(&folder, BTreeSet::new());
+        assert_eq!(state_root(&sled).unwrap(), root);
+        drop(sled);
+        let _ = std::fs::remove_dir_all(&folder);
+    }
+
+    fn archived_store() -> BTreeMapStore {
+        let store = BTreeMapStore::new();
+        let module = vec![1, 2, 3];
+        store.set(StorageClass::Module, store_hash(&[&module]), module).unwrap();
+        let desc = vec![4, 5];
+        store.set(StorageClass::Descriptor, store_hash(&[&desc]), desc).unwrap();
+        store.set(StorageClass::EntryHash, [7; 20], vec![8; 20]).unwrap();
+        store.set(StorageClass::EntryValue, [7; 20], vec![9; 3]).unwrap();
+        store.set(StorageClass::Balance, [1; 20], 25u64.to_be_bytes().to_vec()).unwrap();
+        store.set(StorageClass::Balance, [2; 20], 0u64.to_be_bytes().to_vec()).unwrap();
+        for class in STORAGE_CLASSES {
+            store.commit(class);
+        }
+        store
+    }
+
+    #[test]
+    fn archives_move_the_state_between_backends() {
+        let folder = std::env::temp_dir().join(format!("sanskrit_sled_archive_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&folder);
+        let memory = archived_store();
+        let (archive, root) = export_state(&memory).unwrap();
+        assert_eq!(root, state_root(&memory).unwrap());
+
+        let sled = SledStore::new(&folder, BTreeSet::new());
+        assert_eq!(import_state(&archive, &sled).unwrap(), root);
+        assert_eq!(state_root(&sled).unwrap(), root);
+        for class in STORAGE_CLASSES {
+            assert_eq!(sled.list(class).unwrap(), memory.list(class).unwrap());
+        }
+        assert_eq!(sled.get(StorageClass::Balance, &[1; 20], |d|d.to_vec()).unwrap(), 25u64.to_be_bytes().to_vec());
+        //exporting the imported state gives the same archive
+        assert_eq!(export_state(&sled).unwrap(), (archive, root));
+
+        //a balance that is not an amount is rejected and nothing is imported
+        let broken = archived_store();
+        broken.set(StorageClass::Balance, [3; 20], vec![1; 7]).unwrap();
+        broken.commit(StorageClass::Balance);
+        let target = BTreeMapStore::new();
+        assert!(import_state(&export_state(&broken).unwrap().0, &target).is_err());
+        for class in STORAGE_CLASSES {
+            assert_eq!(target.digest(class).unwrap(), ClassDigest::default());
         }
+        drop(sled);
+        let _ = std::fs::remove_dir_all(&folder);
     }
-    */
 }
@@ -13,16 +13,18 @@ extern crate core;
 use sanskrit_deploy::{deploy_module, deploy_function};
 use sanskrit_common::errors::*;
 use alloc::vec::Vec;
-use sanskrit_common::store::{StorageClass, Store};
+use sanskrit_common::store::{StorageClass, Store, CachedStore};
+use sanskrit_core::model::Module;
 use sanskrit_compile::compile_function;
 use store::ExternalStore;
 use sanskrit_common::model::HASH_SIZE;
+use sanskrit_common::limiter::Limiter;
 use sanskrit_default_externals::{SYS_MODS, ServerExternals};
 
 
 mod store;
 
-extern  {
+extern "C" {
     fn load_input(ptr: *mut u8);
     //if return is positive we assume that it suceeded and that many bytes where llocated
     //if return is negative we assume that we did not had enough space and allocate that many space then reexecute load
@@ -42,7 +44,7 @@ pub fn emit_error(str:&str){
 }
 
 #[no_mangle]
-pub extern fn register(sys_id:isize) -> bool {
+pub extern "C" fn register(sys_id:isize) -> bool {
     let mut hash = [0;HASH_SIZE];
     unsafe{load_input(hash.as_mut_ptr())}
     if sys_id as usize >= SYS_MODS.len() {
@@ -55,7 +57,7 @@ pub extern fn register(sys_id:isize) -> bool {
 }
 
 #[no_mangle]
-pub extern fn compile(input_size: usize, store_prealloc:usize, is_txt:bool, system_mode:bool, system_id:isize) -> bool {
+pub extern "C" fn compile(input_size: usize, store_prealloc:usize, is_txt:bool, system_mode:bool, system_id:isize) -> bool {
     let mut data = Vec::with_capacity(input_size);
     data.resize(input_size, 0);
     unsafe{load_input(data.as_mut_ptr())}
@@ -79,10 +81,10 @@ pub extern fn compile(input_size: usize, store_prealloc:usize, is_txt:bool, syst
 }
 
 fn process_module_deploy(module:Vec<u8>, pre_alloc:usize, system_mode_on:bool, sys_id:isize) -> Result<()>{
-    let store = ExternalStore::new(pre_alloc);
+    let store = CachedStore::<Module,_>::new(ExternalStore::new(pre_alloc), StorageClass::Module);
     //Note: We use a special store that commits eagerly (for efficiency and thus does not implement commit - hence disable auto commit)
     //      This is ok as in this case storing is the last thing that would happen and we store only the module
-    let hash = deploy_module(&store,module,system_mode_on,false)?;
+    let hash = deploy_module(&store,module,system_mode_on,false,&Limiter::unlimited())?;
     if system_mode_on && sys_id >= 0 {
         if sys_id as usize >= SYS_MODS.len() {
             return sanskrit_common::errors::error(||"System module index out of range");
@@ -97,12 +99,13 @@ fn process_module_deploy(module:Vec<u8>, pre_alloc:usize, system_mode_on:bool, s
 //      later we can just keep store external forwarding calls to the user
 //      but this may not be that easy -- bu needs to be done
 fn process_txt_deploy(txt:Vec<u8>, pre_alloc:usize) -> Result<Vec<u8>> {
-    let store = ExternalStore::new(pre_alloc);
+    let store = CachedStore::<Module,_>::new(ExternalStore::new(pre_alloc), StorageClass::Module);
     //Note: We use a special store that commits eagerly (for efficiency and thus does not implement commit - hence disable auto commit)
     //      Note if we fail after deploy we still can record the deploy and only repeat the compile
     //       Later -- we can even make seperate wasm entry points for these
-    let hash = deploy_function(&store,txt,false)?;
-    let (t_hash,_) = compile_function::<_,ServerExternals>(&store,hash, false)?;
+    let limiter = Limiter::unlimited();
+    let hash = deploy_function(&store,txt,false,&limiter)?;
+    let (t_hash,_) = compile_function::<_,ServerExternals>(&store,hash, false,&limiter)?;
     let res = store.get(StorageClass::Descriptor, &t_hash, |d|d.to_vec())?;
     Ok(res)
 }
@@ -0,0 +1,451 @@
+extern crate wasmi;
+extern crate sanskrit_common;
+extern crate sanskrit_core;
+extern crate sanskrit_deploy;
+extern crate sanskrit_compile;
+extern crate sanskrit_memory_store;
+extern crate sanskrit_default_externals;
+
+use sanskrit_common::errors::*;
+use sanskrit_common::model::Hash;
+use sanskrit_common::limiter::Limiter;
+use sanskrit_common::store::{Store, StorageClass, CachedStore};
+use sanskrit_core::model::Module as SModule;
+use sanskrit_memory_store::BTreeMapStore;
+use sanskrit_deploy::{deploy_module, deploy_function};
+use sanskrit_compile::compile_function;
+use sanskrit_default_externals::{SYS_MODS, ServerExternals};
+use wasmi::{Engine, Linker, Caller, Extern, Memory, TypedFunc, Instance, Error as WasmError};
+use wasmi::Module as WasmModule;
+use wasmi::Store as WasmStore;
+
+//Initial size of the buffer the wasm side uses to receive stored values (it grows on demand)
+const STORE_PRE_ALLOC:i32 = 4096;
+
+//A write done by the wasm module over the store import
+pub type Write = (StorageClass, Hash);
+
+//The state visible to the host imports
+pub struct HostState {
+    input:Vec<u8>,
+    store:BTreeMapStore,
+    writes:Vec<Write>,
+    error:Option<String>,
+}
+
+fn class_from_u8(class:i32) -> Option<StorageClass> {
+    Some(match class {
+        0 => StorageClass::Module,
+        1 => StorageClass::Transaction,
+        2 => StorageClass::Descriptor,
+        3 => StorageClass::EntryHash,
+        4 => StorageClass::EntryValue,
+        5 => StorageClass::Balance,
+        _ => return None
+    })
+}
+
+//A module without an exported memory can not exchange data with the host, the call traps instead of panicking the host
+fn get_memory(caller:&Caller<HostState>) -> core::result::Result<Memory, WasmError> {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => Ok(mem),
+        _ => Err(WasmError::new("wasm module does not export its memory"))
+    }
+}
+
+fn read_key(caller:&Caller<HostState>, mem:Memory, ptr:i32) -> Option<Hash> {
+    let mut key = [0; 20];
+    match mem.read(caller, ptr as usize, &mut key) {
+        Ok(_) => Some(key),
+        Err(_) => None
+    }
+}
+
+//Implements the imports of sanskrit_wasm_deploy_compile over a BTreeMapStore
+// The store commits eagerly as the wasm side does never commit or rollback
+fn link(linker:&mut Linker<HostState>) -> Result<()> {
+    let res = linker.func_wrap("env", "load_input", |mut caller:Caller<HostState>, ptr:i32| -> core::result::Result<(), WasmError> {
+        let mem = get_memory(&caller)?;
+        let (data, state) = mem.data_and_store_mut(&mut caller);
+        let start = ptr as usize;
+        match data.get_mut(start..start + state.input.len()) {
+            Some(target) => {
+                target.copy_from_slice(&state.input);
+                Ok(())
+            },
+            None => Err(WasmError::new("input does not fit into the wasm memory"))
+        }
+    }).and_then(|l|l.func_wrap("env", "load", |mut caller:Caller<HostState>, class:i32, key_ptr:i32, target_ptr:i32, reserved:i32| -> core::result::Result<i32, WasmError> {
+        let mem = get_memory(&caller)?;
+        let (class, key) = match (class_from_u8(class), read_key(&caller, mem, key_ptr)) {
+            (Some(class), Some(key)) => (class, key),
+            _ => return Ok(0)
+        };
+        //a missing value is signaled with an empty value which fails to parse on the wasm side
+        let value = match caller.data().store.get(class, &key, |d|d.to_vec()) {
+            Ok(value) => value,
+            Err(_) => return Ok(0)
+        };
+        if value.len() > reserved as usize {
+            //request a bigger buffer
+            return Ok(-(value.len() as i32))
+        }
+        match mem.write(&mut caller, target_ptr as usize, &value) {
+            Ok(_) => Ok(value.len() as i32),
+            Err(_) => Ok(0)
+        }
+    })).and_then(|l|l.func_wrap("env", "store", |mut caller:Caller<HostState>, class:i32, key_ptr:i32, data_ptr:i32, data_size:i32| -> core::result::Result<i32, WasmError> {
+        let mem = get_memory(&caller)?;
+        let (class, key) = match (class_from_u8(class), read_key(&caller, mem, key_ptr)) {
+            (Some(class), Some(key)) => (class, key),
+            _ => return Ok(0)
+        };
+        if data_size < 0 {
+            return Ok(0)
+        }
+        let mut value = vec![0; data_size as usize];
+        if mem.read(&caller, data_ptr as usize, &mut value).is_err() {
+            return Ok(0)
+        }
+        let state = caller.data_mut();
+        if state.store.set(class, key, value).is_err() {
+            return Ok(0)
+        }
+        state.store.commit(class);
+        state.writes.push((class, key));
+        Ok(1)
+    })).and_then(|l|l.func_wrap("env", "error", |mut caller:Caller<HostState>, ptr:i32, len:i32| -> core::result::Result<(), WasmError> {
+        let mem = get_memory(&caller)?;
+        let mut msg = vec![0; len.max(0) as usize];
+        let text = match mem.read(&caller, ptr as usize, &mut msg) {
+            Ok(_) => String::from_utf8_lossy(&msg).into_owned(),
+            Err(_) => "unreadable error message".to_owned()
+        };
+        caller.data_mut().error = Some(text);
+        Ok(())
+    }));
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(_) => error(||"Could not link the host functions")
+    }
+}
+
+//Runs the compiled sanskrit_wasm_deploy_compile module
+pub struct WasmHost {
+    store:WasmStore<HostState>,
+    register:TypedFunc<i32, i32>,
+    compile:TypedFunc<(i32, i32, i32, i32, i32), i32>,
+}
+
+impl WasmHost {
+    pub fn new(wasm:&[u8]) -> Result<Self> {
+        let engine = Engine::default();
+        let module = match WasmModule::new(&engine, wasm) {
+            Ok(module) => module,
+            Err(e) => return owned_error(||format!("Could not load wasm module: {}", e))
+        };
+        let mut store = WasmStore::new(&engine, HostState {
+            input: Vec::new(),
+            store: BTreeMapStore::new(),
+            writes: Vec::new(),
+            error: None,
+        });
+        let mut linker = Linker::new(&engine);
+        link(&mut linker)?;
+        let instance:Instance = match linker.instantiate(&mut store, &module).and_then(|pre|pre.start(&mut store)) {
+            Ok(instance) => instance,
+            Err(e) => return owned_error(||format!("Could not instantiate wasm module: {}", e))
+        };
+        let register = instance.get_typed_func::<i32, i32>(&store, "register");
+        let compile = instance.get_typed_func::<(i32, i32, i32, i32, i32), i32>(&store, "compile");
+        match (register, compile) {
+            (Ok(register), Ok(compile)) => Ok(WasmHost { store, register, compile }),
+            _ => error(||"Wasm module does not export register and compile")
+        }
+    }
+
+    fn start_call(&mut self, input:Vec<u8>) {
+        let state = self.store.data_mut();
+        state.input = input;
+        state.writes.clear();
+        state.error = None;
+    }
+
+    fn finish_call(&mut self, res:core::result::Result<i32, wasmi::Error>) -> Result<Vec<Write>> {
+        let state = self.store.data_mut();
+        match (res, state.error.take()) {
+            (Ok(1), _) => Ok(state.writes.drain(..).collect()),
+            (Ok(_), Some(msg)) => owned_error(||msg),
+            (Ok(_), None) => error(||"Wasm call failed without an error message"),
+            (Err(e), _) => owned_error(||format!("Wasm execution trapped: {}", e)),
+        }
+    }
+
+    //registers the implementation of a system module on the wasm side
+    pub fn register(&mut self, sys_id:u8, module_hash:Hash) -> Result<()> {
+        self.start_call(module_hash.to_vec());
+        let res = self.register.call(&mut self.store, sys_id as i32);
+        self.finish_call(res).map(|_|())
+    }
+
+    //deploys a module and registers it if sys_id is present
+    pub fn deploy_module(&mut self, data:Vec<u8>, system_mode_on:bool, sys_id:Option<u8>) -> Result<Vec<Write>> {
+        let len = data.len() as i32;
+        self.start_call(data);
+        let sys_id = match sys_id {
+            None => -1,
+            Some(id) => id as i32
+        };
+        let res = self.compile.call(&mut self.store, (len, STORE_PRE_ALLOC, 0, system_mode_on as i32, sys_id));
+        self.finish_call(res)
+    }
+
+    //deploys and compiles a transaction
+    pub fn deploy_transaction(&mut self, data:Vec<u8>) -> Result<Vec<Write>> {
+        let len = data.len() as i32;
+        self.start_call(data);
+        let res = self.compile.call(&mut self.store, (len, STORE_PRE_ALLOC, 1, 0, -1));
+        self.finish_call(res)
+    }
+
+    pub fn get(&self, class:StorageClass, key:&Hash) -> Result<Vec<u8>> {
+        self.store.data().store.get(class, key, |d|d.to_vec())
+    }
+}
+
+//Runs the native deploy and compile functions
+pub struct NativeHost {
+    store:CachedStore<SModule, BTreeMapStore>
+}
+
+impl NativeHost {
+    pub fn new() -> Self {
+        NativeHost {
+            store: CachedStore::new(BTreeMapStore::new(), StorageClass::Module)
+        }
+    }
+
+    //Note: System modules are registered in a process wide table (same as in the wasm module)
+    pub fn deploy_module(&self, data:Vec<u8>, system_mode_on:bool, sys_id:Option<u8>) -> Result<Hash> {
+        let hash = deploy_module(&self.store, data, system_mode_on, true, &Limiter::unlimited())?;
+        if let (true, Some(id)) = (system_mode_on, sys_id) {
+            if id as usize >= SYS_MODS.len() {
+                return error(||"System module index out of range")
+            }
+            SYS_MODS[id as usize](hash);
+        }
+        Ok(hash)
+    }
+
+    //returns the transaction and the descriptor hash
+    pub fn deploy_transaction(&self, data:Vec<u8>) -> Result<(Hash, Hash)> {
+        //The wasm deploy is not metered either
+        let limiter = Limiter::unlimited();
+        let hash = deploy_function(&self.store, data, true, &limiter)?;
+        let (desc_hash, _) = compile_function::<_,ServerExternals>(&self.store, hash, true, &limiter)?;
+        Ok((hash, desc_hash))
+    }
+
+    pub fn get(&self, class:StorageClass, key:&Hash) -> Result<Vec<u8>> {
+        self.store.get(class, key, |d|d.to_vec())
+    }
+}
+
+impl Default for NativeHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//Runs the same inputs through the native and the wasm deploy path and checks that they produce the same results
+pub struct Comparison {
+    wasm:WasmHost,
+    native:NativeHost,
+    pub compared:usize,
+}
+
+impl Comparison {
+    pub fn new(wasm:&[u8]) -> Result<Self> {
+        Ok(Comparison {
+            wasm: WasmHost::new(wasm)?,
+            native: NativeHost::new(),
+            compared: 0
+        })
+    }
+
+    //checks that all the values written by wasm are stored natively with the same bytes
+    fn compare_writes(&mut self, expected:&[Write], writes:&[Write]) -> Result<()> {
+        for exp in expected {
+            if !writes.contains(exp) {
+                return owned_error(||format!("Wasm did not store {:?} {}", exp.0, hex(&exp.1)))
+            }
+        }
+        for (class, key) in writes {
+            let native = match self.native.get(*class, key) {
+                Ok(native) => native,
+                Err(_) => return owned_error(||format!("Wasm stored {:?} {} which is not stored natively", class, hex(key)))
+            };
+            if native != self.wasm.get(*class, key)? {
+                return owned_error(||format!("Wasm stored different bytes for {:?} {}", class, hex(key)))
+            }
+            self.compared += 1;
+        }
+        Ok(())
+    }
+
+    //both have to fail or both have to succeed with the same result
+    // returns None if both failed
+    pub fn deploy_module(&mut self, data:Vec<u8>, system_mode_on:bool, sys_id:Option<u8>) -> Result<Option<Hash>> {
+        let native = self.native.deploy_module(data.clone(), system_mode_on, sys_id);
+        let wasm = self.wasm.deploy_module(data, system_mode_on, sys_id);
+        match (native, wasm) {
+            (Ok(hash), Ok(writes)) => {
+                //the module is not stored again if it already exists
+                self.compare_writes(&[], &writes)?;
+                if self.wasm.get(StorageClass::Module, &hash)? != self.native.get(StorageClass::Module, &hash)? {
+                    return owned_error(||format!("Wasm stored different bytes for module {}", hex(&hash)))
+                }
+                Ok(Some(hash))
+            },
+            (Err(_), Err(_)) => Ok(None),
+            (Ok(_), Err(e)) => owned_error(||format!("Module deploy failed only in wasm: {}", error_to_string(&e))),
+            (Err(e), Ok(_)) => owned_error(||format!("Module deploy failed only natively: {}", error_to_string(&e))),
+        }
+    }
+
+    //both have to fail or both have to succeed with the same result
+    // returns None if both failed
+    pub fn deploy_transaction(&mut self, data:Vec<u8>) -> Result<Option<(Hash,Hash)>> {
+        let native = self.native.deploy_transaction(data.clone());
+        let wasm = self.wasm.deploy_transaction(data);
+        match (native, wasm) {
+            (Ok((hash, desc_hash)), Ok(writes)) => {
+                self.compare_writes(&[(StorageClass::Transaction, hash), (StorageClass::Descriptor, desc_hash)], &writes)?;
+                Ok(Some((hash, desc_hash)))
+            },
+            (Err(_), Err(_)) => Ok(None),
+            (Ok(_), Err(e)) => owned_error(||format!("Transaction deploy failed only in wasm: {}", error_to_string(&e))),
+            (Err(e), Ok(_)) => owned_error(||format!("Transaction deploy failed only natively: {}", error_to_string(&e))),
+        }
+    }
+
+    //deploys the modules in an order where dependencies come first (retries until nothing more can be deployed)
+    // returns the modules that could not be deployed
+    pub fn deploy_modules(&mut self, mut modules:Vec<Vec<u8>>, system_mode_on:bool) -> Result<Vec<Vec<u8>>> {
+        loop {
+            let mut failed = Vec::new();
+            let before = modules.len();
+            for module in modules {
+                if self.deploy_module(module.clone(), system_mode_on, None)?.is_none() {
+                    failed.push(module);
+                }
+            }
+            if failed.is_empty() || failed.len() == before {
+                return Ok(failed)
+            }
+            modules = failed;
+        }
+    }
+}
+
+fn hex(data:&[u8]) -> String {
+    data.iter().map(|b|format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use sanskrit_common::store::store_hash;
+
+    //The modules and transactions in fixtures/ are written by the fixture builders of sanskrit_local_server
+    const SYSTEM_MODULES:&[(&str, u8)] = &[("u64.mod.sans", 8), ("data.mod.sans", 10), ("system.mod.sans", 12)];
+    const MODULES:&[&str] = &["empty.mod.sans"];
+    const TRANSACTIONS:&[&str] = &["pay.txt.sans", "mint.txt.sans", "burn.txt.sans", "fail.txt.sans"];
+
+    fn fixture(name:&str) -> Vec<u8> {
+        std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join(name)).unwrap()
+    }
+
+    //The release build of sanskrit_wasm_deploy_compile (or the file in SANSKRIT_WASM)
+    // it is built from its own directory with: cargo build --release --target wasm32-unknown-unknown
+    fn built_wasm() -> Option<Vec<u8>> {
+        let file = match std::env::var_os("SANSKRIT_WASM") {
+            Some(file) => file.into(),
+            None => Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../sanskrit_wasm_deploy_compile/target/wasm32-unknown-unknown/release/sanskrit_wasm_deploy_compile.wasm")
+        };
+        std::fs::read(file).ok()
+    }
+
+    #[test]
+    fn fixtures_deploy_natively() {
+        let native = NativeHost::new();
+        for (name, id) in SYSTEM_MODULES.iter().map(|(name, id)|(name, Some(*id))).chain(MODULES.iter().map(|name|(name, None))) {
+            let data = fixture(name);
+            let hash = native.deploy_module(data.clone(), id.is_some(), id).unwrap();
+            assert_eq!(hash, store_hash(&[&data]));
+            assert_eq!(native.get(StorageClass::Module, &hash).unwrap(), data);
+        }
+        for name in TRANSACTIONS {
+            let data = fixture(name);
+            let (hash, desc_hash) = native.deploy_transaction(data.clone()).unwrap();
+            assert_eq!(hash, store_hash(&[&data]));
+            assert!(native.get(StorageClass::Descriptor, &desc_hash).is_ok());
+        }
+        let mut truncated = fixture("mint.txt.sans");
+        truncated.pop();
+        assert!(native.deploy_transaction(truncated).is_err());
+    }
+
+    #[test]
+    fn wasm_and_native_write_identical_values() {
+        let wasm = match built_wasm() {
+            Some(wasm) => wasm,
+            None => {
+                println!("sanskrit_wasm_deploy_compile is not built, the comparison is skipped");
+                return
+            }
+        };
+        let mut comp = Comparison::new(&wasm).unwrap();
+        for (name, id) in SYSTEM_MODULES {
+            assert!(comp.deploy_module(fixture(name), true, Some(*id)).unwrap().is_some());
+        }
+        for name in MODULES {
+            assert!(comp.deploy_module(fixture(name), false, None).unwrap().is_some());
+        }
+        for name in TRANSACTIONS {
+            assert!(comp.deploy_transaction(fixture(name)).unwrap().is_some());
+        }
+        //one module per module deploy, the transaction and its descriptor per transaction deploy
+        assert_eq!(comp.compared, SYSTEM_MODULES.len() + MODULES.len() + 2*TRANSACTIONS.len());
+
+        //both have to reject the same broken inputs
+        let mut truncated = fixture("mint.txt.sans");
+        truncated.pop();
+        assert!(comp.deploy_transaction(truncated).unwrap().is_none());
+        assert!(comp.deploy_module(fixture("mint.txt.sans"), false, None).unwrap().is_none());
+    }
+
+    //exports register and compile but no memory, register passes its argument to load_input
+    const NO_MEMORY_MODULE:&[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+        //types: (i32) -> (), (i32) -> i32, (i32 x5) -> i32
+        0x01, 0x13, 0x03, 0x60, 0x01, 0x7f, 0x00, 0x60, 0x01, 0x7f, 0x01, 0x7f, 0x60, 0x05, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x01, 0x7f,
+        //import env.load_input
+        0x02, 0x12, 0x01, 0x03, b'e', b'n', b'v', 0x0a, b'l', b'o', b'a', b'd', b'_', b'i', b'n', b'p', b'u', b't', 0x00, 0x00,
+        0x03, 0x03, 0x02, 0x01, 0x02,
+        //export register and compile
+        0x07, 0x16, 0x02, 0x08, b'r', b'e', b'g', b'i', b's', b't', b'e', b'r', 0x00, 0x01, 0x07, b'c', b'o', b'm', b'p', b'i', b'l', b'e', 0x00, 0x02,
+        //register: load_input(arg); 1 - compile: 0
+        0x0a, 0x0f, 0x02, 0x08, 0x00, 0x20, 0x00, 0x10, 0x00, 0x41, 0x01, 0x0b, 0x04, 0x00, 0x41, 0x00, 0x0b,
+    ];
+
+    #[test]
+    fn missing_memory_traps_instead_of_panicking() {
+        let mut host = WasmHost::new(NO_MEMORY_MODULE).unwrap();
+        let err = host.register(0, [0; 20]).unwrap_err();
+        assert!(error_to_string(&err).contains("does not export its memory"));
+    }
+}
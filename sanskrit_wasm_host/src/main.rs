@@ -0,0 +1,65 @@
+extern crate sanskrit_common;
+extern crate sanskrit_validator;
+extern crate sanskrit_wasm_host;
+
+use std::{env, fs};
+use sanskrit_common::errors::*;
+use sanskrit_validator::execute_with_args;
+use sanskrit_wasm_host::Comparison;
+
+//Usage: sanskrit_wasm_host <wasm-file> [-sys <id>=<module-file>]... <validator args>
+// System modules are deployed first in system mode and registered under their id
+pub fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        return error(||"usage: sanskrit_wasm_host <wasm-file> [-sys <id>=<module-file>]... <validator args>")
+    }
+
+    let wasm = match fs::read(&args[1]) {
+        Ok(wasm) => wasm,
+        Err(_) => return error(||"could not read wasm file")
+    };
+    let mut comp = Comparison::new(&wasm)?;
+
+    let mut pos = 2;
+    while pos + 1 < args.len() && args[pos] == "-sys" {
+        let (id, path) = match args[pos+1].split_once('=') {
+            Some((id, path)) => (id, path),
+            None => return error(||"system modules must be specified as <id>=<module-file>")
+        };
+        let id:u8 = match id.parse() {
+            Ok(id) => id,
+            Err(_) => return error(||"system module id must be a number")
+        };
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(_) => return error(||"could not read system module")
+        };
+        if comp.deploy_module(data, true, Some(id))?.is_none() {
+            return error(||"system module deploy failed")
+        }
+        pos += 2;
+    }
+
+    let res = execute_with_args(&args[pos..], |modules, transactions, dependencies, system_mode_on| {
+        let open_deps = comp.deploy_modules(dependencies, system_mode_on)?;
+        let failed_mods = comp.deploy_modules(modules, system_mode_on)?;
+        let mut failed_txts = 0;
+        for txt in transactions {
+            if comp.deploy_transaction(txt)?.is_none() {
+                failed_txts += 1;
+            }
+        }
+        println!("Wasm and native deploy agree on {} stored values", comp.compared);
+        println!("{} dependencies, {} modules and {} transactions were rejected by both", open_deps.len(), failed_mods.len(), failed_txts);
+        Ok(())
+    });
+
+    match res {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            println!("{}", error_to_string(&e));
+            error(||"wasm and native deploy disagree")
+        }
+    }
+}